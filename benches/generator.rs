@@ -93,6 +93,91 @@ fn deep_generate(c: &mut Criterion) {
     );
 }
 
+fn deep_generate_openat(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_generate_openat");
+
+    let num_files = 10_000;
+    group.throughput(Throughput::Elements(num_files));
+    group.bench_with_input(
+        BenchmarkId::from_parameter(num_files),
+        &num_files,
+        |b, num_files| {
+            b.iter_with_large_drop(|| {
+                let dir = tempdir().unwrap();
+
+                GeneratorBuilder::default()
+                    .root_dir(dir.path().to_path_buf())
+                    .num_files(NonZeroUsize::new(*num_files as usize).unwrap())
+                    .max_depth(100)
+                    .openat(true)
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap();
+
+                dir
+            })
+        },
+    );
+}
+
+fn deep_generate_batched(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_generate_batched");
+
+    let num_files = 10_000;
+    group.throughput(Throughput::Elements(num_files));
+    group.bench_with_input(
+        BenchmarkId::from_parameter(num_files),
+        &num_files,
+        |b, num_files| {
+            b.iter_with_large_drop(|| {
+                let dir = tempdir().unwrap();
+
+                GeneratorBuilder::default()
+                    .root_dir(dir.path().to_path_buf())
+                    .num_files(NonZeroUsize::new(*num_files as usize).unwrap())
+                    .max_depth(100)
+                    .task_granularity(2)
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap();
+
+                dir
+            })
+        },
+    );
+}
+
+fn chain_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chain_generate");
+
+    for max_depth in [100, 500] {
+        group.throughput(Throughput::Elements(max_depth as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(max_depth),
+            &max_depth,
+            |b, max_depth| {
+                b.iter_with_large_drop(|| {
+                    let dir = tempdir().unwrap();
+
+                    GeneratorBuilder::default()
+                        .root_dir(dir.path().to_path_buf())
+                        .num_files(NonZeroUsize::new(100).unwrap())
+                        .max_depth(*max_depth)
+                        .chain(true)
+                        .build()
+                        .unwrap()
+                        .generate()
+                        .unwrap();
+
+                    dir
+                })
+            },
+        );
+    }
+}
+
 fn shallow_generate(c: &mut Criterion) {
     let mut group = c.benchmark_group("shallow_generate");
 
@@ -148,6 +233,36 @@ fn sparse_generate(c: &mut Criterion) {
     );
 }
 
+fn wide_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide_generate");
+
+    let num_files = 100_000;
+    group
+        .sample_size(10)
+        .throughput(Throughput::Elements(num_files));
+    group.bench_with_input(
+        BenchmarkId::from_parameter(num_files),
+        &num_files,
+        |b, num_files| {
+            b.iter_with_large_drop(|| {
+                let dir = tempdir().unwrap();
+
+                GeneratorBuilder::default()
+                    .root_dir(dir.path().to_path_buf())
+                    .num_files(NonZeroUsize::new(*num_files as usize).unwrap())
+                    .max_depth(1)
+                    .file_to_dir_ratio(NonZeroUsize::new(1).unwrap())
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap();
+
+                dir
+            })
+        },
+    );
+}
+
 fn dense_generate(c: &mut Criterion) {
     let mut group = c.benchmark_group("dense_generate");
 
@@ -206,16 +321,52 @@ fn bytes_generate(c: &mut Criterion) {
     }
 }
 
+fn large_files_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_files_generate");
+    group.sample_size(10);
+
+    // Files of a megabyte or more span many chunks, so they are written through vectored writes
+    for num_bytes in [10_000_000, 100_000_000] {
+        group.throughput(Throughput::Bytes(num_bytes));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_bytes),
+            &num_bytes,
+            |b, num_bytes| {
+                b.iter_with_large_drop(|| {
+                    let dir = tempdir().unwrap();
+
+                    GeneratorBuilder::default()
+                        .root_dir(dir.path().to_path_buf())
+                        .num_files(NonZeroUsize::new(10).unwrap())
+                        .max_depth(0)
+                        .num_bytes(*num_bytes as usize)
+                        .build()
+                        .unwrap()
+                        .generate()
+                        .unwrap();
+
+                    dir
+                })
+            },
+        );
+    }
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().noise_threshold(0.005).warm_up_time(Duration::from_secs(1));
     targets =
+    chain_generate,
     deep_generate,
+    deep_generate_openat,
+    deep_generate_batched,
     dense_generate,
     huge_generate,
     shallow_generate,
     simple_generate,
     sparse_generate,
+    wide_generate,
     bytes_generate,
+    large_files_generate,
 }
 criterion_main!(benches);
@@ -0,0 +1,404 @@
+use std::{
+    cmp::min,
+    io,
+    io::{BufWriter, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The kind of archive generated files are wrapped in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    /// A POSIX ustar archive.
+    Tar,
+    /// A zip archive whose entries are stored uncompressed.
+    Zip,
+}
+
+impl ArchiveFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Tar => "tar",
+            Self::Zip => "zip",
+        }
+    }
+}
+
+/// Turns each file into an archive whose entries share the file's contents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: ArchiveFormat,
+    pub inner_entries: usize,
+    /// The number of archives wrapped around the entries, each one being the only entry of the
+    /// next.
+    pub nesting_depth: usize,
+}
+
+/// Splits the bytes written to it across the entries of an archive, writing the headers and
+/// trailers around them along the way. Entries are named after their index, with nested archives
+/// also getting the format's extension.
+pub struct ArchiveWriter<W: Write> {
+    out: Output<W>,
+    format: ArchiveFormat,
+    entries: usize,
+    nested: bool,
+    num_bytes: usize,
+    written: u64,
+
+    opened: usize,
+    open: bool,
+    remaining: usize,
+    crc: u32,
+    offset: u64,
+    central_directory: Vec<ZipEntry>,
+}
+
+/// Where an archive's bytes end up: either in the file or in the only entry of an outer archive.
+enum Output<W: Write> {
+    File(BufWriter<W>),
+    Entry(Box<ArchiveWriter<W>>),
+}
+
+struct ZipEntry {
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(out: W, archive: Archive, num_bytes: usize) -> Self {
+        let Archive {
+            format,
+            inner_entries,
+            nesting_depth,
+        } = archive;
+
+        // Each outer archive's entry must be sized up front, so the sizes are computed inside out
+        let mut sizes = Vec::with_capacity(nesting_depth);
+        let mut size = num_bytes;
+        for level in 0..nesting_depth.saturating_sub(1) {
+            size = if level == 0 {
+                archive_size(format, inner_entries, false, size)
+            } else {
+                archive_size(format, 1, true, size)
+            };
+            sizes.push(size);
+        }
+
+        let mut out = Output::File(BufWriter::new(out));
+        for size in sizes.into_iter().rev() {
+            out = Output::Entry(Box::new(Self::level(out, format, 1, true, size)));
+        }
+        Self::level(out, format, inner_entries, false, num_bytes)
+    }
+
+    fn level(
+        out: Output<W>,
+        format: ArchiveFormat,
+        entries: usize,
+        nested: bool,
+        num_bytes: usize,
+    ) -> Self {
+        Self {
+            out,
+            format,
+            entries,
+            nested,
+            num_bytes,
+            written: 0,
+
+            opened: 0,
+            open: false,
+            remaining: 0,
+            crc: 0,
+            offset: 0,
+            central_directory: Vec::new(),
+        }
+    }
+
+    /// Writes out any remaining empty entries and the archive's trailer, along with those of the
+    /// archives it is nested in.
+    pub fn finish(self) -> io::Result<()> {
+        let mut archive = self;
+        loop {
+            archive.advance()?;
+            match archive.format {
+                ArchiveFormat::Tar => archive.put(&[0; 1024])?,
+                ArchiveFormat::Zip => archive.write_zip_central_directory()?,
+            }
+            match archive.out {
+                Output::File(mut out) => return out.flush(),
+                Output::Entry(outer) => archive = *outer,
+            }
+        }
+    }
+
+    fn entry_size(&self, entry: usize) -> usize {
+        entry_size(self.entries, self.num_bytes, entry)
+    }
+
+    fn entry_name(&self, entry: usize) -> String {
+        entry_name(self.format, self.nested, entry)
+    }
+
+    /// Moves on to the next entry that still has room for bytes, if any.
+    fn advance(&mut self) -> io::Result<()> {
+        loop {
+            if self.open {
+                if self.remaining > 0 {
+                    return Ok(());
+                }
+                self.close_entry()?;
+            }
+            if self.opened == self.entries {
+                return Ok(());
+            }
+            self.open_entry()?;
+        }
+    }
+
+    fn open_entry(&mut self) -> io::Result<()> {
+        let size = self.entry_size(self.opened);
+        let name = self.entry_name(self.opened);
+        match self.format {
+            ArchiveFormat::Tar => {
+                if size as u64 >= 1 << 33 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "The file is too large to be a tar archive",
+                    ));
+                }
+                let header = tar_header(&name, size);
+                self.put(&header)?;
+            }
+            ArchiveFormat::Zip => {
+                self.offset = self.written;
+                self.crc = 0;
+                let mut header = Vec::with_capacity(30 + name.len());
+                header.extend(0x0403_4b50u32.to_le_bytes());
+                zip_entry_info(&mut header);
+                // The CRC and sizes follow the data in a descriptor
+                header.extend([0; 12]);
+                header.extend((name.len() as u16).to_le_bytes());
+                header.extend(0u16.to_le_bytes());
+                header.extend(name.as_bytes());
+                self.put(&header)?;
+            }
+        }
+
+        self.opened += 1;
+        self.open = true;
+        self.remaining = size;
+        Ok(())
+    }
+
+    fn close_entry(&mut self) -> io::Result<()> {
+        let size = self.entry_size(self.opened - 1);
+        match self.format {
+            ArchiveFormat::Tar => {
+                let padding = (512 - size % 512) % 512;
+                self.put(&[0; 512][..padding])?;
+            }
+            ArchiveFormat::Zip => {
+                let entry = ZipEntry {
+                    crc: self.crc,
+                    size: zip_u32(size as u64)?,
+                    offset: zip_u32(self.offset)?,
+                };
+                let mut descriptor = Vec::with_capacity(16);
+                descriptor.extend(0x0807_4b50u32.to_le_bytes());
+                descriptor.extend(entry.crc.to_le_bytes());
+                descriptor.extend(entry.size.to_le_bytes());
+                descriptor.extend(entry.size.to_le_bytes());
+                self.put(&descriptor)?;
+                self.central_directory.push(entry);
+            }
+        }
+
+        self.open = false;
+        Ok(())
+    }
+
+    fn write_zip_central_directory(&mut self) -> io::Result<()> {
+        let start = self.written;
+        let mut record = Vec::new();
+        for (i, entry) in self.central_directory.iter().enumerate() {
+            let name = self.entry_name(i);
+            record.extend(0x0201_4b50u32.to_le_bytes());
+            // Version made by
+            record.extend(20u16.to_le_bytes());
+            zip_entry_info(&mut record);
+            record.extend(entry.crc.to_le_bytes());
+            record.extend(entry.size.to_le_bytes());
+            record.extend(entry.size.to_le_bytes());
+            record.extend((name.len() as u16).to_le_bytes());
+            // Extra field and comment lengths, disk number, and attributes
+            record.extend([0; 12]);
+            record.extend(entry.offset.to_le_bytes());
+            record.extend(name.as_bytes());
+        }
+        let num_entries = u16::try_from(self.central_directory.len())
+            .map_err(|_| zip_too_large())?
+            .to_le_bytes();
+        let size = zip_u32(record.len() as u64)?;
+        let start = zip_u32(start)?;
+
+        record.extend(0x0605_4b50u32.to_le_bytes());
+        record.extend([0; 4]);
+        record.extend(num_entries);
+        record.extend(num_entries);
+        record.extend(size.to_le_bytes());
+        record.extend(start.to_le_bytes());
+        record.extend([0; 2]);
+        self.put(&record)
+    }
+
+    fn put(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.out.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(out) => out.write(buf),
+            Self::Entry(outer) => outer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(out) => out.flush(),
+            Self::Entry(outer) => outer.flush(),
+        }
+    }
+}
+
+impl<W: Write> Write for ArchiveWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.advance()?;
+        let used = min(buf.len(), self.remaining);
+        if used == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "Wrote past the end of the archive",
+            ));
+        }
+
+        self.put(&buf[..used])?;
+        if self.format == ArchiveFormat::Zip {
+            self.crc = crc32(self.crc, &buf[..used]);
+        }
+        self.remaining -= used;
+        Ok(used)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn entry_size(entries: usize, num_bytes: usize, entry: usize) -> usize {
+    num_bytes / entries + usize::from(entry < num_bytes % entries)
+}
+
+fn entry_name(format: ArchiveFormat, nested: bool, entry: usize) -> String {
+    if nested {
+        format!("{}.{}", entry, format.extension())
+    } else {
+        entry.to_string()
+    }
+}
+
+/// The number of bytes an archive splitting `num_bytes` across its entries takes up, which must
+/// match what [ArchiveWriter] writes byte for byte.
+fn archive_size(format: ArchiveFormat, entries: usize, nested: bool, num_bytes: usize) -> usize {
+    let entries_size = (0..entries)
+        .map(|entry| {
+            let size = entry_size(entries, num_bytes, entry);
+            let name = entry_name(format, nested, entry).len();
+            match format {
+                ArchiveFormat::Tar => 512 + (size + 511) / 512 * 512,
+                // Local header, data descriptor, and central directory record
+                ArchiveFormat::Zip => 30 + name + size + 16 + 46 + name,
+            }
+        })
+        .sum::<usize>();
+    entries_size
+        + match format {
+            ArchiveFormat::Tar => 1024,
+            ArchiveFormat::Zip => 22,
+        }
+}
+
+fn tar_header(name: &str, size: usize) -> [u8; 512] {
+    let mut header = [0; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..107].copy_from_slice(b"0000644");
+    header[108..115].copy_from_slice(b"0000000");
+    header[116..123].copy_from_slice(b"0000000");
+    header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+    header[136..147].copy_from_slice(b"00000000000");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed as though its own field were blank
+    header[148..156].copy_from_slice(b"        ");
+    let checksum = header.iter().map(|&b| u32::from(b)).sum::<u32>();
+    header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+    header
+}
+
+/// The fields shared by local and central zip headers, from the version needed to extract up to
+/// the modification date.
+fn zip_entry_info(header: &mut Vec<u8>) {
+    // Version needed to extract
+    header.extend(20u16.to_le_bytes());
+    // Sizes and CRC are in a data descriptor
+    header.extend(0x0008u16.to_le_bytes());
+    // Stored
+    header.extend(0u16.to_le_bytes());
+    // Midnight on 1980-01-01, the earliest representable time
+    header.extend(0u16.to_le_bytes());
+    header.extend(0x0021u16.to_le_bytes());
+}
+
+fn zip_u32(n: u64) -> io::Result<u32> {
+    u32::try_from(n).map_err(|_| zip_too_large())
+}
+
+fn zip_too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "The file is too large to be a zip archive",
+    )
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < table.len() {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                0xEDB8_8320 ^ (crc >> 1)
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32(crc: u32, buf: &[u8]) -> u32 {
+    !buf.iter().fold(!crc, |crc, &b| {
+        CRC32_TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
@@ -0,0 +1,129 @@
+use std::{
+    cmp::max,
+    fs,
+    io::ErrorKind::NotFound,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context};
+use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use serde::{Deserialize, Serialize};
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What's persisted in the checkpoint file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Saved {
+    /// Identifies the configuration the checkpoint was written for.
+    fingerprint: u64,
+    /// The number of tasks that completed, counted in the order they were queued.
+    tasks: usize,
+}
+
+/// Records how far generation got such that an interrupted run can be restarted without redoing
+/// its work. Generation is deterministic, so a restarted run queues the same tasks in the same
+/// order and only needs to know how many of them completed. Tasks are awaited in order, so those
+/// form a prefix of the queue.
+#[derive(Debug)]
+pub struct Checkpoint {
+    file: PathBuf,
+    fingerprint: u64,
+    /// The tasks completed by previous runs, which this one skips.
+    resumed: usize,
+    queued: AtomicUsize,
+    completed: AtomicUsize,
+    last_saved: Mutex<Instant>,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint in `file`, starting from scratch if there isn't one yet.
+    pub fn load(file: PathBuf, fingerprint: u64) -> CliResult<Self> {
+        let resumed = match fs::read(&file) {
+            Err(e) if e.kind() == NotFound => 0,
+            contents => {
+                let saved = contents
+                    .with_context(|| format!("Failed to read checkpoint {:?}", file))
+                    .with_code(exitcode::IOERR)
+                    .and_then(|contents| {
+                        serde_json::from_slice::<Saved>(&contents)
+                            .with_context(|| format!("Malformed checkpoint {:?}", file))
+                            .with_code(exitcode::DATAERR)
+                    })?;
+                if saved.fingerprint != fingerprint {
+                    return Err(anyhow!(format!(
+                        "The checkpoint {:?} was written for a different configuration.",
+                        file,
+                    )))
+                    .with_code(exitcode::DATAERR);
+                }
+                saved.tasks
+            }
+        };
+
+        Ok(Self {
+            file,
+            fingerprint,
+            resumed,
+            queued: AtomicUsize::new(0),
+            completed: AtomicUsize::new(0),
+            last_saved: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// The number of tasks previous runs completed.
+    pub fn resumed(&self) -> usize {
+        self.resumed
+    }
+
+    /// Claims the next queued task, returning whether a previous run already completed it.
+    pub fn skip_next(&self) -> bool {
+        self.queued.fetch_add(1, Ordering::Relaxed) < self.resumed
+    }
+
+    /// Records the completion of the oldest outstanding task, saving the checkpoint if it hasn't
+    /// been in a while.
+    pub fn complete_task(&self) -> CliResult<()> {
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut last_saved = self.last_saved.lock().unwrap();
+        if last_saved.elapsed() < SAVE_INTERVAL {
+            return Ok(());
+        }
+
+        *last_saved = Instant::now();
+        self.save(completed)
+    }
+
+    /// Writes the checkpoint, replacing the previous one atomically such that an interruption
+    /// mid-write can't corrupt it.
+    pub fn save(&self, completed: usize) -> CliResult<()> {
+        let saved = serde_json::to_vec(&Saved {
+            fingerprint: self.fingerprint,
+            // Skipped tasks are only counted once awaited, so don't fall behind the previous run
+            tasks: max(completed, self.resumed),
+        })
+        .context("Failed to serialize checkpoint")
+        .with_code(exitcode::SOFTWARE)?;
+
+        let mut tmp = self.file.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, saved)
+            .and_then(|()| fs::rename(&tmp, &self.file))
+            .with_context(|| format!("Failed to write checkpoint {:?}", self.file))
+            .with_code(exitcode::IOERR)
+    }
+
+    /// Deletes the checkpoint once the tree is complete.
+    pub fn finish(&self) -> CliResult<()> {
+        match fs::remove_file(&self.file) {
+            Err(e) if e.kind() == NotFound => Ok(()),
+            r => r,
+        }
+        .with_context(|| format!("Failed to delete checkpoint {:?}", self.file))
+        .with_code(exitcode::IOERR)
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    collections::HashSet,
+    fs::{create_dir, File},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use rand::RngCore;
+
+use crate::core::{
+    file_contents::{random_bytes_digest, write_random_bytes},
+    files::{EntryKind, PlanSender},
+    scheduler::GeneratorStats,
+};
+
+/// How many times a file's contents are redrawn before giving up on it when they keep matching
+/// those of earlier files. Only the smallest files have few enough possible contents for that.
+const MAX_REDRAWS: usize = 16;
+
+/// Names files after the SHA-256 of their contents in a `.git/objects` style layout: the first
+/// two hex digits of the hash name a directory and the rest name the file within it. Files that
+/// turn out to have the same contents as an earlier one are redrawn, and only left out once
+/// redrawing keeps failing, as it always does for empty files.
+pub fn create_content_addressed<R: RngCore + Clone>(
+    root_dir: &Path,
+    byte_counts: &[usize],
+    random: &mut R,
+    plan: Option<&PlanSender>,
+) -> CliResult<GeneratorStats> {
+    let mut stats = GeneratorStats::default();
+    let mut dirs_created = [false; 256];
+    let mut hashes = HashSet::with_capacity(byte_counts.len());
+    'files: for &num_bytes in byte_counts {
+        // The file's location depends on its contents, so they are hashed up front and then
+        // replayed from the same random state when the file is written.
+        let mut redraws = 0;
+        let (hash, mut replay) = loop {
+            let replay = random.clone();
+            let hash = random_bytes_digest(num_bytes, random);
+            if hashes.insert(hash) {
+                break (hash, replay);
+            }
+            if num_bytes == 0 || redraws == MAX_REDRAWS {
+                continue 'files;
+            }
+            redraws += 1;
+        };
+
+        let hex = hash.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let dir = root_dir.join(&hex[..2]);
+        let file = dir.join(&hex[2..]);
+        if !mem::replace(&mut dirs_created[usize::from(hash[0])], true) {
+            if let Some(plan) = plan {
+                emit(plan, dir, EntryKind::Dir)?;
+            } else {
+                create_dir(&dir)
+                    .with_context(|| format!("Failed to create directory {:?}", dir))
+                    .with_code(exitcode::IOERR)?;
+            }
+            stats.dirs += 1;
+        }
+        if let Some(plan) = plan {
+            emit(plan, file, EntryKind::File { bytes: num_bytes })?;
+        } else {
+            File::create(&file)
+                .and_then(|f| write_random_bytes(f, num_bytes, &mut replay, None, None, None))
+                .with_context(|| format!("Failed to create file {:?}", file))
+                .with_code(exitcode::IOERR)?;
+        }
+        stats.files += 1;
+        stats.bytes += num_bytes;
+    }
+    Ok(stats)
+}
+
+fn emit(plan: &PlanSender, path: PathBuf, kind: EntryKind) -> CliResult<()> {
+    plan.send((path, kind))
+        .context("Nobody is listening for planned paths")
+        .with_code(exitcode::SOFTWARE)
+}
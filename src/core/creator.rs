@@ -0,0 +1,163 @@
+use std::{
+    fs::{create_dir_all, File},
+    io,
+    path::Path,
+};
+
+use crate::utils::FastPathBuf;
+
+/// Creates files and directories either by their full path or, when a directory handle is
+/// available, relative to that directory. The latter saves the kernel from resolving the entire
+/// path on every call which adds up in deep trees.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FileCreator<'a> {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    dir: Option<&'a File>,
+}
+
+impl<'a> FileCreator<'a> {
+    pub fn new(dir: Option<&'a File>) -> Self {
+        Self { dir }
+    }
+
+    /// Creates a file to be written to, truncating it if it already exists.
+    #[inline]
+    pub fn create(self, file: &mut FastPathBuf) -> io::Result<File> {
+        #[cfg(unix)]
+        if let Some(dir) = self.dir {
+            use std::os::unix::io::{AsRawFd, FromRawFd};
+
+            use nix::{
+                fcntl::{openat, OFlag},
+                sys::stat::Mode,
+            };
+
+            let cstr = file.to_cstr_mut();
+            return openat(
+                dir.as_raw_fd(),
+                cstr.file_name(),
+                OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC | OFlag::O_CLOEXEC,
+                Mode::from_bits_truncate(0o666),
+            )
+            .map(|fd| unsafe { File::from_raw_fd(fd) })
+            .map_err(io::Error::from);
+        }
+
+        File::create(file)
+    }
+
+    /// Creates an empty file without opening it, truncating it if it already exists.
+    #[inline]
+    pub fn create_empty(self, file: &mut FastPathBuf) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            use nix::{
+                errno::Errno,
+                sys::stat::{mknod, mknodat, Mode, SFlag},
+            };
+
+            let mode =
+                Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IWGRP | Mode::S_IROTH;
+            let cstr = file.to_cstr_mut();
+            let result = match self.dir {
+                Some(dir) => mknodat(dir.as_raw_fd(), cstr.file_name(), SFlag::S_IFREG, mode, 0),
+                None => mknod(&*cstr, SFlag::S_IFREG, mode, 0),
+            };
+            // Only happens when picking up after an interrupted run, so take the slow path
+            match result {
+                Err(Errno::EEXIST) => self.create(file).map(|_| ()),
+                r => r.map_err(io::Error::from),
+            }
+        }
+        // mknod can't create regular files everywhere, so fall back to opening them
+        #[cfg(not(target_os = "linux"))]
+        self.create(file).map(|_| ())
+    }
+
+    /// Creates a named pipe.
+    #[inline]
+    pub fn create_fifo(self, file: &mut FastPathBuf) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            use nix::{
+                sys::stat::Mode,
+                unistd::{mkfifo, mkfifoat},
+            };
+
+            let mode =
+                Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IWGRP | Mode::S_IROTH;
+            let cstr = file.to_cstr_mut();
+            match self.dir {
+                Some(dir) => mkfifoat(Some(dir.as_raw_fd()), cstr.file_name(), mode),
+                None => mkfifo(&*cstr, mode),
+            }
+            .map_err(io::Error::from)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = file;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "FIFOs are only supported on Linux",
+            ))
+        }
+    }
+
+    /// Creates a symbolic link pointing to target.
+    #[inline]
+    pub fn create_symlink(self, file: &mut FastPathBuf, target: &Path) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(dir) = self.dir {
+            use std::os::unix::io::AsRawFd;
+
+            use nix::unistd::symlinkat;
+
+            let cstr = file.to_cstr_mut();
+            return symlinkat(target, Some(dir.as_raw_fd()), cstr.file_name())
+                .map_err(io::Error::from);
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(target, file)
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (file, target);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Symlinks are only supported on Unix",
+            ))
+        }
+    }
+
+    /// Creates a directory, succeeding if it already exists.
+    #[inline]
+    pub fn create_dir(self, dir: &mut FastPathBuf) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(parent) = self.dir {
+            use std::os::unix::io::AsRawFd;
+
+            use nix::{
+                errno::Errno,
+                sys::stat::{mkdirat, Mode},
+            };
+
+            let cstr = dir.to_cstr_mut();
+            return match mkdirat(
+                parent.as_raw_fd(),
+                cstr.file_name(),
+                Mode::S_IRWXU | Mode::S_IRWXG | Mode::S_IRWXO,
+            ) {
+                Ok(()) | Err(Errno::EEXIST) => Ok(()),
+                Err(e) => Err(io::Error::from(e)),
+            };
+        }
+
+        create_dir_all(dir)
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{read_link, File, FileType},
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use sha2::{Digest, Sha256};
+
+/// The differences between two trees, with paths relative to their roots.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// Paths that only exist in the left tree.
+    pub missing: Vec<PathBuf>,
+    /// Paths that only exist in the right tree.
+    pub extra: Vec<PathBuf>,
+    /// Paths that exist in both trees but differ in type, contents, or symlink target.
+    pub mismatched: Vec<PathBuf>,
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Walks both trees side by side, only descending into directories present in both and only
+/// hashing files present in both. Symlinks are compared by target rather than followed and other
+/// special files are only compared by type since reading them could block.
+pub fn diff_trees(left: &Path, right: &Path) -> CliResult<TreeDiff> {
+    let mut diff = TreeDiff::default();
+
+    let mut queue = VecDeque::from([PathBuf::new()]);
+    while let Some(dir) = queue.pop_front() {
+        let mut right_entries = list_dir(&right.join(&dir))?;
+        for (name, left_type) in list_dir(&left.join(&dir))? {
+            let path = dir.join(&name);
+            let right_type = match right_entries.remove(&name) {
+                None => {
+                    diff.missing.push(path);
+                    continue;
+                }
+                Some(t) => t,
+            };
+
+            if left_type.is_dir() && right_type.is_dir() {
+                queue.push_back(path);
+            } else if !same_contents(
+                &left.join(&path),
+                left_type,
+                &right.join(&path),
+                right_type,
+            )? {
+                diff.mismatched.push(path);
+            }
+        }
+        diff.extra.extend(right_entries.into_keys().map(|name| dir.join(name)));
+    }
+
+    diff.missing.sort();
+    diff.extra.sort();
+    diff.mismatched.sort();
+    Ok(diff)
+}
+
+fn list_dir(dir: &Path) -> CliResult<BTreeMap<PathBuf, FileType>> {
+    let mut entries = BTreeMap::new();
+    for entry in dir
+        .read_dir()
+        .with_context(|| format!("Failed to read directory {:?}", dir))
+        .with_code(exitcode::IOERR)?
+    {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?;
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("Failed to stat {:?}", entry.path()))
+            .with_code(exitcode::IOERR)?;
+        entries.insert(PathBuf::from(entry.file_name()), file_type);
+    }
+    Ok(entries)
+}
+
+fn same_contents(
+    left: &Path,
+    left_type: FileType,
+    right: &Path,
+    right_type: FileType,
+) -> CliResult<bool> {
+    if left_type != right_type {
+        return Ok(false);
+    }
+
+    if left_type.is_file() {
+        let (left_len, right_len) = (file_len(left)?, file_len(right)?);
+        Ok(left_len == right_len && file_digest(left)? == file_digest(right)?)
+    } else if left_type.is_symlink() {
+        Ok(link_target(left)? == link_target(right)?)
+    } else {
+        Ok(true)
+    }
+}
+
+fn file_len(file: &Path) -> CliResult<u64> {
+    file.metadata()
+        .map(|metadata| metadata.len())
+        .with_context(|| format!("Failed to stat {:?}", file))
+        .with_code(exitcode::IOERR)
+}
+
+fn file_digest(file: &Path) -> CliResult<[u8; 32]> {
+    let mut digest = Sha256::new();
+    File::open(file)
+        .and_then(|mut f| io::copy(&mut f, &mut digest))
+        .with_context(|| format!("Failed to read file {:?}", file))
+        .with_code(exitcode::IOERR)?;
+    Ok(digest.finalize().into())
+}
+
+fn link_target(link: &Path) -> CliResult<PathBuf> {
+    read_link(link)
+        .with_context(|| format!("Failed to read symlink {:?}", link))
+        .with_code(exitcode::IOERR)
+}
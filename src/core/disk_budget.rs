@@ -0,0 +1,68 @@
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Caps the disk space taken up by the tree, counting whatever was already in it, such that
+/// generation halts once the space is used up. Usage is estimated by rounding every file up to
+/// whole blocks and charging a block for each directory.
+#[derive(Debug)]
+pub struct DiskBudget {
+    limit: usize,
+    block_size: NonZeroUsize,
+    used: AtomicUsize,
+    exhausted: AtomicBool,
+}
+
+impl DiskBudget {
+    pub fn new(limit: usize, block_size: NonZeroUsize, used: usize) -> Self {
+        Self {
+            limit,
+            block_size,
+            used: AtomicUsize::new(used),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// The space taken up by a file of `bytes` bytes.
+    pub fn file_usage(&self, bytes: u64) -> usize {
+        let block_size = self.block_size.get();
+        usize::try_from(bytes)
+            .unwrap_or(usize::MAX)
+            .div_ceil(block_size)
+            .saturating_mul(block_size)
+    }
+
+    /// The space taken up by a directory.
+    pub fn dir_usage(&self) -> usize {
+        self.block_size.get()
+    }
+
+    /// Claims `usage` bytes of disk space, halting generation instead if they don't fit.
+    pub fn charge(&self, usage: usize) -> bool {
+        if self.exhausted() {
+            return false;
+        }
+
+        let fits = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(usage).filter(|&used| used <= self.limit)
+            })
+            .is_ok();
+        if !fits {
+            self.exhausted.store(true, Ordering::Relaxed);
+        }
+        fits
+    }
+
+    /// Whether generation has been halted for running out of space.
+    pub fn exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// The space claimed so far.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+}
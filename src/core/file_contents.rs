@@ -1,18 +1,74 @@
-use std::{cmp::min, fs::File, io, io::Write, mem::MaybeUninit};
+use std::{
+    cmp::min,
+    fmt,
+    fs,
+    fs::File,
+    io,
+    io::{IoSlice, Seek, SeekFrom, Write},
+    mem::MaybeUninit,
+    num::NonZeroUsize,
+    path::Path,
+    sync::Arc,
+};
 
-use rand::{distributions::Distribution, RngCore};
+use anyhow::{anyhow, Context};
+use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use rand::{distributions::Distribution, Rng, RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
 use tracing::instrument;
 
-use crate::utils::FastPathBuf;
+use crate::{
+    core::{
+        archive::{Archive, ArchiveWriter},
+        creator::FileCreator,
+        media::{MediaFormat, MediaWriter},
+        rate_limit::RateLimiter,
+        write_budget::WriteBudget,
+    },
+    utils::FastPathBuf,
+};
+
+/// How the contents of a created file are written.
+pub struct WriteOptions<'a> {
+    /// Fill the file by tiling this pattern instead of with random bytes.
+    pub pattern: Option<&'a [u8]>,
+    /// Replace all but this fraction of the file's random bytes with zeros.
+    pub entropy: Option<f64>,
+    /// Wrap the file's contents in an archive.
+    pub archive: Option<Archive>,
+    /// Wrap the file's contents in a media file.
+    pub media: Option<MediaFormat>,
+    /// Overwrite the first bytes of the file's contents with these bytes.
+    pub header: Option<&'a [u8]>,
+    /// Extend files smaller than the header such that it's never cut short.
+    pub pad_header: bool,
+    /// Extend the file to its size without writing anything, leaving it sparse.
+    pub sparse: bool,
+    /// Write some of a sparse file's blocks, leaving holes between them.
+    pub holes: bool,
+    /// Reserve the file's disk space up front before writing its contents.
+    pub preallocate: bool,
+    /// Throttle the bytes written to the file.
+    pub byte_rate: Option<&'a RateLimiter>,
+    /// Hold the bytes buffered for the file against this budget while writing it.
+    pub memory_budget: Option<&'a WriteBudget>,
+    /// Feed the file's contents into this digest.
+    pub digest: Option<&'a mut Sha256>,
+}
 
 pub trait FileContentsGenerator {
     fn create_file(
         &mut self,
         file: &mut FastPathBuf,
+        creator: FileCreator,
         file_num: usize,
         retryable: bool,
+        write: WriteOptions,
     ) -> io::Result<usize>;
 
+    /// Advances the generator past a file as if it had been created, returning its size.
+    fn skip_file(&mut self, file_num: usize, retryable: bool) -> usize;
+
     fn byte_counts_pool_return(self) -> Option<Vec<usize>>;
 }
 
@@ -20,22 +76,20 @@ pub struct NoGeneratedFileContents;
 
 impl FileContentsGenerator for NoGeneratedFileContents {
     #[inline]
-    fn create_file(&mut self, file: &mut FastPathBuf, _: usize, _: bool) -> io::Result<usize> {
-        #[cfg(target_os = "linux")]
-        {
-            use nix::sys::stat::{mknod, Mode, SFlag};
-            let cstr = file.to_cstr_mut();
-            mknod(
-                &*cstr,
-                SFlag::S_IFREG,
-                Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IWGRP | Mode::S_IROTH,
-                0,
-            )
-            .map_err(io::Error::from)
-            .map(|_| 0)
-        }
-        #[cfg(not(target_os = "linux"))]
-        File::create(file).map(|_| 0)
+    fn create_file(
+        &mut self,
+        file: &mut FastPathBuf,
+        creator: FileCreator,
+        _: usize,
+        _: bool,
+        _: WriteOptions,
+    ) -> io::Result<usize> {
+        creator.create_empty(file).map(|_| 0)
+    }
+
+    #[inline]
+    fn skip_file(&mut self, _: usize, _: bool) -> usize {
+        0
     }
 
     fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
@@ -55,12 +109,20 @@ impl<D: Distribution<f64>, R: RngCore> FileContentsGenerator
     fn create_file(
         &mut self,
         file: &mut FastPathBuf,
+        creator: FileCreator,
         file_num: usize,
         retryable: bool,
+        write: WriteOptions,
     ) -> io::Result<usize> {
         let num_bytes = self.num_bytes_distr.sample(&mut self.random).round() as usize;
-        if num_bytes > 0 || retryable {
-            File::create(file).and_then(|f| {
+        // Archives and media files are valid even when their contents are empty.
+        if num_bytes > 0
+            || retryable
+            || write.archive.is_some()
+            || write.media.is_some()
+            || write.pad_header
+        {
+            creator.create(file).and_then(|f| {
                 // To stay deterministic, we need to ensure `random` is mutated in exactly
                 // the same way regardless of whether or not creating the file fails and
                 // needs to be retried. To do this, we always run num_to_generate() twice
@@ -83,12 +145,229 @@ impl<D: Distribution<f64>, R: RngCore> FileContentsGenerator
                 } else {
                     num_bytes
                 };
-                write_random_bytes(f, num_bytes, &mut self.random)?;
-                Ok(num_bytes)
+                write_contents(f, num_bytes, &mut self.random, write)
             })
         } else {
-            NoGeneratedFileContents.create_file(file, file_num, retryable)
+            NoGeneratedFileContents.create_file(file, creator, file_num, retryable, write)
+        }
+    }
+
+    fn skip_file(&mut self, _: usize, retryable: bool) -> usize {
+        let mut num_bytes = self.num_bytes_distr.sample(&mut self.random).round() as usize;
+        if retryable {
+            num_bytes = self.num_bytes_distr.sample(&mut self.random).round() as usize;
+        }
+        skip_random_bytes(num_bytes, &mut self.random);
+        num_bytes
+    }
+
+    fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
+        None
+    }
+}
+
+/// Leaves every file past the first `first_empty` ones empty, delegating the others.
+pub struct EmptyTailFileContents<G> {
+    pub inner: G,
+    pub first_empty: usize,
+}
+
+impl<G: FileContentsGenerator> FileContentsGenerator for EmptyTailFileContents<G> {
+    #[inline]
+    fn create_file(
+        &mut self,
+        file: &mut FastPathBuf,
+        creator: FileCreator,
+        file_num: usize,
+        retryable: bool,
+        write: WriteOptions,
+    ) -> io::Result<usize> {
+        if file_num < self.first_empty {
+            self.inner.create_file(file, creator, file_num, retryable, write)
+        } else {
+            NoGeneratedFileContents.create_file(file, creator, file_num, retryable, write)
+        }
+    }
+
+    fn skip_file(&mut self, file_num: usize, retryable: bool) -> usize {
+        if file_num < self.first_empty {
+            self.inner.skip_file(file_num, retryable)
+        } else {
+            0
+        }
+    }
+
+    fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
+        self.inner.byte_counts_pool_return()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DuplicateGroups {
+    pub num_groups: NonZeroUsize,
+    pub seed: u64,
+}
+
+/// Assigns each file to one of a fixed number of groups where every file in a group has
+/// byte-identical contents.
+pub struct DuplicateGeneratedFileContents<D: Distribution<f64>, R: RngCore + SeedableRng> {
+    pub num_bytes_distr: D,
+    pub groups: DuplicateGroups,
+    pub random: R,
+}
+
+impl<D: Distribution<f64>, R: RngCore + SeedableRng> DuplicateGeneratedFileContents<D, R> {
+    fn next_group_random(&mut self) -> R {
+        let group = self.random.gen_range(0..self.groups.num_groups.get()) as u64;
+        R::seed_from_u64(
+            self.groups
+                .seed
+                .wrapping_add(group.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        )
+    }
+}
+
+impl<D: Distribution<f64>, R: RngCore + SeedableRng> FileContentsGenerator
+    for DuplicateGeneratedFileContents<D, R>
+{
+    #[inline]
+    fn create_file(
+        &mut self,
+        file: &mut FastPathBuf,
+        creator: FileCreator,
+        _: usize,
+        _: bool,
+        write: WriteOptions,
+    ) -> io::Result<usize> {
+        creator.create(file).and_then(|f| {
+            // Only touch `random` once the file exists so that retries stay deterministic.
+            let mut group_random = self.next_group_random();
+
+            let num_bytes = self.num_bytes_distr.sample(&mut group_random).round() as usize;
+            write_contents(f, num_bytes, &mut group_random, write)
+        })
+    }
+
+    fn skip_file(&mut self, _: usize, _: bool) -> usize {
+        // Group contents come from their own RNG, so only the group choice advances `random`.
+        let mut group_random = self.next_group_random();
+        self.num_bytes_distr.sample(&mut group_random).round() as usize
+    }
+
+    fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
+        None
+    }
+}
+
+/// The contents of every non-empty file found under a directory, held in memory.
+#[derive(Clone)]
+pub struct ContentSource {
+    samples: Arc<[Box<[u8]>]>,
+}
+
+impl ContentSource {
+    pub fn load(dir: &Path) -> CliResult<Self> {
+        let mut paths = Vec::new();
+        let mut queue = vec![dir.to_path_buf()];
+        while let Some(dir) = queue.pop() {
+            for entry in dir
+                .read_dir()
+                .with_context(|| format!("Failed to read directory {:?}", dir))
+                .with_code(exitcode::IOERR)?
+            {
+                let entry = entry
+                    .with_context(|| format!("Failed to read directory {:?}", dir))
+                    .with_code(exitcode::IOERR)?;
+                let file_type = entry
+                    .file_type()
+                    .with_context(|| format!("Failed to stat {:?}", entry.path()))
+                    .with_code(exitcode::IOERR)?;
+
+                if file_type.is_dir() {
+                    queue.push(entry.path());
+                } else if file_type.is_file() {
+                    paths.push(entry.path());
+                }
+            }
+        }
+        // Directory iteration order is unspecified, but sample choices must be reproducible
+        paths.sort_unstable();
+
+        let mut samples = Vec::with_capacity(paths.len());
+        for path in paths {
+            let sample = fs::read(&path)
+                .with_context(|| format!("Failed to read content source {:?}", path))
+                .with_code(exitcode::IOERR)?;
+            // Empty samples can't be tiled
+            if !sample.is_empty() {
+                samples.push(sample.into_boxed_slice());
+            }
+        }
+
+        if samples.is_empty() {
+            return Err(anyhow!(format!(
+                "The content source {:?} does not contain any non-empty files.",
+                dir,
+            )))
+            .with_code(exitcode::NOINPUT);
         }
+        Ok(Self {
+            samples: samples.into(),
+        })
+    }
+}
+
+impl fmt::Debug for ContentSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentSource")
+            .field("samples", &self.samples.len())
+            .finish()
+    }
+}
+
+/// Fills each file with a copy of a randomly chosen sample. Without a size distribution, samples
+/// are copied verbatim. Otherwise, they are truncated or tiled to the sampled size.
+pub struct SampledFileContents<D: Distribution<f64>, R: RngCore> {
+    pub num_bytes_distr: Option<D>,
+    pub source: ContentSource,
+    pub random: R,
+}
+
+impl<D: Distribution<f64>, R: RngCore> SampledFileContents<D, R> {
+    fn next_sample(&mut self) -> (usize, usize) {
+        let sample = self.random.gen_range(0..self.source.samples.len());
+        let num_bytes = match &self.num_bytes_distr {
+            Some(distr) => distr.sample(&mut self.random).round() as usize,
+            None => self.source.samples[sample].len(),
+        };
+        (sample, num_bytes)
+    }
+}
+
+impl<D: Distribution<f64>, R: RngCore> FileContentsGenerator for SampledFileContents<D, R> {
+    #[inline]
+    fn create_file(
+        &mut self,
+        file: &mut FastPathBuf,
+        creator: FileCreator,
+        _: usize,
+        _: bool,
+        write: WriteOptions,
+    ) -> io::Result<usize> {
+        creator.create(file).and_then(|f| {
+            // Only touch `random` once the file exists so that retries stay deterministic.
+            let (sample, num_bytes) = self.next_sample();
+            write_sample(
+                Throttled::new(Digesting::new(f, write.digest), write.byte_rate),
+                &self.source.samples[sample],
+                num_bytes,
+            )?;
+            Ok(num_bytes)
+        })
+    }
+
+    fn skip_file(&mut self, _: usize, _: bool) -> usize {
+        self.next_sample().1
     }
 
     fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
@@ -106,35 +385,421 @@ impl<R: RngCore> FileContentsGenerator for PreDefinedGeneratedFileContents<R> {
     fn create_file(
         &mut self,
         file: &mut FastPathBuf,
+        creator: FileCreator,
         file_num: usize,
         retryable: bool,
+        write: WriteOptions,
     ) -> io::Result<usize> {
         let num_bytes = self.byte_counts[file_num];
-        if num_bytes > 0 {
-            File::create(file)
-                .and_then(|f| write_random_bytes(f, num_bytes, &mut self.random))
-                .map(|_| num_bytes)
+        if num_bytes > 0 || write.archive.is_some() || write.media.is_some() || write.pad_header {
+            creator
+                .create(file)
+                .and_then(|f| write_contents(f, num_bytes, &mut self.random, write))
         } else {
-            NoGeneratedFileContents.create_file(file, file_num, retryable)
+            NoGeneratedFileContents.create_file(file, creator, file_num, retryable, write)
         }
     }
 
+    fn skip_file(&mut self, file_num: usize, _: bool) -> usize {
+        let num_bytes = self.byte_counts[file_num];
+        skip_random_bytes(num_bytes, &mut self.random);
+        num_bytes
+    }
+
     fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
         Some(self.byte_counts)
     }
 }
 
+/// Feeds everything written to a file into a digest along the way.
+struct Digesting<'a, W> {
+    inner: W,
+    digest: Option<&'a mut Sha256>,
+}
+
+impl<'a, W: Write> Digesting<'a, W> {
+    #[inline]
+    fn new(inner: W, digest: Option<&'a mut Sha256>) -> Self {
+        Self { inner, digest }
+    }
+}
+
+impl<W: Write> Write for Digesting<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(digest) = &mut self.digest {
+            digest.update(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let written = self.inner.write_vectored(bufs)?;
+        if let Some(digest) = &mut self.digest {
+            let mut left = written;
+            for buf in bufs {
+                let used = min(left, buf.len());
+                digest.update(&buf[..used]);
+                left -= used;
+            }
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Waits for permission to continue after every write such that bytes are written no faster than
+/// the limiter allows.
+struct Throttled<'a, W> {
+    inner: W,
+    limiter: Option<&'a RateLimiter>,
+}
+
+impl<'a, W: Write> Throttled<'a, W> {
+    #[inline]
+    fn new(inner: W, limiter: Option<&'a RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<W: Write> Write for Throttled<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(limiter) = self.limiter {
+            limiter.acquire_many(written);
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let written = self.inner.write_vectored(bufs)?;
+        if let Some(limiter) = self.limiter {
+            limiter.acquire_many(written);
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The size of the blocks holes are punched in, a multiple of every common file system block size
+/// such that holes are never partially allocated.
+const HOLE_BLOCK_LEN: usize = 64 * 1024;
+
+/// Seeks past some of the blocks written through it instead of writing them, leaving holes in the
+/// middle of the file. A block becomes a hole when its first random byte is even, so holes land in
+/// the same places on every run. The first and last blocks always hold data such that every hole
+/// is surrounded by data.
+struct Holey<W> {
+    inner: W,
+    offset: usize,
+    last_block: usize,
+    hole: bool,
+}
+
+impl<W: Write + Seek> Holey<W> {
+    #[inline]
+    fn new(inner: W, len: usize) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            last_block: len.saturating_sub(1) / HOLE_BLOCK_LEN,
+            hole: false,
+        }
+    }
+}
+
+impl<W: Write + Seek> Write for Holey<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let in_block = self.offset % HOLE_BLOCK_LEN;
+        if in_block == 0 {
+            let block = self.offset / HOLE_BLOCK_LEN;
+            self.hole = block != 0 && block != self.last_block && buf[0] % 2 == 0;
+        }
+
+        let used = min(buf.len(), HOLE_BLOCK_LEN - in_block);
+        if self.hole {
+            self.inner.seek(SeekFrom::Current(used as i64))?;
+        } else {
+            self.inner.write_all(&buf[..used])?;
+        }
+        self.offset += used;
+        Ok(used)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes `num` bytes of contents into `file` as asked for by the write options, returning the
+/// file's size.
+#[inline]
+fn write_contents(
+    file: File,
+    num: usize,
+    random: &mut impl RngCore,
+    write: WriteOptions,
+) -> io::Result<usize> {
+    let _permit = write
+        .memory_budget
+        .map(|budget| budget.acquire(min(num, CHUNK_LEN * CHUNKS_PER_WRITE)));
+    if write.sparse {
+        if write.holes {
+            // Seeking past the holes still consumes their randomness
+            write_random_bytes(Holey::new(&file, num), num, random, None, None, None)?;
+        } else {
+            // The contents are skipped rather than left out to keep the rest of the tree unchanged
+            skip_random_bytes(num, random);
+        }
+        return file.set_len(num as u64).map(|()| num);
+    }
+    if write.preallocate {
+        preallocate(&file, num)?;
+    }
+    let mut file = Throttled::new(Digesting::new(file, write.digest), write.byte_rate);
+    match (write.archive, write.media) {
+        (None, None) => {
+            write_random_bytes(
+                &mut file,
+                num,
+                random,
+                write.pattern,
+                write.entropy,
+                write.header,
+            )?;
+            match write.header {
+                // Written past the sampled size so the same randomness is consumed either way
+                Some(header) if write.pad_header && num < header.len() => {
+                    file.write_all(&header[num..])?;
+                    Ok(header.len())
+                }
+                _ => Ok(num),
+            }
+        }
+        (Some(archive), _) => {
+            // The entries are filled exactly like a plain file would have been to consume the
+            // same randomness.
+            let mut archive = ArchiveWriter::new(file, archive, num);
+            write_random_bytes(
+                &mut archive,
+                num,
+                random,
+                write.pattern,
+                write.entropy,
+                write.header,
+            )?;
+            archive.finish().map(|()| num)
+        }
+        (None, Some(format)) => {
+            let mut media = MediaWriter::new(file, format, num)?;
+            write_random_bytes(
+                &mut media,
+                num,
+                random,
+                write.pattern,
+                write.entropy,
+                write.header,
+            )?;
+            media.finish().map(|()| num)
+        }
+    }
+}
+
+/// Reserves `num` bytes of disk space for `file` such that writing its contents neither fragments
+/// it nor runs out of space halfway through. Elsewhere, blocks are simply allocated as they're
+/// written.
+#[inline]
+fn preallocate(file: &File, num: usize) -> io::Result<()> {
+    // Zero lengths are rejected
+    #[cfg(target_os = "linux")]
+    if num > 0 {
+        use std::os::unix::io::AsRawFd;
+
+        use nix::{fcntl::posix_fallocate, libc::off_t};
+
+        return posix_fallocate(file.as_raw_fd(), 0, num as off_t).map_err(io::Error::from);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (file, num);
+    Ok(())
+}
+
+/// The size of the chunks random bytes are generated in.
+const CHUNK_LEN: usize = 4096;
+/// The size of the blocks entropy is blended over: every block starts with its share of random
+/// bytes and is padded with zeros, which compressors collapse into back-references.
+const BLEND_BLOCK_LEN: usize = 256;
+/// The number of chunks handed to the file in a single vectored write. Large files thus need a
+/// fraction of the syscalls while small files still fit in a single chunk.
+const CHUNKS_PER_WRITE: usize = 16;
+
+/// Streams `num` random bytes into `file` through fixed size stack buffers, so at most
+/// `CHUNK_LEN * CHUNKS_PER_WRITE` bytes of a file are ever buffered at once.
+///
+/// With a pattern, the pattern is tiled into the file instead. Randomness is still consumed as
+/// though random bytes were written such that the generated tree and file sizes don't change. The
+/// same goes for an entropy, which zeroes all but that fraction of the bytes, and for the header,
+/// which overwrites the first bytes of the file.
 #[inline(never)] // Don't muck the stack for the GeneratedFileContents::None case
-#[instrument(level = "trace", skip(file, random))]
-fn write_random_bytes(mut file: File, mut num: usize, random: &mut impl RngCore) -> io::Result<()> {
+#[instrument(level = "trace", skip(file, random, pattern, header))]
+pub fn write_random_bytes(
+    mut file: impl Write,
+    mut num: usize,
+    random: &mut impl RngCore,
+    pattern: Option<&[u8]>,
+    entropy: Option<f64>,
+    header: Option<&[u8]>,
+) -> io::Result<()> {
+    let random_len = entropy.map(|entropy| (entropy * BLEND_BLOCK_LEN as f64).round() as usize);
     #[allow(clippy::uninit_assumed_init)] // u8s do nothing when dropped
-    let mut buf: [u8; 4096] = unsafe { MaybeUninit::uninit().assume_init() };
+    let mut bufs: [[u8; CHUNK_LEN]; CHUNKS_PER_WRITE] =
+        unsafe { MaybeUninit::uninit().assume_init() };
+    let mut written = 0;
     while num > 0 {
-        let used = min(num, buf.len());
-        random.fill_bytes(&mut buf[0..used]);
-        file.write_all(&buf[0..used])?;
+        let batch = min(num, CHUNK_LEN * CHUNKS_PER_WRITE);
+        let num_chunks = batch.div_ceil(CHUNK_LEN);
+        for (i, buf) in bufs[..num_chunks].iter_mut().enumerate() {
+            let start = i * CHUNK_LEN;
+            let used = min(batch - start, CHUNK_LEN);
+            let offset = written + start;
+            fill_chunk(&mut buf[..used], offset, random, pattern, random_len, header);
+        }
+
+        let mut slices = [IoSlice::new(&[]); CHUNKS_PER_WRITE];
+        for (i, (slice, buf)) in slices.iter_mut().zip(&bufs[..num_chunks]).enumerate() {
+            *slice = IoSlice::new(&buf[..min(batch - i * CHUNK_LEN, CHUNK_LEN)]);
+        }
+        file.write_all_vectored(&mut slices[..num_chunks])?;
+
+        num -= batch;
+        written += batch;
+    }
+    Ok(())
+}
 
+/// Fills a chunk starting `offset` bytes into the file with its random bytes, pattern, or header.
+/// With a `random_len`, only the first that many bytes of every blend block stay random.
+#[inline]
+fn fill_chunk(
+    chunk: &mut [u8],
+    offset: usize,
+    random: &mut impl RngCore,
+    pattern: Option<&[u8]>,
+    random_len: Option<usize>,
+    header: Option<&[u8]>,
+) {
+    random.fill_bytes(chunk);
+    if let Some(pattern) = pattern {
+        let tiled = pattern.iter().cycle().skip(offset % pattern.len());
+        for (byte, p) in chunk.iter_mut().zip(tiled) {
+            *byte = *p;
+        }
+    }
+    if let Some(random_len) = random_len {
+        for (i, byte) in chunk.iter_mut().enumerate() {
+            if (offset + i) % BLEND_BLOCK_LEN >= random_len {
+                *byte = 0;
+            }
+        }
+    }
+    if let Some(header) = header && offset < header.len() {
+        let end = min(header.len(), offset + chunk.len());
+        chunk[..end - offset].copy_from_slice(&header[offset..end]);
+    }
+}
+
+/// Writes the first `num` bytes of `sample`, repeating it as many times as necessary.
+fn write_sample(mut file: impl Write, sample: &[u8], mut num: usize) -> io::Result<()> {
+    while num > 0 {
+        let used = min(num, sample.len());
+        file.write_all(&sample[..used])?;
         num -= used;
     }
     Ok(())
 }
+
+/// Hashes the random bytes [write_random_bytes] would write without writing anything.
+pub fn random_bytes_digest(num: usize, random: &mut impl RngCore) -> [u8; 32] {
+    let mut digest = Sha256::new();
+    // Sinks never fail
+    let _ = write_random_bytes(
+        Digesting::new(io::sink(), Some(&mut digest)),
+        num,
+        random,
+        None,
+        None,
+        None,
+    );
+    digest.finalize().into()
+}
+
+/// Consumes exactly as much randomness as [write_random_bytes] would without writing anything.
+fn skip_random_bytes(num: usize, random: &mut impl RngCore) {
+    // Sinks never fail
+    let _ = write_random_bytes(io::sink(), num, random, None, None, None);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn preallocated_files_are_fully_allocated_before_being_written() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::create(dir.path().join("file")).unwrap();
+
+        preallocate(&file, 100_000).unwrap();
+
+        let metadata = file.metadata().unwrap();
+        assert_eq!(metadata.len(), 100_000);
+        assert!(metadata.blocks() * 512 >= metadata.len());
+    }
+
+    #[test]
+    fn large_files_are_written_in_full_through_vectored_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let num = 3 * CHUNK_LEN * CHUNKS_PER_WRITE + 123;
+        let mut digest = Sha256::new();
+
+        write_random_bytes(
+            Digesting::new(File::create(&path).unwrap(), Some(&mut digest)),
+            num,
+            &mut Xoshiro256PlusPlus::seed_from_u64(42),
+            None,
+            None,
+            Some(b"header"),
+        )
+        .unwrap();
+
+        let mut expected = vec![0; num];
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(42);
+        for chunk in expected.chunks_mut(CHUNK_LEN) {
+            random.fill_bytes(chunk);
+        }
+        expected[..6].copy_from_slice(b"header");
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        assert_eq!(digest.finalize(), Sha256::digest(&expected));
+    }
+}
@@ -1,114 +1,1263 @@
-use std::{fs::create_dir_all, io::ErrorKind::NotFound};
+use std::{
+    cmp::{max, min},
+    fs,
+    fs::{create_dir_all, File},
+    io::{ErrorKind::NotFound, Write},
+    num::NonZeroUsize,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::SyncSender,
+        Arc,
+    },
+};
 
 use anyhow::Context;
 use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use filetime::{set_file_times, set_symlink_file_times, FileTime};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tracing::{event, instrument, Level};
 
+#[cfg(feature = "metrics")]
+use crate::core::Progress;
 use crate::{
-    core::file_contents::FileContentsGenerator,
-    utils::{with_dir_name, with_file_name, FastPathBuf},
+    core::{
+        archive::Archive,
+        checkpoint::Checkpoint,
+        creator::FileCreator,
+        disk_budget::DiskBudget,
+        file_contents::{FileContentsGenerator, WriteOptions},
+        media::MediaFormat,
+        rate_limit::RateLimiter,
+        write_budget::WriteBudget,
+    },
+    utils::{FastPathBuf, Naming},
 };
 
+/// What a planned path will be once generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File { bytes: usize },
+    Dir,
+    Fifo,
+    /// A symlink to a sibling path that is never created.
+    BrokenSymlink { target: PathBuf },
+}
+
+/// The order in which a directory's subdirectories and files are created.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreationOrder {
+    /// All subdirectories before any files.
+    #[default]
+    DirsFirst,
+    /// All files before any subdirectories.
+    FilesFirst,
+    /// Alternate between a subdirectory and a file until one of them runs out.
+    Interleaved,
+}
+
+impl CreationOrder {
+    /// Splits a directory's entries into the consecutive batches they are created in.
+    fn batches(self, num_dirs: usize, num_files: usize) -> impl Iterator<Item = Batch> {
+        let (batch_size, rounds) = match self {
+            Self::Interleaved => (1, max(num_dirs, num_files)),
+            Self::DirsFirst | Self::FilesFirst => (usize::MAX, 1),
+        };
+        (0..rounds).flat_map(move |round| {
+            let start = round * batch_size;
+            let end = start.saturating_add(batch_size);
+            let dirs = Batch::Dirs(min(start, num_dirs)..min(end, num_dirs));
+            let files = Batch::Files(min(start, num_files)..min(end, num_files));
+            if self == Self::FilesFirst {
+                [files, dirs]
+            } else {
+                [dirs, files]
+            }
+        })
+    }
+}
+
+impl FromStr for CreationOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dirs-first" => Ok(Self::DirsFirst),
+            "files-first" => Ok(Self::FilesFirst),
+            "interleaved" => Ok(Self::Interleaved),
+            _ => Err(format!("Unknown creation order: {}", s)),
+        }
+    }
+}
+
+/// One of the passes of a two-phase run, which creates every directory before any file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    /// Only create directories.
+    Dirs,
+    /// Only create files, assuming all directories already exist.
+    Files,
+}
+
+enum Batch {
+    Dirs(Range<usize>),
+    Files(Range<usize>),
+}
+
+pub type PlanSender = SyncSender<(PathBuf, EntryKind)>;
+pub type ChecksumSender = SyncSender<(PathBuf, [u8; 32])>;
+pub type SampleSender = SyncSender<NodeSample>;
+
+/// The number of files and directories sampled for a directory as it was visited.
+#[derive(Debug)]
+pub struct NodeSample {
+    pub dir: PathBuf,
+    pub files: usize,
+    pub dirs: usize,
+}
+
+/// Options shared by every task.
+#[derive(Debug, Clone, Default)]
+pub struct TaskOptions {
+    pub naming: Naming,
+    /// Only create files and directories that don't already exist.
+    pub repair: bool,
+    /// Create entries relative to an open handle of their parent directory.
+    pub openat: bool,
+    /// The order in which each directory's entries are created.
+    pub order: CreationOrder,
+    /// Report the entries that would be created here instead of touching the file system.
+    pub plan: Option<PlanSender>,
+    /// Pin the access and modification times of created files.
+    pub fixed_time: Option<FileTime>,
+    /// Number files across the whole tree in the order their tasks are queued.
+    pub positions: Option<FilePositions>,
+    /// Give created files increasing access and modification times by position instead.
+    pub mtime_sequence: Option<MtimeSequence>,
+    /// Create some files as FIFOs or broken symlinks instead.
+    pub special: Option<SpecialFiles>,
+    /// Create some files sparse instead of writing their contents.
+    pub sparse: Option<SparseFiles>,
+    /// Reserve the disk space of files before writing them.
+    pub preallocate: bool,
+    /// Create files as media files of these formats.
+    pub media: Option<MediaFiles>,
+    /// Make some files read-only once written.
+    pub read_only: Option<ReadOnlyEntries>,
+    /// Give some files an extended attribute once written.
+    pub xattrs: Option<XattrFiles>,
+    /// Report the SHA-256 of every created file's contents here.
+    pub checksums: Option<ChecksumSender>,
+    /// Fill files by tiling this pattern instead of with random bytes.
+    pub pattern: Option<Arc<[u8]>>,
+    /// Replace all but this fraction of each file's random bytes with zeros.
+    pub entropy: Option<f64>,
+    /// Wrap the contents of files in an archive.
+    pub archive: Option<Archive>,
+    /// Overwrite the first bytes of files with these bytes.
+    pub header: Option<Arc<[u8]>>,
+    /// Start the contents of files with their position instead.
+    pub sequence_header: Option<SequenceHeader>,
+    /// Keep the sizes of files split out of an exact number of bytes multiples of this.
+    pub size_alignment: Option<NonZeroUsize>,
+    /// Log and count entries that can't be created instead of aborting.
+    pub continue_on_error: bool,
+    /// Throttle entry creations across all tasks.
+    pub rate_limit: Option<Arc<RateLimiter>>,
+    /// Throttle the bytes written to files across all tasks.
+    pub byte_rate: Option<Arc<RateLimiter>>,
+    /// Bound the bytes buffered by concurrent file writes across all tasks.
+    pub memory_budget: Option<Arc<WriteBudget>>,
+    /// Only create one kind of entry.
+    pub phase: Option<Phase>,
+    /// Skip the tasks an interrupted run already completed.
+    pub checkpoint: Option<Arc<Checkpoint>>,
+    /// Stop creating entries once the tree takes up this much disk space.
+    pub budget: Option<Arc<DiskBudget>>,
+    /// Record created entries here as soon as each task completes.
+    #[cfg(feature = "metrics")]
+    pub progress: Option<Arc<Progress>>,
+}
+
+/// Picks which files are created as special files instead of regular files.
+#[derive(Debug, Clone, Copy)]
+pub struct SpecialFiles {
+    /// Files whose hash is at most this become FIFOs.
+    pub fifos: Option<u64>,
+    /// Files whose hash is at most this and that aren't FIFOs become broken symlinks.
+    pub broken_symlinks: Option<u64>,
+    pub seed: u64,
+    /// The length of the root directory's path, ignored when hashing directories such that the
+    /// same files are picked regardless of where the tree is generated.
+    pub root_len: usize,
+}
+
+/// Picks which files are extended to their size without writing anything, leaving them sparse.
+#[derive(Debug, Clone, Copy)]
+pub struct SparseFiles {
+    /// Files whose hash is at most this are created sparse.
+    pub threshold: u64,
+    pub seed: u64,
+    /// See [SpecialFiles::root_len].
+    pub root_len: usize,
+    /// Write some blocks of data between the holes rather than leaving files entirely empty.
+    pub holes: bool,
+}
+
+/// Picks which media format each file is created as.
+#[derive(Debug, Clone)]
+pub struct MediaFiles {
+    /// Each format is picked in proportion to the number of times it appears.
+    pub formats: Arc<[MediaFormat]>,
+    pub seed: u64,
+    /// See [SpecialFiles::root_len].
+    pub root_len: usize,
+}
+
+/// Picks which files and directories are made read-only.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnlyEntries {
+    /// Entries whose hash is at most this are made read-only.
+    pub threshold: u64,
+    pub seed: u64,
+    /// See [SpecialFiles::root_len].
+    pub root_len: usize,
+}
+
+/// Picks which files are given an extended attribute (an alternate data stream on Windows) named
+/// [XATTR_NAME] and what its value is.
+#[derive(Debug, Clone, Copy)]
+pub struct XattrFiles {
+    /// Files whose hash is at most this are given an attribute.
+    pub threshold: u64,
+    pub seed: u64,
+    /// See [SpecialFiles::root_len].
+    pub root_len: usize,
+}
+
+/// Seeds the contents of each directory's files from the directory's path such that they don't
+/// depend on the rest of the tree.
+#[derive(Debug, Clone, Copy)]
+pub struct DirSeeds {
+    pub seed: u64,
+    /// See [SpecialFiles::root_len].
+    pub root_len: usize,
+}
+
+/// The name of the extended attribute attached to files.
+pub const XATTR_NAME: &str = "user.ftzz";
+
+/// Spaces the modification times of files `interval` seconds apart starting at the Unix timestamp
+/// `start`, in the order their tasks are queued.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MtimeSequence {
+    pub start: i64,
+    pub interval: u64,
+}
+
+impl MtimeSequence {
+    fn time(&self, position: usize) -> FileTime {
+        let offset = i64::try_from(self.interval)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(i64::try_from(position).unwrap_or(i64::MAX));
+        FileTime::from_unix_time(self.start.saturating_add(offset), 0)
+    }
+}
+
+/// How files are stamped with their position at the start of their contents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SequenceHeader {
+    /// The position in decimal followed by a newline.
+    Text,
+    /// The position as a little-endian u64.
+    U64Le,
+}
+
+impl SequenceHeader {
+    /// Enough space for any position in decimal followed by a newline.
+    const MAX_LEN: usize = 21;
+
+    /// Writes the header of the file at `position` into `buf`, returning the written part.
+    fn render(self, position: usize, buf: &mut [u8; Self::MAX_LEN]) -> &[u8] {
+        match self {
+            Self::Text => {
+                let mut out = &mut buf[..];
+                writeln!(out, "{}", position).expect("The buffer fits any position");
+                let len = Self::MAX_LEN - out.len();
+                &buf[..len]
+            }
+            Self::U64Le => {
+                buf[..8].copy_from_slice(&(position as u64).to_le_bytes());
+                &buf[..8]
+            }
+        }
+    }
+}
+
+impl FromStr for SequenceHeader {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "u64le" => Ok(Self::U64Le),
+            _ => Err(format!("Unknown sequence header: {}", s)),
+        }
+    }
+}
+
+/// Hands each task the positions of its files in the whole tree.
+#[derive(Debug, Clone, Default)]
+pub struct FilePositions {
+    /// The number of positions handed out across all tasks.
+    claimed: Arc<AtomicUsize>,
+    /// The position of this task's first file.
+    first: usize,
+}
+
+impl FilePositions {
+    /// Claims the next `num_files` positions for a task. Tasks must claim their positions in the
+    /// order they're queued for the positions to be the same on every run.
+    pub fn claim(&mut self, num_files: usize) {
+        self.first = self.claimed.fetch_add(num_files, Ordering::Relaxed);
+    }
+
+    fn get(&self, i: usize) -> usize {
+        self.first + i
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SpecialKind {
+    Fifo,
+    BrokenSymlink(u64),
+}
+
+impl SpecialKind {
+    fn entry_kind(self) -> EntryKind {
+        match self {
+            Self::Fifo => EntryKind::Fifo,
+            Self::BrokenSymlink(hash) => EntryKind::BrokenSymlink {
+                target: PathBuf::from(broken_symlink_target(hash)),
+            },
+        }
+    }
+}
+
+/// Names the missing target of a broken symlink. Generated names never end in `.missing`, so the
+/// target can't accidentally come into existence.
+fn broken_symlink_target(hash: u64) -> String {
+    format!("{:016x}.missing", hash)
+}
+
+/// Hashes a directory's path relative to the root directory.
+fn dir_hash(dir: &Path, seed: u64, root_len: usize) -> u64 {
+    let dir = dir.to_string_lossy();
+    seahash::hash_seeded(
+        dir.as_bytes().get(root_len..).unwrap_or_default(),
+        seed,
+        0,
+        0,
+        0,
+    )
+}
+
+impl SpecialFiles {
+    fn dir_hash(&self, dir: &FastPathBuf) -> u64 {
+        dir_hash(dir, self.seed, self.root_len)
+    }
+
+    fn kind(&self, dir_hash: u64, i: usize) -> Option<SpecialKind> {
+        let hash = seahash::hash_seeded(&i.to_le_bytes(), dir_hash, self.seed, 0, 0);
+        if self.fifos.is_some_and(|threshold| hash <= *threshold) {
+            Some(SpecialKind::Fifo)
+        } else if self.broken_symlinks.is_some_and(|threshold| hash <= *threshold) {
+            Some(SpecialKind::BrokenSymlink(hash))
+        } else {
+            None
+        }
+    }
+}
+
+impl SparseFiles {
+    fn dir_hash(&self, dir: &FastPathBuf) -> u64 {
+        dir_hash(dir, self.seed, self.root_len)
+    }
+
+    fn is_sparse(&self, dir_hash: u64, i: usize) -> bool {
+        // Salted such that the pick is independent from that of special files
+        seahash::hash_seeded(&i.to_le_bytes(), dir_hash, self.seed, 1, 0) <= self.threshold
+    }
+}
+
+impl MediaFiles {
+    fn dir_hash(&self, dir: &FastPathBuf) -> u64 {
+        dir_hash(dir, self.seed, self.root_len)
+    }
+
+    fn format(&self, dir_hash: u64, i: usize) -> MediaFormat {
+        // Salted such that the pick is independent from those of special and sparse files
+        let hash = seahash::hash_seeded(&i.to_le_bytes(), dir_hash, self.seed, 2, 0);
+        self.formats[(hash % self.formats.len() as u64) as usize]
+    }
+}
+
+impl ReadOnlyEntries {
+    fn dir_hash(&self, dir: &FastPathBuf) -> u64 {
+        dir_hash(dir, self.seed, self.root_len)
+    }
+
+    fn is_read_only_file(&self, dir_hash: u64, i: usize) -> bool {
+        // Salted such that the pick is independent from those of special, sparse, and media files
+        seahash::hash_seeded(&i.to_le_bytes(), dir_hash, self.seed, 3, 0) <= self.threshold
+    }
+
+    /// Whether the directory should be made read-only, which can only be done once the tree is
+    /// complete as its entries couldn't be created otherwise.
+    pub fn is_read_only_dir(&self, dir: &Path) -> bool {
+        seahash::hash_seeded(&[], dir_hash(dir, self.seed, self.root_len), self.seed, 4, 0)
+            <= self.threshold
+    }
+}
+
+impl DirSeeds {
+    /// The seed of the directory's contents.
+    pub fn seed(&self, dir: &Path) -> u64 {
+        dir_hash(dir, self.seed, self.root_len)
+    }
+}
+
+impl XattrFiles {
+    fn dir_hash(&self, dir: &FastPathBuf) -> u64 {
+        dir_hash(dir, self.seed, self.root_len)
+    }
+
+    /// The value of the file's attribute if it gets one.
+    fn value(&self, dir_hash: u64, i: usize) -> Option<String> {
+        // Salted such that the pick is independent from those of special, sparse, media, and
+        // read-only files
+        let hash = seahash::hash_seeded(&i.to_le_bytes(), dir_hash, self.seed, 5, 0);
+        (hash <= self.threshold).then(|| {
+            let value = seahash::hash_seeded(&i.to_le_bytes(), dir_hash, self.seed, 6, 0);
+            format!("{:016x}", value)
+        })
+    }
+}
+
 pub struct GeneratorTaskParams<G: FileContentsGenerator> {
     pub target_dir: FastPathBuf,
     pub num_files: usize,
     pub num_dirs: usize,
     pub file_offset: usize,
+    pub dir_offset: usize,
+    /// The number of lone directories ending in the target directory that don't exist yet.
+    pub missing_dirs: usize,
+    pub file_name_width: usize,
+    pub options: TaskOptions,
     pub file_contents: G,
 }
 
 pub struct GeneratorTaskOutcome {
     pub files_generated: usize,
     pub dirs_generated: usize,
+    pub fifos_generated: usize,
+    pub symlinks_generated: usize,
     pub bytes_generated: usize,
+    pub errors: usize,
 
     pub pool_return_file: FastPathBuf,
     pub pool_return_byte_counts: Option<Vec<usize>>,
 }
 
+impl GeneratorTaskOutcome {
+    /// Adds up the work of another task run as part of the same batch. Only this task's pooled
+    /// objects are returned.
+    pub fn merge(&mut self, other: Self) {
+        self.files_generated += other.files_generated;
+        self.dirs_generated += other.dirs_generated;
+        self.fifos_generated += other.fifos_generated;
+        self.symlinks_generated += other.symlinks_generated;
+        self.bytes_generated += other.bytes_generated;
+        self.errors += other.errors;
+    }
+}
+
 #[instrument(level = "trace", skip(params))]
 pub fn create_files_and_dirs(
     params: GeneratorTaskParams<impl FileContentsGenerator>,
 ) -> CliResult<GeneratorTaskOutcome> {
+    if let Some(plan) = params.options.plan.clone() {
+        return plan_files_and_dirs(params, &plan);
+    }
+    // Its directory may not exist as generation halted before getting to it
+    if out_of_budget(&params.options) {
+        return Ok(skip_files_and_dirs(params));
+    }
+
+    let mut errors = 0;
+    let mut dirs_created = create_missing_dirs(
+        params.missing_dirs,
+        &params.options,
+        &params.target_dir,
+        &mut errors,
+    )?;
+    // The missing directories didn't fit in the budget, so there's nowhere to put the entries
+    if params.missing_dirs > 0 && dirs_created == 0 && out_of_budget(&params.options) {
+        return Ok(skip_files_and_dirs(params));
+    }
+
     let mut file = params.target_dir;
     let mut file_contents = params.file_contents;
 
-    create_dirs(params.num_dirs, &mut file)?;
-    create_files(
-        params.num_files,
-        params.file_offset,
-        &mut file,
-        &mut file_contents,
-    )
-    .map(|bytes_written| GeneratorTaskOutcome {
-        files_generated: params.num_files,
-        dirs_generated: params.num_dirs,
+    let dir = if cfg!(unix) && params.options.openat {
+        Some(open_dir(&file)?)
+    } else {
+        None
+    };
+    let creator = FileCreator::new(dir.as_ref());
+
+    let mut files_created = 0;
+    let mut fifos_created = 0;
+    let mut symlinks_created = 0;
+    let mut bytes_written = 0;
+    for batch in params
+        .options
+        .order
+        .batches(params.num_dirs, params.num_files)
+    {
+        match batch {
+            Batch::Dirs(dirs) => {
+                dirs_created += create_dirs(
+                    dirs,
+                    params.dir_offset,
+                    &params.options,
+                    creator,
+                    &mut file,
+                    &mut errors,
+                )?;
+            }
+            Batch::Files(files) => {
+                let (files, specials, bytes) = create_files(
+                    files,
+                    params.file_offset,
+                    params.file_name_width,
+                    &params.options,
+                    creator,
+                    &mut file,
+                    &mut file_contents,
+                    &mut errors,
+                )?;
+                files_created += files;
+                fifos_created += specials.fifos;
+                symlinks_created += specials.symlinks;
+                bytes_written += bytes;
+            }
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    if let Some(progress) = &params.options.progress {
+        progress.record(files_created, dirs_created);
+    }
+
+    Ok(GeneratorTaskOutcome {
+        files_generated: files_created,
+        dirs_generated: dirs_created,
+        fifos_generated: fifos_created,
+        symlinks_generated: symlinks_created,
         bytes_generated: bytes_written,
+        errors,
+
+        pool_return_file: file,
+        pool_return_byte_counts: file_contents.byte_counts_pool_return(),
+    })
+}
+
+/// Stands in for a task an interrupted run already completed or one queued after generation was
+/// halted.
+pub fn skip_files_and_dirs(
+    params: GeneratorTaskParams<impl FileContentsGenerator>,
+) -> GeneratorTaskOutcome {
+    GeneratorTaskOutcome {
+        files_generated: 0,
+        dirs_generated: 0,
+        fifos_generated: 0,
+        symlinks_generated: 0,
+        bytes_generated: 0,
+        errors: 0,
+
+        pool_return_file: params.target_dir,
+        pool_return_byte_counts: params.file_contents.byte_counts_pool_return(),
+    }
+}
+
+fn plan_files_and_dirs(
+    params: GeneratorTaskParams<impl FileContentsGenerator>,
+    plan: &PlanSender,
+) -> CliResult<GeneratorTaskOutcome> {
+    let mut file = params.target_dir;
+    let mut file_contents = params.file_contents;
+    let naming = params.options.naming;
+    let depth = naming.depth(&file);
+    let special = params
+        .options
+        .special
+        .map(|special| (special, special.dir_hash(&file)));
+    let emit = |path: &Path, kind| {
+        plan.send((path.to_path_buf(), kind))
+            .context("Nobody is listening for planned paths")
+            .with_code(exitcode::SOFTWARE)
+    };
+
+    let mut missing_dirs = file.ancestors().take(params.missing_dirs).collect::<Vec<_>>();
+    missing_dirs.reverse();
+    for dir in missing_dirs {
+        emit(dir, EntryKind::Dir)?;
+    }
+
+    let mut fifos_planned = 0;
+    let mut symlinks_planned = 0;
+    let mut bytes_planned = 0;
+    for batch in params
+        .options
+        .order
+        .batches(params.num_dirs, params.num_files)
+    {
+        match batch {
+            Batch::Dirs(dirs) => {
+                for i in dirs {
+                    naming.with_dir_name(depth, i + params.dir_offset, |s| file.push(s));
+                    emit(&file, EntryKind::Dir)?;
+                    file.pop();
+                }
+            }
+            Batch::Files(files) => {
+                for i in files {
+                    naming.with_file_name(
+                        depth,
+                        i + params.file_offset,
+                        params.file_name_width,
+                        |s| file.push(s),
+                    );
+                    // The first file is always created with retries enabled
+                    let bytes = file_contents.skip_file(i, i == 0);
+                    if let Some((special, dir_hash)) = special && let Some(kind) = special.kind(dir_hash, i + params.file_offset) {
+                        if kind == SpecialKind::Fifo {
+                            fifos_planned += 1;
+                        } else {
+                            symlinks_planned += 1;
+                        }
+                        emit(&file, kind.entry_kind())?;
+                    } else {
+                        bytes_planned += bytes;
+                        emit(&file, EntryKind::File { bytes })?;
+                    }
+                    file.pop();
+                }
+            }
+        }
+    }
+
+    Ok(GeneratorTaskOutcome {
+        files_generated: params.num_files - fifos_planned - symlinks_planned,
+        dirs_generated: params.num_dirs + params.missing_dirs,
+        fifos_generated: fifos_planned,
+        symlinks_generated: symlinks_planned,
+        bytes_generated: bytes_planned,
+        errors: 0,
 
         pool_return_file: file,
         pool_return_byte_counts: file_contents.byte_counts_pool_return(),
     })
 }
 
+/// Opens the target directory, creating it if its parent task hasn't gotten to it yet.
+fn open_dir(dir: &FastPathBuf) -> CliResult<File> {
+    match File::open(dir) {
+        Err(e) if e.kind() == NotFound => {
+            event!(Level::TRACE, dir = ?dir, "Directory not created in time");
+
+            create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {:?}", dir))
+                .with_code(exitcode::IOERR)?;
+            File::open(dir)
+        }
+        r => r,
+    }
+    .with_context(|| format!("Failed to open directory {:?}", dir))
+    .with_code(exitcode::IOERR)
+}
+
+/// Creates a run of lone directories ending in `dir` with a single `create_dir_all` instead of
+/// one `create_dir` per level.
+fn create_missing_dirs(
+    missing_dirs: usize,
+    options: &TaskOptions,
+    dir: &FastPathBuf,
+    errors: &mut usize,
+) -> CliResult<usize> {
+    if missing_dirs == 0 || options.phase == Some(Phase::Files) {
+        return Ok(0);
+    }
+
+    if let Some(budget) = &options.budget && !budget.charge(missing_dirs * budget.dir_usage()) {
+        return Ok(0);
+    }
+    if let Some(limiter) = &options.rate_limit {
+        limiter.acquire_many(missing_dirs);
+    }
+    let result =
+        create_dir_all(dir).with_context(|| format!("Failed to create directory {:?}", dir));
+    Ok(tolerate(options, errors, result)?.map_or(0, |()| missing_dirs))
+}
+
 #[instrument(level = "trace")]
-fn create_dirs(num_dirs: usize, dir: &mut FastPathBuf) -> CliResult<()> {
-    for i in 0..num_dirs {
-        with_dir_name(i, |s| dir.push(s));
+fn create_dirs(
+    dirs: Range<usize>,
+    offset: usize,
+    options: &TaskOptions,
+    creator: FileCreator,
+    dir: &mut FastPathBuf,
+    errors: &mut usize,
+) -> CliResult<usize> {
+    if options.phase == Some(Phase::Files) {
+        return Ok(0);
+    }
 
-        create_dir_all(&dir)
-            .with_context(|| format!("Failed to create directory {:?}", dir))
-            .with_code(exitcode::IOERR)?;
+    let depth = options.naming.depth(dir);
+    let mut dirs_created = 0;
+    for i in dirs {
+        options.naming.with_dir_name(depth, i + offset, |s| dir.push(s));
+
+        if !(options.repair && dir.exists()) {
+            if let Some(budget) = &options.budget && !budget.charge(budget.dir_usage()) {
+                dir.pop();
+                break;
+            }
+            throttle(options);
+            let result = creator
+                .create_dir(dir)
+                .with_context(|| format!("Failed to create directory {:?}", dir));
+            if tolerate(options, errors, result)?.is_some() {
+                dirs_created += 1;
+            }
+        }
 
         dir.pop();
     }
-    Ok(())
+    Ok(dirs_created)
+}
+
+/// The number of special files created in place of regular files.
+#[derive(Debug, Default)]
+struct SpecialsCreated {
+    fifos: usize,
+    symlinks: usize,
 }
 
+impl SpecialsCreated {
+    fn add(&mut self, kind: SpecialKind) {
+        match kind {
+            SpecialKind::Fifo => self.fifos += 1,
+            SpecialKind::BrokenSymlink(_) => self.symlinks += 1,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 #[instrument(level = "trace", skip(contents))]
 fn create_files(
-    num_files: usize,
+    files: Range<usize>,
     offset: usize,
+    name_width: usize,
+    options: &TaskOptions,
+    creator: FileCreator,
     file: &mut FastPathBuf,
     contents: &mut impl FileContentsGenerator,
-) -> CliResult<usize> {
+    errors: &mut usize,
+) -> CliResult<(usize, SpecialsCreated, usize)> {
+    if options.phase == Some(Phase::Dirs) {
+        // Consume the same randomness as creating the files would to stay in sync with the files
+        // phase
+        for i in files {
+            contents.skip_file(i, i == 0);
+        }
+        return Ok(Default::default());
+    }
+
+    let mut files_created = 0;
+    let mut specials_created = SpecialsCreated::default();
     let mut bytes_written = 0;
+    let depth = options.naming.depth(file);
 
-    let mut start_file = 0;
-    if num_files > 0 {
-        with_file_name(offset, |s| file.push(s));
+    let special = options
+        .special
+        .map(|special| (special, special.dir_hash(file)));
+    let special_kind =
+        |i: usize| special.and_then(|(special, dir_hash)| special.kind(dir_hash, i + offset));
+    let sparse = options
+        .sparse
+        .map(|sparse| (sparse, sparse.dir_hash(file)));
+    let is_sparse = |i: usize| {
+        sparse.is_some_and(|(sparse, dir_hash)| sparse.is_sparse(*dir_hash, i + offset))
+    };
+    let media = options
+        .media
+        .as_ref()
+        .map(|media| (media, media.dir_hash(file)));
+    let media_format =
+        |i: usize| media.map(|(media, dir_hash)| media.format(dir_hash, i + offset));
+    let read_only = options
+        .read_only
+        .map(|read_only| (read_only, read_only.dir_hash(file)));
+    let is_read_only = |i: usize| {
+        read_only.is_some_and(|(read_only, dir_hash)| {
+            read_only.is_read_only_file(*dir_hash, i + offset)
+        })
+    };
+    let xattrs = options
+        .xattrs
+        .map(|xattrs| (xattrs, xattrs.dir_hash(file)));
+    let xattr_value =
+        |i: usize| xattrs.and_then(|(xattrs, dir_hash)| xattrs.value(dir_hash, i + offset));
 
-        match contents.create_file(file, 0, true) {
-            Ok(bytes) => {
-                bytes_written += bytes;
-                start_file += 1;
-                file.pop();
-            }
-            Err(e) => {
-                if e.kind() == NotFound {
-                    event!(Level::TRACE, file = ?file, "Parent directory not created in time");
+    let mut header_buf = [0; SequenceHeader::MAX_LEN];
 
+    let mut start_file = files.start;
+    // The first file of the directory is retried in case its parent task hasn't created it yet
+    if files.start == 0 && !files.is_empty() && !out_of_budget(options) {
+        options
+            .naming
+            .with_file_name(depth, offset, name_width, |s| file.push(s));
+
+        if options.repair && exists(file) {
+            contents.skip_file(0, true);
+            start_file += 1;
+            file.pop();
+        } else if let Some(kind) = special_kind(0) {
+            contents.skip_file(0, true);
+            throttle(options);
+            if tolerate(options, errors, create_special(creator, file, kind))?.is_some() {
+                pin_times(options, file, 0, Some(kind))?;
+                specials_created.add(kind);
+            }
+            start_file += 1;
+            file.pop();
+        } else {
+            throttle(options);
+            let mut digest = new_digest(options);
+            match contents.create_file(
+                file,
+                creator,
+                0,
+                true,
+                write_options(
+                    options,
+                    0,
+                    is_sparse(0),
+                    media_format(0),
+                    &mut header_buf,
+                    &mut digest,
+                ),
+            ) {
+                Ok(_) if !charge_file(options, file)? => {
+                    // Finish the directory with every other file skipped
+                    start_file = files.end;
+                    file.pop();
+                }
+                Ok(bytes) => {
+                    if let Some(value) = xattr_value(0) {
+                        tolerate(options, errors, set_xattr(file, &value))?;
+                    }
+                    pin_times(options, file, 0, None)?;
+                    if is_read_only(0) {
+                        tolerate(options, errors, make_read_only(file))?;
+                    }
+                    report_checksum(options, file, digest)?;
+                    files_created += 1;
+                    bytes_written += bytes;
+                    start_file += 1;
                     file.pop();
-                    create_dir_all(&file)
-                        .with_context(|| format!("Failed to create directory {:?}", file))
-                        .with_code(exitcode::IOERR)?;
-                } else {
-                    return Err(e)
-                        .with_context(|| format!("Failed to create file {:?}", file))
-                        .with_code(exitcode::IOERR);
+                }
+                Err(e) => {
+                    if e.kind() == NotFound {
+                        event!(Level::TRACE, file = ?file, "Parent directory not created in time");
+
+                        file.pop();
+                        create_dir_all(&file)
+                            .with_context(|| format!("Failed to create directory {:?}", file))
+                            .with_code(exitcode::IOERR)?;
+                    } else {
+                        let result = Err::<(), _>(e)
+                            .with_context(|| format!("Failed to create file {:?}", file));
+                        tolerate(options, errors, result)?;
+                        start_file += 1;
+                        file.pop();
+                    }
                 }
             }
         }
     }
-    for i in start_file..num_files {
-        with_file_name(i + offset, |s| file.push(s));
+    for i in start_file..files.end {
+        if out_of_budget(options) {
+            break;
+        }
+        options
+            .naming
+            .with_file_name(depth, i + offset, name_width, |s| file.push(s));
 
-        bytes_written += contents
-            .create_file(file, i, false)
-            .with_context(|| format!("Failed to create file {:?}", file))
-            .with_code(exitcode::IOERR)?;
+        if options.repair && exists(file) {
+            contents.skip_file(i, false);
+        } else if let Some(kind) = special_kind(i) {
+            contents.skip_file(i, false);
+            throttle(options);
+            if tolerate(options, errors, create_special(creator, file, kind))?.is_some() {
+                pin_times(options, file, i, Some(kind))?;
+                specials_created.add(kind);
+            }
+        } else {
+            throttle(options);
+            let mut digest = new_digest(options);
+            let result = contents
+                .create_file(
+                    file,
+                    creator,
+                    i,
+                    false,
+                    write_options(
+                        options,
+                        i,
+                        is_sparse(i),
+                        media_format(i),
+                        &mut header_buf,
+                        &mut digest,
+                    ),
+                )
+                .with_context(|| format!("Failed to create file {:?}", file));
+            if let Some(bytes) = tolerate(options, errors, result)? {
+                if !charge_file(options, file)? {
+                    file.pop();
+                    break;
+                }
+                files_created += 1;
+                bytes_written += bytes;
+                if let Some(value) = xattr_value(i) {
+                    tolerate(options, errors, set_xattr(file, &value))?;
+                }
+                pin_times(options, file, i, None)?;
+                if is_read_only(i) {
+                    tolerate(options, errors, make_read_only(file))?;
+                }
+                report_checksum(options, file, digest)?;
+            }
+        }
 
         file.pop();
     }
 
-    Ok(bytes_written)
+    Ok((files_created, specials_created, bytes_written))
+}
+
+#[inline]
+fn out_of_budget(options: &TaskOptions) -> bool {
+    options.budget.as_ref().is_some_and(|budget| budget.exhausted())
+}
+
+/// Charges a freshly written file to the disk budget, deleting it again if it doesn't fit.
+fn charge_file(options: &TaskOptions, file: &FastPathBuf) -> CliResult<bool> {
+    let budget = match &options.budget {
+        Some(budget) => budget,
+        None => return Ok(true),
+    };
+
+    let len = file
+        .symlink_metadata()
+        .with_context(|| format!("Failed to stat {:?}", file))
+        .with_code(exitcode::IOERR)?
+        .len();
+    if budget.charge(budget.file_usage(len)) {
+        return Ok(true);
+    }
+    fs::remove_file(file)
+        .with_context(|| format!("Failed to delete file {:?}", file))
+        .with_code(exitcode::IOERR)?;
+    Ok(false)
+}
+
+#[inline]
+fn throttle(options: &TaskOptions) {
+    if let Some(limiter) = &options.rate_limit {
+        limiter.acquire();
+    }
+}
+
+/// Whether file exists without following symlinks, which are broken by design.
+#[inline]
+fn exists(file: &FastPathBuf) -> bool {
+    file.symlink_metadata().is_ok()
+}
+
+/// Creates a special file, creating its parent directory if its parent task hasn't gotten to it
+/// yet.
+fn create_special(
+    creator: FileCreator,
+    file: &mut FastPathBuf,
+    kind: SpecialKind,
+) -> anyhow::Result<()> {
+    let create = |file: &mut FastPathBuf| match kind {
+        SpecialKind::Fifo => creator.create_fifo(file),
+        SpecialKind::BrokenSymlink(hash) => {
+            creator.create_symlink(file, Path::new(&broken_symlink_target(hash)))
+        }
+    };
+
+    match create(file) {
+        Err(e) if e.kind() == NotFound => {
+            event!(Level::TRACE, file = ?file, "Parent directory not created in time");
+
+            if let Some(parent) = file.parent() {
+                create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {:?}", parent))?;
+            }
+            create(file)
+        }
+        r => r,
+    }
+    .with_context(|| match kind {
+        SpecialKind::Fifo => format!("Failed to create FIFO {:?}", file),
+        SpecialKind::BrokenSymlink(_) => format!("Failed to create symlink {:?}", file),
+    })
+}
+
+/// Logs and counts a failure to create an entry if errors should be tolerated, returning `None`.
+#[inline]
+fn tolerate<T>(
+    options: &TaskOptions,
+    errors: &mut usize,
+    result: anyhow::Result<T>,
+) -> CliResult<Option<T>> {
+    match result {
+        Ok(t) => Ok(Some(t)),
+        Err(e) if options.continue_on_error => {
+            warn!("{:#}", e);
+            *errors += 1;
+            Ok(None)
+        }
+        Err(e) => Err(e).with_code(exitcode::IOERR),
+    }
+}
+
+#[inline]
+fn pin_times(
+    options: &TaskOptions,
+    file: &FastPathBuf,
+    i: usize,
+    kind: Option<SpecialKind>,
+) -> CliResult<()> {
+    let time = if let Some((sequence, positions)) =
+        options.mtime_sequence.zip(options.positions.as_ref())
+    {
+        sequence.time(positions.get(i))
+    } else if let Some(time) = options.fixed_time {
+        time
+    } else {
+        return Ok(());
+    };
+
+    // Following a broken symlink would fail, so pin the link itself
+    if matches!(kind, Some(SpecialKind::BrokenSymlink(_))) {
+        set_symlink_file_times(file, time, time)
+    } else {
+        set_file_times(file, time, time)
+    }
+    .with_context(|| format!("Failed to set the timestamps of {:?}", file))
+    .with_code(exitcode::IOERR)
+}
+
+/// Attaches the extended attribute to a file, or an alternate data stream of the same name on
+/// Windows.
+fn set_xattr(file: &Path, value: &str) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    let result = xattr::set(file, XATTR_NAME, value.as_bytes());
+    #[cfg(windows)]
+    let result = {
+        let mut stream = file.as_os_str().to_owned();
+        stream.push(":");
+        stream.push(XATTR_NAME);
+        fs::write(stream, value)
+    };
+    #[cfg(not(any(unix, windows)))]
+    let result = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::Unsupported));
+
+    result.with_context(|| format!("Failed to set the extended attribute of {:?}", file))
+}
+
+/// Makes an entry read-only, which only prevents further writes as it's already been created.
+pub fn make_read_only(path: &Path) -> anyhow::Result<()> {
+    fs::metadata(path)
+        .and_then(|metadata| {
+            let mut permissions = metadata.permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(path, permissions)
+        })
+        .with_context(|| format!("Failed to make {:?} read-only", path))
+}
+
+#[inline]
+fn write_options<'a>(
+    options: &'a TaskOptions,
+    i: usize,
+    sparse: bool,
+    media: Option<MediaFormat>,
+    header_buf: &'a mut [u8; SequenceHeader::MAX_LEN],
+    digest: &'a mut Option<Sha256>,
+) -> WriteOptions<'a> {
+    let (header, pad_header) = match (options.sequence_header, &options.positions) {
+        (Some(header), Some(positions)) => {
+            (Some(header.render(positions.get(i), header_buf)), true)
+        }
+        _ => (options.header.as_deref(), false),
+    };
+    WriteOptions {
+        pattern: options.pattern.as_deref(),
+        entropy: options.entropy,
+        archive: options.archive,
+        media,
+        header,
+        pad_header,
+        sparse,
+        holes: options.sparse.is_some_and(|sparse| sparse.holes),
+        preallocate: options.preallocate,
+        byte_rate: options.byte_rate.as_deref(),
+        memory_budget: options.memory_budget.as_deref(),
+        digest: digest.as_mut(),
+    }
+}
+
+#[inline]
+fn new_digest(options: &TaskOptions) -> Option<Sha256> {
+    options.checksums.as_ref().map(|_| Sha256::new())
+}
+
+#[inline]
+fn report_checksum(
+    options: &TaskOptions,
+    file: &FastPathBuf,
+    digest: Option<Sha256>,
+) -> CliResult<()> {
+    match (&options.checksums, digest) {
+        (Some(checksums), Some(digest)) => checksums
+            .send((file.to_path_buf(), digest.finalize().into()))
+            .context("Nobody is listening for checksums")
+            .with_code(exitcode::SOFTWARE),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::core::file_contents::NoGeneratedFileContents;
+
+    /// Fails to create every third file.
+    struct FlakyFileContents;
+
+    impl FileContentsGenerator for FlakyFileContents {
+        fn create_file(
+            &mut self,
+            file: &mut FastPathBuf,
+            creator: FileCreator,
+            file_num: usize,
+            retryable: bool,
+            write: WriteOptions,
+        ) -> io::Result<usize> {
+            if file_num % 3 == 0 {
+                Err(io::Error::new(io::ErrorKind::Other, "injected failure"))
+            } else {
+                NoGeneratedFileContents.create_file(file, creator, file_num, retryable, write)
+            }
+        }
+
+        fn skip_file(&mut self, _: usize, _: bool) -> usize {
+            0
+        }
+
+        fn byte_counts_pool_return(self) -> Option<Vec<usize>> {
+            None
+        }
+    }
+
+    fn params(dir: &Path, continue_on_error: bool) -> GeneratorTaskParams<FlakyFileContents> {
+        GeneratorTaskParams {
+            target_dir: FastPathBuf::from(dir.to_path_buf()),
+            num_files: 9,
+            num_dirs: 2,
+            file_offset: 0,
+            dir_offset: 0,
+            missing_dirs: 0,
+            file_name_width: 0,
+            options: TaskOptions {
+                continue_on_error,
+                ..Default::default()
+            },
+            file_contents: FlakyFileContents,
+        }
+    }
+
+    #[test]
+    fn failures_are_counted_when_continuing() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let outcome = create_files_and_dirs(params(dir.path(), true)).unwrap();
+
+        assert_eq!(outcome.errors, 3);
+        assert_eq!(outcome.files_generated, 6);
+        assert_eq!(outcome.dirs_generated, 2);
+        assert_eq!(dir.path().read_dir().unwrap().count(), 8);
+    }
+
+    #[test]
+    fn failures_abort_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(create_files_and_dirs(params(dir.path(), false)).is_err());
+    }
+
+    #[test]
+    fn missing_dirs_are_created_in_one_go() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("0").join("0").join("0");
+
+        let outcome = create_files_and_dirs(GeneratorTaskParams {
+            num_files: 0,
+            num_dirs: 0,
+            missing_dirs: 3,
+            ..params(&target, false)
+        })
+        .unwrap();
+
+        assert_eq!(outcome.dirs_generated, 3);
+        assert!(target.is_dir());
+    }
+
+    #[test]
+    fn sequence_headers_are_rendered() {
+        let mut buf = [0; SequenceHeader::MAX_LEN];
+
+        assert_eq!(SequenceHeader::Text.render(42, &mut buf), b"42\n");
+        assert_eq!(
+            SequenceHeader::Text.render(usize::MAX, &mut buf),
+            format!("{}\n", usize::MAX).as_bytes()
+        );
+        assert_eq!(
+            SequenceHeader::U64Le.render(42, &mut buf),
+            42u64.to_le_bytes()
+        );
+    }
 }
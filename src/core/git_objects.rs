@@ -0,0 +1,328 @@
+use std::{
+    collections::HashSet,
+    fs::{create_dir, rename, File},
+    io::{self, BufWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use crc32fast::Hasher;
+use flate2::{write::ZlibEncoder, Compression};
+use rand::RngCore;
+use sha1::{Digest, Sha1};
+
+use crate::core::{
+    file_contents::write_random_bytes,
+    files::{EntryKind, PlanSender},
+    scheduler::GeneratorStats,
+};
+
+/// The type of blobs in a pack.
+const OBJ_BLOB: u8 = 3;
+
+/// A blob destined for the object store along with the random state its contents are replayed
+/// from.
+struct Blob<R> {
+    id: [u8; 20],
+    len: usize,
+    random: R,
+}
+
+/// Lays out blobs of the given sizes like a `.git/objects` directory. Blobs are named after the
+/// SHA-1 of their contents: the most recent quarter are loose objects stored under `xx/yyyy...`
+/// and the rest are stored in a single pack with its index, as if the repository had recently
+/// been garbage collected. Blobs that turn out to have identical contents are only stored once.
+///
+/// Contents are stored uncompressed (random bytes wouldn't compress anyway), but in zlib streams
+/// so git can read the objects back.
+pub fn create_git_objects<R: RngCore + Clone>(
+    root_dir: &Path,
+    byte_counts: &[usize],
+    random: &mut R,
+    plan: Option<&PlanSender>,
+) -> CliResult<GeneratorStats> {
+    let mut ids = HashSet::with_capacity(byte_counts.len());
+    let mut blobs = Vec::with_capacity(byte_counts.len());
+    for &len in byte_counts {
+        // The blob's name depends on its contents, so they are hashed up front and then replayed
+        // from the same random state when the blob is written.
+        let replay = random.clone();
+        let mut digest = Sha1::new();
+        digest.update(blob_header(len));
+        // Digests never fail
+        let _ = write_random_bytes(&mut digest, len, random, None, None, None);
+        let id: [u8; 20] = digest.finalize().into();
+        if ids.insert(id) {
+            blobs.push(Blob {
+                id,
+                len,
+                random: replay,
+            });
+        }
+    }
+
+    let mut stats = GeneratorStats::default();
+    for dir in ["info", "pack"] {
+        create_dir_or_emit(plan, root_dir.join(dir))?;
+        stats.dirs += 1;
+    }
+
+    let num_loose = blobs.len() / 4;
+    let (packed, loose) = blobs.split_at_mut(blobs.len() - num_loose);
+    if !packed.is_empty() {
+        let pack_dir = root_dir.join("pack");
+        // Packs are named after their checksum, which is only known once they've been written
+        let tmp_file = pack_dir.join("tmp_pack");
+        let mut pack = if plan.is_some() {
+            write_pack(io::sink(), packed)
+        } else {
+            File::create(&tmp_file).and_then(|f| write_pack(BufWriter::new(f), packed))
+        }
+        .with_context(|| format!("Failed to create pack {:?}", tmp_file))
+        .with_code(exitcode::IOERR)?;
+
+        let name = format!("pack-{}", hex(&pack.checksum));
+        let pack_file = pack_dir.join(format!("{}.pack", name));
+        let index_file = pack_dir.join(format!("{}.idx", name));
+        let index_len = if let Some(plan) = plan {
+            let index_len = write_index(io::sink(), &mut pack)
+                .context("Sinks never fail")
+                .with_code(exitcode::SOFTWARE)?;
+            emit(plan, pack_file, EntryKind::File { bytes: pack.len })?;
+            emit(plan, index_file, EntryKind::File { bytes: index_len })?;
+            index_len
+        } else {
+            rename(&tmp_file, &pack_file)
+                .with_context(|| format!("Failed to rename {:?} to {:?}", tmp_file, pack_file))
+                .with_code(exitcode::IOERR)?;
+            File::create(&index_file)
+                .and_then(|f| write_index(BufWriter::new(f), &mut pack))
+                .with_context(|| format!("Failed to create pack index {:?}", index_file))
+                .with_code(exitcode::IOERR)?
+        };
+        stats.files += 2;
+        stats.bytes += pack.len + index_len;
+    }
+
+    let mut dirs_created = [false; 256];
+    for blob in loose {
+        let id = hex(&blob.id);
+        let dir = root_dir.join(&id[..2]);
+        let file = dir.join(&id[2..]);
+        if !mem::replace(&mut dirs_created[usize::from(blob.id[0])], true) {
+            create_dir_or_emit(plan, dir)?;
+            stats.dirs += 1;
+        }
+
+        let bytes = if let Some(plan) = plan {
+            let bytes = write_loose(io::sink(), blob)
+                .context("Sinks never fail")
+                .with_code(exitcode::SOFTWARE)?;
+            emit(plan, file, EntryKind::File { bytes })?;
+            bytes
+        } else {
+            File::create(&file)
+                .and_then(|f| write_loose(BufWriter::new(f), blob))
+                .with_context(|| format!("Failed to create loose object {:?}", file))
+                .with_code(exitcode::IOERR)?
+        };
+        stats.files += 1;
+        stats.bytes += bytes;
+    }
+    Ok(stats)
+}
+
+/// Writes the blob as a loose object, returning its length.
+fn write_loose<R: RngCore>(out: impl Write, blob: &mut Blob<R>) -> io::Result<usize> {
+    let mut out = ZlibEncoder::new(out, Compression::none());
+    out.write_all(blob_header(blob.len).as_bytes())?;
+    write_random_bytes(&mut out, blob.len, &mut blob.random, None, None, None)?;
+    out.try_finish()?;
+    let len = out.total_out() as usize;
+    out.finish()?.flush()?;
+    Ok(len)
+}
+
+/// What the pack index needs to know about a packed object.
+struct PackEntry {
+    id: [u8; 20],
+    crc: u32,
+    offset: u64,
+}
+
+struct Pack {
+    checksum: [u8; 20],
+    len: usize,
+    entries: Vec<PackEntry>,
+}
+
+/// Writes a version 2 pack of the given blobs.
+fn write_pack<R: RngCore>(out: impl Write, blobs: &mut [Blob<R>]) -> io::Result<Pack> {
+    let num_blobs = u32::try_from(blobs.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many objects to pack"))?;
+    let mut out = Checksummed::new(out);
+    out.write_all(b"PACK")?;
+    out.write_all(&2u32.to_be_bytes())?;
+    out.write_all(&num_blobs.to_be_bytes())?;
+
+    let mut entries = Vec::with_capacity(blobs.len());
+    for blob in blobs {
+        let offset = out.len;
+        out.crc = Hasher::new();
+        out.write_all(&entry_header(blob.len))?;
+        let mut data = ZlibEncoder::new(&mut out, Compression::none());
+        write_random_bytes(&mut data, blob.len, &mut blob.random, None, None, None)?;
+        data.finish()?;
+        entries.push(PackEntry {
+            id: blob.id,
+            crc: mem::take(&mut out.crc).finalize(),
+            offset,
+        });
+    }
+
+    let (checksum, len) = out.finish()?;
+    Ok(Pack {
+        checksum,
+        len,
+        entries,
+    })
+}
+
+/// Writes a version 2 index of the pack, returning its length.
+fn write_index(out: impl Write, pack: &mut Pack) -> io::Result<usize> {
+    pack.entries.sort_unstable_by_key(|entry| entry.id);
+    let mut out = Checksummed::new(out);
+    out.write_all(b"\xfftOc")?;
+    out.write_all(&2u32.to_be_bytes())?;
+
+    let mut fanout = [0u32; 256];
+    for entry in &pack.entries {
+        fanout[usize::from(entry.id[0])] += 1;
+    }
+    let mut total = 0;
+    for count in fanout {
+        total += count;
+        out.write_all(&total.to_be_bytes())?;
+    }
+    for entry in &pack.entries {
+        out.write_all(&entry.id)?;
+    }
+    for entry in &pack.entries {
+        out.write_all(&entry.crc.to_be_bytes())?;
+    }
+    // Offsets that don't fit in 31 bits point into a table of 64 bit offsets instead
+    let mut large_offsets = Vec::new();
+    for entry in &pack.entries {
+        let offset = match u32::try_from(entry.offset) {
+            Ok(offset) if offset < 1 << 31 => offset,
+            _ => {
+                large_offsets.push(entry.offset);
+                (1 << 31) | (large_offsets.len() - 1) as u32
+            }
+        };
+        out.write_all(&offset.to_be_bytes())?;
+    }
+    for offset in large_offsets {
+        out.write_all(&offset.to_be_bytes())?;
+    }
+    out.write_all(&pack.checksum)?;
+
+    out.finish().map(|(_, len)| len)
+}
+
+/// Keeps the SHA-1, length, and a resettable CRC-32 of everything written through it so the
+/// checksum can be appended to the end.
+struct Checksummed<W> {
+    inner: W,
+    digest: Sha1,
+    crc: Hasher,
+    len: u64,
+}
+
+impl<W: Write> Checksummed<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            digest: Sha1::new(),
+            crc: Hasher::new(),
+            len: 0,
+        }
+    }
+
+    /// Appends the checksum, returning it along with the total length written.
+    fn finish(mut self) -> io::Result<([u8; 20], usize)> {
+        let checksum: [u8; 20] = mem::take(&mut self.digest).finalize().into();
+        self.inner.write_all(&checksum)?;
+        self.inner.flush()?;
+        Ok((checksum, self.len as usize + checksum.len()))
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.digest.update(&buf[..n]);
+        self.crc.update(&buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The header hashed and stored in front of a loose blob's contents.
+fn blob_header(len: usize) -> String {
+    format!("blob {}\0", len)
+}
+
+/// A packed object's type and size, the size spread 7 bits at a time across continuation bytes.
+fn entry_header(len: usize) -> Vec<u8> {
+    let mut header = vec![(OBJ_BLOB << 4) | (len & 0xF) as u8];
+    let mut len = len >> 4;
+    while len > 0 {
+        *header.last_mut().unwrap() |= 0x80;
+        header.push((len & 0x7F) as u8);
+        len >>= 7;
+    }
+    header
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn create_dir_or_emit(plan: Option<&PlanSender>, dir: PathBuf) -> CliResult<()> {
+    if let Some(plan) = plan {
+        emit(plan, dir, EntryKind::Dir)
+    } else {
+        create_dir(&dir)
+            .with_context(|| format!("Failed to create directory {:?}", dir))
+            .with_code(exitcode::IOERR)
+    }
+}
+
+fn emit(plan: &PlanSender, path: PathBuf, kind: EntryKind) -> CliResult<()> {
+    plan.send((path, kind))
+        .context("Nobody is listening for planned paths")
+        .with_code(exitcode::SOFTWARE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_blobs_are_named_like_git_names_them() {
+        let mut digest = Sha1::new();
+        digest.update(blob_header(0));
+
+        assert_eq!(
+            hex(&digest.finalize()),
+            "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391"
+        );
+    }
+}
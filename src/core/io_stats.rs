@@ -0,0 +1,70 @@
+use std::{fs, io};
+
+use serde::Serialize;
+
+/// The kernel's write accounting for this process, as found in `/proc/self/io`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IoCounters {
+    /// Bytes passed to write calls, whether or not they reached the disk.
+    pub written_bytes: u64,
+    /// Number of write calls.
+    pub write_syscalls: u64,
+    /// Bytes sent to the storage layer, which can lag behind or be dwarfed by what was written.
+    pub storage_bytes: u64,
+}
+
+impl IoCounters {
+    /// Reads the counters of the calling process.
+    pub fn read() -> io::Result<Self> {
+        Self::parse(&fs::read_to_string("/proc/self/io")?)
+    }
+
+    fn parse(contents: &str) -> io::Result<Self> {
+        let mut counters = Self::default();
+        for (key, value) in contents.lines().filter_map(|line| line.split_once(':')) {
+            let field = match key {
+                "wchar" => &mut counters.written_bytes,
+                "syscw" => &mut counters.write_syscalls,
+                "write_bytes" => &mut counters.storage_bytes,
+                _ => continue,
+            };
+            *field = value
+                .trim()
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        Ok(counters)
+    }
+
+    /// The I/O performed between `earlier` and these counters.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            written_bytes: self.written_bytes.saturating_sub(earlier.written_bytes),
+            write_syscalls: self.write_syscalls.saturating_sub(earlier.write_syscalls),
+            storage_bytes: self.storage_bytes.saturating_sub(earlier.storage_bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_self_io() {
+        let counters = IoCounters::parse(
+            "rchar: 323934931\nwchar: 323929600\nsyscr: 632687\nsyscw: 632675\nread_bytes: \
+             0\nwrite_bytes: 323932160\ncancelled_write_bytes: 0\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            counters,
+            IoCounters {
+                written_bytes: 323929600,
+                write_syscalls: 632675,
+                storage_bytes: 323932160,
+            }
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use anyhow::Context;
+use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use rand::{distributions::Distribution, RngCore, SeedableRng};
+use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::core::{
+    file_contents::{
+        NoGeneratedFileContents, OnTheFlyGeneratedFileContents, PreDefinedGeneratedFileContents,
+    },
+    files::{create_files_and_dirs, EntryKind, GeneratorTaskParams, TaskOptions},
+    scheduler::GeneratorStats,
+    tasks::even_byte_count,
+};
+
+/// The key holding the number of files in a directory of a JSON layout.
+pub const LAYOUT_FILES_KEY: &str = "$files";
+
+/// A directory of a declared tree: how many files it holds and its subdirectories by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub files: usize,
+    pub dirs: Vec<(String, Layout)>,
+}
+
+impl Layout {
+    /// Parses a JSON document in which the keys of objects name directories. A directory's files
+    /// are counted by its [LAYOUT_FILES_KEY] key, and a bare number stands for a directory holding
+    /// that many files and nothing else. For example,
+    /// `{"$files": 1, "src": {"$files": 10, "bin": 2}, "docs": {}}`.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Self::from_value(&value, Path::new("."))
+    }
+
+    fn from_value(value: &Value, path: &Path) -> Result<Self, String> {
+        let files = |value: &Value| {
+            value
+                .as_u64()
+                .and_then(|files| usize::try_from(files).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "The number of files in {:?} must be a non-negative integer.",
+                        path,
+                    )
+                })
+        };
+
+        match value {
+            Value::Number(_) => Ok(Self {
+                files: files(value)?,
+                dirs: Vec::new(),
+            }),
+            Value::Object(entries) => {
+                let mut layout = Self::default();
+                for (name, value) in entries {
+                    if name == LAYOUT_FILES_KEY {
+                        layout.files = files(value)?;
+                        continue;
+                    }
+                    if name.is_empty()
+                        || name == "."
+                        || name == ".."
+                        || name.contains(['/', '\0'])
+                    {
+                        return Err(format!(
+                            "{:?} in {:?} is not a valid directory name.",
+                            name,
+                            path,
+                        ));
+                    }
+
+                    let dir = Self::from_value(value, &path.join(name))?;
+                    layout.dirs.push((name.clone(), dir));
+                }
+                Ok(layout)
+            }
+            _ => Err(format!(
+                "{:?} must be a number of files or an object of directories.",
+                path,
+            )),
+        }
+    }
+
+    /// The number of files across the whole tree.
+    pub fn num_files(&self) -> usize {
+        self.dirs
+            .iter()
+            .fold(self.files, |sum, (_, dir)| sum.saturating_add(dir.num_files()))
+    }
+
+    /// The number of directories below this one.
+    pub fn num_dirs(&self) -> usize {
+        self.dirs
+            .iter()
+            .fold(self.dirs.len(), |sum, (_, dir)| sum.saturating_add(dir.num_dirs()))
+    }
+
+    /// How many levels of directories there are below this one.
+    pub fn depth(&self) -> u32 {
+        self.dirs
+            .iter()
+            .map(|(_, dir)| dir.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Layouts are written in the same shape they're parsed from.
+impl Serialize for Layout {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.dirs.len() + 1))?;
+        map.serialize_entry(LAYOUT_FILES_KEY, &self.files)?;
+        for (name, dir) in &self.dirs {
+            map.serialize_entry(name, dir)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Layout {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(&value, Path::new(".")).map_err(de::Error::custom)
+    }
+}
+
+/// Creates exactly the directories of the layout below the root, filling each one with its files
+/// just like the randomly shaped trees are. Directories are visited breadth first such that files
+/// are numbered the same way every time.
+pub fn create_layout<DB, R>(
+    root_dir: &Path,
+    layout: &Layout,
+    num_bytes_distr: Option<DB>,
+    bytes_exact: Option<usize>,
+    mut random: R,
+    options: TaskOptions,
+) -> CliResult<GeneratorStats>
+where
+    DB: Distribution<f64> + Clone,
+    R: RngCore + SeedableRng,
+{
+    let total_files = layout.num_files();
+    let mut stats = GeneratorStats::default();
+    let mut file_offset = 0;
+    let mut queue = VecDeque::from([(root_dir.to_path_buf(), layout)]);
+    while let Some((dir, layout)) = queue.pop_front() {
+        for (name, child) in &layout.dirs {
+            let path = dir.join(name);
+            if let Some(plan) = &options.plan {
+                plan.send((path.clone(), EntryKind::Dir))
+                    .context("Nobody is listening for planned paths")
+                    .with_code(exitcode::SOFTWARE)?;
+            } else {
+                fs::create_dir(&path)
+                    .with_context(|| format!("Failed to create directory {:?}", path))
+                    .with_code(exitcode::IOERR)?;
+            }
+            #[cfg(feature = "metrics")]
+            if let Some(progress) = &options.progress {
+                progress.record(0, 1);
+            }
+            stats.dirs += 1;
+            queue.push_back((path, child));
+        }
+        if layout.files == 0 {
+            continue;
+        }
+
+        let mut options = options.clone();
+        if let Some(positions) = &mut options.positions {
+            positions.claim(layout.files);
+        }
+        macro_rules! build_params {
+            ($file_contents:expr) => {{
+                GeneratorTaskParams {
+                    target_dir: dir.into(),
+                    num_files: layout.files,
+                    num_dirs: 0,
+                    file_offset: 0,
+                    dir_offset: 0,
+                    missing_dirs: 0,
+                    file_name_width: options.naming.file_name_width(layout.files - 1),
+                    options: options.clone(),
+                    file_contents: $file_contents,
+                }
+            }};
+        }
+
+        // Each directory gets its own stream such that the contents of one don't shift the next
+        let dir_random = R::seed_from_u64(random.next_u64());
+        let outcome = match (&num_bytes_distr, bytes_exact) {
+            (Some(_), Some(bytes)) => {
+                let alignment = options.size_alignment;
+                create_files_and_dirs(build_params!(PreDefinedGeneratedFileContents {
+                    byte_counts: (file_offset..file_offset + layout.files)
+                        .map(|i| even_byte_count(bytes, total_files, alignment, i))
+                        .collect(),
+                    random: dir_random,
+                }))
+            }
+            (Some(distr), None) => {
+                create_files_and_dirs(build_params!(OnTheFlyGeneratedFileContents {
+                    num_bytes_distr: distr.clone(),
+                    random: dir_random,
+                }))
+            }
+            (None, _) => create_files_and_dirs(build_params!(NoGeneratedFileContents)),
+        }?;
+        stats += &outcome;
+        file_offset += layout.files;
+    }
+    Ok(stats)
+}
@@ -0,0 +1,276 @@
+use std::{
+    cmp::min,
+    io,
+    io::{BufWriter, Write},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive::crc32;
+
+/// The kind of media file generated files are disguised as.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaFormat {
+    /// A 1x1 grayscale PNG image.
+    Png,
+    /// An 8x8 grayscale baseline JPEG image.
+    Jpeg,
+    /// An 8 kHz mono 8-bit PCM WAV recording.
+    Wav,
+    /// A silent mono MPEG-1 Layer III stream.
+    Mp3,
+}
+
+impl FromStr for MediaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "wav" => Ok(Self::Wav),
+            "mp3" => Ok(Self::Mp3),
+            _ => Err(format!("Unknown media format: {}", s)),
+        }
+    }
+}
+
+impl MediaFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Wav => "wav",
+            Self::Mp3 => "mp3",
+        }
+    }
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// A 1x1 image with 8-bit grayscale pixels.
+const PNG_HEADER: [u8; 13] = [0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0];
+/// A zlib stream holding a single stored block with the row's filter type and its black pixel.
+const PNG_IMAGE_DATA: [u8; 13] = [
+    0x78, 0x01, 0x01, 0x02, 0x00, 0xFD, 0xFF, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+];
+/// An ancillary, private, and safe to copy chunk type, which decoders skip.
+const PNG_PAYLOAD_CHUNK: &[u8; 4] = b"ftZz";
+const PNG_MAX_CHUNK: usize = 1 << 30;
+
+const JPEG_MAX_COMMENT: usize = u16::MAX as usize - 2;
+
+/// MPEG-1 Layer III at 128 kbps and 44.1 kHz in mono, without a CRC or padding.
+const MP3_FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0xC0];
+const MP3_SIDE_INFO: usize = 17;
+/// What's left of a 417 byte frame after the header and side information. With all-zero side
+/// information, none of it is decoded and it counts as ancillary data.
+const MP3_FRAME_DATA: usize = 417 - MP3_FRAME_HEADER.len() - MP3_SIDE_INFO;
+
+/// Wraps the bytes written to it in the smallest structure that makes for a valid media file of
+/// the given format. The bytes end up where decoders ignore them: in private PNG chunks, JPEG
+/// comments, MP3 ancillary data, or as the samples of a WAV recording.
+pub struct MediaWriter<W: Write> {
+    out: BufWriter<W>,
+    format: MediaFormat,
+    num_bytes: usize,
+    left: usize,
+
+    open: bool,
+    unit_size: usize,
+    remaining: usize,
+    crc: u32,
+}
+
+impl<W: Write> MediaWriter<W> {
+    pub fn new(out: W, format: MediaFormat, num_bytes: usize) -> io::Result<Self> {
+        let mut writer = Self {
+            out: BufWriter::new(out),
+            format,
+            num_bytes,
+            left: num_bytes,
+
+            open: false,
+            unit_size: 0,
+            remaining: 0,
+            crc: 0,
+        };
+
+        match format {
+            MediaFormat::Png => {
+                writer.out.write_all(&PNG_SIGNATURE)?;
+                writer.png_chunk(b"IHDR", &PNG_HEADER)?;
+            }
+            MediaFormat::Jpeg => writer.out.write_all(&[0xFF, 0xD8])?,
+            MediaFormat::Wav => {
+                let padded = num_bytes + num_bytes % 2;
+                let riff_size = padded
+                    .checked_add(36)
+                    .and_then(|size| u32::try_from(size).ok())
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "The file is too large to be a WAV recording",
+                        )
+                    })?;
+
+                let mut header = Vec::with_capacity(44);
+                header.extend(b"RIFF");
+                header.extend(riff_size.to_le_bytes());
+                header.extend(b"WAVEfmt ");
+                header.extend(16u32.to_le_bytes());
+                // PCM
+                header.extend(1u16.to_le_bytes());
+                // Mono
+                header.extend(1u16.to_le_bytes());
+                // Sample and byte rates
+                header.extend(8000u32.to_le_bytes());
+                header.extend(8000u32.to_le_bytes());
+                // Block alignment and bits per sample
+                header.extend(1u16.to_le_bytes());
+                header.extend(8u16.to_le_bytes());
+                header.extend(b"data");
+                header.extend((num_bytes as u32).to_le_bytes());
+                writer.out.write_all(&header)?;
+            }
+            MediaFormat::Mp3 => {}
+        }
+        Ok(writer)
+    }
+
+    /// Closes the last unit holding written bytes and writes out the rest of the media file.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.open {
+            self.close_unit()?;
+        }
+        match self.format {
+            MediaFormat::Png => {
+                self.png_chunk(b"IDAT", &PNG_IMAGE_DATA)?;
+                self.png_chunk(b"IEND", &[])?;
+            }
+            MediaFormat::Jpeg => {
+                // A flat quantization table, Huffman tables with a single symbol, and one block
+                // whose DC difference and AC coefficients are all zero
+                let mut quantization = [1; 65];
+                quantization[0] = 0;
+                self.jpeg_segment(0xDB, &quantization)?;
+                self.jpeg_segment(0xC0, &[8, 0, 8, 0, 8, 1, 1, 0x11, 0])?;
+                for class in [0x00, 0x10] {
+                    let mut huffman = [0; 18];
+                    huffman[0] = class;
+                    huffman[1] = 1;
+                    self.jpeg_segment(0xC4, &huffman)?;
+                }
+                self.jpeg_segment(0xDA, &[1, 1, 0, 0, 63, 0])?;
+                // Two 1-bit codes padded with ones and the end of image marker
+                self.out.write_all(&[0x3F, 0xFF, 0xD9])?;
+            }
+            MediaFormat::Wav => {
+                if self.num_bytes % 2 == 1 {
+                    self.out.write_all(&[0])?;
+                }
+            }
+            MediaFormat::Mp3 => {
+                // A stream needs at least one frame
+                if self.num_bytes == 0 {
+                    self.open_unit()?;
+                    self.close_unit()?;
+                }
+            }
+        }
+        self.out.flush()
+    }
+
+    /// Starts the next chunk, comment, or frame the written bytes go in.
+    fn open_unit(&mut self) -> io::Result<()> {
+        let size = min(
+            self.left,
+            match self.format {
+                MediaFormat::Png => PNG_MAX_CHUNK,
+                MediaFormat::Jpeg => JPEG_MAX_COMMENT,
+                MediaFormat::Wav => usize::MAX,
+                MediaFormat::Mp3 => MP3_FRAME_DATA,
+            },
+        );
+        match self.format {
+            MediaFormat::Png => {
+                self.out.write_all(&(size as u32).to_be_bytes())?;
+                self.out.write_all(PNG_PAYLOAD_CHUNK)?;
+                self.crc = crc32(0, PNG_PAYLOAD_CHUNK);
+            }
+            MediaFormat::Jpeg => {
+                self.out.write_all(&[0xFF, 0xFE])?;
+                self.out.write_all(&(size as u16 + 2).to_be_bytes())?;
+            }
+            MediaFormat::Wav => {}
+            MediaFormat::Mp3 => {
+                self.out.write_all(&MP3_FRAME_HEADER)?;
+                self.out.write_all(&[0; MP3_SIDE_INFO])?;
+            }
+        }
+
+        self.open = true;
+        self.unit_size = size;
+        self.remaining = size;
+        Ok(())
+    }
+
+    fn close_unit(&mut self) -> io::Result<()> {
+        match self.format {
+            MediaFormat::Png => self.out.write_all(&self.crc.to_be_bytes())?,
+            MediaFormat::Mp3 => self
+                .out
+                .write_all(&[0; MP3_FRAME_DATA][..MP3_FRAME_DATA - self.unit_size])?,
+            MediaFormat::Jpeg | MediaFormat::Wav => {}
+        }
+
+        self.open = false;
+        Ok(())
+    }
+
+    fn png_chunk(&mut self, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+        self.out.write_all(&(data.len() as u32).to_be_bytes())?;
+        self.out.write_all(kind)?;
+        self.out.write_all(data)?;
+        self.out.write_all(&crc32(crc32(0, kind), data).to_be_bytes())
+    }
+
+    fn jpeg_segment(&mut self, marker: u8, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(&[0xFF, marker])?;
+        self.out.write_all(&(data.len() as u16 + 2).to_be_bytes())?;
+        self.out.write_all(data)
+    }
+}
+
+impl<W: Write> Write for MediaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.open && self.remaining == 0 {
+            self.close_unit()?;
+        }
+        if !self.open {
+            if self.left == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "Wrote past the end of the media file",
+                ));
+            }
+            self.open_unit()?;
+        }
+
+        let used = min(buf.len(), self.remaining);
+        self.out.write_all(&buf[..used])?;
+        if self.format == MediaFormat::Png {
+            self.crc = crc32(self.crc, &buf[..used]);
+        }
+        self.remaining -= used;
+        self.left -= used;
+        Ok(used)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    fmt::Write as _,
+    io,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use tracing::{event, Level};
+
+/// Live counts of the entries created so far, updated as tasks complete.
+#[derive(Debug, Default)]
+pub struct Progress {
+    pub files: AtomicUsize,
+    pub dirs: AtomicUsize,
+}
+
+impl Progress {
+    #[inline]
+    pub fn record(&self, files: usize, dirs: usize) {
+        self.files.fetch_add(files, Ordering::Relaxed);
+        self.dirs.fetch_add(dirs, Ordering::Relaxed);
+    }
+
+    /// Renders the counters in the Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            (
+                "ftzz_files_created_total",
+                "Files created so far.",
+                &self.files,
+            ),
+            (
+                "ftzz_dirs_created_total",
+                "Directories created so far.",
+                &self.dirs,
+            ),
+        ] {
+            // Strings never fail to be written to
+            let _ = write!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} counter\n{name} {}\n",
+                value.load(Ordering::Relaxed),
+            );
+        }
+        out
+    }
+}
+
+/// A tiny HTTP server answering every request with the current [Progress].
+pub struct MetricsServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    pub fn start(addr: SocketAddr, progress: Arc<Progress>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::Acquire) {
+                        break;
+                    }
+                    if let Err(e) = stream.and_then(|stream| serve(stream, &progress)) {
+                        event!(Level::DEBUG, error = ?e, "Failed to serve metrics");
+                    }
+                }
+            })
+        };
+        Ok(Self { addr, stop, handle })
+    }
+
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Release);
+        // Wake up the listener so it notices it should stop
+        let _ = TcpStream::connect(self.addr);
+        let _ = self.handle.join();
+    }
+}
+
+fn serve(stream: TcpStream, progress: &Progress) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+    // Skip the request: there's only one thing to serve
+    let mut request = BufReader::new(&stream);
+    let mut line = String::new();
+    while request.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let body = progress.render();
+    write!(
+        &stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        body.len(),
+        body,
+    )
+}
@@ -1,9 +1,52 @@
+pub use archive::{Archive, ArchiveFormat};
+pub use checkpoint::Checkpoint;
+pub use content_addressed::create_content_addressed;
+pub use diff::{diff_trees, TreeDiff};
+pub use disk_budget::DiskBudget;
+pub use file_contents::{ContentSource, DuplicateGroups};
+pub use files::{
+    make_read_only, ChecksumSender, CreationOrder, DirSeeds, EntryKind, FilePositions, MediaFiles,
+    MtimeSequence, NodeSample, Phase, PlanSender, ReadOnlyEntries, SampleSender, SequenceHeader,
+    SparseFiles, SpecialFiles, TaskOptions, XattrFiles, XATTR_NAME,
+};
+pub use git_objects::create_git_objects;
+#[cfg(target_os = "linux")]
+pub use io_stats::IoCounters;
+pub use layout::{create_layout, Layout, LAYOUT_FILES_KEY};
+pub use media::MediaFormat;
+#[cfg(feature = "metrics")]
+pub use metrics::{MetricsServer, Progress};
+pub use ramp_up::RampUp;
+pub use rate_limit::RateLimiter;
 pub use scheduler::*;
+#[cfg(feature = "syslog")]
+pub use system_log::{Severity, Syslog, SyslogSink};
 pub use tasks::{
-    FilesAndContentsGenerator, FilesNoContentsGenerator, OtherFilesAndContentsGenerator,
+    even_byte_count, BalancedGenerator, ChainGenerator, DirWidth, DirsFloor, EmptyFiles,
+    FilesAndContentsGenerator, FilesFloor, FilesNoContentsGenerator, OtherFilesAndContentsGenerator,
+    SingleDirGenerator, StructuralLevels,
 };
+pub use write_budget::WriteBudget;
 
+mod archive;
+mod checkpoint;
+mod content_addressed;
+mod creator;
+mod diff;
+mod disk_budget;
 mod file_contents;
 mod files;
+mod git_objects;
+#[cfg(target_os = "linux")]
+mod io_stats;
+mod layout;
+mod media;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod ramp_up;
+mod rate_limit;
 mod scheduler;
+#[cfg(feature = "syslog")]
+mod system_log;
 mod tasks;
+mod write_budget;
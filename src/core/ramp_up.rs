@@ -0,0 +1,111 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Raises the number of tasks allowed to run at once from 1 to the maximum over a period, easing
+/// cold storage into the full load instead of hitting it with every task at once.
+#[derive(Debug)]
+pub struct RampUp {
+    start: Instant,
+    period: Duration,
+    max: NonZeroUsize,
+    running: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Allows its task to run until dropped.
+pub struct RampUpPermit<'a>(&'a RampUp);
+
+impl RampUp {
+    pub fn new(period: Duration, max: NonZeroUsize) -> Self {
+        Self {
+            start: Instant::now(),
+            period,
+            max,
+            running: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// The number of tasks allowed to run at once by now.
+    pub fn permits(&self) -> usize {
+        let progress = self.start.elapsed().as_secs_f64() / self.period.as_secs_f64();
+        if progress >= 1. {
+            return self.max.get();
+        }
+        1 + ((self.max.get() - 1) as f64 * progress) as usize
+    }
+
+    /// Blocks until another task is allowed to run.
+    pub fn acquire(&self) -> RampUpPermit<'_> {
+        // Permits also appear as time passes, so waiters can't only rely on being notified
+        let step = self.period / u32::try_from(self.max.get()).unwrap_or(u32::MAX);
+        let mut running = self.running.lock().unwrap();
+        while *running >= self.permits() {
+            running = self.released.wait_timeout(running, step).unwrap().0;
+        }
+        *running += 1;
+        RampUpPermit(self)
+    }
+}
+
+impl Drop for RampUpPermit<'_> {
+    fn drop(&mut self) {
+        *self.0.running.lock().unwrap() -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    fn concurrency_starts_low_and_reaches_the_max_after_the_ramp() {
+        const PERIOD: Duration = Duration::from_millis(400);
+
+        let ramp_up = Arc::new(RampUp::new(PERIOD, NonZeroUsize::new(4).unwrap()));
+        let running = Arc::new(AtomicUsize::new(0));
+        // The most tasks seen running at once in the first quarter of the ramp and after it
+        let early_peak = Arc::new(AtomicUsize::new(0));
+        let late_peak = Arc::new(AtomicUsize::new(0));
+        let threads = (0..8)
+            .map(|_| {
+                let ramp_up = ramp_up.clone();
+                let running = running.clone();
+                let early_peak = early_peak.clone();
+                let late_peak = late_peak.clone();
+                thread::spawn(move || {
+                    while ramp_up.start.elapsed() < PERIOD * 3 / 2 {
+                        let _permit = ramp_up.acquire();
+                        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        let elapsed = ramp_up.start.elapsed();
+                        if elapsed < PERIOD / 4 {
+                            early_peak.fetch_max(now_running, Ordering::SeqCst);
+                        } else if elapsed >= PERIOD {
+                            late_peak.fetch_max(now_running, Ordering::SeqCst);
+                        }
+                        thread::sleep(Duration::from_millis(5));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert!(early_peak.load(Ordering::SeqCst) <= 1);
+        assert_eq!(late_peak.load(Ordering::SeqCst), 4);
+    }
+}
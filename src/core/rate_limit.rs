@@ -0,0 +1,41 @@
+use std::{
+    cmp::max,
+    num::NonZeroUsize,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Spaces out entry creations (or bytes written) evenly across every task such that no more than
+/// the given number happen per second.
+#[derive(Debug)]
+pub struct RateLimiter {
+    secs_per_permit: f64,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(per_sec: NonZeroUsize) -> Self {
+        Self {
+            secs_per_permit: 1. / per_sec.get() as f64,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Blocks until the caller's turn to create an entry has come.
+    pub fn acquire(&self) {
+        self.acquire_many(1);
+    }
+
+    /// Blocks until the caller's turn to use this many permits, such as bytes, has come.
+    pub fn acquire_many(&self, permits: usize) {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            // Idle time doesn't accumulate into a burst
+            *next = max(*next, Instant::now())
+                + Duration::from_secs_f64(permits as f64 * self.secs_per_permit);
+            *next
+        };
+        thread::sleep(slot.saturating_duration_since(Instant::now()));
+    }
+}
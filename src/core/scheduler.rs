@@ -1,46 +1,223 @@
-use std::{collections::VecDeque, num::NonZeroUsize, ops::AddAssign, path::PathBuf};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io,
+    io::Write,
+    mem,
+    num::NonZeroUsize,
+    ops::AddAssign,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use anyhow::Context;
 use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use rand::{distributions::Distribution, RngCore};
+use serde::{Deserialize, Serialize};
+use tokio::task;
 use tracing::{event, span, Level};
 
+#[cfg(target_os = "linux")]
+use crate::core::io_stats::IoCounters;
 use crate::{
     core::{
-        files::GeneratorTaskOutcome,
-        tasks::{QueueErrors, TaskGenerator},
+        checkpoint::Checkpoint,
+        disk_budget::DiskBudget,
+        files::{GeneratorTaskOutcome, NodeSample, SampleSender},
+        ramp_up::RampUp,
+        tasks::{Job, QueueErrors, TaskGenerator},
     },
-    utils::{with_dir_name, FastPathBuf},
+    utils::{FastPathBuf, Naming},
 };
 
+#[derive(Debug, Copy, Clone, Default)]
 pub struct GeneratorStats {
     pub files: usize,
     pub dirs: usize,
+    pub fifos: usize,
+    pub symlinks: usize,
     pub bytes: usize,
+    pub errors: usize,
 }
 
 impl AddAssign<&GeneratorTaskOutcome> for GeneratorStats {
     fn add_assign(&mut self, rhs: &GeneratorTaskOutcome) {
         self.files += rhs.files_generated;
         self.dirs += rhs.dirs_generated;
+        self.fifos += rhs.fifos_generated;
+        self.symlinks += rhs.symlinks_generated;
         self.bytes += rhs.bytes_generated;
+        self.errors += rhs.errors;
+    }
+}
+
+impl AddAssign for GeneratorStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.files += rhs.files;
+        self.dirs += rhs.dirs;
+        self.fifos += rhs.fifos;
+        self.symlinks += rhs.symlinks;
+        self.bytes += rhs.bytes;
+        self.errors += rhs.errors;
+    }
+}
+
+/// Caps how deep each branch hanging off the root directory grows such that leaves end up at
+/// varied depths.
+pub struct LeafDepths<D, R> {
+    pub distr: D,
+    pub random: R,
+}
+
+impl<D: Distribution<f64>, R: RngCore> LeafDepths<D, R> {
+    fn sample(&mut self, max_depth: usize) -> usize {
+        (self.distr.sample(&mut self.random).round() as usize).clamp(1, max_depth)
+    }
+}
+
+/// Receives the running totals as directories finish being populated.
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(&GeneratorStats) + Send + Sync>);
+
+impl<F: Fn(&GeneratorStats) + Send + Sync + 'static> From<F> for ProgressHook {
+    fn from(f: F) -> Self {
+        Self(Arc::new(f))
     }
 }
 
+impl ProgressHook {
+    /// Writes every tick to `out` as a line of JSON holding the totals so far and the number of
+    /// milliseconds elapsed since the hook was created.
+    pub fn json_lines(out: impl Write + Send + 'static) -> Self {
+        Self::json_lines_with(out, || None::<()>)
+    }
+
+    /// Like [ProgressHook::json_lines], but each tick also holds an `io` object with the kernel's
+    /// write counters for this process accumulated since the hook was created.
+    #[cfg(target_os = "linux")]
+    pub fn json_lines_with_io(out: impl Write + Send + 'static) -> Self {
+        let start = IoCounters::read().unwrap_or_default();
+        Self::json_lines_with(out, move || IoCounters::read().ok().map(|now| now.since(&start)))
+    }
+
+    fn json_lines_with<T: Serialize>(
+        out: impl Write + Send + 'static,
+        io_counters: impl Fn() -> Option<T> + Send + Sync + 'static,
+    ) -> Self {
+        #[derive(Serialize)]
+        struct Tick<T> {
+            files: usize,
+            dirs: usize,
+            bytes: usize,
+            elapsed_ms: u64,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            io: Option<T>,
+        }
+
+        let start = Instant::now();
+        let out = Mutex::new(out);
+        Self::from(move |stats: &GeneratorStats| {
+            let tick = Tick {
+                files: stats.files,
+                dirs: stats.dirs,
+                bytes: stats.bytes,
+                elapsed_ms: start.elapsed().as_millis() as u64,
+                io: io_counters(),
+            };
+            let mut out = out.lock().unwrap();
+            // Progress is best effort, so a reader going away shouldn't abort generation
+            let _ = serde_json::to_writer(&mut *out, &tick)
+                .map_err(io::Error::from)
+                .and_then(|_| writeln!(out))
+                .and_then(|_| out.flush());
+        })
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// How often the progress hook is invoked.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressInterval {
+    /// Report every time new files have been created.
+    EveryFile,
+    /// Report each time another N files have been created.
+    EveryNFiles(usize),
+    /// Report at most once per duration.
+    EveryDuration(Duration),
+}
+
+impl Default for ProgressInterval {
+    fn default() -> Self {
+        Self::EveryDuration(Duration::from_millis(100))
+    }
+}
+
+pub struct ProgressReporter {
+    hook: ProgressHook,
+    interval: ProgressInterval,
+    last_files: usize,
+    last_emitted: Instant,
+}
+
+impl ProgressReporter {
+    pub fn new(hook: ProgressHook, interval: ProgressInterval) -> Self {
+        Self {
+            hook,
+            interval,
+            last_files: 0,
+            last_emitted: Instant::now(),
+        }
+    }
+
+    fn maybe_report(&mut self, stats: &GeneratorStats) {
+        let due = match self.interval {
+            ProgressInterval::EveryFile => stats.files > self.last_files,
+            ProgressInterval::EveryNFiles(n) => stats.files / n > self.last_files / n,
+            ProgressInterval::EveryDuration(d) => self.last_emitted.elapsed() >= d,
+        };
+        if !due {
+            return;
+        }
+
+        self.last_files = stats.files;
+        self.last_emitted = Instant::now();
+        (self.hook.0)(stats);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     root_dir: PathBuf,
     max_depth: usize,
     parallelism: NonZeroUsize,
+    task_granularity: Option<usize>,
+    ramp_up: Option<Arc<RampUp>>,
+    naming: Naming,
+    mut leaf_depths: Option<LeafDepths<impl Distribution<f64>, impl RngCore>>,
+    mut progress: Option<ProgressReporter>,
+    checkpoint: Option<Arc<Checkpoint>>,
+    budget: Option<Arc<DiskBudget>>,
+    samples: Option<SampleSender>,
     mut generator: impl TaskGenerator,
 ) -> CliResult<GeneratorStats> {
     let mut stack = Vec::with_capacity(max_depth);
     // Minus 1 because VecDeque adds 1 and then rounds to a power of 2
     let mut tasks = VecDeque::with_capacity(parallelism.get().pow(2) - 1);
     let mut target_dir = FastPathBuf::from(root_dir);
-    let mut stats = GeneratorStats {
-        files: 0,
-        dirs: 0,
-        bytes: 0,
-    };
+    let mut stats = GeneratorStats::default();
+
+    // The depth each of the root's subdirectories is allowed to grow to
+    let mut branch_depths = Vec::new();
+    // The jobs of directories at least task_granularity levels deep, waiting to share a task with
+    // the rest of their subtree
+    #[cfg(not(dry_run))]
+    let mut batch = Vec::<Job>::new();
 
     let mut vec_pool = Vec::with_capacity(max_depth);
     let mut path_pool = Vec::with_capacity(tasks.capacity() / 2);
@@ -72,6 +249,12 @@ pub async fn run(
                 let outcome = task;
 
                 stats += &outcome;
+                if let Some(progress) = &mut progress {
+                    progress.maybe_report(&stats);
+                }
+                if let Some(checkpoint) = &checkpoint {
+                    checkpoint.complete_task()?;
+                }
 
                 path_pool.push(outcome.pool_return_file);
                 if let Some(mut vec) = outcome.pool_return_byte_counts {
@@ -82,14 +265,71 @@ pub async fn run(
         };
     }
 
+    macro_rules! spawn {
+        ($job:expr) => {{
+            let job = $job;
+            let ramp_up = ramp_up.clone();
+            task::spawn_blocking(move || {
+                let _permit = ramp_up.as_deref().map(RampUp::acquire);
+                job()
+            })
+        }};
+    }
+
+    macro_rules! spawn_batch {
+        () => {
+            #[cfg(not(dry_run))]
+            if !batch.is_empty() {
+                if tasks.len() == tasks.capacity() {
+                    flush_tasks!();
+                }
+                let jobs = mem::take(&mut batch);
+                tasks.push_back(spawn!(move || run_batch(jobs)));
+            }
+        };
+    }
+
+    macro_rules! push_task {
+        ($task:expr, $depth:expr) => {
+            #[cfg(not(dry_run))]
+            if task_granularity.is_some_and(|granularity| $depth >= *granularity) {
+                batch.push($task);
+                if batch.len() == tasks.capacity() {
+                    spawn_batch!();
+                }
+            } else {
+                tasks.push_back(spawn!($task));
+            }
+            #[cfg(dry_run)]
+            tasks.push_back($task);
+        };
+    }
+
+    macro_rules! record_sample {
+        ($dir:expr, $files:expr, $dirs:expr) => {
+            if let Some(samples) = &samples {
+                // A failed writer reports its own error once generation is done
+                let _ = samples.send(NodeSample {
+                    dir: $dir,
+                    files: $files,
+                    dirs: $dirs,
+                });
+            }
+        };
+    }
+
     match generator.queue_gen(target_dir.clone(), max_depth > 0, &mut byte_counts_pool) {
         Ok(outcome) => {
-            tasks.push_back(outcome.task);
+            record_sample!(target_dir.to_path_buf(), outcome.num_files, outcome.num_dirs);
+            push_task!(outcome.task, 0);
             if outcome.num_dirs > 0 {
                 stack.push((1, vec![outcome.num_dirs]));
             }
         }
-        Err(QueueErrors::NothingToDo(path)) => path_pool.push(path),
+        Err(QueueErrors::NothingToDo(path)) => {
+            record_sample!(path.to_path_buf(), 0, 0);
+            path_pool.push(path);
+        }
     };
 
     let gen_span = span!(Level::TRACE, "dir_gen");
@@ -98,12 +338,16 @@ pub async fn run(
 
         if num_dirs_to_generate == None {
             vec_pool.push(unsafe { stack.pop().unwrap_unchecked().1 });
+            // The subtree is complete, so its batch can get going
+            if task_granularity.is_some_and(|granularity| stack.len() <= *granularity) {
+                spawn_batch!();
+            }
 
             if let Some((tot_dirs, dirs_left)) = stack.last() {
                 target_dir.pop();
 
                 if !dirs_left.is_empty() {
-                    with_dir_name(*tot_dirs - dirs_left.len(), |s| {
+                    naming.with_dir_name(stack.len() - 2, *tot_dirs - dirs_left.len(), |s| {
                         target_dir.set_file_name(s);
                     });
                 }
@@ -115,7 +359,11 @@ pub async fn run(
         let num_dirs_to_generate = unsafe { num_dirs_to_generate.unwrap_unchecked() };
         let next_stack_dir = *tot_dirs - dirs_left.len();
         let is_completing = dirs_left.is_empty();
-        let gen_next_dirs = stack.len() < max_depth;
+        let mut gen_next_dirs = stack.len() < max_depth;
+        if leaf_depths.is_some() && let Some((branch_tot_dirs, branch_dirs_left)) = stack.get(1) {
+            let branch = branch_tot_dirs - branch_dirs_left.len() - 1;
+            gen_next_dirs &= stack.len() < branch_depths[branch];
+        }
 
         if tasks.len() + num_dirs_to_generate >= tasks.capacity() {
             flush_tasks!();
@@ -135,9 +383,19 @@ pub async fn run(
         // minimize lock contention).
         let raw_next_dirs = next_dirs.spare_capacity_mut();
 
-        let span_guard = gen_span.enter();
         for i in 0..num_dirs_to_generate {
-            let path = with_dir_name(i, |s| {
+            // Wide directories would otherwise queue a task for every single child before any of
+            // them are awaited.
+            if tasks.len() == tasks.capacity() {
+                flush_tasks!();
+            }
+            // There's no point in queueing tasks that will have nothing to create
+            if budget.as_ref().is_some_and(|budget| budget.exhausted()) {
+                break 'outer;
+            }
+
+            let _span_guard = gen_span.enter();
+            let path = naming.with_dir_name(stack.len() - 1, i, |s| {
                 let mut buf = path_pool.pop().unwrap_or_else(|| {
                     // Space for inner, the path seperator, name, and a NUL terminator
                     FastPathBuf::with_capacity(target_dir.capacity() + 1 + s.len() + 1)
@@ -149,15 +407,28 @@ pub async fn run(
                 buf
             });
 
-            let num_dirs = match generator.queue_gen(path, gen_next_dirs, &mut byte_counts_pool) {
+            let gen_dirs = match &mut leaf_depths {
+                Some(leaf_depths) if stack.len() == 1 => {
+                    let depth = leaf_depths.sample(max_depth);
+                    branch_depths.push(depth);
+                    depth > 1
+                }
+                _ => gen_next_dirs,
+            };
+            let sampled_dir = samples.as_ref().map(|_| path.to_path_buf());
+            let num_dirs = match generator.queue_gen(path, gen_dirs, &mut byte_counts_pool) {
                 Ok(outcome) => {
-                    tasks.push_back(outcome.task);
+                    if let Some(dir) = sampled_dir {
+                        record_sample!(dir, outcome.num_files, outcome.num_dirs);
+                    }
+                    push_task!(outcome.task, stack.len());
                     if outcome.done {
                         break 'outer;
                     }
                     outcome.num_dirs
                 }
                 Err(QueueErrors::NothingToDo(path)) => {
+                    record_sample!(path.to_path_buf(), 0, 0);
                     path_pool.push(path);
                     0
                 }
@@ -167,7 +438,6 @@ pub async fn run(
                 raw_next_dirs[num_dirs_to_generate - i - 1].write(num_dirs);
             }
         }
-        drop(span_guard);
 
         if gen_next_dirs {
             unsafe {
@@ -175,17 +445,30 @@ pub async fn run(
             }
             stack.push((num_dirs_to_generate, next_dirs));
 
-            with_dir_name(0, |s| target_dir.push(s));
+            naming.with_dir_name(stack.len() - 2, 0, |s| target_dir.push(s));
         } else {
             if !is_completing {
-                with_dir_name(next_stack_dir, |s| target_dir.set_file_name(s));
+                naming.with_dir_name(stack.len() - 2, next_stack_dir, |s| {
+                    target_dir.set_file_name(s);
+                });
             }
             vec_pool.push(next_dirs);
         }
     }
 
+    spawn_batch!();
+    let root_dir = target_dir.clone();
     if let Ok(outcome) = generator.maybe_queue_final_gen(target_dir, &mut byte_counts_pool) {
-        tasks.push_back(outcome.task);
+        push_task!(outcome.task, 0);
+    }
+    // Files that didn't fit within the maximum directory width get directories of their own
+    while let Ok(outcome) =
+        generator.maybe_queue_overflow_gen(root_dir.clone(), &mut byte_counts_pool)
+    {
+        if tasks.len() == tasks.capacity() {
+            flush_tasks!();
+        }
+        push_task!(outcome.task, 0);
     }
 
     #[cfg(not(dry_run))]
@@ -194,11 +477,35 @@ pub async fn run(
             .await
             .context("Failed to retrieve task result")
             .with_code(exitcode::SOFTWARE)??;
+        if let Some(progress) = &mut progress {
+            progress.maybe_report(&stats);
+        }
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.complete_task()?;
+        }
     }
     #[cfg(dry_run)]
     for task in tasks {
         stats += &task;
+        if let Some(progress) = &mut progress {
+            progress.maybe_report(&stats);
+        }
     }
 
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.finish()?;
+    }
     Ok(stats)
 }
+
+/// Runs the jobs of a batch one after the other, adding up their outcomes.
+#[cfg(not(dry_run))]
+fn run_batch(jobs: Vec<Job>) -> CliResult<GeneratorTaskOutcome> {
+    let mut jobs = jobs.into_iter();
+    // Batches are only spawned once they hold a job
+    let mut outcome = jobs.next().unwrap()()?;
+    for job in jobs {
+        outcome.merge(job()?);
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,64 @@
+use std::{
+    fmt, io,
+    sync::{Arc, Mutex},
+};
+
+use log::warn;
+use syslog::{Facility, Formatter3164, Logger, LoggerBackend};
+
+/// How important a message sent to the system log is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Error,
+}
+
+/// Somewhere system log messages can be delivered to.
+pub trait SyslogSink: Send {
+    fn send(&mut self, severity: Severity, message: &str) -> io::Result<()>;
+}
+
+impl SyslogSink for Logger<LoggerBackend, Formatter3164> {
+    fn send(&mut self, severity: Severity, message: &str) -> io::Result<()> {
+        match severity {
+            Severity::Info => self.info(message),
+            Severity::Error => self.err(message),
+        }
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    }
+}
+
+/// Reports the outcome of a run to the system log in addition to stdout.
+#[derive(Clone)]
+pub struct Syslog(Arc<Mutex<dyn SyslogSink>>);
+
+impl Syslog {
+    /// Connects to the local syslog daemon, which journald also listens on, under the user
+    /// facility.
+    pub fn connect() -> io::Result<Self> {
+        let formatter = Formatter3164 {
+            facility: Facility::LOG_USER,
+            ..Formatter3164::default()
+        };
+        syslog::unix(formatter)
+            .map(Self::new)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    }
+
+    pub fn new(sink: impl SyslogSink + 'static) -> Self {
+        Self(Arc::new(Mutex::new(sink)))
+    }
+
+    /// Sends a message, only warning on failure since the log is a secondary output.
+    pub fn log(&self, severity: Severity, message: &str) {
+        if let Err(e) = self.0.lock().unwrap().send(severity, message) {
+            warn!("Failed to write to the system log: {}", e);
+        }
+    }
+}
+
+impl fmt::Debug for Syslog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Syslog")
+    }
+}
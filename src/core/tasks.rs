@@ -1,27 +1,41 @@
-use std::{cmp::min, num::NonZeroUsize};
+use std::{
+    cmp::{max, min},
+    mem,
+    num::NonZeroUsize,
+    path::Path,
+};
 
 use cli_errors::CliResult;
-use rand::{distributions::Distribution, RngCore};
-use tokio::{task, task::JoinHandle};
+use rand::{distributions::Distribution, RngCore, SeedableRng};
+use rand_distr::Poisson;
 
 use crate::{
     core::{
         file_contents::{
-            NoGeneratedFileContents, OnTheFlyGeneratedFileContents, PreDefinedGeneratedFileContents,
+            ContentSource, DuplicateGeneratedFileContents, DuplicateGroups, EmptyTailFileContents,
+            NoGeneratedFileContents, OnTheFlyGeneratedFileContents,
+            PreDefinedGeneratedFileContents, SampledFileContents,
+        },
+        files::{
+            create_files_and_dirs, skip_files_and_dirs, DirSeeds, GeneratorTaskOutcome,
+            GeneratorTaskParams, TaskOptions,
         },
-        files::{create_files_and_dirs, GeneratorTaskOutcome, GeneratorTaskParams},
     },
-    utils::FastPathBuf,
+    utils::{FastPathBuf, Naming},
 };
 
 pub type QueueResult = Result<QueueOutcome, QueueErrors>;
 
+/// The work of a single directory, left for the scheduler to run on a blocking thread.
+pub type Job = Box<dyn FnOnce() -> CliResult<GeneratorTaskOutcome> + Send>;
+
 pub struct QueueOutcome {
     #[cfg(not(dry_run))]
-    pub task: JoinHandle<CliResult<GeneratorTaskOutcome>>,
+    pub task: Job,
     #[cfg(dry_run)]
     pub task: GeneratorTaskOutcome,
 
+    pub num_files: usize,
     pub num_dirs: usize,
     pub done: bool,
 }
@@ -43,6 +57,16 @@ pub trait TaskGenerator {
         Err(QueueErrors::NothingToDo(file))
     }
 
+    /// Queues the next directory of files left over by the maximum directory width, given the
+    /// root directory. Only called once the final task has been queued.
+    fn maybe_queue_overflow_gen(
+        &mut self,
+        file: FastPathBuf,
+        _: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        Err(QueueErrors::NothingToDo(file))
+    }
+
     fn uses_byte_counts_pool(&self) -> bool {
         false
     }
@@ -50,19 +74,35 @@ pub trait TaskGenerator {
 
 macro_rules! queue {
     ($params:expr, $done:expr) => {{
-        let params = $params;
-        if params.num_files > 0 || params.num_dirs > 0 {
+        let mut params = $params;
+        if params.num_files > 0 || params.num_dirs > 0 || params.missing_dirs > 0 {
+            if let Some(positions) = &mut params.options.positions {
+                positions.claim(params.num_files);
+            }
             Ok(QueueOutcome {
+                num_files: params.num_files,
                 num_dirs: params.num_dirs,
                 done: $done,
 
                 #[cfg(not(dry_run))]
-                task: task::spawn_blocking(move || create_files_and_dirs(params)),
+                task: if params
+                    .options
+                    .checkpoint
+                    .as_ref()
+                    .is_some_and(|checkpoint| checkpoint.skip_next())
+                {
+                    Box::new(move || Ok(skip_files_and_dirs(params)))
+                } else {
+                    Box::new(move || create_files_and_dirs(params))
+                },
                 #[cfg(dry_run)]
                 task: GeneratorTaskOutcome {
                     files_generated: params.num_files,
-                    dirs_generated: params.num_dirs,
+                    dirs_generated: params.num_dirs + params.missing_dirs,
+                    fifos_generated: 0,
+                    symlinks_generated: 0,
                     bytes_generated: 0,
+                    errors: 0,
 
                     pool_return_file: params.target_dir,
                     pool_return_byte_counts: None,
@@ -74,10 +114,329 @@ macro_rules! queue {
     }};
 }
 
+/// Samples the number of directories to create from the dedicated structure stream if there is
+/// one, such that the shape of the tree doesn't depend on the file counts sampled in between.
+#[inline]
+fn sample_num_dirs<R: RngCore>(
+    num_dirs_distr: &impl Distribution<f64>,
+    random: &mut R,
+    structure_random: &mut Option<R>,
+) -> usize {
+    match structure_random {
+        Some(structure_random) => num_dirs_distr.sample(structure_random),
+        None => num_dirs_distr.sample(random),
+    }
+    .round() as usize
+}
+
+/// Samples the sizes of a directory's files up front and sorts them such that files are named in
+/// ascending order of size.
+fn sorted_byte_counts(
+    num_files: usize,
+    num_bytes_distr: &impl Distribution<f64>,
+    random: &mut impl RngCore,
+) -> Vec<usize> {
+    let mut byte_counts = (0..num_files)
+        .map(|_| num_bytes_distr.sample(random).round() as usize)
+        .collect::<Vec<_>>();
+    byte_counts.sort_unstable();
+    byte_counts
+}
+
+/// The size of file `i` when splitting `bytes` between `num_files` files as evenly as possible in
+/// multiples of `alignment`. Whatever can't be aligned goes to the first file.
+pub fn even_byte_count(
+    bytes: usize,
+    num_files: usize,
+    alignment: Option<NonZeroUsize>,
+    i: usize,
+) -> usize {
+    let alignment = alignment.map_or(1, NonZeroUsize::get);
+    let units = bytes / alignment;
+    let base = units / num_files;
+    let leftovers = units % num_files;
+    let count = (if i < leftovers { base + 1 } else { base }) * alignment;
+    if i == 0 {
+        count + bytes % alignment
+    } else {
+        count
+    }
+}
+
+/// Keeps track of the files queued so far such that any shortfall below a minimum number of files
+/// can be topped up in the root directory once the tree has been generated.
+#[derive(Debug, Default)]
+pub struct FilesFloor {
+    min_files: usize,
+    files_queued: usize,
+    root_num_files: Option<usize>,
+    root_file_name_width: usize,
+}
+
+impl FilesFloor {
+    pub fn new(min_files: usize) -> Self {
+        Self {
+            min_files,
+            ..Default::default()
+        }
+    }
+
+    /// Records the queued files, returning the width their names should be padded to.
+    fn track(&mut self, naming: &Naming, num_files: usize) -> usize {
+        self.files_queued += num_files;
+        if self.root_num_files.is_none() {
+            self.root_num_files = Some(num_files);
+            // The root directory receives the top up, so its file names must be wide enough to
+            // accommodate them.
+            self.root_file_name_width =
+                naming.file_name_width(max(num_files, self.min_files).saturating_sub(1));
+            self.root_file_name_width
+        } else {
+            naming.file_name_width(num_files.saturating_sub(1))
+        }
+    }
+
+    /// The number of missing files, the index to start naming them at, and their name width.
+    fn top_up(&self) -> (usize, usize, usize) {
+        (
+            self.min_files.saturating_sub(self.files_queued),
+            self.root_num_files.unwrap_or(0),
+            self.root_file_name_width,
+        )
+    }
+}
+
+/// Keeps track of the directories queued so far such that any shortfall below a minimum number of
+/// directories can be topped up in the root directory once the tree has been generated. The top up
+/// is made of empty directories, which fit within any depth of at least one.
+#[derive(Debug, Default)]
+pub struct DirsFloor {
+    min_dirs: usize,
+    dirs_queued: usize,
+    root_num_dirs: Option<usize>,
+}
+
+impl DirsFloor {
+    pub fn new(min_dirs: usize) -> Self {
+        Self {
+            min_dirs,
+            ..Default::default()
+        }
+    }
+
+    /// Records the queued directories.
+    fn track(&mut self, num_dirs: usize) {
+        self.dirs_queued += num_dirs;
+        self.root_num_dirs.get_or_insert(num_dirs);
+    }
+
+    /// The number of missing directories and the index to start naming them at.
+    fn top_up(&self) -> (usize, usize) {
+        (
+            self.min_dirs.saturating_sub(self.dirs_queued),
+            self.root_num_dirs.unwrap_or(0),
+        )
+    }
+}
+
+/// Scatters an exact number of empty files across the tree. Whatever is left over once the tree
+/// has been generated is placed in the root directory.
+#[derive(Debug)]
+pub struct EmptyFiles {
+    per_dir_distr: Poisson<f64>,
+    remaining: usize,
+}
+
+impl EmptyFiles {
+    pub fn new(num_files: usize, expected_dirs: usize) -> Option<Self> {
+        if num_files == 0 {
+            return None;
+        }
+        Some(Self {
+            per_dir_distr: Poisson::new(num_files as f64 / max(expected_dirs, 1) as f64).unwrap(),
+            remaining: num_files,
+        })
+    }
+
+    fn take(&mut self, random: &mut impl RngCore) -> usize {
+        if self.remaining == 0 {
+            return 0;
+        }
+        let num_files = min(
+            self.remaining,
+            self.per_dir_distr.sample(random).round() as usize,
+        );
+        self.remaining -= num_files;
+        num_files
+    }
+
+    fn take_rest(&mut self) -> usize {
+        mem::take(&mut self.remaining)
+    }
+}
+
+/// Samples how many empty files to add to the next directory.
+#[inline]
+fn take_empty_files(empty_files: &mut Option<EmptyFiles>, random: &mut impl RngCore) -> usize {
+    empty_files
+        .as_mut()
+        .map_or(0, |empty_files| empty_files.take(random))
+}
+
+/// Hands out all remaining empty files.
+#[inline]
+fn take_rest_empty_files(empty_files: &mut Option<EmptyFiles>) -> usize {
+    empty_files.as_mut().map_or(0, EmptyFiles::take_rest)
+}
+
+/// Caps the number of entries in each directory. Files that don't fit are spilled into the
+/// directories queued next, making room for a new subdirectory to take them in where possible.
+/// Whatever is still left over once the tree has been generated goes into a new directory in the
+/// root, which the root keeps a slot free for.
+#[derive(Debug, Default)]
+pub struct DirWidth {
+    max: Option<NonZeroUsize>,
+    max_depth: usize,
+    spilled: usize,
+    root_clamped: bool,
+    /// The directories of leftover files still to be queued as their path of directory indices
+    /// below the root, number of files, and number of subdirectories, last one first.
+    overflow: Vec<(Vec<usize>, usize, usize)>,
+}
+
+impl DirWidth {
+    pub fn new(max: Option<NonZeroUsize>, max_depth: usize) -> Self {
+        Self {
+            max,
+            max_depth,
+            ..Default::default()
+        }
+    }
+
+    /// Fits a directory's sampled entries and any previously spilled files within the cap.
+    fn clamp(&mut self, num_files: usize, num_dirs: usize, gen_dirs: bool) -> (usize, usize) {
+        let mut max = match self.max {
+            None => return (num_files, num_dirs),
+            Some(max) => max.get(),
+        };
+        if !mem::replace(&mut self.root_clamped, true) && max > 1 {
+            max -= 1;
+        }
+
+        let num_files = num_files + mem::take(&mut self.spilled);
+        let mut num_dirs = min(num_dirs, max);
+        let mut kept_files = min(num_files, max - num_dirs);
+        if kept_files < num_files && gen_dirs && kept_files > 0 {
+            kept_files -= 1;
+            num_dirs += 1;
+        }
+        self.spilled = num_files - kept_files;
+        (kept_files, num_dirs)
+    }
+
+    fn spilling(&self) -> bool {
+        self.spilled > 0
+    }
+
+    /// Plans the directories taking in the spilled files along with `num_files` more files the
+    /// root would otherwise receive once the tree has been generated. Returns the files left for
+    /// the root and the number of new directories it must create, starting at `dir_index`.
+    fn plan_overflow(&mut self, num_files: usize, dir_index: usize) -> (usize, usize) {
+        let max = match self.max {
+            None => return (num_files, 0),
+            Some(max) => max.get(),
+        };
+        let num_files = num_files + mem::take(&mut self.spilled);
+        if num_files == 0 {
+            return (0, 0);
+        }
+
+        // The new directory already sits one level below the root
+        self.place_overflow(vec![dir_index], num_files, max, self.max_depth.saturating_sub(1));
+        self.overflow.reverse();
+        (0, 1)
+    }
+
+    /// Splits the files evenly between as few subdirectories as keep each directory within the
+    /// cap, unless the maximum depth has been reached.
+    fn place_overflow(&mut self, dir: Vec<usize>, num_files: usize, max: usize, levels: usize) {
+        if num_files <= max || levels == 0 {
+            self.overflow.push((dir, num_files, 0));
+            return;
+        }
+
+        let num_dirs = min(max, num_files.div_ceil(max));
+        self.overflow.push((dir.clone(), 0, num_dirs));
+        for i in 0..num_dirs {
+            let mut subdir = dir.clone();
+            subdir.push(i);
+            let share = num_files / num_dirs + usize::from(i < num_files % num_dirs);
+            self.place_overflow(subdir, share, max, levels - 1);
+        }
+    }
+
+    /// Pushes the next directory of leftover files onto the root, returning its number of files
+    /// and subdirectories.
+    fn next_overflow(&mut self, naming: &Naming, root: &mut FastPathBuf) -> Option<(usize, usize)> {
+        let (dir, num_files, num_dirs) = self.overflow.pop()?;
+        for (depth, &i) in dir.iter().enumerate() {
+            naming.with_dir_name(depth, i, |s| root.push(s));
+        }
+        Some((num_files, num_dirs))
+    }
+}
+
+/// Keeps the top levels of the tree free of files. The files sampled for those directories are
+/// carried over to the directories queued next, and each of them gets at least one subdirectory
+/// such that the carried files always reach a deeper level.
+#[derive(Debug, Default)]
+pub struct StructuralLevels {
+    levels: usize,
+    root_depth: usize,
+    carried: usize,
+}
+
+impl StructuralLevels {
+    pub fn new(levels: usize, root_dir: &Path) -> Self {
+        Self {
+            levels,
+            root_depth: root_dir.components().count(),
+            carried: 0,
+        }
+    }
+
+    /// Empties a structural directory of its files, handing them to the next one that isn't.
+    fn clamp(
+        &mut self,
+        dir: &Path,
+        num_files: usize,
+        num_dirs: usize,
+        gen_dirs: bool,
+    ) -> (usize, usize) {
+        if self.levels == 0 {
+            return (num_files, num_dirs);
+        }
+
+        if dir.components().count() - self.root_depth < self.levels {
+            self.carried += num_files;
+            (0, if gen_dirs { max(num_dirs, 1) } else { num_dirs })
+        } else {
+            (num_files + mem::take(&mut self.carried), num_dirs)
+        }
+    }
+}
+
 pub struct FilesNoContentsGenerator<DF, DD, R> {
     pub num_files_distr: DF,
     pub num_dirs_distr: DD,
     pub random: R,
+    pub structure_random: Option<R>,
+    pub options: TaskOptions,
+    pub floor: FilesFloor,
+    pub dirs_floor: DirsFloor,
+    pub empty_files: Option<EmptyFiles>,
+    pub width: DirWidth,
+    pub levels: StructuralLevels,
 }
 
 impl<DF: Distribution<f64>, DD: Distribution<f64>, R: RngCore> TaskGenerator
@@ -90,20 +449,83 @@ impl<DF: Distribution<f64>, DD: Distribution<f64>, R: RngCore> TaskGenerator
         _: &mut Vec<Vec<usize>>,
     ) -> QueueResult {
         let num_files = self.num_files_distr.sample(&mut self.random).round() as usize;
+        let num_dirs = if gen_dirs {
+            sample_num_dirs(
+                &self.num_dirs_distr,
+                &mut self.random,
+                &mut self.structure_random,
+            )
+        } else {
+            0
+        };
+        let (num_files, num_dirs) = self.width.clamp(num_files, num_dirs, gen_dirs);
+        let (num_files, num_dirs) = self.levels.clamp(&file, num_files, num_dirs, gen_dirs);
+        // Every file is empty anyway
+        let num_files = num_files + take_empty_files(&mut self.empty_files, &mut self.random);
+        self.dirs_floor.track(num_dirs);
         let params = GeneratorTaskParams {
             target_dir: file,
             num_files,
-            num_dirs: if gen_dirs {
-                self.num_dirs_distr.sample(&mut self.random).round() as usize
-            } else {
-                0
-            },
+            num_dirs,
             file_offset: 0,
+            dir_offset: 0,
+            missing_dirs: 0,
+            file_name_width: self.floor.track(&self.options.naming, num_files),
+            options: self.options.clone(),
             file_contents: NoGeneratedFileContents,
         };
 
         queue!(params, false)
     }
+
+    fn maybe_queue_final_gen(&mut self, file: FastPathBuf, _: &mut Vec<Vec<usize>>) -> QueueResult {
+        let (num_files, offset, file_name_width) = self.floor.top_up();
+        let num_files = num_files + take_rest_empty_files(&mut self.empty_files);
+        let (num_dirs, dir_offset) = self.dirs_floor.top_up();
+        let (_, overflow_dirs) = self.width.plan_overflow(0, dir_offset + num_dirs);
+
+        queue!(
+            GeneratorTaskParams {
+                target_dir: file,
+                num_files,
+                num_dirs: num_dirs + overflow_dirs,
+                file_offset: offset,
+                dir_offset,
+                missing_dirs: 0,
+                file_name_width,
+                options: self.options.clone(),
+                file_contents: NoGeneratedFileContents,
+            },
+            true
+        )
+    }
+
+    fn maybe_queue_overflow_gen(
+        &mut self,
+        mut file: FastPathBuf,
+        _: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        let overflow = self.width.next_overflow(&self.options.naming, &mut file);
+        let (num_files, num_dirs) = match overflow {
+            None => return Err(QueueErrors::NothingToDo(file)),
+            Some(entries) => entries,
+        };
+
+        queue!(
+            GeneratorTaskParams {
+                target_dir: file,
+                num_files,
+                num_dirs,
+                file_offset: 0,
+                dir_offset: 0,
+                missing_dirs: 0,
+                file_name_width: self.options.naming.file_name_width(num_files.saturating_sub(1)),
+                options: self.options.clone(),
+                file_contents: NoGeneratedFileContents,
+            },
+            true
+        )
+    }
 }
 
 pub struct FilesAndContentsGenerator<DF, DD, DB, R> {
@@ -111,13 +533,23 @@ pub struct FilesAndContentsGenerator<DF, DD, DB, R> {
     pub num_dirs_distr: DD,
     pub num_bytes_distr: DB,
     pub random: R,
+    pub structure_random: Option<R>,
+    pub options: TaskOptions,
+    pub duplicate_groups: Option<DuplicateGroups>,
+    pub floor: FilesFloor,
+    pub dirs_floor: DirsFloor,
+    pub empty_files: Option<EmptyFiles>,
+    pub width: DirWidth,
+    pub levels: StructuralLevels,
+    pub sort_by_size: bool,
+    pub dir_seeds: Option<DirSeeds>,
 }
 
 impl<
         DF: Distribution<f64>,
         DD: Distribution<f64>,
         DB: Distribution<f64> + Clone + Send + 'static,
-        R: RngCore + Clone + Send + 'static,
+        R: RngCore + SeedableRng + Clone + Send + 'static,
     > TaskGenerator for FilesAndContentsGenerator<DF, DD, DB, R>
 {
     fn queue_gen(
@@ -127,22 +559,454 @@ impl<
         _: &mut Vec<Vec<usize>>,
     ) -> QueueResult {
         let num_files = self.num_files_distr.sample(&mut self.random).round() as usize;
-        let params = GeneratorTaskParams {
-            target_dir: file,
+        let num_dirs = if gen_dirs {
+            sample_num_dirs(
+                &self.num_dirs_distr,
+                &mut self.random,
+                &mut self.structure_random,
+            )
+        } else {
+            0
+        };
+        let (num_files, num_dirs) = self.width.clamp(num_files, num_dirs, gen_dirs);
+        let (num_files, num_dirs) = self.levels.clamp(&file, num_files, num_dirs, gen_dirs);
+        let num_empty_files = take_empty_files(&mut self.empty_files, &mut self.random);
+        let file_name_width = self
+            .floor
+            .track(&self.options.naming, num_files + num_empty_files);
+        self.dirs_floor.track(num_dirs);
+
+        self.queue_gen_internal(
+            file,
             num_files,
-            num_dirs: if gen_dirs {
-                self.num_dirs_distr.sample(&mut self.random).round() as usize
-            } else {
-                0
-            },
-            file_offset: 0,
-            file_contents: OnTheFlyGeneratedFileContents {
-                num_bytes_distr: self.num_bytes_distr.clone(),
-                random: self.random.clone(),
+            num_empty_files,
+            num_dirs,
+            0,
+            0,
+            file_name_width,
+            false,
+        )
+    }
+
+    fn maybe_queue_final_gen(&mut self, file: FastPathBuf, _: &mut Vec<Vec<usize>>) -> QueueResult {
+        let (num_files, offset, file_name_width) = self.floor.top_up();
+        let num_empty_files = take_rest_empty_files(&mut self.empty_files);
+        let (num_dirs, dir_offset) = self.dirs_floor.top_up();
+        let (_, overflow_dirs) = self.width.plan_overflow(0, dir_offset + num_dirs);
+        let num_dirs = num_dirs + overflow_dirs;
+        if num_files == 0 && num_empty_files == 0 && num_dirs == 0 {
+            return Err(QueueErrors::NothingToDo(file));
+        }
+        // Don't hand out the same random state as the last task
+        self.random.next_u64();
+
+        self.queue_gen_internal(
+            file,
+            num_files,
+            num_empty_files,
+            num_dirs,
+            offset,
+            dir_offset,
+            file_name_width,
+            true,
+        )
+    }
+
+    fn maybe_queue_overflow_gen(
+        &mut self,
+        mut file: FastPathBuf,
+        _: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        let overflow = self.width.next_overflow(&self.options.naming, &mut file);
+        let (num_files, num_dirs) = match overflow {
+            None => return Err(QueueErrors::NothingToDo(file)),
+            Some(entries) => entries,
+        };
+        self.random.next_u64();
+
+        let file_name_width = self.options.naming.file_name_width(num_files.saturating_sub(1));
+        self.queue_gen_internal(file, num_files, 0, num_dirs, 0, 0, file_name_width, true)
+    }
+}
+
+impl<
+        DF: Distribution<f64>,
+        DD: Distribution<f64>,
+        DB: Distribution<f64> + Clone + Send + 'static,
+        R: RngCore + SeedableRng + Clone + Send + 'static,
+    > FilesAndContentsGenerator<DF, DD, DB, R>
+{
+    #[allow(clippy::too_many_arguments)]
+    fn queue_gen_internal(
+        &mut self,
+        file: FastPathBuf,
+        num_files: usize,
+        num_empty_files: usize,
+        num_dirs: usize,
+        offset: usize,
+        dir_offset: usize,
+        file_name_width: usize,
+        done: bool,
+    ) -> QueueResult {
+        macro_rules! build_params {
+            ($file_contents:expr) => {{
+                GeneratorTaskParams {
+                    target_dir: file,
+                    num_files: num_files + num_empty_files,
+                    num_dirs,
+                    file_offset: offset,
+                    dir_offset,
+                    missing_dirs: 0,
+                    file_name_width,
+                    options: self.options.clone(),
+                    file_contents: EmptyTailFileContents {
+                        inner: $file_contents,
+                        first_empty: num_files,
+                    },
+                }
+            }};
+        }
+
+        if let Some(groups) = self.duplicate_groups {
+            queue!(
+                build_params!(DuplicateGeneratedFileContents {
+                    num_bytes_distr: self.num_bytes_distr.clone(),
+                    groups,
+                    random: self.random.clone(),
+                }),
+                done
+            )
+        } else if self.sort_by_size {
+            let mut random = self.random.clone();
+            let byte_counts = sorted_byte_counts(num_files, &self.num_bytes_distr, &mut random);
+            queue!(
+                build_params!(PreDefinedGeneratedFileContents {
+                    byte_counts,
+                    random,
+                }),
+                done
+            )
+        } else {
+            let random = match self.dir_seeds {
+                Some(seeds) => R::seed_from_u64(seeds.seed(&file)),
+                None => self.random.clone(),
+            };
+            queue!(
+                build_params!(OnTheFlyGeneratedFileContents {
+                    num_bytes_distr: self.num_bytes_distr.clone(),
+                    random,
+                }),
+                done
+            )
+        }
+    }
+}
+
+/// Regenerates the files of a single directory from the seed and number of files recorded for it
+/// without replaying the rest of the tree.
+pub struct SingleDirGenerator<DB, R> {
+    /// The directory's path relative to the root directory.
+    pub dir: String,
+    pub num_files: usize,
+    pub num_bytes_distr: DB,
+    /// Seeded with the directory's seed.
+    pub random: R,
+    pub options: TaskOptions,
+}
+
+impl<DB: Distribution<f64> + Clone + Send + 'static, R: RngCore + Clone + Send + 'static>
+    TaskGenerator for SingleDirGenerator<DB, R>
+{
+    fn queue_gen(
+        &mut self,
+        mut file: FastPathBuf,
+        _: bool,
+        _: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        if !self.dir.is_empty() {
+            file.push(&self.dir);
+        }
+
+        queue!(
+            GeneratorTaskParams {
+                target_dir: file,
+                num_files: self.num_files,
+                num_dirs: 0,
+                file_offset: 0,
+                dir_offset: 0,
+                missing_dirs: 0,
+                file_name_width: self
+                    .options
+                    .naming
+                    .file_name_width(self.num_files.saturating_sub(1)),
+                options: self.options.clone(),
+                file_contents: EmptyTailFileContents {
+                    inner: OnTheFlyGeneratedFileContents {
+                        num_bytes_distr: self.num_bytes_distr.clone(),
+                        random: self.random.clone(),
+                    },
+                    first_empty: self.num_files,
+                },
             },
+            true
+        )
+    }
+}
+
+/// Generates a single chain of directories with all files placed in the deepest one.
+pub struct ChainGenerator<DB, R> {
+    pub num_files: usize,
+    pub num_bytes_distr: Option<DB>,
+    pub bytes_exact: Option<usize>,
+    pub random: R,
+    pub options: TaskOptions,
+    pub duplicate_groups: Option<DuplicateGroups>,
+    pub empty_files: usize,
+    pub sort_by_size: bool,
+    /// Create the chain this many levels deep with a single `create_dir_all` from the root's task
+    /// instead of one `create_dir` per level. Zero leaves each level to its parent's task.
+    pub batch_depth: usize,
+}
+
+impl<
+        DB: Distribution<f64> + Clone + Send + 'static,
+        R: RngCore + SeedableRng + Clone + Send + 'static,
+    > TaskGenerator for ChainGenerator<DB, R>
+{
+    fn queue_gen(
+        &mut self,
+        mut file: FastPathBuf,
+        gen_dirs: bool,
+        _: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        // The chain is entirely linear, so its deepest directory can be targeted right away with
+        // every level above it created in one go.
+        let missing_dirs = if gen_dirs { self.batch_depth } else { 0 };
+        let depth = self.options.naming.depth(&file);
+        for level in 0..missing_dirs {
+            self.options.naming.with_dir_name(depth + level, 0, |s| file.push(s));
+        }
+        let (num_files, num_empty_files, num_dirs) = if gen_dirs && missing_dirs == 0 {
+            (0, 0, 1)
+        } else {
+            (self.num_files, self.empty_files, 0)
         };
 
-        queue!(params, false)
+        macro_rules! build_params {
+            ($file_contents:expr) => {{
+                GeneratorTaskParams {
+                    target_dir: file,
+                    num_files: num_files + num_empty_files,
+                    num_dirs,
+                    file_offset: 0,
+                    dir_offset: 0,
+                    missing_dirs,
+                    file_name_width: self
+                        .options
+                        .naming
+                        .file_name_width((num_files + num_empty_files).saturating_sub(1)),
+                    options: self.options.clone(),
+                    file_contents: EmptyTailFileContents {
+                        inner: $file_contents,
+                        first_empty: num_files,
+                    },
+                }
+            }};
+        }
+
+        if num_files == 0 {
+            queue!(build_params!(NoGeneratedFileContents), false)
+        } else if let Some(bytes) = self.bytes_exact && bytes > 0 {
+            let alignment = self.options.size_alignment;
+            queue!(
+                build_params!(PreDefinedGeneratedFileContents {
+                    byte_counts: (0..num_files)
+                        .map(|i| even_byte_count(bytes, num_files, alignment, i))
+                        .collect(),
+                    random: self.random.clone(),
+                }),
+                true
+            )
+        } else if let Some(bytes_distr) = &self.num_bytes_distr {
+            if let Some(groups) = self.duplicate_groups {
+                queue!(
+                    build_params!(DuplicateGeneratedFileContents {
+                        num_bytes_distr: bytes_distr.clone(),
+                        groups,
+                        random: self.random.clone(),
+                    }),
+                    true
+                )
+            } else if self.sort_by_size {
+                let mut random = self.random.clone();
+                let byte_counts = sorted_byte_counts(num_files, bytes_distr, &mut random);
+                queue!(
+                    build_params!(PreDefinedGeneratedFileContents {
+                        byte_counts,
+                        random,
+                    }),
+                    true
+                )
+            } else {
+                queue!(
+                    build_params!(OnTheFlyGeneratedFileContents {
+                        num_bytes_distr: bytes_distr.clone(),
+                        random: self.random.clone(),
+                    }),
+                    true
+                )
+            }
+        } else {
+            queue!(build_params!(NoGeneratedFileContents), true)
+        }
+    }
+}
+
+/// Generates a perfectly balanced tree where every directory above the leaves has exactly `fanout`
+/// subdirectories and files are spread as evenly as possible across the leaves.
+pub struct BalancedGenerator<DB, R> {
+    fanout: usize,
+    num_leaves: usize,
+    num_files: usize,
+    num_bytes_distr: Option<DB>,
+    bytes_exact: Option<usize>,
+    random: R,
+    options: TaskOptions,
+    duplicate_groups: Option<DuplicateGroups>,
+    sort_by_size: bool,
+
+    leaves_queued: usize,
+    files_queued: usize,
+}
+
+impl<DB, R> BalancedGenerator<DB, R> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        fanout: usize,
+        depth: usize,
+        num_files: usize,
+        num_bytes_distr: Option<DB>,
+        bytes_exact: Option<usize>,
+        random: R,
+        options: TaskOptions,
+        duplicate_groups: Option<DuplicateGroups>,
+        sort_by_size: bool,
+    ) -> Self {
+        Self {
+            fanout,
+            num_leaves: fanout.pow(depth as u32),
+            num_files,
+            num_bytes_distr,
+            bytes_exact,
+            random,
+            options,
+            duplicate_groups,
+            sort_by_size,
+            leaves_queued: 0,
+            files_queued: 0,
+        }
+    }
+}
+
+impl<
+        DB: Distribution<f64> + Clone + Send + 'static,
+        R: RngCore + SeedableRng + Clone + Send + 'static,
+    > TaskGenerator for BalancedGenerator<DB, R>
+{
+    fn queue_gen(
+        &mut self,
+        file: FastPathBuf,
+        gen_dirs: bool,
+        _: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        if gen_dirs {
+            return queue!(
+                GeneratorTaskParams {
+                    target_dir: file,
+                    num_files: 0,
+                    num_dirs: self.fanout,
+                    file_offset: 0,
+                    dir_offset: 0,
+                    missing_dirs: 0,
+                    file_name_width: 0,
+                    options: self.options.clone(),
+                    file_contents: NoGeneratedFileContents,
+                },
+                false
+            );
+        }
+
+        let leaf = self.leaves_queued;
+        let num_files =
+            self.num_files / self.num_leaves + usize::from(leaf < self.num_files % self.num_leaves);
+        let first_file = self.files_queued;
+        self.leaves_queued += 1;
+        self.files_queued += num_files;
+        let done = self.leaves_queued == self.num_leaves;
+        // Leaves are filled in one go, so each gets its own stream rather than a shifted copy
+        let mut random = R::seed_from_u64(self.random.next_u64());
+
+        macro_rules! build_params {
+            ($file_contents:expr) => {{
+                GeneratorTaskParams {
+                    target_dir: file,
+                    num_files,
+                    num_dirs: 0,
+                    file_offset: 0,
+                    dir_offset: 0,
+                    missing_dirs: 0,
+                    file_name_width: self
+                        .options
+                        .naming
+                        .file_name_width(num_files.saturating_sub(1)),
+                    options: self.options.clone(),
+                    file_contents: $file_contents,
+                }
+            }};
+        }
+
+        if let Some(bytes) = self.bytes_exact && bytes > 0 {
+            let alignment = self.options.size_alignment;
+            queue!(
+                build_params!(PreDefinedGeneratedFileContents {
+                    byte_counts: (first_file..first_file + num_files)
+                        .map(|i| even_byte_count(bytes, self.num_files, alignment, i))
+                        .collect(),
+                    random,
+                }),
+                done
+            )
+        } else if let Some(bytes_distr) = &self.num_bytes_distr {
+            if let Some(groups) = self.duplicate_groups {
+                queue!(
+                    build_params!(DuplicateGeneratedFileContents {
+                        num_bytes_distr: bytes_distr.clone(),
+                        groups,
+                        random,
+                    }),
+                    done
+                )
+            } else if self.sort_by_size {
+                let byte_counts = sorted_byte_counts(num_files, bytes_distr, &mut random);
+                queue!(
+                    build_params!(PreDefinedGeneratedFileContents {
+                        byte_counts,
+                        random,
+                    }),
+                    done
+                )
+            } else {
+                queue!(
+                    build_params!(OnTheFlyGeneratedFileContents {
+                        num_bytes_distr: bytes_distr.clone(),
+                        random,
+                    }),
+                    done
+                )
+            }
+        } else {
+            queue!(build_params!(NoGeneratedFileContents), done)
+        }
     }
 }
 
@@ -151,19 +1015,34 @@ pub struct OtherFilesAndContentsGenerator<DF, DD, DB, R> {
     num_dirs_distr: DD,
     num_bytes_distr: Option<DB>,
     random: R,
+    structure_random: Option<R>,
 
     files_exact: Option<NonZeroUsize>,
     bytes_exact: Option<usize>,
+    dirs_exact: Option<usize>,
+    min_files: usize,
+    dirs_floor: DirsFloor,
+    size_schedule: Option<Vec<usize>>,
+
+    options: TaskOptions,
+    duplicate_groups: Option<DuplicateGroups>,
+    content_source: Option<ContentSource>,
+    empty_files: Option<EmptyFiles>,
+    width: DirWidth,
 
     done: bool,
+    files_done: bool,
+    files_queued: usize,
     root_num_files_hack: Option<usize>,
+    root_num_dirs_hack: Option<usize>,
+    root_file_name_width: usize,
 }
 
 impl<
         DF: Distribution<f64>,
         DD: Distribution<f64>,
         DB: Distribution<f64> + Clone + Send + 'static,
-        R: RngCore + Clone + Send + 'static,
+        R: RngCore + SeedableRng + Clone + Send + 'static,
     > TaskGenerator for OtherFilesAndContentsGenerator<DF, DD, DB, R>
 {
     fn queue_gen(
@@ -174,27 +1053,74 @@ impl<
     ) -> QueueResult {
         debug_assert!(!self.done);
 
-        let mut num_files = self.num_files_distr.sample(&mut self.random).round() as usize;
-        if let Some(ref mut files) = self.files_exact {
+        let mut num_files = if self.files_done {
+            0
+        } else {
+            self.num_files_distr.sample(&mut self.random).round() as usize
+        };
+        // The root directory receives any leftovers in maybe_queue_final_gen, so its file names
+        // must be wide enough to accommodate them.
+        let root_max_file_index = self
+            .files_exact
+            .map_or(max(num_files, self.min_files), |files| files.get() - 1);
+        if !self.files_done && let Some(ref mut files) = self.files_exact {
             if num_files >= files.get() {
-                self.done = true;
+                self.files_done = true;
                 num_files = files.get();
             } else {
                 *files = unsafe { NonZeroUsize::new_unchecked(files.get() - num_files) };
             }
         }
+        // Without an exact directory count, there's no point in continuing once we're out of files
+        let dirs_done = self.dirs_exact.map_or(self.files_done, |dirs| dirs == 0);
+        let num_empty_files = take_empty_files(&mut self.empty_files, &mut self.random);
 
-        if self.root_num_files_hack.is_none() {
-            self.root_num_files_hack = Some(num_files);
+        let mut num_dirs = if gen_dirs && !dirs_done {
+            sample_num_dirs(
+                &self.num_dirs_distr,
+                &mut self.random,
+                &mut self.structure_random,
+            )
+        } else {
+            0
+        };
+        if let Some(ref mut dirs) = self.dirs_exact {
+            num_dirs = min(num_dirs, *dirs);
+            *dirs -= num_dirs;
         }
+        let (num_files, num_dirs) = self.width.clamp(num_files, num_dirs, gen_dirs);
+        if self.root_num_dirs_hack.is_none() {
+            self.root_num_dirs_hack = Some(num_dirs);
+        }
+        self.dirs_floor.track(num_dirs);
 
-        let num_dirs = if gen_dirs && !self.done {
-            self.num_dirs_distr.sample(&mut self.random).round() as usize
+        let file_name_width = if self.root_num_files_hack.is_none() {
+            self.root_num_files_hack = Some(num_files + num_empty_files);
+            self.root_file_name_width = self
+                .options
+                .naming
+                .file_name_width(root_max_file_index + num_empty_files);
+            self.root_file_name_width
         } else {
-            0
+            self.options
+                .naming
+                .file_name_width((num_files + num_empty_files).saturating_sub(1))
         };
 
-        self.queue_gen_internal(file, num_files, num_dirs, 0, byte_counts_pool)
+        self.done =
+            self.files_done && self.dirs_exact.unwrap_or(0) == 0 && !self.width.spilling();
+        self.files_queued += num_files;
+
+        self.queue_gen_internal(
+            file,
+            num_files,
+            num_empty_files,
+            num_dirs,
+            0,
+            0,
+            file_name_width,
+            byte_counts_pool,
+        )
     }
 
     fn maybe_queue_final_gen(
@@ -202,10 +1128,26 @@ impl<
         file: FastPathBuf,
         byte_counts_pool: &mut Vec<Vec<usize>>,
     ) -> QueueResult {
+        let num_empty_files = take_rest_empty_files(&mut self.empty_files);
+        let (missing_dirs, dir_offset) = self.dirs_floor.top_up();
         if self.done {
-            return Err(QueueErrors::NothingToDo(file));
+            if num_empty_files == 0 && missing_dirs == 0 {
+                return Err(QueueErrors::NothingToDo(file));
+            }
+            return self.queue_gen_internal(
+                file,
+                0,
+                num_empty_files,
+                missing_dirs,
+                self.root_num_files_hack.unwrap_or(0),
+                dir_offset,
+                self.root_file_name_width,
+                byte_counts_pool,
+            );
         }
         self.done = true;
+        let files_done = self.files_done;
+        self.files_done = true;
 
         // TODO Dumping all the remaining files or bytes in the root directory is very dumb and wrong
         //  1. If there are a lot of files, we're missing out on performance gains from generating
@@ -213,29 +1155,60 @@ impl<
         //  2. The distribution will be totally wrong
         //  Ideally we would continue the while loop above until enough files have been generated,
         //  but I haven't had time to think about how to do so properly.
-        if let Some(files) = self.files_exact {
-            self.queue_gen_internal(
-                file,
-                files.get(),
-                0,
-                self.root_num_files_hack.unwrap_or(0),
-                byte_counts_pool,
-            )
+        let num_files = if files_done {
+            0
+        } else if let Some(files) = self.files_exact {
+            files.get()
         } else if self.bytes_exact.is_some_and(|b| *b > 0) {
-            self.queue_gen_internal(
-                file,
-                1,
-                0,
-                self.root_num_files_hack.unwrap_or(0),
-                byte_counts_pool,
-            )
+            max(1, self.min_files.saturating_sub(self.files_queued))
         } else {
-            Err(QueueErrors::NothingToDo(file))
-        }
+            self.min_files.saturating_sub(self.files_queued)
+        };
+        // An exact number of directories can't be combined with a minimum
+        let num_dirs = self.dirs_exact.unwrap_or(missing_dirs);
+        let dir_offset = self.root_num_dirs_hack.unwrap_or(0);
+        let (num_files, overflow_dirs) = self.width.plan_overflow(num_files, dir_offset + num_dirs);
+
+        self.queue_gen_internal(
+            file,
+            num_files,
+            num_empty_files,
+            num_dirs + overflow_dirs,
+            self.root_num_files_hack.unwrap_or(0),
+            dir_offset,
+            self.root_file_name_width,
+            byte_counts_pool,
+        )
+    }
+
+    fn maybe_queue_overflow_gen(
+        &mut self,
+        mut file: FastPathBuf,
+        byte_counts_pool: &mut Vec<Vec<usize>>,
+    ) -> QueueResult {
+        let overflow = self.width.next_overflow(&self.options.naming, &mut file);
+        let (num_files, num_dirs) = match overflow {
+            None => return Err(QueueErrors::NothingToDo(file)),
+            Some(entries) => entries,
+        };
+        self.random.next_u64();
+
+        let file_name_width = self.options.naming.file_name_width(num_files.saturating_sub(1));
+        self.queue_gen_internal(
+            file,
+            num_files,
+            0,
+            num_dirs,
+            0,
+            0,
+            file_name_width,
+            byte_counts_pool,
+        )
     }
 
     fn uses_byte_counts_pool(&self) -> bool {
-        self.num_bytes_distr.is_some() && self.bytes_exact.is_some_and(|b| *b > 0)
+        self.size_schedule.is_some()
+            || self.num_bytes_distr.is_some() && self.bytes_exact.is_some_and(|b| *b > 0)
     }
 }
 
@@ -243,50 +1216,108 @@ impl<
         DF: Distribution<f64>,
         DD: Distribution<f64>,
         DB: Distribution<f64> + Clone + Send + 'static,
-        R: RngCore + Clone + Send + 'static,
+        R: RngCore + SeedableRng + Clone + Send + 'static,
     > OtherFilesAndContentsGenerator<DF, DD, DB, R>
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         num_files_distr: DF,
         num_dirs_distr: DD,
         num_bytes_distr: Option<DB>,
         random: R,
+        structure_random: Option<R>,
         files_exact: Option<NonZeroUsize>,
         bytes_exact: Option<usize>,
+        dirs_exact: Option<usize>,
+        min_files: usize,
+        min_dirs: usize,
+        size_schedule: Option<Vec<usize>>,
+        options: TaskOptions,
+        duplicate_groups: Option<DuplicateGroups>,
+        content_source: Option<ContentSource>,
+        empty_files: Option<EmptyFiles>,
+        width: DirWidth,
     ) -> OtherFilesAndContentsGenerator<DF, DD, DB, R> {
         OtherFilesAndContentsGenerator {
             num_files_distr,
             num_dirs_distr,
             num_bytes_distr,
             random,
+            structure_random,
             files_exact,
             bytes_exact,
+            dirs_exact,
+            min_files,
+            dirs_floor: DirsFloor::new(min_dirs),
+            size_schedule,
+            options,
+            duplicate_groups,
+            content_source,
+            empty_files,
+            width,
             done: false,
+            files_done: false,
+            files_queued: 0,
             root_num_files_hack: None,
+            root_num_dirs_hack: None,
+            root_file_name_width: 0,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn queue_gen_internal(
         &mut self,
         file: FastPathBuf,
         num_files: usize,
+        num_empty_files: usize,
         num_dirs: usize,
         offset: usize,
+        dir_offset: usize,
+        file_name_width: usize,
         byte_counts_pool: &mut Vec<Vec<usize>>,
     ) -> QueueResult {
         macro_rules! build_params {
             ($file_contents:expr) => {{
                 GeneratorTaskParams {
                     target_dir: file,
-                    num_files,
+                    num_files: num_files + num_empty_files,
                     num_dirs,
                     file_offset: offset,
-                    file_contents: $file_contents,
+                    dir_offset,
+                    missing_dirs: 0,
+                    file_name_width,
+                    options: self.options.clone(),
+                    file_contents: EmptyTailFileContents {
+                        inner: $file_contents,
+                        first_empty: num_files,
+                    },
                 }
             }};
         }
 
-        if num_files > 0 && let Some(bytes_distr) = &self.num_bytes_distr {
+        if num_files > 0 && let Some(ref mut sizes) = self.size_schedule {
+            // Exact file counts guarantee the schedule is used up precisely
+            let mut byte_counts: Vec<usize> = byte_counts_pool.pop().unwrap_or_default();
+            debug_assert!(byte_counts.is_empty());
+            byte_counts.extend(sizes.drain(sizes.len() - num_files..));
+
+            queue!(
+                build_params!(PreDefinedGeneratedFileContents {
+                    byte_counts,
+                    random: self.random.clone(),
+                }),
+                self.done
+            )
+        } else if num_files > 0 && let Some(source) = &self.content_source {
+            queue!(
+                build_params!(SampledFileContents {
+                    num_bytes_distr: self.num_bytes_distr.clone(),
+                    source: source.clone(),
+                    random: self.random.clone(),
+                }),
+                self.done
+            )
+        } else if num_files > 0 && let Some(bytes_distr) = &self.num_bytes_distr {
             if let Some(ref mut bytes) = self.bytes_exact {
                 if *bytes > 0 {
                     let mut byte_counts: Vec<usize> = byte_counts_pool.pop().unwrap_or_default();
@@ -309,16 +1340,10 @@ impl<
                         byte_counts.set_len(num_files);
                     }
 
-                    if self.done {
-                        let base = *bytes / num_files;
-                        let mut leftovers = *bytes % num_files;
-                        for count in &mut byte_counts {
-                            if leftovers > 0 {
-                                *count += base + 1;
-                                leftovers -= 1;
-                            } else {
-                                *count += base;
-                            }
+                    if self.files_done {
+                        let alignment = self.options.size_alignment;
+                        for (i, count) in byte_counts.iter_mut().enumerate() {
+                            *count += even_byte_count(*bytes, num_files, alignment, i);
                         }
                     }
 
@@ -332,6 +1357,15 @@ impl<
                 } else {
                     queue!(build_params!(NoGeneratedFileContents), self.done)
                 }
+            } else if let Some(groups) = self.duplicate_groups {
+                queue!(
+                    build_params!(DuplicateGeneratedFileContents {
+                        num_bytes_distr: bytes_distr.clone(),
+                        groups,
+                        random: self.random.clone(),
+                    }),
+                    self.done
+                )
             } else {
                 queue!(
                     build_params!(OnTheFlyGeneratedFileContents {
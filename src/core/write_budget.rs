@@ -0,0 +1,101 @@
+use std::{
+    cmp::min,
+    num::NonZeroUsize,
+    sync::{Condvar, Mutex},
+};
+
+/// Caps the number of bytes buffered across every concurrent file write such that writing many
+/// large files at once can't run out of memory.
+#[derive(Debug)]
+pub struct WriteBudget {
+    total: NonZeroUsize,
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Holds its bytes of the budget until dropped.
+pub struct WriteBudgetPermit<'a> {
+    budget: &'a WriteBudget,
+    bytes: usize,
+}
+
+impl WriteBudget {
+    pub fn new(total: NonZeroUsize) -> Self {
+        Self {
+            total,
+            available: Mutex::new(total.get()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until this many bytes can be buffered. Writes asking for more than the entire budget
+    /// get all of it instead so they aren't left waiting forever.
+    pub fn acquire(&self, bytes: usize) -> WriteBudgetPermit<'_> {
+        let bytes = min(bytes, self.total.get());
+        let mut available = self.available.lock().unwrap();
+        while *available < bytes {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= bytes;
+        WriteBudgetPermit {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+impl Drop for WriteBudgetPermit<'_> {
+    fn drop(&mut self) {
+        *self.budget.available.lock().unwrap() += self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn buffered_bytes_never_exceed_the_budget() {
+        const BUDGET: usize = 10_000;
+
+        let budget = Arc::new(WriteBudget::new(NonZeroUsize::new(BUDGET).unwrap()));
+        // Counts the bytes of every buffer allocated under a permit that hasn't been freed yet
+        let buffered = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let threads = (1..=8)
+            .map(|i| {
+                let budget = budget.clone();
+                let buffered = buffered.clone();
+                let peak = peak.clone();
+                thread::spawn(move || {
+                    for _ in 0..20 {
+                        // Includes a buffer larger than the entire budget
+                        let len = i * 1_500;
+                        let permit = budget.acquire(len);
+                        let buf = vec![0u8; permit.bytes];
+                        let now = buffered.fetch_add(buf.len(), Ordering::SeqCst) + buf.len();
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(1));
+                        buffered.fetch_sub(buf.len(), Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= BUDGET);
+        assert_eq!(*budget.available.lock().unwrap(), BUDGET);
+    }
+}
@@ -1,42 +1,600 @@
-use std::{cmp::max, fs::create_dir_all, num::NonZeroUsize, path::PathBuf, thread};
+#[cfg(feature = "metrics")]
+use std::net::SocketAddr;
+use std::{
+    borrow::Cow,
+    cmp::{max, min},
+    collections::{HashSet, VecDeque},
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    fs::{create_dir, create_dir_all, remove_dir_all, remove_file, File, OpenOptions},
+    io,
+    io::{BufWriter, Write},
+    mem,
+    num::NonZeroUsize,
+    path::{is_separator, Component, Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::{anyhow, Context};
+#[cfg(feature = "syslog")]
+use cli_errors::CliExitError;
 use cli_errors::{CliExitAnyhowWrapper, CliResult};
+use core_affinity::CoreId;
 use derive_builder::Builder;
+use filetime::{set_file_times, FileTime};
+use log::{info, warn};
 use num_format::{Locale, ToFormattedString};
-use rand::SeedableRng;
+use owo_colors::OwoColorize;
+use rand::{distributions::Distribution, seq::SliceRandom, Rng, SeedableRng};
 use rand_distr::Normal;
 use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use tracing::{event, Level};
 
-use crate::core::{
-    run, FilesAndContentsGenerator, FilesNoContentsGenerator, GeneratorStats,
-    OtherFilesAndContentsGenerator,
+#[cfg(feature = "metrics")]
+use crate::core::{MetricsServer, Progress};
+#[cfg(feature = "syslog")]
+pub use crate::core::{Severity, Syslog, SyslogSink};
+use crate::{
+    core::{
+        create_content_addressed, create_git_objects, create_layout, even_byte_count,
+        make_read_only, run, Archive, BalancedGenerator, ChainGenerator, Checkpoint, ChecksumSender,
+        ContentSource, DirSeeds, DirWidth, DirsFloor, DiskBudget, DuplicateGroups, EmptyFiles,
+        FilePositions, FilesAndContentsGenerator, FilesFloor, FilesNoContentsGenerator, LeafDepths,
+        MediaFiles, NodeSample, OtherFilesAndContentsGenerator, Phase, PlanSender,
+        ProgressReporter, RampUp, RateLimiter, ReadOnlyEntries, SampleSender, SingleDirGenerator,
+        SparseFiles, SpecialFiles, StructuralLevels, TaskOptions, WriteBudget, XattrFiles,
+    },
+    utils::{validate_timestamps, validate_wordlist, CustomNames, Naming, Timestamps},
+};
+pub use crate::{
+    core::{
+        diff_trees, ArchiveFormat, CreationOrder, EntryKind, GeneratorStats, Layout, MediaFormat,
+        MtimeSequence, ProgressHook, ProgressInterval, SequenceHeader, TreeDiff, LAYOUT_FILES_KEY,
+        XATTR_NAME,
+    },
+    utils::{
+        NameContext, NameFn, NameKind, NameStyle, DEFAULT_DIR_WORDLIST, DEFAULT_FILE_WORDLIST,
+        MIN_NAME_LENGTH, NAME_MAX,
+    },
+};
+
+/// The default cap on the number of files and directories a single run may plan to create.
+pub const DEFAULT_MAX_ENTRIES: usize = 1_000_000_000;
+
+/// The deepest archives can be nested, bounding the per-file memory spent on outer archives.
+pub const MAX_NESTING_DEPTH: usize = 1_000;
+
+/// The default block size disk usage is estimated with.
+pub const DEFAULT_BLOCK_SIZE: NonZeroUsize = match NonZeroUsize::new(4096) {
+    Some(size) => size,
+    None => panic!("The default block size must be nonzero."),
 };
 
-#[derive(Builder, Debug)]
+/// The name of the file at the root of a tree that records how it was generated.
+pub const MARKER_FILE_NAME: &str = ".ftzz-manifest.toml";
+
+/// What generated files are filled with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FillMode {
+    #[default]
+    Random,
+    /// Tile these bytes to each file's size, making contents as compressible as the pattern.
+    Repeating(Vec<u8>),
+    /// Keep only `permille` (between 0 and 1000) thousandths of each file's bytes random and zero
+    /// the rest, from entirely compressible contents at 0 to incompressible ones at 1000.
+    Blended { permille: u16 },
+    /// Wrap each file's random contents in an archive split across `inner_entries` entries.
+    Archive {
+        format: ArchiveFormat,
+        inner_entries: usize,
+    },
+}
+
+/// The parameter interactions [Generator::explain] can walk through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExplainTopic {
+    /// How the file to directory ratio determines the number of directories.
+    Ratio,
+    /// How the maximum depth spreads directories across levels.
+    Depth,
+    /// How the total number of bytes is split across files.
+    Bytes,
+}
+
+impl FromStr for ExplainTopic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ratio" => Ok(Self::Ratio),
+            "depth" => Ok(Self::Depth),
+            "bytes" => Ok(Self::Bytes),
+            _ => Err(format!("Unknown topic: {}", s)),
+        }
+    }
+}
+
+/// How deep the branches of the tree grow before ending in leaf directories.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DepthDistribution {
+    /// Normally distributed around `mean` with a standard deviation of `cv` times the mean.
+    Normal { mean: f64, cv: f64 },
+}
+
+/// How the sizes of files are spread around the average file size.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum SizeDistribution {
+    /// Normally distributed with a standard deviation of a fifth of the mean.
+    #[default]
+    Normal,
+    /// Sizes fall off with their rank raised to the `exponent` as per Zipf's law: a few files are
+    /// very large while most are tiny.
+    Zipf { exponent: f64 },
+}
+
+/// Presets for the file names, sizes, and contents of commonly needed kinds of trees.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Profile {
+    /// Source code and project files of a few kilobytes each.
+    SourceRepo,
+    /// Photo albums of JPEG images.
+    MediaLibrary,
+    /// Hourly rotated text logs, a few of which have grown very large.
+    Logs,
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "source-repo" => Ok(Self::SourceRepo),
+            "media-library" => Ok(Self::MediaLibrary),
+            "logs" => Ok(Self::Logs),
+            _ => Err(format!("Unknown profile: {}", s)),
+        }
+    }
+}
+
+impl Profile {
+    /// The average size of a file.
+    fn mean_file_size(self) -> usize {
+        match self {
+            Self::SourceRepo => 4 << 10,
+            Self::MediaLibrary => 512 << 10,
+            Self::Logs => 64 << 10,
+        }
+    }
+
+    fn size_distribution(self) -> SizeDistribution {
+        match self {
+            Self::SourceRepo | Self::MediaLibrary => SizeDistribution::Normal,
+            Self::Logs => SizeDistribution::Zipf { exponent: 1. },
+        }
+    }
+}
+
+const SOURCE_REPO_DIRS: &[&str] = &[
+    "src",
+    "lib",
+    "tests",
+    "benches",
+    "examples",
+    "scripts",
+    "docs",
+    "cmd",
+    "internal",
+    "pkg",
+    "include",
+    "utils",
+    "tools",
+    "fixtures",
+];
+
+const SOURCE_REPO_FILES: &[&str] = &[
+    "main.rs",
+    "lib.rs",
+    "mod.rs",
+    "index.ts",
+    "app.js",
+    "utils.py",
+    "server.go",
+    "Main.java",
+    "parser.c",
+    "parser.h",
+    "widget.cpp",
+    "handler.rb",
+    "README.md",
+    "Cargo.toml",
+    "package.json",
+];
+
+const SOURCE_REPO_CONTENTS: &[u8] = b"fn main() {\n    println!(\"Hello, world!\");\n}\n\n";
+
+const MEDIA_LIBRARY_DIRS: &[&str] = &[
+    "camera",
+    "screenshots",
+    "albums",
+    "holidays",
+    "family",
+    "pets",
+    "exports",
+    "raw",
+];
+
+const MEDIA_LIBRARY_FILES: &[&str] = &[
+    "IMG.jpg",
+    "DSC.jpg",
+    "PXL.jpg",
+    "photo.jpg",
+    "scan.jpg",
+    "portrait.jpg",
+    "sunset.jpg",
+    "cover.jpg",
+];
+
+const LOGS_CONTENTS: &[u8] =
+    b"2020-01-01T00:00:00Z INFO request completed status=200 duration_ms=12\n";
+
+#[derive(Builder, Debug, Clone)]
 #[builder(build_fn(validate = "Self::validate"))]
 pub struct Generator {
     root_dir: PathBuf,
+    #[builder(default = "self.default_num_files()")]
     num_files: NonZeroUsize,
+    #[builder(default, setter(strip_option))]
+    num_entries: Option<NonZeroUsize>,
     #[builder(default = "false")]
     files_exact: bool,
     #[builder(default = "0")]
+    min_files: usize,
+    #[builder(default = "0")]
+    min_dirs: usize,
+    #[builder(default = "0")]
+    min_files_per_dir: usize,
+    #[builder(default = "0.")]
+    cluster_factor: f64,
+    #[builder(default = "0")]
     num_bytes: usize,
     #[builder(default = "false")]
     bytes_exact: bool,
+    #[builder(default, setter(strip_option))]
+    size_schedule: Option<Vec<usize>>,
+    #[builder(default, setter(strip_option))]
+    layout: Option<Layout>,
+    #[builder(default, setter(strip_option))]
+    target_disk_usage: Option<usize>,
+    #[builder(default = "DEFAULT_BLOCK_SIZE")]
+    block_size: NonZeroUsize,
+    #[builder(default, setter(strip_option))]
+    max_total_size: Option<usize>,
+    #[builder(default)]
+    size_distribution: SizeDistribution,
+    #[builder(default, setter(strip_option))]
+    size_alignment: Option<NonZeroUsize>,
+    #[builder(default, setter(strip_option))]
+    dirs_exact: Option<usize>,
+    #[builder(default = "0")]
+    empty_files: usize,
     #[builder(default = "5")]
     max_depth: u32,
+    /// Whether the root directory counts as the first level of `max_depth`. By default only the
+    /// directories below the root count, so a maximum depth of 1 allows one level of
+    /// subdirectories. Counting the root instead leaves one level fewer for its descendants.
+    #[builder(default = "false")]
+    depth_includes_root: bool,
+    #[builder(default, setter(strip_option))]
+    leaf_depth_distribution: Option<DepthDistribution>,
+    #[builder(default = "0")]
+    depth_jitter: u32,
+    #[builder(default = "0")]
+    structural_levels: u32,
     #[builder(default = "self.default_ftd_ratio()")]
     file_to_dir_ratio: NonZeroUsize,
+    #[builder(default, setter(strip_option))]
+    dir_fraction: Option<f64>,
+    #[builder(default, setter(strip_option))]
+    balanced_fanout: Option<usize>,
     #[builder(default = "0")]
     seed: u64,
+    #[builder(default, setter(into, strip_option))]
+    seed_phrase: Option<String>,
+    #[builder(default, setter(strip_option))]
+    structure_seed: Option<u64>,
+    #[builder(default, setter(strip_option))]
+    content_seed: Option<u64>,
+    #[builder(default = "false")]
+    lex_sortable: bool,
+    #[builder(default = "false")]
+    sort_by_size: bool,
+    #[builder(default = "false")]
+    name_hash_jitter: bool,
+    #[builder(default)]
+    name_style: NameStyle,
+    #[builder(default, setter(custom))]
+    name_length_distribution: Option<(usize, usize)>,
+    #[builder(default = "false")]
+    case_collisions: bool,
+    #[builder(default = "false")]
+    names_from_content: bool,
+    /// Replaces the tree with a `.git/objects` style store holding this many blobs, named after
+    /// their contents like loose objects or packed alongside an index.
+    #[builder(default, setter(strip_option))]
+    git_objects: Option<NonZeroUsize>,
+    #[builder(default = "0.")]
+    hidden_ratio: f64,
+    #[builder(default, setter(strip_option))]
+    dir_wordlist: Option<Vec<String>>,
+    #[builder(default, setter(strip_option))]
+    file_wordlist: Option<Vec<String>>,
+    #[builder(default, setter(into, strip_option))]
+    name_fn: Option<NameFn>,
+    #[builder(default, setter(strip_option))]
+    duplicate_groups: Option<NonZeroUsize>,
+    #[builder(default)]
+    fill_mode: FillMode,
+    #[builder(default = "1")]
+    nesting_depth: usize,
+    #[builder(default, setter(strip_option))]
+    text_header: Option<Vec<u8>>,
+    #[builder(default, setter(strip_option))]
+    sequence_header: Option<SequenceHeader>,
+    #[builder(default = "0.")]
+    sparse_ratio: f64,
+    #[builder(default = "false")]
+    sparse_holes: bool,
+    #[builder(default = "false")]
+    preallocate: bool,
+    #[builder(default, setter(strip_option))]
+    media_mix: Option<Vec<MediaFormat>>,
+    /// Puts the files of each media format in a directory at the top of the tree named after its
+    /// extension, each holding its own tree.
+    #[builder(default = "false")]
+    group_by_extension: bool,
+    #[builder(default = "0.")]
+    read_only_ratio: f64,
+    #[builder(default = "false")]
+    read_only_dirs: bool,
+    #[builder(default = "0.")]
+    xattr_ratio: f64,
+    #[builder(default, setter(into, strip_option))]
+    content_source: Option<PathBuf>,
+    #[builder(default)]
+    seed_paths: Vec<String>,
+    #[builder(default, setter(into, strip_option))]
+    overlay_source: Option<PathBuf>,
+    #[builder(default = "0.")]
+    special_ratio: f64,
+    #[cfg(unix)]
+    #[builder(default = "0.")]
+    broken_symlink_ratio: f64,
+    #[builder(default)]
+    creation_order: CreationOrder,
+    #[builder(default = "false")]
+    repair: bool,
+    #[builder(default, setter(strip_option))]
+    repair_dir: Option<PathBuf>,
+    #[builder(default = "false")]
+    openat: bool,
+    #[builder(default, setter(strip_option))]
+    task_granularity: Option<u32>,
+    #[builder(default, setter(strip_option))]
+    ramp_up: Option<Duration>,
+    #[builder(default = "false")]
+    continue_on_error: bool,
+    #[builder(default, setter(strip_option))]
+    rate_limit: Option<NonZeroUsize>,
+    #[builder(default, setter(strip_option))]
+    byte_rate: Option<NonZeroUsize>,
+    #[builder(default, setter(strip_option))]
+    write_memory_budget: Option<NonZeroUsize>,
+    #[builder(default, setter(strip_option))]
+    cpu_affinity: Option<Vec<usize>>,
+    #[builder(default = "false")]
+    two_phase: bool,
+    #[builder(default = "false")]
+    chain: bool,
+    #[builder(default = "false")]
+    shrink_depth: bool,
+    #[builder(default, setter(strip_option))]
+    max_dir_width: Option<NonZeroUsize>,
+    #[builder(default = "false")]
+    overwrite: bool,
+    #[builder(default = "false")]
+    force_dangerous: bool,
+    #[builder(default = "DEFAULT_MAX_ENTRIES")]
+    max_entries: usize,
+    #[builder(default = "false")]
+    self_check: bool,
+    #[builder(default = "false")]
+    write_marker: bool,
+    #[builder(default = "false")]
+    dir_seeds: bool,
+    #[builder(default, setter(strip_option))]
+    umask: Option<u32>,
+    #[builder(default, setter(strip_option))]
+    fixed_time: Option<SystemTime>,
+    #[builder(default, setter(strip_option))]
+    mtime_sequence: Option<MtimeSequence>,
+    #[builder(default, setter(strip_option))]
+    checksums: Option<PathBuf>,
+    #[builder(default, setter(into, strip_option))]
+    catalog: Option<PathBuf>,
+    #[builder(default, setter(into, strip_option))]
+    write_seed: Option<PathBuf>,
+    #[builder(default, setter(into, strip_option))]
+    checkpoint: Option<PathBuf>,
+    #[builder(default, setter(into, strip_option))]
+    dump_samples: Option<PathBuf>,
+    #[cfg(feature = "metrics")]
+    #[builder(default, setter(strip_option))]
+    metrics_addr: Option<SocketAddr>,
+    #[cfg(feature = "syslog")]
+    #[builder(default, setter(strip_option))]
+    syslog: Option<Syslog>,
+    #[builder(default, setter(into, strip_option))]
+    on_progress: Option<ProgressHook>,
+    #[builder(default)]
+    progress_interval: ProgressInterval,
+    #[builder(default = "false")]
+    color: bool,
 }
 
 impl GeneratorBuilder {
+    /// Varies the length of random names between `min` and `max` bytes, inclusive. The upper
+    /// bound is clamped to [NAME_MAX].
+    pub fn name_length_distribution(&mut self, min: usize, max: usize) -> &mut Self {
+        self.name_length_distribution = Some(Some((min, max.min(NAME_MAX))));
+        self
+    }
+
+    /// Presets the names, sizes, and contents of files to those of the given kind of tree. Options
+    /// set afterwards take precedence. The total number of bytes is derived from the number of
+    /// files, so those (or a layout, size schedule, or disk usage target) should be set first.
+    pub fn profile(&mut self, profile: Profile) -> &mut Self {
+        let words = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        match profile {
+            Profile::SourceRepo => {
+                self.dir_wordlist(words(SOURCE_REPO_DIRS))
+                    .file_wordlist(words(SOURCE_REPO_FILES))
+                    .fill_mode(FillMode::Repeating(SOURCE_REPO_CONTENTS.to_vec()));
+            }
+            Profile::MediaLibrary => {
+                self.dir_wordlist(words(MEDIA_LIBRARY_DIRS))
+                    .file_wordlist(words(MEDIA_LIBRARY_FILES))
+                    .media_mix(vec![MediaFormat::Jpeg]);
+            }
+            Profile::Logs => {
+                self.name_style(NameStyle::Timestamped {
+                    format: String::from("app-%Y-%m-%dT%H.log"),
+                    // 2020-01-01
+                    start: 1_577_836_800,
+                    step: 60 * 60,
+                })
+                .fill_mode(FillMode::Repeating(LOGS_CONTENTS.to_vec()));
+            }
+        }
+
+        // Sizes are already dictated
+        if matches!(self.size_schedule, Some(Some(_)))
+            || matches!(self.target_disk_usage, Some(Some(_)))
+        {
+            return self;
+        }
+        let layout_files = match &self.layout {
+            Some(Some(layout)) => NonZeroUsize::new(layout.num_files()),
+            _ => None,
+        };
+        // Entries slightly overestimate the files, which is close enough for a preset
+        if let Some(num_files) = self.num_files.or(self.num_entries.flatten()).or(layout_files) {
+            self.num_bytes(num_files.get().saturating_mul(profile.mean_file_size()));
+        }
+        self.size_distribution(profile.size_distribution())
+    }
+
+    /// The number of levels of directories the tree may have below the root.
+    fn levels_below_root(&self) -> u32 {
+        let max_depth = self.max_depth.unwrap_or(5);
+        if self.depth_includes_root == Some(true) {
+            max_depth.saturating_sub(1)
+        } else {
+            max_depth
+        }
+    }
+
     fn validate(&self) -> Result<(), String> {
-        if let Some(ratio) = self.file_to_dir_ratio && let Some(num_files) = self.num_files && ratio > num_files {
+        let schedule = self.size_schedule.as_ref().and_then(Option::as_ref);
+        let disk_usage = self.target_disk_usage.flatten();
+        let entries = self.num_entries.flatten();
+        // Git objects are named after their contents too
+        let named_from_content =
+            self.names_from_content == Some(true) || matches!(self.git_objects, Some(Some(_)));
+
+        if self.depth_includes_root == Some(true) && self.max_depth == Some(0) {
+            return Err(String::from(
+                "A maximum depth of 0 leaves no room for the root directory when it counts \
+                toward the depth.",
+            ));
+        }
+        let no_bytes =
+            self.num_bytes.unwrap_or(0) == 0 && schedule.is_none() && disk_usage.is_none();
+        if let Some(sizes) = schedule {
+            if sizes.is_empty() {
+                return Err(String::from(
+                    "The size schedule must contain at least one file.",
+                ));
+            }
+            if sizes
+                .iter()
+                .try_fold(0usize, |sum, &size| sum.checked_add(size))
+                .is_none()
+            {
+                return Err(String::from(
+                    "The sizes in the schedule add up to more bytes than can be generated.",
+                ));
+            }
+            if self.chain == Some(true) {
+                return Err(String::from(
+                    "A size schedule cannot be used with a chain tree.",
+                ));
+            }
+            if matches!(self.duplicate_groups, Some(Some(_))) {
+                return Err(String::from(
+                    "Duplicate groups cannot be used with a size schedule.",
+                ));
+            }
+            if let Some(ratio) = self.special_ratio && ratio != 0. {
+                return Err(String::from(
+                    "Special files cannot be used with a size schedule.",
+                ));
+            }
+        } else if self.num_files.is_none()
+            && entries.is_none()
+            && !matches!(self.layout, Some(Some(_)))
+            && !matches!(self.git_objects, Some(Some(_)))
+        {
+            return Err(String::from(
+                "Either the number of files, entries, a size schedule, a layout, or a number of \
+                git objects must be specified.",
+            ));
+        }
+        if let Some(entries) = entries {
+            if schedule.is_some() || self.num_files.is_some() {
+                return Err(String::from(
+                    "The number of entries replaces the number of files and size schedule.",
+                ));
+            }
+            if self.chain == Some(true) || matches!(self.balanced_fanout, Some(Some(_))) {
+                return Err(String::from(
+                    "The number of entries cannot be used with chain or balanced trees.",
+                ));
+            }
+            if let Some(Some(dirs)) = self.dirs_exact && dirs >= entries.get() {
+                return Err(format!(
+                    "The number of directories ({}) leaves no room for files among the {} \
+                     entries.",
+                    dirs, entries,
+                ));
+            }
+        }
+        // The schedule dictates the exact number of files
+        let num_files = schedule
+            .and_then(|sizes| NonZeroUsize::new(sizes.len()))
+            .or(self.num_files)
+            .or(entries);
+        let files_exact = schedule.is_some() || self.files_exact == Some(true);
+
+        if let Some(ratio) = self.file_to_dir_ratio && let Some(num_files) = num_files && ratio > num_files {
             return Err(format!(
                 "The file to dir ratio ({}) cannot be larger than the number of files to generate ({}).",
                 ratio,
@@ -44,17 +602,1213 @@ impl GeneratorBuilder {
             ));
         }
 
+        if let Some(min_files) = self.min_files && let Some(num_files) = num_files && min_files > num_files.get() {
+            return Err(format!(
+                "The minimum number of files ({}) cannot be larger than the number of files to generate ({}).",
+                min_files,
+                num_files,
+            ));
+        }
+
+        if let Some(min) = self.min_dirs && min > 0 {
+            if self.levels_below_root() == 0 {
+                return Err(String::from(
+                    "A minimum number of directories needs a maximum depth of at least 1.",
+                ));
+            }
+            // Missing directories are topped up in the root once the tree has been generated
+            if self.chain == Some(true)
+                || matches!(self.balanced_fanout, Some(Some(_)))
+                || matches!(self.layout, Some(Some(_)))
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || matches!(self.max_dir_width, Some(Some(_)))
+                || named_from_content
+            {
+                return Err(String::from(
+                    "A minimum number of directories cannot be used with an exact number of \
+                    directories, a fixed tree shape, a maximum directory width, or names from \
+                    content.",
+                ));
+            }
+        }
+
+        if let Some(factor) = self.cluster_factor && !(0. ..1.).contains(&factor) {
+            return Err(format!(
+                "The cluster factor ({}) must be at least 0 and less than 1.",
+                factor,
+            ));
+        }
+
+        if let Some(min) = self.min_files_per_dir && min > 0 {
+            if files_exact {
+                return Err(String::from(
+                    "A minimum number of files per directory cannot be used with an exact number \
+                    of files.",
+                ));
+            }
+            if matches!(self.dirs_exact, Some(Some(_))) {
+                // Directories that don't fit in the tree are dumped in the root without any files
+                return Err(String::from(
+                    "A minimum number of files per directory cannot be used with an exact number \
+                    of directories.",
+                ));
+            }
+            if self.chain == Some(true) {
+                return Err(String::from(
+                    "A minimum number of files per directory cannot be used with a chain tree.",
+                ));
+            }
+        }
+
+        if let Some(Some(dirs)) = self.dirs_exact && dirs > 0 && self.levels_below_root() == 0 {
+            return Err(format!(
+                "Cannot generate exactly {} directories with a maximum depth of 0.",
+                dirs,
+            ));
+        }
+
+        if let Some(Some(fraction)) = self.dir_fraction {
+            if !(fraction > 0. && fraction < 1.) {
+                return Err(format!(
+                    "The directory fraction ({}) must be between 0 and 1, exclusive.",
+                    fraction,
+                ));
+            }
+            if self.file_to_dir_ratio.is_some() {
+                return Err(String::from(
+                    "A directory fraction cannot be combined with a file to dir ratio.",
+                ));
+            }
+            if self.chain == Some(true) || matches!(self.dirs_exact, Some(Some(_))) {
+                return Err(String::from(
+                    "A directory fraction cannot be used with a chain tree or an exact number of \
+                    directories.",
+                ));
+            }
+        }
+
+        if let Some(Some(fanout)) = self.balanced_fanout {
+            if fanout < 2 {
+                return Err(format!(
+                    "The fan-out of a balanced tree ({}) must be at least 2.",
+                    fanout,
+                ));
+            }
+            if self.chain == Some(true)
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || self.file_to_dir_ratio.is_some()
+                || matches!(self.dir_fraction, Some(Some(_)))
+                || matches!(self.leaf_depth_distribution, Some(Some(_)))
+                || self.depth_jitter.is_some_and(|j| *j > 0)
+                || matches!(self.max_dir_width, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "A balanced tree's shape is fixed by its fan-out and cannot be combined with \
+                    other tree shape options.",
+                ));
+            }
+            if schedule.is_some()
+                || self.min_files.is_some_and(|m| *m > 0)
+                || self.min_files_per_dir.is_some_and(|m| *m > 0)
+                || self.empty_files.is_some_and(|e| *e > 0)
+                || self.cluster_factor.is_some_and(|f| *f != 0.)
+                || matches!(self.content_source, Some(Some(_)))
+                || named_from_content
+            {
+                return Err(String::from(
+                    "A balanced tree spreads files evenly across its leaves and cannot be used \
+                    with a size schedule, minimum numbers of files, empty files, clustering, a \
+                    content source, or names from content.",
+                ));
+            }
+        }
+
+        if let Some(Some(DepthDistribution::Normal { mean, cv })) = self.leaf_depth_distribution {
+            if !(mean > 0. && mean.is_finite() && cv >= 0. && cv.is_finite()) {
+                return Err(format!(
+                    "The leaf depth distribution (mean {}, cv {}) must have a positive mean and \
+                    a non-negative coefficient of variation.",
+                    mean, cv,
+                ));
+            }
+            if self.chain == Some(true) {
+                return Err(String::from(
+                    "A leaf depth distribution cannot be used with a chain tree.",
+                ));
+            }
+        }
+
+        if let Some(jitter) = self.depth_jitter && jitter > 0 {
+            let max_depth = self.levels_below_root();
+            if jitter >= max_depth {
+                return Err(format!(
+                    "The depth jitter ({}) must be less than the maximum depth ({}).",
+                    jitter, max_depth,
+                ));
+            }
+            if matches!(self.leaf_depth_distribution, Some(Some(_))) {
+                return Err(String::from(
+                    "Depth jitter cannot be combined with a leaf depth distribution.",
+                ));
+            }
+            if self.chain == Some(true) {
+                return Err(String::from(
+                    "Depth jitter cannot be used with a chain tree.",
+                ));
+            }
+        }
+
+        if let Some(levels) = self.structural_levels && levels > 0 {
+            let max_depth = self.levels_below_root();
+            if levels >= max_depth {
+                return Err(format!(
+                    "The number of structural levels ({}) must be less than the maximum depth \
+                    ({}).",
+                    levels, max_depth,
+                ));
+            }
+            // Every directory in the structural levels must grow subdirectories for the files
+            // carried over from it to reach
+            if matches!(self.leaf_depth_distribution, Some(Some(_)))
+                || self.depth_jitter.is_some_and(|jitter| *jitter > 0)
+                || self.shrink_depth == Some(true)
+                || matches!(self.max_dir_width, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "Structural levels cannot be combined with varying depths or a maximum \
+                    directory width.",
+                ));
+            }
+            // These place files in the root directory or outside of the usual tree
+            if files_exact
+                || self.bytes_exact == Some(true)
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || schedule.is_some()
+                || self.min_files.is_some_and(|min| *min > 0)
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+                || matches!(self.content_source, Some(Some(_)))
+                || self.chain == Some(true)
+                || matches!(self.balanced_fanout, Some(Some(_)))
+                || matches!(self.layout, Some(Some(_)))
+                || named_from_content
+            {
+                return Err(String::from(
+                    "Structural levels cannot be used with exact counts, a minimum number of \
+                    files, empty files, a content source, fixed tree shapes, or names from \
+                    content.",
+                ));
+            }
+        }
+
+        if matches!(self.max_dir_width, Some(Some(_))) {
+            // Leftover files get directories of their own, but bytes and directories are placed in
+            // the root directory
+            if self.bytes_exact == Some(true) || matches!(self.dirs_exact, Some(Some(_))) {
+                return Err(String::from(
+                    "A maximum directory width cannot be used with exact byte or directory \
+                    counts.",
+                ));
+            }
+            if self.min_files.is_some_and(|min| *min > 0)
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+            {
+                return Err(String::from(
+                    "A maximum directory width cannot be used with a minimum number of files or \
+                    empty files.",
+                ));
+            }
+            if self.chain == Some(true) || self.levels_below_root() == 0 {
+                return Err(String::from(
+                    "A maximum directory width needs subdirectories to spill files into.",
+                ));
+            }
+        }
+
+        if disk_usage.is_some() {
+            if self.num_bytes.unwrap_or(0) != 0 || schedule.is_some() {
+                return Err(String::from(
+                    "A target disk usage replaces the number of bytes.",
+                ));
+            }
+            if matches!(self.content_source, Some(Some(_))) {
+                return Err(String::from(
+                    "A target disk usage cannot be used with a content source.",
+                ));
+            }
+        }
+
+        if self.chain == Some(true) && matches!(self.dirs_exact, Some(Some(_))) {
+            return Err(String::from(
+                "An exact number of directories cannot be used with a chain tree.",
+            ));
+        }
+
+        if (matches!(self.structure_seed, Some(Some(_)))
+            || matches!(self.content_seed, Some(Some(_))))
+            && files_exact
+            && self.chain != Some(true)
+            && !matches!(self.dirs_exact, Some(Some(_)))
+        {
+            return Err(String::from(
+                "Separate structure and content seeds require an exact number of directories when \
+                the number of files is exact, as the tree otherwise stops growing once the files \
+                run out.",
+            ));
+        }
+
+        if let Some(Some(mask)) = self.umask {
+            if !cfg!(unix) {
+                return Err(String::from("A umask can only be set on Unix."));
+            }
+            if mask > 0o777 {
+                return Err(format!("The umask ({:o}) must be at most 777.", mask));
+            }
+        }
+
+        if let Some(Some(cores)) = &self.cpu_affinity {
+            if cores.is_empty() {
+                return Err(String::from(
+                    "At least one CPU core must be given to pin threads to.",
+                ));
+            }
+            if !cfg!(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "windows",
+                target_os = "freebsd",
+            )) {
+                return Err(String::from(
+                    "CPU affinity is only supported on Linux, Android, Windows, and FreeBSD.",
+                ));
+            }
+            if named_from_content {
+                return Err(String::from(
+                    "CPU affinity cannot be used when naming files after their contents as those \
+                    are created on the calling thread.",
+                ));
+            }
+        }
+
+        if matches!(self.checksums, Some(Some(_))) && self.repair == Some(true) {
+            return Err(String::from(
+                "Checksums cannot be written when repairing a tree as existing files are skipped.",
+            ));
+        }
+
+        if let Some(paths) = &self.seed_paths {
+            SeedEntries::new(paths)?;
+        }
+
+        if self.overwrite == Some(true) && self.repair == Some(true) {
+            return Err(String::from(
+                "Overwriting and repairing a tree are mutually exclusive.",
+            ));
+        }
+
+        if matches!(self.overlay_source, Some(Some(_))) {
+            if self.repair == Some(true) || self.self_check == Some(true) {
+                return Err(String::from(
+                    "An overlay cannot be repaired or self-checked as the copied entries aren't \
+                    part of the generated tree.",
+                ));
+            }
+            if schedule.is_some()
+                || disk_usage.is_some()
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || matches!(self.layout, Some(Some(_)))
+                || named_from_content
+            {
+                return Err(String::from(
+                    "An overlay splits the files between its directories, so it cannot be \
+                    combined with size schedules, target disk usages, exact directory counts, \
+                    layouts, or names from content.",
+                ));
+            }
+        }
+
+        if self.dir_seeds == Some(true) {
+            if self.write_marker != Some(true) {
+                return Err(String::from(
+                    "Directory seeds are recorded in the marker file, so it must be written.",
+                ));
+            }
+            if no_bytes {
+                return Err(String::from(
+                    "Directory seeds need a nonzero number of bytes to be written into files.",
+                ));
+            }
+            // These tie the contents of a directory to the rest of the tree or put entries in it
+            // that aren't generated
+            if files_exact
+                || self.bytes_exact == Some(true)
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || self.min_files.is_some_and(|min| *min > 0)
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+                || self.chain == Some(true)
+                || matches!(self.balanced_fanout, Some(Some(_)))
+                || matches!(self.layout, Some(Some(_)))
+                || matches!(self.content_source, Some(Some(_)))
+                || matches!(self.duplicate_groups, Some(Some(_)))
+                || self.sort_by_size == Some(true)
+                || matches!(self.sequence_header, Some(Some(_)))
+                || matches!(self.mtime_sequence, Some(Some(_)))
+                || self.seed_paths.as_ref().is_some_and(|paths| !paths.is_empty())
+                || matches!(self.overlay_source, Some(Some(_)))
+                || named_from_content
+            {
+                return Err(String::from(
+                    "Directory seeds cannot be used with exact counts, a minimum number of files, \
+                    empty files, fixed tree shapes, a content source, duplicates, sorting by size, \
+                    sequence numbers, seed paths, overlays, or names from content.",
+                ));
+            }
+        }
+
+        if let Some(Some(dir)) = &self.repair_dir {
+            if self.dir_seeds != Some(true) || self.repair != Some(true) {
+                return Err(String::from(
+                    "A single directory can only be repaired in trees with directory seeds.",
+                ));
+            }
+            if !dir
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(String::from(
+                    "The directory to repair must be a relative path within the root directory.",
+                ));
+            }
+        }
+
+        if matches!(self.checkpoint, Some(Some(_))) {
+            if self.overwrite == Some(true)
+                || self.repair == Some(true)
+                || self.two_phase == Some(true)
+                || named_from_content
+                || matches!(self.overlay_source, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "Checkpoints cannot be combined with overwriting, repairing, two phases, \
+                    overlays, or files named after their contents.",
+                ));
+            }
+            if matches!(self.checksums, Some(Some(_))) {
+                return Err(String::from(
+                    "Checksums cannot be written with a checkpoint as completed tasks are skipped \
+                    on restart.",
+                ));
+            }
+            // Special files can't be recreated on top of the ones an interrupted run left behind
+            if self.special_ratio.is_some_and(|r| *r != 0.) {
+                return Err(String::from(
+                    "Checkpoints cannot be combined with special files.",
+                ));
+            }
+            #[cfg(unix)]
+            if self.broken_symlink_ratio.is_some_and(|r| *r != 0.) {
+                return Err(String::from(
+                    "Checkpoints cannot be combined with broken symlinks.",
+                ));
+            }
+            // Checkpoints count the tasks that completed, not the directories within them
+            if matches!(self.task_granularity, Some(Some(_))) {
+                return Err(String::from(
+                    "Checkpoints cannot be combined with a task granularity.",
+                ));
+            }
+        }
+
+        if let Some(Some(0)) = self.task_granularity {
+            return Err(String::from(
+                "The task granularity must be at least 1 as the root directory always gets a task \
+                of its own.",
+            ));
+        }
+
+        if let Some(Some(period)) = self.ramp_up {
+            if period.is_zero() {
+                return Err(String::from(
+                    "The ramp up period must be longer than zero.",
+                ));
+            }
+            if named_from_content || matches!(self.layout, Some(Some(_))) {
+                return Err(String::from(
+                    "Layouts and files named after their contents are created on the calling \
+                    thread, so there is nothing to ramp up.",
+                ));
+            }
+        }
+
+        if let Some(Some((min, max))) = self.name_length_distribution {
+            if self.name_style != Some(NameStyle::UnicodeRandom) {
+                return Err(String::from(
+                    "Name lengths can only be varied with random names.",
+                ));
+            }
+            if min < MIN_NAME_LENGTH {
+                return Err(format!(
+                    "Names must be allowed to be at least {} bytes long.",
+                    MIN_NAME_LENGTH,
+                ));
+            }
+            if min > max {
+                return Err(format!(
+                    "The minimum name length ({}) cannot exceed the maximum ({}).",
+                    min, max,
+                ));
+            }
+        }
+
+        let dir_words = self.dir_wordlist.as_ref().and_then(Option::as_ref);
+        let file_words = self.file_wordlist.as_ref().and_then(Option::as_ref);
+        if matches!(self.name_fn, Some(Some(_)))
+            && (dir_words.is_some()
+                || file_words.is_some()
+                || self.lex_sortable == Some(true)
+                || self.sort_by_size == Some(true)
+                || self.name_hash_jitter == Some(true)
+                || self.name_style.is_some_and(|style| *style != NameStyle::Numeric)
+                || self.case_collisions == Some(true)
+                || named_from_content
+                || self.hidden_ratio.is_some_and(|r| *r != 0.))
+        {
+            return Err(String::from(
+                "A name function cannot be combined with other naming options.",
+            ));
+        }
+        if self.case_collisions == Some(true)
+            && (dir_words.is_some()
+                || file_words.is_some()
+                || self.name_hash_jitter == Some(true)
+                || self.name_style.is_some_and(|style| *style != NameStyle::Numeric))
+        {
+            return Err(String::from(
+                "Case collisions cannot be combined with other naming options.",
+            ));
+        }
+        if dir_words.is_some() || file_words.is_some() {
+            if self.name_hash_jitter == Some(true)
+                || self.name_style.is_some_and(|style| *style != NameStyle::Numeric)
+            {
+                return Err(String::from(
+                    "Wordlists cannot be combined with other naming options.",
+                ));
+            }
+            for words in [dir_words, file_words].into_iter().flatten() {
+                validate_wordlist(words)?;
+            }
+        }
+        if let Some(NameStyle::Timestamped {
+            format,
+            start,
+            step,
+        }) = &self.name_style
+        {
+            if self.name_hash_jitter == Some(true) {
+                return Err(String::from(
+                    "Timestamped names cannot be combined with other naming options.",
+                ));
+            }
+            validate_timestamps(
+                &Timestamps {
+                    format: Arc::from(format.as_str()),
+                    start: *start,
+                    step: *step,
+                },
+                num_files
+                    .map_or(1, NonZeroUsize::get)
+                    .saturating_add(self.empty_files.unwrap_or(0)),
+            )?;
+        }
+        if let Some(ratio) = self.hidden_ratio && ratio != 0. {
+            if !(0. ..=1.).contains(&ratio) {
+                return Err(format!(
+                    "The hidden entry ratio ({}) must be between 0 and 1.",
+                    ratio,
+                ));
+            }
+            // Hiding `x` could otherwise collide with a word named `.x`
+            if let Some(word) = [dir_words, file_words]
+                .into_iter()
+                .flatten()
+                .flatten()
+                .find(|w| w.starts_with('.'))
+            {
+                return Err(format!(
+                    "Hidden entries cannot be combined with wordlists containing dotfiles such as \
+                     {:?}.",
+                    word,
+                ));
+            }
+        }
+        match (dir_words, file_words) {
+            (Some(dir_words), None) => {
+                if let Some(word) = dir_words
+                    .iter()
+                    .find(|w| w.bytes().all(|b| b.is_ascii_digit()))
+                {
+                    return Err(format!(
+                        "Directories cannot be named {:?} as it would collide with file names.",
+                        word,
+                    ));
+                }
+            }
+            (None, Some(file_words)) => {
+                if let Some(word) = file_words.iter().find(|w| w.ends_with(".dir")) {
+                    return Err(format!(
+                        "Files cannot be named {:?} as it would collide with directory names.",
+                        word,
+                    ));
+                }
+            }
+            (Some(dir_words), Some(file_words)) => {
+                if let Some(word) = dir_words.iter().find(|w| file_words.contains(w)) {
+                    return Err(format!(
+                        "{:?} cannot be used to name both files and directories.",
+                        word,
+                    ));
+                }
+            }
+            (None, None) => {}
+        }
+
+        if let Some(depth) = self.nesting_depth && depth != 1 {
+            if depth == 0 {
+                return Err(String::from("Archives must be nested at least once."));
+            }
+            if depth > MAX_NESTING_DEPTH {
+                return Err(format!(
+                    "Archives cannot be nested more than {} levels deep.",
+                    MAX_NESTING_DEPTH,
+                ));
+            }
+            if !matches!(self.fill_mode, Some(FillMode::Archive { .. })) {
+                return Err(String::from("Only archives can be nested."));
+            }
+        }
+
+        if let Some(FillMode::Repeating(pattern)) = &self.fill_mode && pattern.is_empty() {
+            return Err(String::from("The fill pattern cannot be empty."));
+        }
+
+        if let Some(FillMode::Blended { permille }) = self.fill_mode && permille > 1000 {
+            return Err(format!(
+                "The content entropy ({} permille) cannot exceed 1000 permille.",
+                permille,
+            ));
+        }
+
+        if let Some(FillMode::Archive { format, inner_entries }) = self.fill_mode {
+            if inner_entries == 0 {
+                return Err(String::from("Archives must contain at least one entry."));
+            }
+            if no_bytes {
+                return Err(String::from(
+                    "Archives need a nonzero number of bytes to fill their entries.",
+                ));
+            }
+            if format == ArchiveFormat::Zip && inner_entries > usize::from(u16::MAX) {
+                return Err(format!(
+                    "Zip archives cannot contain more than {} entries.",
+                    u16::MAX,
+                ));
+            }
+            if self.bytes_exact == Some(true) || schedule.is_some() {
+                return Err(String::from(
+                    "Archives cannot be used with exact file sizes.",
+                ));
+            }
+        }
+
+        if let Some(Some(header)) = &self.text_header {
+            if header.is_empty() {
+                return Err(String::from("The text header cannot be empty."));
+            }
+            if no_bytes {
+                return Err(String::from(
+                    "Text headers need a nonzero number of bytes to be written into files.",
+                ));
+            }
+            if matches!(self.fill_mode, Some(FillMode::Archive { .. })) {
+                return Err(String::from("Text headers cannot be used with archives."));
+            }
+            if matches!(self.content_source, Some(Some(_))) {
+                return Err(String::from(
+                    "Text headers cannot be used with a content source.",
+                ));
+            }
+        }
+
+        if matches!(self.sequence_header, Some(Some(_))) {
+            if no_bytes {
+                return Err(String::from(
+                    "Sequence headers need a nonzero number of bytes to be written into files.",
+                ));
+            }
+            if matches!(self.text_header, Some(Some(_))) {
+                return Err(String::from(
+                    "Files cannot start with both a text header and a sequence header.",
+                ));
+            }
+            // Every file must actually be written for its header to be readable
+            if matches!(self.fill_mode, Some(FillMode::Archive { .. }))
+                || matches!(self.content_source, Some(Some(_)))
+                || matches!(self.duplicate_groups, Some(Some(_)))
+                || self.sparse_ratio.is_some_and(|r| *r != 0.)
+                || named_from_content
+            {
+                return Err(String::from(
+                    "Sequence headers cannot be used with archives, a content source, duplicates, \
+                    sparse files, or files named after their contents.",
+                ));
+            }
+            // Files smaller than their header are padded up to it
+            if self.bytes_exact == Some(true)
+                || schedule.is_some()
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+            {
+                return Err(String::from(
+                    "Sequence headers cannot be used with exact file sizes or empty files.",
+                ));
+            }
+        }
+
+        if self.sort_by_size == Some(true) {
+            if no_bytes {
+                return Err(String::from(
+                    "Sorting files by size needs a nonzero number of bytes.",
+                ));
+            }
+            // Leftovers are placed in a separate batch of files whose sizes aren't ordered
+            // relative to the rest of their directory.
+            if files_exact
+                || self.bytes_exact == Some(true)
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || self.min_files.is_some_and(|min| *min > 0)
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+            {
+                return Err(String::from(
+                    "Files cannot be sorted by size with exact counts, a minimum number of files, \
+                    or empty files.",
+                ));
+            }
+            if matches!(self.duplicate_groups, Some(Some(_)))
+                || matches!(self.content_source, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "Files cannot be sorted by size with duplicate groups or a content source.",
+                ));
+            }
+            if dir_words.is_some()
+                || file_words.is_some()
+                || self.name_hash_jitter == Some(true)
+                || self.case_collisions == Some(true)
+                || self.hidden_ratio.is_some_and(|r| *r != 0.)
+                || self.name_style.is_some_and(|style| *style != NameStyle::Numeric)
+            {
+                return Err(String::from(
+                    "Files can only be sorted by size with plain numeric names.",
+                ));
+            }
+        }
+
+        if let Some(SizeDistribution::Zipf { exponent }) = self.size_distribution {
+            if !(exponent > 0. && exponent.is_finite()) {
+                return Err(format!(
+                    "The Zipf exponent ({}) must be positive.",
+                    exponent,
+                ));
+            }
+            if no_bytes {
+                return Err(String::from(
+                    "A Zipf size distribution needs a nonzero number of bytes.",
+                ));
+            }
+            if schedule.is_some() || disk_usage.is_some() {
+                return Err(String::from(
+                    "A Zipf size distribution cannot be used with a size schedule or a disk usage \
+                    target.",
+                ));
+            }
+        }
+
+        if matches!(self.size_alignment, Some(Some(_))) {
+            if no_bytes {
+                return Err(String::from(
+                    "Aligning file sizes needs a nonzero number of bytes.",
+                ));
+            }
+            if schedule.is_some() {
+                return Err(String::from(
+                    "File sizes cannot be aligned when they come from a size schedule.",
+                ));
+            }
+            // Both wrap the sampled number of bytes in extra data of their own
+            if matches!(self.fill_mode, Some(FillMode::Archive { .. }))
+                || matches!(self.media_mix, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "Archives and media files cannot have their sizes aligned.",
+                ));
+            }
+        }
+
+        if let Some(ratio) = self.sparse_ratio && ratio != 0. {
+            if !(0. ..=1.).contains(&ratio) {
+                return Err(format!(
+                    "The sparse file ratio ({}) must be between 0 and 1.",
+                    ratio,
+                ));
+            }
+            if no_bytes {
+                return Err(String::from(
+                    "Sparse files need a nonzero number of bytes.",
+                ));
+            }
+            if !matches!(self.fill_mode, None | Some(FillMode::Random))
+                || matches!(self.text_header, Some(Some(_)))
+                || matches!(self.content_source, Some(Some(_)))
+                || matches!(self.duplicate_groups, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "Sparse files cannot be combined with other file contents options.",
+                ));
+            }
+            if matches!(self.checksums, Some(Some(_))) {
+                return Err(String::from(
+                    "Checksums cannot be written when creating sparse files.",
+                ));
+            }
+        }
+        if self.sparse_holes == Some(true) && !self.sparse_ratio.is_some_and(|r| *r != 0.) {
+            return Err(String::from(
+                "Holes can only be left in sparse files, which need a sparse file ratio.",
+            ));
+        }
+
+        // Archives and media files grow past the number of bytes they're given while
+        // content-addressed trees are written without any of the usual contents options
+        if self.preallocate == Some(true)
+            && (self.sparse_ratio.is_some_and(|r| *r != 0.)
+                || matches!(self.fill_mode, Some(FillMode::Archive { .. }))
+                || matches!(self.media_mix, Some(Some(_)))
+                || named_from_content)
+        {
+            return Err(String::from(
+                "Files cannot be preallocated when they are sparse, archives, media files, or \
+                named after their contents.",
+            ));
+        }
+
+        if let Some(Some(sequence)) = &self.mtime_sequence {
+            if matches!(self.fixed_time, Some(Some(_))) {
+                return Err(String::from(
+                    "Modification times cannot both be pinned and form a sequence.",
+                ));
+            }
+            if sequence.interval == 0 {
+                return Err(String::from(
+                    "Modification times in a sequence must be at least a second apart.",
+                ));
+            }
+        }
+
+        if let Some(Some(formats)) = &self.media_mix {
+            if formats.is_empty() {
+                return Err(String::from(
+                    "The media mix must contain at least one format.",
+                ));
+            }
+            if no_bytes {
+                return Err(String::from(
+                    "Media files need a nonzero number of bytes.",
+                ));
+            }
+            if matches!(self.fill_mode, Some(FillMode::Archive { .. }))
+                || matches!(self.content_source, Some(Some(_)))
+                || self.sparse_ratio.is_some_and(|r| *r != 0.)
+                || named_from_content
+            {
+                return Err(String::from(
+                    "Media files cannot be archives, sparse, filled from a content source, or \
+                    named after their contents.",
+                ));
+            }
+            if self.bytes_exact == Some(true) || schedule.is_some() {
+                return Err(String::from(
+                    "Media files cannot be used with exact file sizes.",
+                ));
+            }
+        }
+
+        if self.group_by_extension == Some(true) {
+            if !matches!(self.media_mix, Some(Some(_))) {
+                return Err(String::from(
+                    "Grouping files by extension needs a media mix to give the files their types.",
+                ));
+            }
+            if schedule.is_some()
+                || disk_usage.is_some()
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || matches!(self.layout, Some(Some(_)))
+                || self.min_files.is_some_and(|min| *min > 0)
+                || self.min_dirs.is_some_and(|min| *min > 0)
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+                || self.seed_paths.as_ref().is_some_and(|paths| !paths.is_empty())
+                || matches!(self.overlay_source, Some(Some(_)))
+                || matches!(self.checkpoint, Some(Some(_)))
+                || self.dir_seeds == Some(true)
+            {
+                return Err(String::from(
+                    "Grouping files by extension splits them between a directory per format, so \
+                    it cannot be combined with size schedules, target disk usages, exact \
+                    directory counts, layouts, minimum counts, empty files, seed paths, overlays, \
+                    checkpoints, or directory seeds.",
+                ));
+            }
+        }
+
+        if matches!(self.max_total_size, Some(Some(_))) {
+            if self.self_check == Some(true) {
+                return Err(String::from(
+                    "A tree cut short by its maximum total size cannot be self-checked.",
+                ));
+            }
+            // Restarts can't tell how much of the budget the interrupted run used up
+            if matches!(self.checkpoint, Some(Some(_))) {
+                return Err(String::from(
+                    "A maximum total size cannot be combined with checkpoints.",
+                ));
+            }
+            if named_from_content {
+                return Err(String::from(
+                    "A maximum total size cannot be combined with files named after their \
+                    contents.",
+                ));
+            }
+        }
+
+        // Both phases and content-addressed trees visit directories differently
+        if matches!(self.dump_samples, Some(Some(_)))
+            && (self.two_phase == Some(true) || named_from_content)
+        {
+            return Err(String::from(
+                "Samples cannot be dumped with two phases or files named after their contents.",
+            ));
+        }
+
+        if let Some(ratio) = self.read_only_ratio && ratio != 0. {
+            if !(0. ..=1.).contains(&ratio) {
+                return Err(format!(
+                    "The read-only ratio ({}) must be between 0 and 1.",
+                    ratio,
+                ));
+            }
+            // Restarts rewrite the files the interrupted run may have gotten to
+            if matches!(self.checkpoint, Some(Some(_))) {
+                return Err(String::from(
+                    "Read-only entries cannot be combined with checkpoints.",
+                ));
+            }
+            if named_from_content {
+                return Err(String::from(
+                    "Files named after their contents cannot be made read-only.",
+                ));
+            }
+        } else if self.read_only_dirs == Some(true) {
+            return Err(String::from(
+                "Read-only directories need a nonzero read-only ratio.",
+            ));
+        }
+
+        if let Some(ratio) = self.xattr_ratio && ratio != 0. {
+            if !(0. ..=1.).contains(&ratio) {
+                return Err(format!(
+                    "The extended attribute ratio ({}) must be between 0 and 1.",
+                    ratio,
+                ));
+            }
+            if !cfg!(any(unix, windows)) {
+                return Err(String::from(
+                    "Extended attributes are only supported on Unix and Windows.",
+                ));
+            }
+            if named_from_content {
+                return Err(String::from(
+                    "Files named after their contents cannot be given extended attributes.",
+                ));
+            }
+        }
+
+        if let Some(Some(layout)) = &self.layout {
+            if layout.num_files() == 0 {
+                return Err(String::from(
+                    "The layout must contain at least one file.",
+                ));
+            }
+            if self.num_files.is_some()
+                || entries.is_some()
+                || self.files_exact == Some(true)
+                || matches!(self.dirs_exact, Some(Some(_)))
+                || schedule.is_some()
+                || self.min_files.is_some_and(|min| *min > 0)
+                || self.min_files_per_dir.is_some_and(|min| *min > 0)
+                || self.cluster_factor.is_some_and(|f| *f != 0.)
+                || matches!(self.leaf_depth_distribution, Some(Some(_)))
+                || self.depth_jitter.is_some_and(|jitter| *jitter > 0)
+                || self.file_to_dir_ratio.is_some()
+                || matches!(self.dir_fraction, Some(Some(_)))
+                || self.chain == Some(true)
+                || matches!(self.balanced_fanout, Some(Some(_)))
+                || matches!(self.max_dir_width, Some(Some(_)))
+                || self.shrink_depth == Some(true)
+            {
+                return Err(String::from(
+                    "A layout already dictates the files and directories of the tree, so it \
+                    cannot be combined with other options that shape it.",
+                ));
+            }
+            // Directories are created by name outside of the usual tasks
+            if named_from_content
+                || self.sort_by_size == Some(true)
+                || matches!(self.duplicate_groups, Some(Some(_)))
+                || matches!(self.content_source, Some(Some(_)))
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+            {
+                return Err(String::from(
+                    "A layout cannot be combined with files named after or sorted by their \
+                    contents, duplicates, content sources, or empty files.",
+                ));
+            }
+            if self.repair == Some(true)
+                || self.two_phase == Some(true)
+                || self.self_check == Some(true)
+                || matches!(self.checkpoint, Some(Some(_)))
+                || matches!(self.max_total_size, Some(Some(_)))
+                || matches!(self.dump_samples, Some(Some(_)))
+            {
+                return Err(String::from(
+                    "A layout cannot be repaired, checked, checkpointed, capped, split into \
+                    phases, or sampled.",
+                ));
+            }
+        }
+
+        if let Some(Some(objects)) = self.git_objects {
+            if self.num_files.is_some()
+                || entries.is_some()
+                || schedule.is_some()
+                || matches!(self.layout, Some(Some(_)))
+                || self.names_from_content == Some(true)
+            {
+                return Err(String::from(
+                    "The number of git objects replaces the number of files and how they're \
+                    named.",
+                ));
+            }
+            if u32::try_from(objects.get()).is_err() {
+                return Err(format!(
+                    "A git object store can hold at most {} objects.",
+                    u32::MAX,
+                ));
+            }
+        }
+
+        if named_from_content {
+            if no_bytes {
+                return Err(String::from(
+                    "Naming files after their contents needs a nonzero number of bytes.",
+                ));
+            }
+            if !matches!(self.fill_mode, None | Some(FillMode::Random))
+                || matches!(self.text_header, Some(Some(_)))
+                || matches!(self.content_source, Some(Some(_)))
+                || matches!(self.duplicate_groups, Some(Some(_)))
+                || self.sparse_ratio.is_some_and(|r| *r != 0.)
+                || self.empty_files.is_some_and(|empty| *empty > 0)
+            {
+                return Err(String::from(
+                    "Files named after their contents cannot be combined with other file contents \
+                    options.",
+                ));
+            }
+            if self.repair == Some(true)
+                || self.two_phase == Some(true)
+                || self.self_check == Some(true)
+                || matches!(self.byte_rate, Some(Some(_)))
+                || matches!(self.write_memory_budget, Some(Some(_)))
+                || matches!(self.checksums, Some(Some(_)))
+                || matches!(self.fixed_time, Some(Some(_)))
+                || matches!(self.mtime_sequence, Some(Some(_)))
+                || self.special_ratio.is_some_and(|r| *r != 0.)
+            {
+                return Err(String::from(
+                    "Files named after their contents cannot be repaired, checked, pinned, \
+                    throttled, or mixed with special files.",
+                ));
+            }
+            #[cfg(unix)]
+            if self.broken_symlink_ratio.is_some_and(|r| *r != 0.) {
+                return Err(String::from(
+                    "Files named after their contents cannot be mixed with broken symlinks.",
+                ));
+            }
+        }
+
+        if let Some(empty_files) = self.empty_files && empty_files > 0 {
+            if self.special_ratio.is_some_and(|r| *r != 0.) {
+                return Err(String::from(
+                    "Empty files cannot be used with special files.",
+                ));
+            }
+            #[cfg(unix)]
+            if self.broken_symlink_ratio.is_some_and(|r| *r != 0.) {
+                return Err(String::from(
+                    "Empty files cannot be used with broken symlinks.",
+                ));
+            }
+        }
+
+        if matches!(self.duplicate_groups, Some(Some(_))) {
+            if no_bytes {
+                return Err(String::from(
+                    "Duplicate groups need a nonzero number of bytes to be written into files.",
+                ));
+            }
+            if self.bytes_exact == Some(true) {
+                return Err(String::from(
+                    "Duplicate groups cannot be used with an exact number of bytes.",
+                ));
+            }
+        }
+
+        if let Some(ratio) = self.special_ratio && ratio != 0. {
+            if !(0. ..=1.).contains(&ratio) {
+                return Err(format!(
+                    "The special file ratio ({}) must be between 0 and 1.",
+                    ratio,
+                ));
+            }
+            if !cfg!(target_os = "linux") {
+                return Err(String::from("Special files are only supported on Linux."));
+            }
+            if self.bytes_exact == Some(true) {
+                return Err(String::from(
+                    "Special files cannot be used with an exact number of bytes.",
+                ));
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(ratio) = self.broken_symlink_ratio && ratio != 0. {
+            if !(0. ..=1.).contains(&ratio) {
+                return Err(format!(
+                    "The broken symlink ratio ({}) must be between 0 and 1.",
+                    ratio,
+                ));
+            }
+            if ratio + self.special_ratio.unwrap_or(0.) > 1. {
+                return Err(String::from(
+                    "The special file and broken symlink ratios cannot add up to more than 1.",
+                ));
+            }
+            if self.bytes_exact == Some(true) {
+                return Err(String::from(
+                    "Broken symlinks cannot be used with an exact number of bytes.",
+                ));
+            }
+            if schedule.is_some() {
+                return Err(String::from(
+                    "Broken symlinks cannot be used with a size schedule.",
+                ));
+            }
+            if let Some(word) = [dir_words, file_words]
+                .into_iter()
+                .flatten()
+                .flatten()
+                .find(|w| w.ends_with(".missing"))
+            {
+                return Err(format!(
+                    "{:?} cannot be used as a name as it could be the target of a broken symlink.",
+                    word,
+                ));
+            }
+        }
+
+        if matches!(self.content_source, Some(Some(_))) {
+            if matches!(self.fill_mode, Some(FillMode::Repeating(_) | FillMode::Blended { .. })) {
+                return Err(String::from(
+                    "Files cannot be filled with both a pattern and a content source.",
+                ));
+            }
+            if matches!(self.fill_mode, Some(FillMode::Archive { .. })) {
+                return Err(String::from(
+                    "Files cannot be archives when filled from a content source.",
+                ));
+            }
+            if matches!(self.duplicate_groups, Some(Some(_))) {
+                return Err(String::from(
+                    "Duplicate groups cannot be used with a content source.",
+                ));
+            }
+            if self.bytes_exact == Some(true) || schedule.is_some() {
+                return Err(String::from(
+                    "A content source cannot be used with exact file sizes.",
+                ));
+            }
+            if self.chain == Some(true) {
+                return Err(String::from(
+                    "A content source cannot be used with a chain tree.",
+                ));
+            }
+        }
+
+        if self.progress_interval == Some(ProgressInterval::EveryNFiles(0)) {
+            return Err(String::from(
+                "Progress must be reported at least every one file.",
+            ));
+        }
+
         Ok(())
     }
 
+    fn default_num_files(&self) -> NonZeroUsize {
+        // Stands in for the files until the entries are split between files and directories
+        if let Some(Some(entries)) = self.num_entries {
+            return entries;
+        }
+        if let Some(Some(layout)) = &self.layout {
+            return NonZeroUsize::new(layout.num_files())
+                .unwrap_or_else(|| NonZeroUsize::new(1).unwrap());
+        }
+        if let Some(Some(objects)) = self.git_objects {
+            return objects;
+        }
+        let sizes = self.size_schedule.as_ref().unwrap().as_ref().unwrap();
+        NonZeroUsize::new(sizes.len()).unwrap()
+    }
+
     fn default_ftd_ratio(&self) -> NonZeroUsize {
-        let r = max(self.num_files.unwrap().get() / 1000, 1);
+        let num_files = match &self.size_schedule {
+            Some(Some(sizes)) => sizes.len(),
+            _ => self.num_files.unwrap_or_else(|| self.default_num_files()).get(),
+        };
+        let r = max(
+            match self.dirs_exact {
+                // Spread the files evenly across the requested directories (plus the root)
+                Some(Some(dirs)) => num_files / dirs.saturating_add(1),
+                _ => num_files / 1000,
+            },
+            1,
+        );
         unsafe { NonZeroUsize::new_unchecked(r) }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
     #[test]
@@ -68,11 +1822,51 @@ mod tests {
         assert_eq!(g.root_dir, PathBuf::from("abc"));
         assert_eq!(g.num_files.get(), 1);
         assert!(!g.files_exact);
+        assert_eq!(g.min_files, 0);
+        assert_eq!(g.min_dirs, 0);
+        assert_eq!(g.min_files_per_dir, 0);
+        assert_eq!(g.cluster_factor, 0.);
+        assert_eq!(g.creation_order, CreationOrder::DirsFirst);
         assert_eq!(g.num_bytes, 0);
         assert!(!g.bytes_exact);
+        assert_eq!(g.size_schedule, None);
+        assert_eq!(g.dirs_exact, None);
         assert_eq!(g.max_depth, 5);
+        assert!(!g.depth_includes_root);
+        assert_eq!(g.leaf_depth_distribution, None);
+        assert_eq!(g.structural_levels, 0);
+        assert_eq!(g.size_distribution, SizeDistribution::Normal);
         assert_eq!(g.file_to_dir_ratio.get(), 1);
+        assert_eq!(g.dir_fraction, None);
         assert_eq!(g.seed, 0);
+        assert_eq!(g.seed_phrase, None);
+        assert_eq!(g.structure_seed, None);
+        assert_eq!(g.content_seed, None);
+        assert!(!g.lex_sortable);
+        assert!(!g.name_hash_jitter);
+        assert_eq!(g.name_style, NameStyle::Numeric);
+        assert_eq!(g.dir_wordlist, None);
+        assert_eq!(g.file_wordlist, None);
+        assert_eq!(g.duplicate_groups, None);
+        assert_eq!(g.fill_mode, FillMode::Random);
+        assert_eq!(g.nesting_depth, 1);
+        assert_eq!(g.special_ratio, 0.);
+        #[cfg(unix)]
+        assert_eq!(g.broken_symlink_ratio, 0.);
+        assert!(!g.repair);
+        assert!(!g.openat);
+        assert!(!g.chain);
+        assert_eq!(g.balanced_fanout, None);
+        assert!(!g.overwrite);
+        assert!(!g.force_dangerous);
+        assert!(!g.self_check);
+        assert!(!g.write_marker);
+        assert!(!g.color);
+        assert_eq!(g.umask, None);
+        assert_eq!(g.fixed_time, None);
+        assert_eq!(g.mtime_sequence, None);
+        assert_eq!(g.checksums, None);
+        assert_eq!(g.write_seed, None);
     }
 
     #[test]
@@ -85,46 +1879,2172 @@ mod tests {
 
         assert!(g.is_err());
     }
-}
 
-impl Generator {
-    pub fn generate(self) -> CliResult<()> {
-        let options = validated_options(self)?;
-        print_configuration_info(&options);
-        print_stats(run_generator(options)?);
-        Ok(())
-    }
-}
+    #[test]
+    fn missing_num_files_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .build();
 
-#[derive(Debug)]
-struct Configuration {
-    root_dir: PathBuf,
-    files: usize,
-    bytes: usize,
-    files_exact: bool,
-    bytes_exact: bool,
-    files_per_dir: f64,
-    dirs_per_dir: f64,
-    bytes_per_file: f64,
-    max_depth: u32,
-    seed: u64,
+        assert!(g.is_err());
+    }
 
-    informational_dirs_per_dir: usize,
-    informational_total_dirs: usize,
-    informational_bytes_per_files: usize,
+    #[test]
+    fn size_schedule_replaces_num_files() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .size_schedule(vec![1, 2, 3])
+            .build()
+            .unwrap();
+
+        let config = configuration(g);
+
+        assert_eq!(config.files, 3);
+        assert_eq!(config.bytes, 6);
+        assert!(config.files_exact);
+    }
+
+    #[rstest]
+    #[case(None, None, 10_000)]
+    #[case(None, Some(0.5), 5_500)]
+    #[case(Some(1_000), None, 10_000)]
+    fn num_entries_are_split_between_files_and_dirs(
+        #[case] dirs_exact: Option<usize>,
+        #[case] dir_fraction: Option<f64>,
+        #[case] expected_files: usize,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_entries(NonZeroUsize::new(11_000).unwrap());
+        if let Some(dirs) = dirs_exact {
+            builder.dirs_exact(dirs);
+        }
+        match dir_fraction {
+            Some(fraction) => builder.dir_fraction(fraction),
+            None => builder.file_to_dir_ratio(NonZeroUsize::new(10).unwrap()),
+        };
+
+        let config = configuration(builder.build().unwrap());
+
+        assert_eq!(config.files, expected_files);
+        if dirs_exact.is_none() && dir_fraction.is_none() {
+            assert_eq!(config.files + config.informational_total_dirs, 11_000);
+        }
+    }
+
+    #[test]
+    fn num_entries_with_num_files_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_entries(NonZeroUsize::new(10).unwrap())
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(Vec::new(), false)]
+    #[case(vec![usize::MAX, 1], false)]
+    #[case(vec![1], true)]
+    fn invalid_size_schedules_fail(#[case] sizes: Vec<usize>, #[case] chain: bool) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .size_schedule(sizes)
+            .chain(chain)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn min_files_greater_than_num_files_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .min_files(2)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(true, None, false)]
+    #[case(false, Some(1), false)]
+    #[case(false, None, true)]
+    fn min_files_per_dir_with_exact_counts_or_chain_fails(
+        #[case] files_exact: bool,
+        #[case] dirs_exact: Option<usize>,
+        #[case] chain: bool,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .min_files_per_dir(1)
+            .files_exact(files_exact)
+            .chain(chain);
+        if let Some(dirs) = dirs_exact {
+            builder.dirs_exact(dirs);
+        }
+
+        assert!(builder.build().is_err());
+    }
+
+    const ALL_CAPABILITIES: Capabilities = Capabilities {
+        fifos: true,
+        symlinks: true,
+        sparse_files: true,
+        permissions: true,
+        case_sensitive: true,
+    };
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn missing_fifo_support_falls_back_to_regular_files() {
+        let mut g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .special_ratio(0.5)
+            .build()
+            .unwrap();
+
+        Capabilities {
+            fifos: false,
+            ..ALL_CAPABILITIES
+        }
+        .restrict(&mut g);
+
+        assert_eq!(g.special_ratio, 0.);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn missing_symlink_support_falls_back_to_regular_files() {
+        let mut g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .broken_symlink_ratio(0.5)
+            .build()
+            .unwrap();
+
+        Capabilities {
+            symlinks: false,
+            ..ALL_CAPABILITIES
+        }
+        .restrict(&mut g);
+
+        assert_eq!(g.broken_symlink_ratio, 0.);
+    }
+
+    #[test]
+    fn missing_sparse_file_support_falls_back_to_full_writes() {
+        let mut g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1_000_000)
+            .sparse_ratio(0.5)
+            .sparse_holes(true)
+            .build()
+            .unwrap();
+
+        Capabilities {
+            sparse_files: false,
+            ..ALL_CAPABILITIES
+        }
+        .restrict(&mut g);
+
+        assert_eq!(g.sparse_ratio, 0.);
+        assert!(!g.sparse_holes);
+    }
+
+    #[test]
+    fn missing_permission_support_leaves_entries_writable() {
+        let mut g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .read_only_ratio(0.5)
+            .read_only_dirs(true)
+            .build()
+            .unwrap();
+
+        Capabilities {
+            permissions: false,
+            ..ALL_CAPABILITIES
+        }
+        .restrict(&mut g);
+
+        assert_eq!(g.read_only_ratio, 0.);
+        assert!(!g.read_only_dirs);
+    }
+
+    #[test]
+    fn supported_options_are_left_alone() {
+        let mut g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1_000_000)
+            .sparse_ratio(0.5)
+            .read_only_ratio(0.5)
+            .build()
+            .unwrap();
+
+        ALL_CAPABILITIES.restrict(&mut g);
+
+        assert_eq!(g.sparse_ratio, 0.5);
+        assert_eq!(g.read_only_ratio, 0.5);
+    }
+
+    #[rstest]
+    #[case(-0.5)]
+    #[case(1.)]
+    #[case(f64::NAN)]
+    fn out_of_range_cluster_factor_fails(#[case] factor: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .cluster_factor(factor)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn exact_dirs_with_zero_depth_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .dirs_exact(1)
+            .max_depth(0)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(0., 0.5)]
+    #[case(-1., 0.5)]
+    #[case(3., -0.5)]
+    #[case(f64::NAN, 0.5)]
+    #[case(3., f64::INFINITY)]
+    fn invalid_leaf_depth_distribution_fails(#[case] mean: f64, #[case] cv: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .leaf_depth_distribution(DepthDistribution::Normal { mean, cv })
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn depth_jitter_of_max_depth_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .max_depth(3)
+            .depth_jitter(3)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn timestamped_names_are_validated(#[values(false, true)] jitter: bool) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .name_hash_jitter(jitter)
+            .name_style(NameStyle::Timestamped {
+                format: String::from("%Y-%m"),
+                start: 0,
+                step: 86_400,
+            })
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(0.)]
+    #[case(1.)]
+    #[case(f64::NAN)]
+    fn invalid_dir_fraction_fails(#[case] fraction: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .dir_fraction(fraction)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn dir_fraction_with_ratio_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+            .dir_fraction(0.2)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn dir_fraction_sets_files_per_dir() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(10_000).unwrap())
+            .dir_fraction(0.25)
+            .build()
+            .unwrap();
+
+        let config = configuration(g);
+
+        assert_eq!(config.files_per_dir, 3.);
+        assert_eq!(config.informational_total_dirs, 3_333);
+    }
+
+    #[rstest]
+    #[case(1, 10, 0)]
+    #[case(10, 10, 0)]
+    #[case(11, 10, 1)]
+    #[case(100, 10, 1)]
+    #[case(101, 10, 2)]
+    #[case(1_000_000, 2, 19)]
+    fn balanced_trees_are_just_deep_enough(
+        #[case] num_files: usize,
+        #[case] fanout: usize,
+        #[case] depth: u32,
+    ) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(num_files).unwrap())
+            .balanced_fanout(fanout)
+            .build()
+            .unwrap();
+
+        let config = configuration(g);
+
+        assert_eq!(config.max_depth, depth);
+        assert!(config.files_per_dir <= fanout as f64);
+    }
+
+    #[rstest]
+    #[case(1, false)]
+    #[case(2, true)]
+    fn invalid_balanced_trees_fail(#[case] fanout: usize, #[case] chain: bool) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .balanced_fanout(fanout)
+            .chain(chain)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(0.)]
+    #[case(-1.)]
+    #[case(f64::NAN)]
+    #[case(f64::INFINITY)]
+    fn invalid_zipf_exponent_fails(#[case] exponent: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(100)
+            .size_distribution(SizeDistribution::Zipf { exponent })
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn generalized_harmonic_tail_is_accurate(#[values(0.5, 1., 1.5)] exponent: f64) {
+        let n = 3 << 20;
+        let exact = (1..=n).map(|k| (k as f64).powf(-exponent)).sum::<f64>();
+
+        let approx = generalized_harmonic(n, exponent);
+
+        assert!((approx - exact).abs() / exact < 1e-8, "{} vs {}", approx, exact);
+    }
+
+    #[test]
+    fn progress_every_zero_files_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .progress_interval(ProgressInterval::EveryNFiles(0))
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn chain_with_exact_dirs_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .dirs_exact(1)
+            .chain(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn split_seeds_with_exact_files_require_exact_dirs() {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .files_exact(true)
+            .content_seed(1);
+
+        assert!(builder.build().is_err());
+        assert!(builder.dirs_exact(0).build().is_ok());
+    }
+
+    #[test]
+    fn checksums_with_repair_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .checksums(PathBuf::from("sums"))
+            .repair(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn dir_seeds_without_their_prerequisites_fail(
+        #[values("no_marker", "no_bytes", "files_exact", "seed_paths")] missing: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(1000)
+            .write_marker(true)
+            .dir_seeds(true);
+        match missing {
+            "no_marker" => builder.write_marker(false),
+            "no_bytes" => builder.num_bytes(0),
+            "files_exact" => builder.files_exact(true),
+            _ => builder.seed_paths(vec![String::from("seed")]),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[rstest]
+    fn repair_dir_without_dir_seeds_or_repair_fails(#[values(false, true)] repair: bool) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(1000)
+            .write_marker(true)
+            .dir_seeds(!repair)
+            .repair(repair)
+            .repair_dir(PathBuf::from("0.dir"))
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn repair_dir_outside_the_root_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(1000)
+            .write_marker(true)
+            .dir_seeds(true)
+            .repair(true)
+            .repair_dir(PathBuf::from("../0.dir"))
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn overwrite_with_repair_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .overwrite(true)
+            .repair(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn media_mix_needs_bytes_and_formats(#[values(false, true)] empty: bool) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap());
+        if empty {
+            builder.num_bytes(100).media_mix(Vec::new());
+        } else {
+            builder.media_mix(vec![MediaFormat::Png]);
+        }
+
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn size_alignment_without_bytes_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .size_alignment(NonZeroUsize::new(512).unwrap())
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn mtime_sequence_with_fixed_time_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .fixed_time(SystemTime::UNIX_EPOCH)
+            .mtime_sequence(MtimeSequence {
+                start: 0,
+                interval: 1,
+            })
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn read_only_dirs_without_ratio_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .read_only_dirs(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn xattr_ratio_out_of_range_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .xattr_ratio(1.5)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn empty_cpu_affinity_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .cpu_affinity(Vec::new())
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pinned_threads_are_confined_to_their_cores() {
+        let core = core_affinity::get_core_ids().unwrap().last().unwrap().id;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .max_blocking_threads(4)
+            .on_thread_start(pin_to_cores(Arc::from([core])))
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let tasks = (0..4)
+                .map(|_| tokio::task::spawn_blocking(|| core_affinity::get_core_ids().unwrap()))
+                .collect::<Vec<_>>();
+            for task in tasks {
+                let cores = task.await.unwrap();
+                assert_eq!(cores.iter().map(|id| id.id).collect::<Vec<_>>(), [core]);
+            }
+        });
+    }
+
+    #[rstest]
+    fn ramp_up_that_is_empty_or_has_nothing_to_ramp_fails(
+        #[values("zero", "names_from_content", "layout")] reason: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_bytes(1000)
+            .ramp_up(Duration::from_secs(1));
+        match reason {
+            "zero" => builder
+                .num_files(NonZeroUsize::new(1).unwrap())
+                .ramp_up(Duration::ZERO),
+            "names_from_content" => builder
+                .num_files(NonZeroUsize::new(1).unwrap())
+                .names_from_content(true),
+            _ => builder.layout(Layout::from_json(r#"{"a": 1}"#).unwrap()),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[rstest]
+    fn checkpoint_with_incompatible_options_fails(
+        #[values("overwrite", "two_phase", "checksums", "special_ratio", "task_granularity")]
+        option: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .checkpoint("progress.json");
+        match option {
+            "overwrite" => builder.overwrite(true),
+            "two_phase" => builder.two_phase(true),
+            "checksums" => builder.checksums(PathBuf::from("sums")),
+            "special_ratio" => builder.special_ratio(0.5),
+            "task_granularity" => builder.task_granularity(2),
+            _ => unreachable!(),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[rstest]
+    fn overlay_with_repair_or_self_check_fails(#[values(false, true)] repair: bool) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .overlay_source("src")
+            .repair(repair)
+            .self_check(!repair)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn group_by_extension_needs_a_media_mix(#[values(false, true)] mix: bool) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(100)
+            .group_by_extension(true);
+        if mix {
+            builder.media_mix(vec![MediaFormat::Png]).min_dirs(5);
+        }
+
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn overlay_with_exact_dirs_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .overlay_source("src")
+            .dirs_exact(5)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn filesystem_root_is_dangerous() {
+        assert!(is_dangerous_root(Path::new("/")));
+        assert!(!is_dangerous_root(&env::temp_dir()));
+    }
+
+    #[test]
+    fn config_dump_contains_derived_fields() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1000).unwrap())
+            .num_bytes(10_000)
+            .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+            .max_depth(2)
+            .seed(42)
+            .seed_phrase("abc")
+            .build()
+            .unwrap();
+
+        let mut out = Vec::new();
+        g.write_config(&mut out).unwrap();
+        let config: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(config["root_dir"], "abc");
+        assert_eq!(config["files"], 1000);
+        assert_eq!(config["max_depth"], 2);
+        assert_eq!(config["files_per_dir"], 10.);
+        assert!((config["dirs_per_dir"].as_f64().unwrap() - 10.).abs() < 1e-9);
+        assert_eq!(config["bytes_per_file"], 10.);
+        assert_eq!(config["seed"], 42u64.wrapping_add(seahash::hash(b"abc")));
+        assert_eq!(config["informational_total_dirs"], 100);
+        assert!(config.get("plan").is_none());
+    }
+
+    #[rstest]
+    fn huge_file_counts_produce_sane_trees(
+        #[values(1 << 53, 1 << 62, usize::MAX)] num_files: usize,
+        #[values(1, 5, 100)] max_depth: u32,
+    ) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(num_files).unwrap())
+            .max_depth(max_depth)
+            .build()
+            .unwrap();
+
+        let config = configuration(g);
+
+        assert_eq!(config.informational_total_dirs, 1000);
+        assert!(config.dirs_per_dir > 1. && config.dirs_per_dir < 1001.);
+        assert_eq!(config.files_per_dir, (num_files / 1000) as f64);
+    }
+
+    #[test]
+    fn precise_div_handles_unrepresentable_numerators() {
+        assert_eq!(precise_div(7, 2), 3.5);
+        assert_eq!(precise_div(usize::MAX, usize::MAX), 1.);
+        assert_eq!(
+            precise_div((1 << 60) + 1, 1 << 60),
+            1. + 1. / (1u64 << 60) as f64
+        );
+    }
+
+    #[rstest]
+    #[case(Some(&["src", "src"][..]), None, false)]
+    #[case(Some(&["v-1"][..]), None, false)]
+    #[case(Some(&["src", "42"][..]), None, false)]
+    #[case(None, Some(&["a.dir"][..]), false)]
+    #[case(Some(&["src"][..]), Some(&["src"][..]), false)]
+    #[case(Some(&["src"][..]), None, true)]
+    fn invalid_wordlists_fail(
+        #[case] dir_words: Option<&[&str]>,
+        #[case] file_words: Option<&[&str]>,
+        #[case] jitter: bool,
+    ) {
+        let to_vec = |words: &[&str]| words.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .name_hash_jitter(jitter);
+        if let Some(words) = dir_words {
+            builder.dir_wordlist(to_vec(words));
+        }
+        if let Some(words) = file_words {
+            builder.file_wordlist(to_vec(words));
+        }
+
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn default_wordlists_can_be_combined() {
+        let to_vec = |words: &[&str]| words.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .dir_wordlist(to_vec(DEFAULT_DIR_WORDLIST))
+            .file_wordlist(to_vec(DEFAULT_FILE_WORDLIST))
+            .build()
+            .unwrap();
+    }
+
+    #[rstest]
+    #[case("abc", "'abc'")]
+    #[case("a b/$c", "'a b/$c'")]
+    #[case("it's", r"'it'\''s'")]
+    fn shell_quote_correctness(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(shell_quote(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case("a/b", "a/b")]
+    #[case("a,b", r#""a,b""#)]
+    #[case(r#"a"b"#, r#""a""b""#)]
+    #[case("a\nb", "\"a\nb\"")]
+    fn csv_quote_correctness(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(csv_quote(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case("a/b", "0102  a/b\n")]
+    #[case("a\\b", "\\0102  a\\\\b\n")]
+    #[case("a\nb", "\\0102  a\\nb\n")]
+    fn checksum_line_correctness(#[case] path: &str, #[case] expected: &str) {
+        let mut line = Vec::new();
+        write_checksum(&mut line, Path::new(path), &[1, 2]).unwrap();
+
+        assert_eq!(String::from_utf8(line).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(-0.5)]
+    #[case(1.5)]
+    #[case(f64::NAN)]
+    fn out_of_range_special_ratio_fails(#[case] ratio: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .special_ratio(ratio)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case(-0.5, 0.)]
+    #[case(1.5, 0.)]
+    #[case(f64::NAN, 0.)]
+    #[case(0.6, 0.5)]
+    fn out_of_range_broken_symlink_ratio_fails(#[case] ratio: f64, #[case] special_ratio: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .broken_symlink_ratio(ratio)
+            .special_ratio(special_ratio)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_symlinks_with_missing_names_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .broken_symlink_ratio(0.5)
+            .file_wordlist(vec![String::from("0123456789abcdef.missing")])
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn special_files_with_exact_bytes_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1)
+            .bytes_exact(true)
+            .special_ratio(0.5)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn empty_fill_pattern_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .fill_mode(FillMode::Repeating(Vec::new()))
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn invalid_seed_paths_fail(#[values("", "/abs", "../up", "./a", "a/b,a/b/c")] paths: &str) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .seed_paths(paths.split(',').map(String::from).collect())
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn layout_is_parsed_from_json() {
+        let layout =
+            Layout::from_json(r#"{"$files": 1, "src": {"$files": 2, "bin": 3}, "docs": {}}"#)
+                .unwrap();
+
+        assert_eq!(layout.files, 1);
+        assert_eq!(layout.num_files(), 6);
+        assert_eq!(layout.num_dirs(), 3);
+        assert_eq!(layout.depth(), 2);
+    }
+
+    #[rstest]
+    fn invalid_layouts_fail(
+        #[values("[]", "-1", r#"{"$files": 1.5}"#, r#"{"": 1}"#, r#"{"..": 1}"#, r#"{"a/b": 1}"#)]
+        json: &str,
+    ) {
+        assert!(Layout::from_json(json).is_err());
+    }
+
+    #[rstest]
+    fn layout_with_num_files_or_without_files_fails(#[values(false, true)] empty: bool) {
+        let mut builder = GeneratorBuilder::default();
+        builder.root_dir(PathBuf::from("abc"));
+        if empty {
+            builder.layout(Layout::from_json(r#"{"a": {"b": 0}}"#).unwrap());
+        } else {
+            builder
+                .num_files(NonZeroUsize::new(1).unwrap())
+                .layout(Layout::from_json(r#"{"a": 1}"#).unwrap());
+        }
+
+        let e = builder.build().unwrap_err().to_string();
+        if empty {
+            assert!(e.contains("The layout must contain at least one file."), "{}", e);
+        } else {
+            assert!(e.contains("A layout already dictates the files"), "{}", e);
+        }
+    }
+
+    #[test]
+    fn layout_provides_the_number_of_files() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .layout(Layout::from_json(r#"{"$files": 2, "a": 3}"#).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(g.num_files.get(), 5);
+    }
+
+    #[test]
+    fn too_many_entries_are_rejected() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .max_entries(100)
+            .build()
+            .unwrap();
+
+        let e = check_max_entries(&g).unwrap_err();
+
+        assert_eq!(e.code, exitcode::DATAERR);
+        assert!(format!("{:?}", e.source.unwrap()).contains("exceeds the limit of 100"));
+    }
+
+    #[test]
+    fn unlimited_entries_are_allowed() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100_000_000_000).unwrap())
+            .max_entries(usize::MAX)
+            .build()
+            .unwrap();
+
+        assert!(check_max_entries(&g).is_ok());
+    }
+
+    #[test]
+    fn target_disk_usage_with_num_bytes_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(100)
+            .target_disk_usage(1_000_000)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn target_disk_usage_rounds_files_up_to_blocks() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .target_disk_usage(100_000_000)
+            .build()
+            .unwrap();
+
+        let config = configuration(g.clone());
+
+        assert!(check_target_disk_usage(&g).is_ok());
+        assert_eq!(
+            config.bytes,
+            100_000_000 - config.informational_total_dirs * 4096 - 1_000 * 2048
+        );
+    }
+
+    #[test]
+    fn unreachable_target_disk_usage_is_rejected() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .target_disk_usage(1_000_000)
+            .build()
+            .unwrap();
+
+        let e = check_target_disk_usage(&g).unwrap_err();
+
+        assert_eq!(e.code, exitcode::DATAERR);
+    }
+
+    #[rstest]
+    fn invalid_name_lengths_fail(
+        #[values(NameStyle::Numeric, NameStyle::UnicodeRandom)] name_style: NameStyle,
+        #[values((MIN_NAME_LENGTH - 1, 100), (100, 50), (300, 400))] lengths: (usize, usize),
+    ) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .name_style(name_style)
+            .name_length_distribution(lengths.0, lengths.1)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn two_phase_reports_both_phases() {
+        let dir = tempfile::tempdir().unwrap();
+        let g = GeneratorBuilder::default()
+            .root_dir(dir.path().to_path_buf())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .max_depth(3)
+            .two_phase(true)
+            .build()
+            .unwrap();
+
+        let [(dirs_phase, _), (files_phase, _)] = run_two_phase(configuration(g)).unwrap();
+
+        assert_eq!(dirs_phase.files, 0);
+        assert!(dirs_phase.dirs > 0);
+        assert!(files_phase.files > 0);
+        assert_eq!(files_phase.dirs, 0);
+        assert!(phase_summary(Phase::Dirs, (dirs_phase, Duration::ZERO), false)
+            .starts_with(&format!(
+                "Phase 1: created {} directories",
+                dirs_phase.dirs.to_formatted_string(&Locale::en)
+            )));
+    }
+
+    #[test]
+    fn summary_is_only_colored_when_asked() {
+        let stats = GeneratorStats {
+            files: 1_000,
+            dirs: 10,
+            bytes: 4_000,
+            ..GeneratorStats::default()
+        };
+
+        assert_eq!(
+            stats_summary(stats, false),
+            "Created 1,000 files (4.0 KB) across 10 directories."
+        );
+        assert!(stats_summary(stats, true).contains("\x1b["));
+    }
+
+    #[rstest]
+    #[case(-0.5)]
+    #[case(1.5)]
+    #[case(f64::NAN)]
+    fn out_of_range_hidden_ratio_fails(#[case] ratio: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .hidden_ratio(ratio)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(-0.5)]
+    #[case(1.5)]
+    #[case(f64::NAN)]
+    fn out_of_range_sparse_ratio_fails(#[case] ratio: f64) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(100)
+            .sparse_ratio(ratio)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn sparse_holes_without_sparse_files_fail() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1)
+            .sparse_holes(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn sparse_files_with_other_contents_fail(
+        #[values("no_bytes", "pattern", "duplicate_groups", "checksums")] contents: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(1000)
+            .sparse_ratio(0.5);
+        match contents {
+            "no_bytes" => builder.num_bytes(0),
+            "pattern" => builder.fill_mode(FillMode::Repeating(b"abc".to_vec())),
+            "duplicate_groups" => builder.duplicate_groups(NonZeroUsize::new(3).unwrap()),
+            _ => builder.checksums(PathBuf::from("sums")),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[rstest]
+    fn names_from_content_with_other_contents_fail(
+        #[values("no_bytes", "pattern", "empty_files", "repair", "two_phase")] contents: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(1000)
+            .names_from_content(true);
+        match contents {
+            "no_bytes" => builder.num_bytes(0),
+            "pattern" => builder.fill_mode(FillMode::Repeating(b"abc".to_vec())),
+            "empty_files" => builder.empty_files(10),
+            "repair" => builder.repair(true),
+            _ => builder.two_phase(true),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn git_objects_provide_the_number_of_files() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_bytes(1000)
+            .git_objects(NonZeroUsize::new(100).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(g.num_files.get(), 100);
+    }
+
+    #[rstest]
+    fn git_objects_with_other_files_or_contents_fail(
+        #[values("num_files", "names_from_content", "pattern", "repair")] other: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_bytes(1000)
+            .git_objects(NonZeroUsize::new(100).unwrap());
+        match other {
+            "num_files" => builder.num_files(NonZeroUsize::new(100).unwrap()),
+            "names_from_content" => builder.names_from_content(true),
+            "pattern" => builder.fill_mode(FillMode::Repeating(b"abc".to_vec())),
+            _ => builder.repair(true),
+        };
+
+        let e = builder.build().unwrap_err().to_string();
+        let expected = match other {
+            "num_files" | "names_from_content" => "The number of git objects replaces",
+            "pattern" => "Files named after their contents cannot be combined with other file",
+            _ => "Files named after their contents cannot be repaired",
+        };
+        assert!(e.contains(expected), "{}", e);
+    }
+
+    #[rstest]
+    fn min_dirs_with_a_fixed_number_of_dirs_fails(
+        #[values("dirs_exact", "chain", "balanced", "max_dir_width", "depth")] fixed: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .min_dirs(10);
+        match fixed {
+            "dirs_exact" => builder.dirs_exact(10),
+            "chain" => builder.chain(true),
+            "balanced" => builder.balanced_fanout(2),
+            "max_dir_width" => builder.max_dir_width(NonZeroUsize::new(10).unwrap()),
+            _ => builder.max_depth(0),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[rstest]
+    fn structural_levels_with_files_near_the_root_fail(
+        #[values("too_deep", "files_exact", "min_files", "jitter", "chain", "layout")] near: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder.root_dir(PathBuf::from("abc")).structural_levels(2);
+        // A layout replaces the number of files
+        if near == "layout" {
+            builder.layout(Layout::from_json(r#"{"a": 1}"#).unwrap());
+        } else {
+            builder.num_files(NonZeroUsize::new(100).unwrap());
+        }
+        match near {
+            "too_deep" => builder.max_depth(2),
+            "files_exact" => builder.files_exact(true),
+            "min_files" => builder.min_files(10),
+            "jitter" => builder.depth_jitter(1),
+            "chain" => builder.chain(true),
+            _ => &mut builder,
+        };
+
+        let e = builder.build().unwrap_err().to_string();
+        let expected = match near {
+            "too_deep" => "must be less than the maximum depth",
+            "jitter" => "Structural levels cannot be combined with varying depths",
+            _ => "Structural levels cannot be used with exact counts",
+        };
+        assert!(e.contains(expected), "{}", e);
+    }
+
+    #[rstest]
+    fn max_dir_width_with_leftovers_in_the_root_fails(
+        #[values("bytes_exact", "dirs_exact", "min_files", "empty_files", "chain", "depth")]
+        leftovers: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .max_dir_width(NonZeroUsize::new(10).unwrap());
+        match leftovers {
+            "bytes_exact" => builder.num_bytes(1000).bytes_exact(true),
+            "dirs_exact" => builder.dirs_exact(10),
+            "min_files" => builder.min_files(100),
+            "empty_files" => builder.empty_files(10),
+            "chain" => builder.chain(true),
+            _ => builder.max_depth(0),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[rstest]
+    fn sort_by_size_with_unordered_files_fails(
+        #[values("no_bytes", "files_exact", "min_files", "duplicate_groups", "hash_jitter")]
+        unordered: &str,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(1000)
+            .sort_by_size(true);
+        match unordered {
+            "no_bytes" => builder.num_bytes(0),
+            "files_exact" => builder.files_exact(true),
+            "min_files" => builder.min_files(100),
+            "duplicate_groups" => builder.duplicate_groups(NonZeroUsize::new(3).unwrap()),
+            _ => builder.name_hash_jitter(true),
+        };
+
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn hidden_entries_with_dotfile_wordlists_fail() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .hidden_ratio(0.5)
+            .dir_wordlist(vec![String::from("src"), String::from(".src")])
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn case_collisions_with_wordlists_fail() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .case_collisions(true)
+            .file_wordlist(vec![String::from("a")])
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn name_fn_with_other_naming_options_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .name_fn(|context: NameContext| context.index.to_string())
+            .lex_sortable(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(ExplainTopic::Ratio, "you'll get ~100 directories holding ~100 files each.")]
+    #[case(ExplainTopic::Depth, "you'll get ~100 dirs, ~2.5 dirs per dir.")]
+    #[case(ExplainTopic::Bytes, "each file will be ~1,000 bytes.")]
+    fn explain_computes_derived_numbers(#[case] topic: ExplainTopic, #[case] expected: &str) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(10_000).unwrap())
+            .num_bytes(10_000_000)
+            .max_depth(5)
+            .file_to_dir_ratio(NonZeroUsize::new(100).unwrap())
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+
+        g.explain(topic, &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.trim_end().ends_with(expected), "{}", out);
+    }
+
+    #[rstest]
+    #[case(1, 5, 1, Some(0))]
+    #[case(3, 5, 1, Some(1))]
+    #[case(10_000, 5, 100, None)]
+    #[case(1, 1, 1, None)]
+    fn degenerate_trees_are_detected(
+        #[case] files: usize,
+        #[case] depth: u32,
+        #[case] ratio: usize,
+        #[case] expected: Option<u32>,
+    ) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(files).unwrap())
+            .max_depth(depth)
+            .file_to_dir_ratio(NonZeroUsize::new(ratio).unwrap());
+
+        let config = configuration(builder.build().unwrap());
+        assert_eq!(degenerate_depth(&config), expected);
+
+        let config = configuration(builder.shrink_depth(true).build().unwrap());
+        assert_eq!(config.max_depth, expected.unwrap_or(depth));
+        assert_eq!(degenerate_depth(&config), None);
+    }
+
+    #[rstest]
+    fn depth_including_the_root_leaves_one_level_fewer(
+        #[values(1, 2, 5)] depth: u32,
+        #[values(false, true)] includes_root: bool,
+    ) {
+        let config = configuration(
+            GeneratorBuilder::default()
+                .root_dir(PathBuf::from("abc"))
+                .num_files(NonZeroUsize::new(1_000).unwrap())
+                .max_depth(depth)
+                .depth_includes_root(includes_root)
+                .build()
+                .unwrap(),
+        );
+
+        assert_eq!(config.max_depth, depth - u32::from(includes_root));
+    }
+
+    #[test]
+    fn depth_including_the_root_needs_a_level_for_it() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .max_depth(0)
+            .depth_includes_root(true)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    fn content_entropy_out_of_range_fails(#[values(1001, u16::MAX)] permille: u16) {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1)
+            .fill_mode(FillMode::Blended { permille })
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn archive_without_entries_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1)
+            .fill_mode(FillMode::Archive {
+                format: ArchiveFormat::Tar,
+                inner_entries: 0,
+            })
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[rstest]
+    #[case(None, 2)]
+    #[case(Some(ArchiveFormat::Zip), 0)]
+    #[case(Some(ArchiveFormat::Tar), MAX_NESTING_DEPTH + 1)]
+    fn invalid_nesting_depth_fails(#[case] format: Option<ArchiveFormat>, #[case] depth: usize) {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1)
+            .nesting_depth(depth);
+        if let Some(format) = format {
+            builder.fill_mode(FillMode::Archive {
+                format,
+                inner_entries: 1,
+            });
+        }
+
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn empty_files_with_special_files_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .empty_files(1)
+            .special_ratio(0.5)
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn pattern_with_content_source_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .fill_mode(FillMode::Repeating(b"test".to_vec()))
+            .content_source("samples")
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn duplicate_groups_with_exact_bytes_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .num_bytes(1)
+            .bytes_exact(true)
+            .duplicate_groups(NonZeroUsize::new(1).unwrap())
+            .build();
+
+        assert!(g.is_err());
+    }
+
+    #[test]
+    fn duplicate_groups_without_bytes_fails() {
+        let g = GeneratorBuilder::default()
+            .root_dir(PathBuf::from("abc"))
+            .num_files(NonZeroUsize::new(10).unwrap())
+            .duplicate_groups(NonZeroUsize::new(2).unwrap())
+            .build();
+
+        assert!(g.is_err());
+    }
+}
+
+impl Generator {
+    pub fn generate(self) -> CliResult<()> {
+        #[cfg(feature = "syslog")]
+        if let Some(syslog) = self.syslog.clone() {
+            let result = self.generate_tree();
+            if let Err(CliExitError { source: Some(e), .. }) = &result {
+                syslog.log(Severity::Error, &format!("{:#}", e));
+            }
+            return result;
+        }
+        self.generate_tree()
+    }
+
+    fn generate_tree(self) -> CliResult<()> {
+        #[cfg(feature = "syslog")]
+        let syslog = self.syslog.clone();
+        let self_check = self.self_check;
+        let two_phase = self.two_phase;
+        let color = self.color;
+        let checksums_file = self.checksums.clone();
+        let catalog_file = self.catalog.clone();
+        let samples_file = self.dump_samples.clone();
+        let seed_file = self.write_seed.clone();
+        #[cfg(feature = "metrics")]
+        let metrics_addr = self.metrics_addr;
+        // Held until generation finishes so every entry is created under the same mask
+        #[cfg(unix)]
+        let _umask = self.umask.map(UmaskGuard::set);
+        let mut options = validated_options(self)?;
+        let seed = options.seed;
+        print_configuration_info(&options, color);
+        let expected = if self_check {
+            Some(ExpectedCounts::from(&options))
+        } else {
+            None
+        };
+        let pinned_dirs = options
+            .fixed_time
+            .map(|time| (options.root_dir.clone(), time));
+        let recorded_seeds = dir_seeds(&options)
+            .filter(|_| options.repair_dir.is_none())
+            .map(|seeds| (options.root_dir.clone(), seeds));
+        let catalog = catalog_file.map(|file| (options.root_dir.clone(), file));
+        let checksums_writer = match checksums_file {
+            None => None,
+            Some(file) => {
+                let (sender, writer) = spawn_checksums_writer(&file, options.root_dir.clone())?;
+                options.checksums = Some(sender);
+                Some(writer)
+            }
+        };
+        let samples_writer = match samples_file {
+            None => None,
+            Some(file) => {
+                let (sender, writer) = spawn_samples_writer(&file, &options)?;
+                options.samples = Some(sender);
+                Some(writer)
+            }
+        };
+        #[cfg(feature = "metrics")]
+        let metrics_server = match metrics_addr {
+            None => None,
+            Some(addr) => {
+                let progress = Arc::new(Progress::default());
+                let server = MetricsServer::start(addr, progress.clone())
+                    .with_context(|| format!("Failed to start the metrics server on {}", addr))
+                    .with_code(exitcode::UNAVAILABLE)?;
+                options.progress = Some(progress);
+                Some(server)
+            }
+        };
+        let stats = if two_phase {
+            run_two_phase(options).map(|[dirs_phase, files_phase]| {
+                println!("{}", phase_summary(Phase::Dirs, dirs_phase, color));
+                println!("{}", phase_summary(Phase::Files, files_phase, color));
+
+                let mut stats = dirs_phase.0;
+                stats += files_phase.0;
+                stats
+            })
+        } else {
+            run_generator(options)
+        };
+        #[cfg(feature = "metrics")]
+        if let Some(server) = metrics_server {
+            server.stop();
+        }
+        // Tasks fail to report checksums if writing them failed, so surface the root cause first
+        if let Some(writer) = checksums_writer {
+            writer.join().unwrap_or_else(|_| {
+                Err(anyhow!("The checksums writer panicked")).with_code(exitcode::SOFTWARE)
+            })?;
+        }
+        if let Some(writer) = samples_writer {
+            writer.join().unwrap_or_else(|_| {
+                Err(anyhow!("The samples writer panicked")).with_code(exitcode::SOFTWARE)
+            })?;
+        }
+        let stats = stats?;
+        println!("{}", stats_summary(stats, color));
+        #[cfg(feature = "syslog")]
+        if let Some(syslog) = syslog {
+            syslog.log(Severity::Info, &stats_summary(stats, false));
+        }
+        // Listed before the directory times are pinned as reading a directory may bump them, and
+        // before failing on tolerated errors such that the catalog shows what was left behind
+        if let Some((root_dir, file)) = catalog {
+            write_catalog(&root_dir, &file)?;
+        }
+        if stats.errors > 0 {
+            return Err(anyhow!(format!(
+                "Failed to create {} {}.",
+                stats.errors.to_formatted_string(&Locale::en),
+                if stats.errors == 1 { "entry" } else { "entries" },
+            )))
+            .with_code(exitcode::IOERR);
+        }
+        if let Some(expected) = expected {
+            expected.verify()?;
+        }
+        if let Some((root_dir, seeds)) = recorded_seeds {
+            record_dir_seeds(&root_dir, seeds)?;
+        }
+        if let Some((root_dir, time)) = pinned_dirs {
+            pin_dir_times(root_dir, time)?;
+        }
+        if let Some(file) = seed_file {
+            write_seed(&file, seed)?;
+        }
+        Ok(())
+    }
+
+    /// Regenerates the tree a marker file was written for into the empty (or missing) `root_dir`
+    /// from the configuration it records. The marker file is copied along such that the new tree
+    /// is identical to the original one.
+    pub fn reproduce_from_manifest(manifest: &Path, root_dir: impl Into<PathBuf>) -> CliResult<()> {
+        let root_dir = root_dir.into();
+        let recorded = parse_marker_file(manifest)?
+            .get("configuration")
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(format!("The marker file {:?} records no configuration.", manifest))
+            })
+            .with_code(exitcode::DATAERR)?;
+        if recorded.get("name_fn").and_then(toml::Value::as_bool) == Some(true) {
+            return Err(anyhow!("Trees named by a custom function cannot be reproduced."))
+                .with_code(exitcode::DATAERR);
+        }
+        let mut config = recorded
+            .try_into::<Configuration>()
+            .with_context(|| format!("Failed to parse the configuration in {:?}", manifest))
+            .with_code(exitcode::DATAERR)?;
+        // The copied entries aren't recorded, only the trees generated around them
+        if !config.group_by_extension && !config.subtrees.is_empty() {
+            return Err(anyhow!("Trees generated on top of an overlay source cannot be reproduced."))
+                .with_code(exitcode::DATAERR);
+        }
+        // Which files fit depends on the disk the tree was generated on
+        if config.max_total_size.is_some() {
+            return Err(anyhow!("Trees capped by a maximum total size cannot be reproduced."))
+                .with_code(exitcode::DATAERR);
+        }
+
+        // Grouped subtrees live in directories at the top of the tree
+        for subtree in &mut config.subtrees {
+            subtree.root_dir = root_dir.join(subtree.root_dir.file_name().unwrap_or_default());
+        }
+        config.root_dir = root_dir.clone();
+
+        create_dir_all(&root_dir)
+            .with_context(|| format!("Failed to create directory {:?}", root_dir))
+            .with_code(exitcode::IOERR)?;
+        if root_dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", root_dir))
+            .with_code(exitcode::IOERR)?
+            .count()
+            != 0
+        {
+            return Err(anyhow!(format!("The root directory {:?} must be empty.", root_dir)))
+                .with_code(exitcode::DATAERR);
+        }
+        SeedEntries::new(&config.seed_paths)
+            .map_err(|e| anyhow!(e))
+            .with_code(exitcode::DATAERR)?
+            .create(&root_dir)?;
+        let marker = root_dir.join(MARKER_FILE_NAME);
+        fs::copy(manifest, &marker)
+            .with_context(|| format!("Failed to copy {:?} to {:?}", manifest, marker))
+            .with_code(exitcode::IOERR)?;
+
+        let fixed_time = config.fixed_time;
+        let stats = if config.two_phase {
+            run_two_phase(config).map(|[(mut stats, _), (files_phase, _)]| {
+                stats += files_phase;
+                stats
+            })
+        } else {
+            run_generator(config)
+        }?;
+        println!("{}", stats_summary(stats, false));
+        if stats.errors > 0 {
+            return Err(anyhow!(format!(
+                "Failed to create {} {}.",
+                stats.errors.to_formatted_string(&Locale::en),
+                if stats.errors == 1 { "entry" } else { "entries" },
+            )))
+            .with_code(exitcode::IOERR);
+        }
+        if let Some(time) = fixed_time {
+            pin_dir_times(root_dir, time)?;
+        }
+        Ok(())
+    }
+
+    /// Lazily yields every path that [Generator::generate] would create along with what it is,
+    /// without touching the file system.
+    pub fn plan_paths(&self) -> impl Iterator<Item = (PathBuf, EntryKind)> {
+        let (sender, receiver) = mpsc::sync_channel(1024);
+
+        let mut config = configuration(self.clone());
+        config.plan = Some(sender);
+        config.on_progress = None;
+        thread::spawn(move || {
+            // The only possible failure is the iterator being dropped early
+            let _ = run_generator(config);
+        });
+
+        receiver.into_iter()
+    }
+
+    /// Picks `count` of the files [Generator::generate] would create, evenly spaced through the
+    /// plan, without touching the file system. Useful to spot check a tree generated elsewhere.
+    pub fn sample_paths(&self, count: NonZeroUsize) -> Vec<PathBuf> {
+        let is_leaf = |(_, kind): &(PathBuf, EntryKind)| !matches!(kind, EntryKind::Dir);
+        let total = self.plan_paths().filter(is_leaf).count();
+        let count = count.get().min(total);
+
+        let mut samples = Vec::with_capacity(count);
+        for (i, (path, _)) in self.plan_paths().filter(is_leaf).enumerate() {
+            if samples.len() == count {
+                break;
+            }
+            if i == (samples.len() as u128 * total as u128 / count as u128) as usize {
+                samples.push(path);
+            }
+        }
+        samples
+    }
+
+    /// Writes a shell script that recreates the tree [Generator::generate] would create, without
+    /// touching the file system. Files with contents are filled from `/dev/urandom`, so only their
+    /// sizes are reproduced.
+    pub fn write_script(&self, mut out: impl Write) -> CliResult<()> {
+        writeln!(
+            out,
+            "#!/bin/sh\nset -e\nmkdir -p {}",
+            shell_quote(&self.root_dir)
+        )
+        .and_then(|_| {
+            for (path, kind) in self.plan_paths() {
+                match kind {
+                    EntryKind::Dir => writeln!(out, "mkdir -p {}", shell_quote(&path)),
+                    EntryKind::Fifo => writeln!(out, "mkfifo {}", shell_quote(&path)),
+                    EntryKind::BrokenSymlink { target } => writeln!(
+                        out,
+                        "ln -s {} {}",
+                        shell_quote(&target),
+                        shell_quote(&path)
+                    ),
+                    EntryKind::File { bytes: 0 } => writeln!(out, "touch {}", shell_quote(&path)),
+                    EntryKind::File { bytes } => writeln!(
+                        out,
+                        "head -c {} /dev/urandom > {}",
+                        bytes,
+                        shell_quote(&path)
+                    ),
+                }?;
+            }
+            out.flush()
+        })
+        .context("Failed to write script")
+        .with_code(exitcode::IOERR)
+    }
+
+    /// Runs the checks generation would that don't need the file system, such that parameters can
+    /// be linted without the root directory being created or inspected.
+    pub fn validate_params(&self) -> CliResult<()> {
+        check_params(self)
+    }
+
+    /// Explains how the parameters covered by `topic` shape the tree, using this generator's
+    /// parameters as a worked example computed from the same math generation runs on.
+    pub fn explain(&self, topic: ExplainTopic, mut out: impl Write) -> CliResult<()> {
+        let config = configuration(self.clone());
+        let locale = Locale::en;
+        let files = config.files.to_formatted_string(&locale);
+        let dirs = config.informational_total_dirs.to_formatted_string(&locale);
+        let text = match topic {
+            ExplainTopic::Ratio => format!(
+                "Files are spread across directories such that each directory holds about RATIO \
+                 files, so the number of directories is the number of files divided by the \
+                 ratio. Without a ratio, one is picked from the number of files and the depth.\n\n\
+                 With -n {} -r {}, you'll get ~{} directories holding ~{} files each.",
+                files,
+                config.files_per_dir.round(),
+                dirs,
+                config.files_per_dir.round(),
+            ),
+            ExplainTopic::Depth => format!(
+                "Directories are nested up to DEPTH levels deep. Every directory gets about the \
+                 same number of subdirectories, chosen such that the levels multiply out to the \
+                 total number of directories: dirs per dir = total dirs ^ (1 / depth).\n\n\
+                 With -n {} -d {} -r {}, you'll get ~{} dirs, ~{:.1} dirs per dir.",
+                files,
+                config.max_depth,
+                config.files_per_dir.round(),
+                dirs,
+                config.dirs_per_dir,
+            ),
+            ExplainTopic::Bytes => format!(
+                "File sizes are sampled from a normal distribution centered on the total number \
+                 of bytes divided by the number of files, with a standard deviation of 20% of the \
+                 mean. Unless exact bytes are requested, the total is only approximately met.\n\n\
+                 With -n {} -b {}, each file will be ~{} bytes.",
+                files,
+                config.bytes.to_formatted_string(&locale),
+                config
+                    .informational_bytes_per_files
+                    .to_formatted_string(&locale),
+            ),
+        };
+        writeln!(out, "{}", text)
+            .and_then(|_| out.flush())
+            .context("Failed to write explanation")
+            .with_code(exitcode::IOERR)
+    }
+
+    /// Writes the fully resolved configuration [Generator::generate] runs with as JSON, including
+    /// the derived directory and file distributions.
+    pub fn write_config(&self, mut out: impl Write) -> CliResult<()> {
+        serde_json::to_writer_pretty(&mut out, &configuration(self.clone()))
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(out))
+            .and_then(|_| out.flush())
+            .context("Failed to write configuration")
+            .with_code(exitcode::IOERR)
+    }
+}
+
+/// Writes the checksums reported by tasks to file in `sha256sum` format as they arrive. Paths are
+/// relative to the root directory.
+fn spawn_checksums_writer(
+    file: &Path,
+    root_dir: PathBuf,
+) -> CliResult<(ChecksumSender, JoinHandle<CliResult<()>>)> {
+    let out = File::create(file)
+        .with_context(|| format!("Failed to create checksums file {:?}", file))
+        .with_code(exitcode::CANTCREAT)?;
+    let (sender, receiver) = mpsc::sync_channel::<(PathBuf, [u8; 32])>(1024);
+
+    let file = file.to_path_buf();
+    let writer = thread::spawn(move || {
+        let mut out = BufWriter::new(out);
+        receiver
+            .into_iter()
+            .try_for_each(|(path, digest)| {
+                write_checksum(
+                    &mut out,
+                    path.strip_prefix(&root_dir).unwrap_or(&path),
+                    &digest,
+                )
+            })
+            .and_then(|_| out.flush())
+            .with_context(|| format!("Failed to write checksums file {:?}", file))
+            .with_code(exitcode::IOERR)
+    });
+    Ok((sender, writer))
+}
+
+/// Writes the number of files and directories sampled for every visited directory to file as lines
+/// of JSON, next to the averages they were sampled around.
+fn spawn_samples_writer(
+    file: &Path,
+    config: &Configuration,
+) -> CliResult<(SampleSender, JoinHandle<CliResult<()>>)> {
+    #[derive(Serialize)]
+    struct Line<'a> {
+        dir: &'a str,
+        planned_files: f64,
+        planned_dirs: f64,
+        files: usize,
+        dirs: usize,
+    }
+
+    let out = File::create(file)
+        .with_context(|| format!("Failed to create samples file {:?}", file))
+        .with_code(exitcode::CANTCREAT)?;
+    let (sender, receiver) = mpsc::sync_channel::<NodeSample>(1024);
+
+    let file = file.to_path_buf();
+    let root_dir = config.root_dir.clone();
+    let (planned_files, planned_dirs) = (config.files_per_dir, config.dirs_per_dir);
+    let writer = thread::spawn(move || {
+        let mut out = BufWriter::new(out);
+        receiver
+            .into_iter()
+            .try_for_each(|sample| {
+                let dir = sample.dir.strip_prefix(&root_dir).unwrap_or(&sample.dir);
+                let dir = dir.to_string_lossy();
+                let line = Line {
+                    dir: &dir,
+                    planned_files,
+                    planned_dirs,
+                    files: sample.files,
+                    dirs: sample.dirs,
+                };
+                serde_json::to_writer(&mut out, &line)?;
+                writeln!(out)
+            })
+            .and_then(|_| out.flush())
+            .with_context(|| format!("Failed to write samples file {:?}", file))
+            .with_code(exitcode::IOERR)
+    });
+    Ok((sender, writer))
+}
+
+/// Persists the seed a tree was generated with (after mixing in the seed phrase) such that later
+/// runs can read it back.
+fn write_seed(file: &Path, seed: u64) -> CliResult<()> {
+    fs::write(file, format!("{}\n", seed))
+        .with_context(|| format!("Failed to write seed file {:?}", file))
+        .with_code(exitcode::CANTCREAT)
+}
+
+/// Writes a single `sha256sum` line, escaping paths the same way it does.
+fn write_checksum(out: &mut impl Write, path: &Path, digest: &[u8]) -> io::Result<()> {
+    let path = path.to_string_lossy();
+    let escape = path.contains(['\\', '\n']);
+    if escape {
+        write!(out, "\\")?;
+    }
+    for byte in digest {
+        write!(out, "{:02x}", byte)?;
+    }
+    if escape {
+        writeln!(out, "  {}", path.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        writeln!(out, "  {}", path)
+    }
+}
+
+/// Quotes path such that a POSIX shell interprets it literally.
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// Quotes path as a CSV field if it contains separators, quotes, or line breaks.
+fn csv_quote(path: &Path) -> Cow<str> {
+    let path = path.to_string_lossy();
+    if path.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", path.replace('"', "\"\"")))
+    } else {
+        path
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Configuration {
+    root_dir: PathBuf,
+    files: usize,
+    bytes: usize,
+    files_exact: bool,
+    bytes_exact: bool,
+    dirs_exact: Option<usize>,
+    empty_files: usize,
+    size_schedule: Option<Vec<usize>>,
+    layout: Option<Arc<Layout>>,
+    min_files: usize,
+    min_dirs: usize,
+    min_files_per_dir: usize,
+    cluster_factor: f64,
+    files_per_dir: f64,
+    dirs_per_dir: f64,
+    bytes_per_file: f64,
+    size_distribution: SizeDistribution,
+    size_alignment: Option<NonZeroUsize>,
+    max_depth: u32,
+    leaf_depth_distribution: Option<DepthDistribution>,
+    depth_jitter: u32,
+    structural_levels: u32,
+    #[serde(deserialize_with = "deserialize_seed")]
+    seed: u64,
+    #[serde(default, deserialize_with = "deserialize_optional_seed")]
+    structure_seed: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_optional_seed")]
+    content_seed: Option<u64>,
+    lex_sortable: bool,
+    sort_by_size: bool,
+    name_hash_jitter: bool,
+    name_style: NameStyle,
+    name_length_distribution: Option<(usize, usize)>,
+    case_collisions: bool,
+    names_from_content: bool,
+    git_objects: bool,
+    hidden_ratio: f64,
+    dir_wordlist: Option<Arc<[String]>>,
+    file_wordlist: Option<Arc<[String]>>,
+    /// Only whether there is one is recorded, as functions can't be.
+    #[serde(serialize_with = "serialize_is_some", skip_deserializing)]
+    name_fn: Option<NameFn>,
+    duplicate_groups: Option<NonZeroUsize>,
+    pattern: Option<Arc<[u8]>>,
+    entropy: Option<f64>,
+    archive: Option<Archive>,
+    text_header: Option<Arc<[u8]>>,
+    sequence_header: Option<SequenceHeader>,
+    sparse_ratio: f64,
+    sparse_holes: bool,
+    preallocate: bool,
+    media_mix: Option<Arc<[MediaFormat]>>,
+    read_only_ratio: f64,
+    read_only_dirs: bool,
+    xattr_ratio: f64,
+    content_source: Option<PathBuf>,
+    seed_paths: Vec<String>,
+    special_ratio: f64,
+    broken_symlink_ratio: f64,
+    creation_order: CreationOrder,
+    repair: bool,
+    #[serde(skip)]
+    repair_dir: Option<RepairDir>,
+    dir_seeds: bool,
+    /// Trees generated into directories at the top of this one after it.
+    subtrees: Vec<Configuration>,
+    /// Whether this tree is made up entirely of its subtrees, one per media format.
+    group_by_extension: bool,
+    openat: bool,
+    task_granularity: Option<u32>,
+    ramp_up: Option<Duration>,
+    continue_on_error: bool,
+    rate_limit: Option<NonZeroUsize>,
+    byte_rate: Option<NonZeroUsize>,
+    write_memory_budget: Option<NonZeroUsize>,
+    cpu_affinity: Option<Arc<[usize]>>,
+    two_phase: bool,
+    chain: bool,
+    balanced_fanout: Option<usize>,
+    max_dir_width: Option<NonZeroUsize>,
+    #[serde(skip)]
+    plan: Option<PlanSender>,
+    #[serde(skip)]
+    phase: Option<Phase>,
+    #[serde(skip)]
+    checksums: Option<ChecksumSender>,
+    #[serde(skip)]
+    samples: Option<SampleSender>,
+    #[serde(skip)]
+    checkpoint: Option<Arc<Checkpoint>>,
+    max_total_size: Option<usize>,
+    #[serde(skip)]
+    disk_budget: Option<Arc<DiskBudget>>,
+    #[cfg(feature = "metrics")]
+    #[serde(skip)]
+    progress: Option<Arc<Progress>>,
+    #[serde(skip)]
+    on_progress: Option<ProgressHook>,
+    progress_interval: ProgressInterval,
+    #[serde(
+        default,
+        serialize_with = "serialize_unix_seconds",
+        deserialize_with = "deserialize_unix_seconds"
+    )]
+    fixed_time: Option<FileTime>,
+    mtime_sequence: Option<MtimeSequence>,
+
+    informational_dirs_per_dir: usize,
+    informational_total_dirs: usize,
+    informational_bytes_per_files: usize,
+}
+
+fn serialize_unix_seconds<S: Serializer>(
+    time: &Option<FileTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    time.map(|time| time.unix_seconds()).serialize(serializer)
+}
+
+fn deserialize_unix_seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<FileTime>, D::Error> {
+    Ok(Option::<i64>::deserialize(deserializer)?
+        .map(|seconds| FileTime::from_unix_time(seconds, 0)))
+}
+
+fn serialize_is_some<T, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(value.is_some())
+}
+
+/// Seeds too large for a TOML integer are recorded as strings, see [json_to_toml].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecordedSeed {
+    Integer(u64),
+    String(String),
+}
+
+impl RecordedSeed {
+    fn parse<E: de::Error>(self) -> Result<u64, E> {
+        match self {
+            Self::Integer(seed) => Ok(seed),
+            Self::String(seed) => seed.parse().map_err(E::custom),
+        }
+    }
+}
+
+fn deserialize_seed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    RecordedSeed::deserialize(deserializer)?.parse()
+}
+
+fn deserialize_optional_seed<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<RecordedSeed>::deserialize(deserializer)?
+        .map(RecordedSeed::parse)
+        .transpose()
 }
 
-fn validated_options(generator: Generator) -> CliResult<Configuration> {
+fn validated_options(mut generator: Generator) -> CliResult<Configuration> {
+    check_params(&generator)?;
+    let repair_dir = match &generator.repair_dir {
+        None => None,
+        Some(dir) => Some(RepairDir::recorded(&generator.root_dir, dir)?),
+    };
+    let seeds = SeedEntries::new(&generator.seed_paths)
+        .map_err(|e| anyhow!(e))
+        .with_code(exitcode::DATAERR)?;
+    check_root_collisions(&generator, "Seed path", seeds.top_level_names())?;
     create_dir_all(&generator.root_dir)
         .with_context(|| format!("Failed to create directory {:?}", generator.root_dir))
         .with_code(exitcode::IOERR)?;
-    if generator
-        .root_dir
-        .read_dir()
-        .with_context(|| format!("Failed to read directory {:?}", generator.root_dir))
-        .with_code(exitcode::IOERR)?
-        .count()
-        != 0
+    let mut overlay = Vec::new();
+    if let Some(source) = generator.overlay_source.clone() {
+        check_overlay_source(&source, &generator.root_dir)?;
+        overlay = split_overlay(&mut generator, &source)?;
+    }
+    if generator.overwrite {
+        if !generator.force_dangerous && is_dangerous_root(&generator.root_dir) {
+            return Err(anyhow!(format!(
+                "Refusing to overwrite {:?}, use --force-dangerous if you're really sure.",
+                generator.root_dir,
+            )))
+            .with_code(exitcode::DATAERR);
+        }
+        clear_dir(&generator.root_dir)?;
+    }
+    // An interrupted run leaves a partial tree behind to pick up from
+    let resuming = generator
+        .checkpoint
+        .as_deref()
+        .is_some_and(|path| path.exists());
+    if !generator.repair
+        && !resuming
+        && generator
+            .root_dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", generator.root_dir))
+            .with_code(exitcode::IOERR)?
+            .count()
+            != 0
     {
         return Err(anyhow!(format!(
             "The root directory {:?} must be empty.",
@@ -133,70 +4053,1376 @@ fn validated_options(generator: Generator) -> CliResult<Configuration> {
         .with_code(exitcode::DATAERR);
     }
 
+    // Seed paths and the overlay are created after the emptiness check so they don't trip it
+    seeds.create(&generator.root_dir)?;
+    if let Some(source) = &generator.overlay_source {
+        copy_tree(source, &generator.root_dir)?;
+    }
+
+    if Capabilities::needed(&generator) {
+        let capabilities = Capabilities::probe(&generator.root_dir);
+        if generator.case_collisions && !capabilities.case_sensitive {
+            return Err(anyhow!(format!(
+                "{:?} is case-insensitive, so names that only differ in case would collide.",
+                generator.root_dir,
+            )))
+            .with_code(exitcode::DATAERR);
+        }
+        capabilities.restrict(&mut generator);
+    }
+
+    let write_marker = generator.write_marker;
+    let generator_block_size = generator.block_size;
+    let checkpoint = generator.checkpoint.clone();
+    let overlay = overlay
+        .iter()
+        .map(|dir| configuration(dir.generator(&generator)))
+        .collect::<Vec<_>>();
+    let mut config = configuration(generator);
+    config.repair_dir = repair_dir;
+    config.subtrees.extend(overlay);
+    if let Some(file) = checkpoint {
+        let checkpoint = Checkpoint::load(file, fingerprint(&config)?)?;
+        if checkpoint.resumed() > 0 {
+            info!(
+                "Resuming after the {} tasks completed by a previous run.",
+                checkpoint.resumed().to_formatted_string(&Locale::en),
+            );
+        } else {
+            // Save right away such that even an early interruption can be picked up from
+            checkpoint.save(0)?;
+        }
+        config.checkpoint = Some(Arc::new(checkpoint));
+    }
+    if let Some(limit) = config.max_total_size {
+        // Counted after the overlay and seed paths such that they share the budget
+        let used = disk_usage(&config.root_dir, generator_block_size)?;
+        if used > limit {
+            return Err(anyhow!(format!(
+                "{:?} already takes up ~{} bytes, more than the maximum total size of {} bytes.",
+                config.root_dir,
+                used.to_formatted_string(&Locale::en),
+                limit.to_formatted_string(&Locale::en),
+            )))
+            .with_code(exitcode::DATAERR);
+        }
+        config.disk_budget = Some(Arc::new(DiskBudget::new(limit, generator_block_size, used)));
+    }
+    // Repairing a single directory keeps the seeds recorded for the rest of the tree
+    if write_marker && config.repair_dir.is_none() {
+        write_marker_file(&config)?;
+    }
+    if let Some(depth) = degenerate_depth(&config) {
+        warn!(
+            "Only ~{} directories are spread across {} levels, so most directories will contain \
+             a single subdirectory. Consider a maximum depth of {} or a smaller file to \
+             directory ratio, or use --shrink-depth.",
+            config.informational_total_dirs.to_formatted_string(&Locale::en),
+            config.max_depth,
+            depth,
+        );
+    }
+    Ok(config)
+}
+
+/// Identifies the tree a configuration generates, ignoring the options that only affect how fast
+/// it's generated such that those can be changed when restarting from a checkpoint.
+fn fingerprint(config: &Configuration) -> CliResult<u64> {
+    let config = Configuration {
+        rate_limit: None,
+        byte_rate: None,
+        write_memory_budget: None,
+        ramp_up: None,
+        cpu_affinity: None,
+        progress_interval: ProgressInterval::default(),
+        ..config.clone()
+    };
+    serde_json::to_vec(&config)
+        .context("Failed to serialize configuration")
+        .with_code(exitcode::SOFTWARE)
+        .map(|config| seahash::hash(&config))
+}
+
+/// Records the ftzz version, time, and resolved configuration a tree is generated with in a TOML
+/// file at its root.
+fn write_marker_file(config: &Configuration) -> CliResult<()> {
+    let mut manifest = toml::value::Table::new();
+    manifest.insert(
+        String::from("ftzz_version"),
+        toml::Value::String(String::from(env!("CARGO_PKG_VERSION"))),
+    );
+    manifest.insert(
+        String::from("generated_at"),
+        toml::Value::Integer(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs() as i64),
+        ),
+    );
+    let configuration = serde_json::to_value(config)
+        .context("Failed to serialize configuration")
+        .with_code(exitcode::SOFTWARE)?;
+    if let Some(configuration) = json_to_toml(configuration) {
+        manifest.insert(String::from("configuration"), configuration);
+    }
+
+    let file = config.root_dir.join(MARKER_FILE_NAME);
+    toml::to_string(&toml::Value::Table(manifest))
+        .context("Failed to serialize marker file")
+        .with_code(exitcode::SOFTWARE)
+        .and_then(|manifest| {
+            fs::write(&file, manifest)
+                .with_context(|| format!("Failed to write marker file {:?}", file))
+                .with_code(exitcode::IOERR)
+        })
+}
+
+/// Converts JSON to TOML, dropping nulls as TOML has no equivalent.
+fn json_to_toml(value: serde_json::Value) -> Option<toml::Value> {
+    use serde_json::Value;
+
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml::Value::Boolean(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => toml::Value::Integer(n),
+            // TOML integers are signed, so larger ones (such as seeds) are kept as strings
+            None if n.is_u64() => toml::Value::String(n.to_string()),
+            None => toml::Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => toml::Value::String(s),
+        Value::Array(values) => {
+            toml::Value::Array(values.into_iter().filter_map(json_to_toml).collect())
+        }
+        Value::Object(map) => toml::Value::Table(
+            map.into_iter()
+                .filter_map(|(key, value)| json_to_toml(value).map(|value| (key, value)))
+                .collect(),
+        ),
+    })
+}
+
+/// The seeds of each directory's contents if they're derived from the directory's path.
+fn dir_seeds(config: &Configuration) -> Option<DirSeeds> {
+    config.dir_seeds.then(|| DirSeeds {
+        seed: config.content_seed.unwrap_or(config.seed),
+        root_len: config.root_dir.as_os_str().len(),
+    })
+}
+
+/// The key a directory's seed is recorded under in the marker file.
+fn dir_seeds_key(dir: &Path) -> String {
+    dir.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Parses the marker file at the root of a tree.
+fn read_marker_file(root_dir: &Path) -> CliResult<toml::Value> {
+    parse_marker_file(&root_dir.join(MARKER_FILE_NAME))
+}
+
+fn parse_marker_file(file: &Path) -> CliResult<toml::Value> {
+    fs::read_to_string(file)
+        .with_context(|| format!("Failed to read marker file {:?}", file))
+        .with_code(exitcode::NOINPUT)?
+        .parse::<toml::Value>()
+        .with_context(|| format!("Failed to parse marker file {:?}", file))
+        .with_code(exitcode::DATAERR)
+}
+
+/// Adds the seed and number of files of every directory in the freshly generated tree to the
+/// marker file such that any one of them can be repaired on its own later.
+fn record_dir_seeds(root_dir: &Path, seeds: DirSeeds) -> CliResult<()> {
+    let mut records = toml::value::Table::new();
+    let mut queue = VecDeque::from([root_dir.to_path_buf()]);
+    while let Some(dir) = queue.pop_front() {
+        let mut num_files = 0;
+        for entry in dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?
+        {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory {:?}", dir))
+                .with_code(exitcode::IOERR)?;
+            let file_type = entry
+                .file_type()
+                .with_context(|| format!("Failed to stat {:?}", entry.path()))
+                .with_code(exitcode::IOERR)?;
+
+            if file_type.is_dir() {
+                queue.push_back(entry.path());
+            } else if dir != root_dir || entry.file_name() != MARKER_FILE_NAME {
+                num_files += 1;
+            }
+        }
+
+        let mut record = toml::value::Table::new();
+        // TOML integers are signed, so seeds are kept as strings
+        record.insert(
+            String::from("seed"),
+            toml::Value::String(seeds.seed(&dir).to_string()),
+        );
+        record.insert(String::from("files"), toml::Value::Integer(num_files));
+        records.insert(
+            dir_seeds_key(dir.strip_prefix(root_dir).unwrap()),
+            toml::Value::Table(record),
+        );
+    }
+
+    let mut manifest = read_marker_file(root_dir)?;
+    if let Some(manifest) = manifest.as_table_mut() {
+        manifest.insert(String::from("dir_seeds"), toml::Value::Table(records));
+    }
+    let file = root_dir.join(MARKER_FILE_NAME);
+    toml::to_string(&manifest)
+        .context("Failed to serialize marker file")
+        .with_code(exitcode::SOFTWARE)
+        .and_then(|manifest| {
+            fs::write(&file, manifest)
+                .with_context(|| format!("Failed to write marker file {:?}", file))
+                .with_code(exitcode::IOERR)
+        })
+}
+
+/// A directory to regenerate on its own from the seed and number of files recorded for it.
+#[derive(Debug, Clone)]
+struct RepairDir {
+    dir: String,
+    seed: u64,
+    num_files: usize,
+}
+
+impl RepairDir {
+    /// Looks up what the marker file records for the directory.
+    fn recorded(root_dir: &Path, dir: &Path) -> CliResult<Self> {
+        let manifest = read_marker_file(root_dir)?;
+        let key = dir_seeds_key(dir);
+        let record = manifest
+            .get("dir_seeds")
+            .and_then(|records| records.get(key.as_str()));
+        let seed = record
+            .and_then(|record| record.get("seed"))
+            .and_then(toml::Value::as_str)
+            .and_then(|seed| seed.parse().ok());
+        let num_files = record
+            .and_then(|record| record.get("files"))
+            .and_then(toml::Value::as_integer)
+            .and_then(|files| usize::try_from(files).ok());
+        match (seed, num_files) {
+            (Some(seed), Some(num_files)) => Ok(Self {
+                dir: key,
+                seed,
+                num_files,
+            }),
+            _ => Err(anyhow!(format!(
+                "The marker file records no seed for directory {:?}.",
+                dir,
+            )))
+            .with_code(exitcode::DATAERR),
+        }
+    }
+}
+
+/// The deepest depth at which directories still average at least two subdirectories, if the
+/// tree is deeper than that and would thus grow chains of lone directories.
+fn degenerate_depth(config: &Configuration) -> Option<u32> {
+    if config.chain
+        || config.layout.is_some()
+        || config.max_depth <= 1
+        || config.dirs_per_dir >= 2.
+    {
+        return None;
+    }
+    Some((config.informational_total_dirs as f64).log2().floor().max(0.) as u32)
+}
+
+/// Picks the number of bytes such that files rounded up to whole blocks plus a block for every
+/// directory add up to the target disk usage, unless the entries alone already take up more space
+/// than that.
+fn bytes_for_disk_usage(
+    target: usize,
+    block_size: NonZeroUsize,
+    config: &Configuration,
+) -> Option<usize> {
+    let block_size = block_size.get() as f64;
+    let files = config.files as f64;
+    let per_file = (target as f64 - config.informational_total_dirs as f64 * block_size) / files;
+    if per_file < block_size {
+        return None;
+    }
+    // The last block of a file is half empty on average, but even tiny files take up a block
+    Some(((per_file - block_size / 2.) * files).round() as usize)
+}
+
+/// Rejects disk usage targets that can't be reached without creating fewer entries.
+fn check_target_disk_usage(generator: &Generator) -> CliResult<()> {
+    if generator.target_disk_usage.is_none() {
+        return Ok(());
+    }
+    let config = configuration(generator.clone());
+    if config.bytes == 0 {
+        return Err(anyhow!(format!(
+            "Approximately {} files and {} directories take up more than the target disk usage \
+             with {}-byte blocks. Decrease the number of files or increase the target.",
+            config.files.to_formatted_string(&Locale::en),
+            config.informational_total_dirs.to_formatted_string(&Locale::en),
+            generator.block_size,
+        )))
+        .with_code(exitcode::DATAERR);
+    }
+    Ok(())
+}
+
+/// Rejects cores the process isn't allowed to run on, as pinning threads to them would fail.
+fn check_cpu_affinity(generator: &Generator) -> CliResult<()> {
+    let cores = match &generator.cpu_affinity {
+        Some(cores) => cores,
+        None => return Ok(()),
+    };
+    let available = core_affinity::get_core_ids().unwrap_or_default();
+    if let Some(core) = cores
+        .iter()
+        .find(|&&core| !available.iter().any(|id| id.id == core))
+    {
+        return Err(anyhow!(format!(
+            "CPU core {} is not available to this process.",
+            core,
+        )))
+        .with_code(exitcode::USAGE);
+    }
+    Ok(())
+}
+
+/// The validations that go beyond what the builder checks but don't touch the disk.
+fn check_params(generator: &Generator) -> CliResult<()> {
+    check_max_entries(generator)?;
+    check_max_dir_width(generator)?;
+    check_target_disk_usage(generator)?;
+    check_cpu_affinity(generator)?;
+    SeedEntries::new(&generator.seed_paths)
+        .map(drop)
+        .map_err(|e| anyhow!(e))
+        .with_code(exitcode::DATAERR)
+}
+
+/// Rejects runs that would create more entries than allowed before anything touches the disk.
+fn check_max_entries(generator: &Generator) -> CliResult<()> {
+    let config = configuration(generator.clone());
+    let entries = config
+        .files
+        .saturating_add(config.empty_files)
+        .saturating_add(config.informational_total_dirs);
+    if entries > generator.max_entries {
+        return Err(anyhow!(format!(
+            "Refusing to create approximately {} entries as it exceeds the limit of {}, use \
+             --no-limit if you're really sure.",
+            entries.to_formatted_string(&Locale::en),
+            generator.max_entries.to_formatted_string(&Locale::en),
+        )))
+        .with_code(exitcode::DATAERR);
+    }
+    Ok(())
+}
+
+/// Rejects trees too wide to fit in the maximum directory width at their depth, as the directories
+/// that don't fit would take the files spilled into them along.
+fn check_max_dir_width(generator: &Generator) -> CliResult<()> {
+    let width = match generator.max_dir_width {
+        None => return Ok(()),
+        Some(width) => width.get(),
+    };
+    let config = configuration(generator.clone());
+    let entries_per_dir = config.files_per_dir + config.dirs_per_dir.ceil();
+    if entries_per_dir > width as f64 {
+        return Err(anyhow!(format!(
+            "Directories would hold approximately {} entries each, which exceeds the maximum \
+             width of {}. Increase the depth to spread entries out further.",
+            (entries_per_dir.round() as usize).to_formatted_string(&Locale::en),
+            width.to_formatted_string(&Locale::en),
+        )))
+        .with_code(exitcode::DATAERR);
+    }
+    Ok(())
+}
+
+/// The files and directories seed paths expand to, including the directories leading up to them.
+#[derive(Debug, Default)]
+struct SeedEntries {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl SeedEntries {
+    fn new(paths: &[String]) -> Result<Self, String> {
+        let mut entries = Self::default();
+        for path in paths {
+            let is_dir = path.ends_with(is_separator);
+            let path = Path::new(path);
+            if path.as_os_str().is_empty()
+                || !path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(format!(
+                    "Seed path {:?} must be relative and cannot contain special components.",
+                    path,
+                ));
+            }
+            // Drop any trailing separator
+            let path = path.components().collect::<PathBuf>();
+
+            entries
+                .dirs
+                .extend(path.ancestors().skip(1).map(Path::to_path_buf));
+            if is_dir {
+                entries.dirs.insert(path);
+            } else {
+                entries.files.insert(path);
+            }
+        }
+        entries.dirs.remove(Path::new(""));
+
+        if let Some(path) = entries.files.iter().find(|f| entries.dirs.contains(*f)) {
+            return Err(format!(
+                "Seed path {:?} cannot be both a file and a directory.",
+                path,
+            ));
+        }
+        Ok(entries)
+    }
+
+    /// The names of the entries created directly in the root.
+    fn top_level_names(&self) -> impl Iterator<Item = &OsStr> {
+        self.files
+            .iter()
+            .chain(&self.dirs)
+            .filter(|path| path.parent() == Some(Path::new("")))
+            .map(|path| path.as_os_str())
+    }
+
+    fn create(&self, root_dir: &Path) -> CliResult<()> {
+        for dir in &self.dirs {
+            let dir = root_dir.join(dir);
+            create_dir_all(&dir)
+                .with_context(|| format!("Failed to create directory {:?}", dir))
+                .with_code(exitcode::IOERR)?;
+        }
+        for file in &self.files {
+            let file = root_dir.join(file);
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(&file)
+                .with_context(|| format!("Failed to create file {:?}", file))
+                .with_code(exitcode::IOERR)?;
+        }
+        Ok(())
+    }
+}
+
+/// Operations the filesystem being generated into supports, as found by trying them out.
+#[derive(Debug, Copy, Clone)]
+struct Capabilities {
+    fifos: bool,
+    symlinks: bool,
+    sparse_files: bool,
+    permissions: bool,
+    case_sensitive: bool,
+}
+
+impl Capabilities {
+    fn probe(dir: &Path) -> Self {
+        let capabilities = Self {
+            fifos: supports_fifos(dir),
+            symlinks: supports_symlinks(dir),
+            sparse_files: supports_sparse_files(dir),
+            permissions: supports_permissions(dir),
+            case_sensitive: is_case_sensitive(dir),
+        };
+        info!("Detected filesystem capabilities: {:?}", capabilities);
+        capabilities
+    }
+
+    /// Turns off options the filesystem can't handle rather than failing halfway through.
+    fn restrict(self, generator: &mut Generator) {
+        if !self.fifos && generator.special_ratio > 0. {
+            warn!(
+                "{:?} does not support FIFOs, creating regular files instead.",
+                generator.root_dir,
+            );
+            generator.special_ratio = 0.;
+        }
+        #[cfg(unix)]
+        if !self.symlinks && generator.broken_symlink_ratio > 0. {
+            warn!(
+                "{:?} does not support symlinks, creating regular files instead.",
+                generator.root_dir,
+            );
+            generator.broken_symlink_ratio = 0.;
+        }
+        if !self.sparse_files && generator.sparse_ratio > 0. {
+            warn!(
+                "{:?} does not support sparse files, writing their contents in full instead.",
+                generator.root_dir,
+            );
+            generator.sparse_ratio = 0.;
+            generator.sparse_holes = false;
+        }
+        if !self.permissions && (generator.read_only_ratio > 0. || generator.read_only_dirs) {
+            warn!(
+                "{:?} does not support permissions, leaving every entry writable instead.",
+                generator.root_dir,
+            );
+            generator.read_only_ratio = 0.;
+            generator.read_only_dirs = false;
+        }
+    }
+
+    /// Whether or not any of the options the filesystem is probed for are used.
+    fn needed(generator: &Generator) -> bool {
+        #[cfg(unix)]
+        let symlinks = generator.broken_symlink_ratio > 0.;
+        #[cfg(not(unix))]
+        let symlinks = false;
+        generator.special_ratio > 0.
+            || symlinks
+            || generator.sparse_ratio > 0.
+            || generator.read_only_ratio > 0.
+            || generator.case_collisions
+    }
+}
+
+/// Sets the process umask for as long as it's alive, restoring the previous one when dropped.
+#[cfg(unix)]
+struct UmaskGuard(nix::sys::stat::Mode);
+
+#[cfg(unix)]
+impl UmaskGuard {
+    fn set(mask: u32) -> Self {
+        use nix::sys::stat::{umask, Mode};
+
+        Self(umask(Mode::from_bits_truncate(mask as nix::libc::mode_t)))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UmaskGuard {
+    fn drop(&mut self) {
+        nix::sys::stat::umask(self.0);
+    }
+}
+
+fn supports_fifos(dir: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::{sys::stat::Mode, unistd::mkfifo};
+
+        let probe = dir.join(".ftzz-fifo-probe");
+        mkfifo(&probe, Mode::S_IRUSR | Mode::S_IWUSR).is_ok() && remove_file(&probe).is_ok()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        false
+    }
+}
+
+fn supports_symlinks(dir: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        let probe = dir.join(".ftzz-symlink-probe");
+        std::os::unix::fs::symlink(".ftzz-symlink-target", &probe).is_ok()
+            && remove_file(&probe).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = dir;
+        false
+    }
+}
+
+fn supports_sparse_files(dir: &Path) -> bool {
+    let probe = dir.join(".ftzz-sparse-probe");
+    let file = match File::create(&probe) {
+        Ok(file) => file,
+        // Nothing can be created anyway, so let generation report the real error
+        Err(_) => return true,
+    };
+    let sparse = file.set_len(1 << 20).is_ok() && {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            // Filesystems without holes allocate the whole extended length up front
+            file.metadata()
+                .map_or(false, |metadata| metadata.blocks() * 512 < metadata.len())
+        }
+        // Allocation can't be inspected, so assume extending files works as it always has
+        #[cfg(not(unix))]
+        true
+    };
+    drop(file);
+    let _ = remove_file(&probe);
+    sparse
+}
+
+fn supports_permissions(dir: &Path) -> bool {
+    let probe = dir.join(".ftzz-permissions-probe");
+    if File::create(&probe).is_err() {
+        // Nothing can be created anyway, so let generation report the real error
+        return true;
+    }
+    let read_only = |readonly| {
+        let mut permissions = probe.metadata()?.permissions();
+        permissions.set_readonly(readonly);
+        fs::set_permissions(&probe, permissions)?;
+        probe.metadata().map(|metadata| metadata.permissions().readonly())
+    };
+    let permissions = matches!(read_only(true), Ok(true)) && matches!(read_only(false), Ok(false));
+    let _ = remove_file(&probe);
+    permissions
+}
+
+fn is_case_sensitive(dir: &Path) -> bool {
+    let probe = dir.join(".ftzz-case-probe");
+    if File::create(&probe).is_err() {
+        // Nothing can be created anyway, so let generation report the real error
+        return true;
+    }
+    let case_sensitive = dir.join(".FTZZ-CASE-PROBE").symlink_metadata().is_err();
+    let _ = remove_file(&probe);
+    case_sensitive
+}
+
+/// Whether or not deleting everything in dir is almost certainly a mistake.
+fn is_dangerous_root(dir: &Path) -> bool {
+    let dir = match dir.canonicalize() {
+        Ok(dir) => dir,
+        Err(_) => return true,
+    };
+    let home = env::var_os("HOME").and_then(|home| Path::new(&home).canonicalize().ok());
+
+    dir.parent().is_none() || home.as_deref() == Some(&dir)
+}
+
+/// Removes the contents of dir, leaving dir itself in place.
+fn clear_dir(dir: &Path) -> CliResult<()> {
+    for entry in dir
+        .read_dir()
+        .with_context(|| format!("Failed to read directory {:?}", dir))
+        .with_code(exitcode::IOERR)?
+    {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?;
+        let path = entry.path();
+        if entry
+            .file_type()
+            .with_context(|| format!("Failed to stat {:?}", path))
+            .with_code(exitcode::IOERR)?
+            .is_dir()
+        {
+            remove_dir_all(&path)
+        } else {
+            remove_file(&path)
+        }
+        .with_context(|| format!("Failed to delete {:?}", path))
+        .with_code(exitcode::IOERR)?;
+    }
+    Ok(())
+}
+
+/// Makes sure the overlay source is a directory that won't be cleared or copied into itself.
+fn check_overlay_source(source: &Path, root_dir: &Path) -> CliResult<()> {
+    let source = source
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve overlay source {:?}", source))
+        .with_code(exitcode::NOINPUT)?;
+    if !source.is_dir() {
+        return Err(anyhow!(format!(
+            "The overlay source {:?} must be a directory.",
+            source,
+        )))
+        .with_code(exitcode::DATAERR);
+    }
+    let root_dir = root_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve directory {:?}", root_dir))
+        .with_code(exitcode::IOERR)?;
+    if source.starts_with(&root_dir) || root_dir.starts_with(&source) {
+        return Err(anyhow!(format!(
+            "The overlay source {:?} and root directory {:?} cannot contain one another.",
+            source, root_dir,
+        )))
+        .with_code(exitcode::DATAERR);
+    }
+    Ok(())
+}
+
+/// Estimates the disk space taken up by `root_dir` and everything under it the same way a
+/// [DiskBudget] charges for it.
+fn disk_usage(root_dir: &Path, block_size: NonZeroUsize) -> CliResult<usize> {
+    let budget = DiskBudget::new(usize::MAX, block_size, 0);
+    let mut used = budget.dir_usage();
+    let mut queue = VecDeque::from([root_dir.to_path_buf()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?
+        {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory {:?}", dir))
+                .with_code(exitcode::IOERR)?;
+            let metadata = entry
+                .metadata()
+                .with_context(|| format!("Failed to stat {:?}", entry.path()))
+                .with_code(exitcode::IOERR)?;
+
+            if metadata.is_dir() {
+                used = used.saturating_add(budget.dir_usage());
+                queue.push_back(entry.path());
+            } else {
+                used = used.saturating_add(budget.file_usage(metadata.len()));
+            }
+        }
+    }
+    Ok(used)
+}
+
+/// Fails if one of the names put in the root ahead of generation is also the name of an entry the
+/// generator creates there, which would otherwise be overwritten or fail generation halfway
+/// through. Only the root's own entries are planned to find out.
+fn check_root_collisions<'a>(
+    generator: &Generator,
+    what: &str,
+    names: impl Iterator<Item = &'a OsStr>,
+) -> CliResult<()> {
+    let names = names.collect::<HashSet<_>>();
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let root_dir = &generator.root_dir;
+    for (path, _) in generator
+        .plan_paths()
+        .take_while(|(path, _)| path.parent() == Some(root_dir.as_path()))
+    {
+        if let Some(name) = path.file_name() && names.contains(&name) {
+            return Err(anyhow!(format!(
+                "{} {:?} collides with an entry the generator creates in {:?}.",
+                what, name, root_dir,
+            )))
+            .with_code(exitcode::DATAERR);
+        }
+    }
+    Ok(())
+}
+
+/// A directory at the top of the tree along with its share of the files and bytes.
+struct Subtree {
+    name: OsString,
+    files: NonZeroUsize,
+    bytes: usize,
+    /// Offsets the seeds such that each directory gets a different tree.
+    index: u64,
+    /// The only media format of the directory's files when grouping them by extension.
+    media: Option<MediaFormat>,
+}
+
+impl Subtree {
+    /// Generates this directory's share of the tree into it instead of the root.
+    fn generator(&self, root: &Generator) -> Generator {
+        Generator {
+            root_dir: root.root_dir.join(&self.name),
+            num_files: self.files,
+            num_bytes: self.bytes,
+            seed: root.seed.wrapping_add(self.index),
+            structure_seed: root.structure_seed.map(|seed| seed.wrapping_add(self.index)),
+            content_seed: root.content_seed.map(|seed| seed.wrapping_add(self.index)),
+            seed_paths: Vec::new(),
+            overlay_source: None,
+            media_mix: self.media.map_or_else(|| root.media_mix.clone(), |media| Some(vec![media])),
+            group_by_extension: false,
+            ..root.clone()
+        }
+    }
+}
+
+/// Splits the files and bytes evenly between the root directory and the directories at the top of
+/// the overlay source such that synthetic entries are mixed into the copied ones. Fails if a copied
+/// entry shares its name with one the generator creates next to it.
+fn split_overlay(generator: &mut Generator, source: &Path) -> CliResult<Vec<Subtree>> {
+    let (names, mut dirs) = overlay_entries(source)?;
+    // Sorted such that the same source always gets the same tree
+    dirs.sort();
+
+    if let Some(entries) = generator.num_entries.take() {
+        generator.num_files = files_for_entries(entries, generator);
+    }
+    let parts = dirs.len() + 1;
+    let files = generator.num_files.get() / parts;
+    let bytes = generator.num_bytes / parts;
+    let overlay = match NonZeroUsize::new(files) {
+        // Too few files to go around, so they all stay in the root
+        None => Vec::new(),
+        Some(files) => dirs
+            .into_iter()
+            .zip(1..)
+            .map(|(name, index)| Subtree {
+                name,
+                files,
+                bytes,
+                index,
+                media: None,
+            })
+            .collect::<Vec<_>>(),
+    };
+    // The root keeps whatever doesn't divide evenly
+    generator.num_files =
+        NonZeroUsize::new(generator.num_files.get() - files * overlay.len()).unwrap();
+    generator.num_bytes -= bytes * overlay.len();
+
+    check_root_collisions(generator, "Overlay entry", names.iter().map(OsString::as_os_str))?;
+    for dir in &overlay {
+        let (names, _) = overlay_entries(&source.join(&dir.name))?;
+        check_root_collisions(
+            &dir.generator(generator),
+            "Overlay entry",
+            names.iter().map(OsString::as_os_str),
+        )?;
+    }
+    Ok(overlay)
+}
+
+/// Splits the files and bytes between a directory per format of the media mix, named after its
+/// extension, in proportion to how often the format appears in the mix.
+fn split_by_extension(generator: &mut Generator) -> Vec<Subtree> {
+    if let Some(entries) = generator.num_entries.take() {
+        generator.num_files = files_for_entries(entries, generator);
+    }
+    let mut weights = Vec::<(MediaFormat, usize)>::new();
+    for &format in generator.media_mix.iter().flatten() {
+        match weights.iter_mut().find(|(other, _)| *other == format) {
+            Some((_, weight)) => *weight += 1,
+            None => weights.push((format, 1)),
+        }
+    }
+    let total = weights.iter().map(|&(_, weight)| weight).sum::<usize>();
+    let files_per_weight = generator.num_files.get() / total;
+    if files_per_weight == 0 {
+        // Too few files to go around, so they all go to the first format
+        weights.truncate(1);
+    }
+    let total = weights.iter().map(|&(_, weight)| weight).sum::<usize>();
+    let bytes_per_weight = generator.num_bytes / total;
+
+    let mut shares = weights
+        .iter()
+        .map(|&(_, weight)| (files_per_weight * weight, bytes_per_weight * weight))
+        .collect::<Vec<_>>();
+    let assigned_files = shares.iter().map(|&(files, _)| files).sum::<usize>();
+    let assigned_bytes = shares.iter().map(|&(_, bytes)| bytes).sum::<usize>();
+    // The first format gets whatever doesn't divide evenly
+    shares[0].0 += generator.num_files.get() - assigned_files;
+    shares[0].1 += generator.num_bytes - assigned_bytes;
+    weights
+        .into_iter()
+        .zip(shares)
+        .zip(1..)
+        .map(|(((format, _), (files, bytes)), index)| Subtree {
+            name: OsString::from(format.extension()),
+            files: NonZeroUsize::new(files).unwrap(),
+            bytes,
+            index,
+            media: Some(format),
+        })
+        .collect()
+}
+
+/// The names of the entries in `dir` of the overlay source, followed by those of its directories.
+fn overlay_entries(dir: &Path) -> CliResult<(Vec<OsString>, Vec<OsString>)> {
+    let mut names = Vec::new();
+    let mut dirs = Vec::new();
+    for entry in dir
+        .read_dir()
+        .with_context(|| format!("Failed to read directory {:?}", dir))
+        .with_code(exitcode::IOERR)?
+    {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?;
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("Failed to stat {:?}", entry.path()))
+            .with_code(exitcode::IOERR)?;
+
+        if file_type.is_dir() {
+            dirs.push(entry.file_name());
+        }
+        names.push(entry.file_name());
+    }
+    Ok((names, dirs))
+}
+
+/// Copies the directories, files, and symlinks under `source` into `dest`. Other special files
+/// are skipped since reading them could block.
+fn copy_tree(source: &Path, dest: &Path) -> CliResult<()> {
+    let mut queue = VecDeque::from([PathBuf::new()]);
+    while let Some(dir) = queue.pop_front() {
+        let from_dir = source.join(&dir);
+        for entry in from_dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", from_dir))
+            .with_code(exitcode::IOERR)?
+        {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory {:?}", from_dir))
+                .with_code(exitcode::IOERR)?;
+            let from = entry.path();
+            let path = dir.join(entry.file_name());
+            let to = dest.join(&path);
+            let file_type = entry
+                .file_type()
+                .with_context(|| format!("Failed to stat {:?}", from))
+                .with_code(exitcode::IOERR)?;
+
+            if file_type.is_dir() {
+                create_dir_all(&to)
+                    .with_context(|| format!("Failed to create directory {:?}", to))
+                    .with_code(exitcode::IOERR)?;
+                queue.push_back(path);
+            } else if file_type.is_file() {
+                fs::copy(&from, &to)
+                    .with_context(|| format!("Failed to copy {:?} to {:?}", from, to))
+                    .with_code(exitcode::IOERR)?;
+            } else if cfg!(unix) && file_type.is_symlink() {
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(
+                    fs::read_link(&from)
+                        .with_context(|| format!("Failed to read symlink {:?}", from))
+                        .with_code(exitcode::IOERR)?,
+                    &to,
+                )
+                .with_context(|| format!("Failed to create symlink {:?}", to))
+                .with_code(exitcode::IOERR)?;
+            } else {
+                warn!("Skipping special file {:?} in the overlay source.", from);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn configuration(mut generator: Generator) -> Configuration {
+    if generator.group_by_extension {
+        let groups = split_by_extension(&mut generator);
+        let subtrees = groups
+            .iter()
+            .map(|dir| configuration(dir.generator(&generator)))
+            .collect();
+        generator.group_by_extension = false;
+        let mut config = configuration(generator);
+        config.subtrees = subtrees;
+        config.group_by_extension = true;
+        return config;
+    }
+    if generator.depth_includes_root {
+        // The root takes up the first level, leaving the rest for its descendants
+        generator.depth_includes_root = false;
+        generator.max_depth -= 1;
+    }
+    if let Some(entries) = generator.num_entries.take() {
+        generator.num_files = files_for_entries(entries, &generator);
+    }
+    if let Some(fanout) = generator.balanced_fanout {
+        generator.max_depth = balanced_depth(generator.num_files.get(), fanout);
+    }
+    if let Some(layout) = &generator.layout {
+        generator.max_depth = layout.depth();
+    }
+    if generator.shrink_depth {
+        generator.shrink_depth = false;
+        if let Some(depth) = degenerate_depth(&configuration(generator.clone())) {
+            generator.max_depth = depth;
+        }
+    }
+    if let Some(target) = generator.target_disk_usage.take() {
+        let config = configuration(generator.clone());
+        generator.num_bytes =
+            bytes_for_disk_usage(target, generator.block_size, &config).unwrap_or(0);
+    }
+    if let Some(sizes) = &generator.size_schedule {
+        generator.num_files = NonZeroUsize::new(sizes.len()).unwrap();
+        generator.num_bytes = sizes.iter().sum();
+        generator.files_exact = true;
+        // The schedule already pins down every byte
+        generator.bytes_exact = false;
+    }
+    #[cfg(unix)]
+    let broken_symlink_ratio = generator.broken_symlink_ratio;
+    #[cfg(not(unix))]
+    let broken_symlink_ratio = 0.;
+    let phrase_offset = generator
+        .seed_phrase
+        .as_ref()
+        .map_or(0, |phrase| seahash::hash(phrase.as_bytes()));
+    let seed = generator.seed.wrapping_add(phrase_offset);
+    let structure_seed = generator
+        .structure_seed
+        .map(|seed| seed.wrapping_add(phrase_offset));
+    let content_seed = generator
+        .content_seed
+        .map(|seed| seed.wrapping_add(phrase_offset));
+    let (pattern, entropy, archive) = match generator.fill_mode {
+        FillMode::Random => (None, None, None),
+        FillMode::Repeating(ref pattern) => (Some(Arc::from(pattern.as_slice())), None, None),
+        FillMode::Blended { permille } => (None, Some(f64::from(permille) / 1000.), None),
+        FillMode::Archive {
+            format,
+            inner_entries,
+        } => (
+            None,
+            None,
+            Some(Archive {
+                format,
+                inner_entries,
+                nesting_depth: generator.nesting_depth,
+            }),
+        ),
+    };
     let num_files = generator.num_files.get() as f64;
-    let bytes_per_file = generator.num_bytes as f64 / num_files;
+    let bytes_per_file = precise_div(generator.num_bytes, generator.num_files.get());
 
     if generator.max_depth == 0 {
-        return Ok(Configuration {
+        return Configuration {
             root_dir: generator.root_dir,
             files: generator.num_files.get(),
             bytes: generator.num_bytes,
             files_exact: generator.files_exact,
             bytes_exact: generator.bytes_exact,
+            dirs_exact: generator.dirs_exact,
+            empty_files: generator.empty_files,
+            size_schedule: generator.size_schedule,
+            layout: generator.layout.map(Arc::new),
+            min_files: generator.min_files,
+            min_dirs: generator.min_dirs,
+            min_files_per_dir: generator.min_files_per_dir,
+            cluster_factor: generator.cluster_factor,
             files_per_dir: num_files,
             dirs_per_dir: 0.,
             bytes_per_file,
+            size_distribution: generator.size_distribution,
+            size_alignment: generator.size_alignment,
             max_depth: 0,
-            seed: generator.seed,
+            leaf_depth_distribution: generator.leaf_depth_distribution,
+            depth_jitter: generator.depth_jitter,
+            structural_levels: generator.structural_levels,
+            seed,
+            structure_seed,
+            content_seed,
+            lex_sortable: generator.lex_sortable || generator.sort_by_size,
+            sort_by_size: generator.sort_by_size,
+            name_hash_jitter: generator.name_hash_jitter,
+            name_style: generator.name_style,
+            name_length_distribution: generator.name_length_distribution,
+            case_collisions: generator.case_collisions,
+            names_from_content: generator.names_from_content || generator.git_objects.is_some(),
+            git_objects: generator.git_objects.is_some(),
+            hidden_ratio: generator.hidden_ratio,
+            dir_wordlist: generator.dir_wordlist.map(Arc::from),
+            file_wordlist: generator.file_wordlist.map(Arc::from),
+            name_fn: generator.name_fn,
+            duplicate_groups: generator.duplicate_groups,
+            pattern,
+            entropy,
+            archive,
+            text_header: generator.text_header.map(Arc::from),
+            sequence_header: generator.sequence_header,
+            sparse_ratio: generator.sparse_ratio,
+            sparse_holes: generator.sparse_holes,
+            preallocate: generator.preallocate,
+            media_mix: generator.media_mix.map(Arc::from),
+            read_only_ratio: generator.read_only_ratio,
+            read_only_dirs: generator.read_only_dirs,
+            xattr_ratio: generator.xattr_ratio,
+            content_source: generator.content_source,
+            seed_paths: generator.seed_paths,
+            special_ratio: generator.special_ratio,
+            broken_symlink_ratio,
+            creation_order: generator.creation_order,
+            repair: generator.repair,
+            repair_dir: None,
+            subtrees: Vec::new(),
+            group_by_extension: false,
+            dir_seeds: generator.dir_seeds,
+            openat: generator.openat,
+            task_granularity: generator.task_granularity,
+            ramp_up: generator.ramp_up,
+            continue_on_error: generator.continue_on_error,
+            rate_limit: generator.rate_limit,
+            byte_rate: generator.byte_rate,
+            write_memory_budget: generator.write_memory_budget,
+            cpu_affinity: generator.cpu_affinity.map(Arc::from),
+            two_phase: generator.two_phase,
+            chain: generator.chain,
+            balanced_fanout: generator.balanced_fanout,
+            max_dir_width: generator.max_dir_width,
+            plan: None,
+            phase: None,
+            checksums: None,
+            samples: None,
+            checkpoint: None,
+            max_total_size: generator.max_total_size,
+            disk_budget: None,
+            #[cfg(feature = "metrics")]
+            progress: None,
+            on_progress: generator.on_progress,
+            progress_interval: generator.progress_interval,
+            fixed_time: generator.fixed_time.map(FileTime::from_system_time),
+            mtime_sequence: generator.mtime_sequence,
 
             informational_dirs_per_dir: 0,
             informational_total_dirs: 1,
             informational_bytes_per_files: bytes_per_file.round() as usize,
-        });
+        };
     }
 
-    let ratio = generator.file_to_dir_ratio.get() as f64;
-    let num_dirs = num_files / ratio;
-    // This formula was derived from the following equation:
-    // num_dirs = unknown_num_dirs_per_dir^max_depth
-    let dirs_per_dir = num_dirs.powf(1f64 / generator.max_depth as f64);
+    let (ratio, num_dirs, dirs_per_dir) = if generator.chain {
+        (num_files, generator.max_depth as f64, 1.)
+    } else if let Some(layout) = &generator.layout {
+        let num_dirs = layout.num_dirs() as f64;
+        (
+            num_files / (num_dirs + 1.),
+            num_dirs,
+            num_dirs.powf(1f64 / generator.max_depth as f64),
+        )
+    } else if let Some(fanout) = generator.balanced_fanout {
+        let leaves = fanout.pow(generator.max_depth);
+        (
+            precise_div(generator.num_files.get(), leaves),
+            balanced_dirs(fanout, generator.max_depth) as f64,
+            fanout as f64,
+        )
+    } else if let Some(fraction) = generator.dir_fraction {
+        // For a fraction f of all entries to be directories, each directory holds (1 - f) / f files
+        let ratio = (1. - fraction) / fraction;
+        let ratio = generator
+            .max_dir_width
+            .map_or(ratio, |width| ratio.min(max(width.get() / 2, 1) as f64));
+        let num_dirs = num_files / ratio;
+        (
+            ratio,
+            num_dirs,
+            num_dirs.powf(1f64 / generator.max_depth as f64),
+        )
+    } else {
+        // Leave room for subdirectories and the variance in files per directory
+        let ratio = generator.max_dir_width.map_or(
+            generator.file_to_dir_ratio.get(),
+            |width| min(generator.file_to_dir_ratio.get(), max(width.get() / 2, 1)),
+        );
+        let num_dirs = generator.dirs_exact.map_or_else(
+            || precise_div(generator.num_files.get(), ratio),
+            |dirs| dirs as f64,
+        );
+        // This formula was derived from the following equation:
+        // num_dirs = unknown_num_dirs_per_dir^max_depth
+        (
+            ratio as f64,
+            num_dirs,
+            num_dirs.powf(1f64 / generator.max_depth as f64),
+        )
+    };
 
-    Ok(Configuration {
+    Configuration {
         root_dir: generator.root_dir,
         files: generator.num_files.get(),
         bytes: generator.num_bytes,
         files_exact: generator.files_exact,
         bytes_exact: generator.bytes_exact,
+        dirs_exact: generator.dirs_exact,
+        empty_files: generator.empty_files,
+        size_schedule: generator.size_schedule,
+        layout: generator.layout.map(Arc::new),
+        min_files: generator.min_files,
+        min_dirs: generator.min_dirs,
+        min_files_per_dir: generator.min_files_per_dir,
+        cluster_factor: generator.cluster_factor,
         files_per_dir: ratio,
         bytes_per_file,
+        size_distribution: generator.size_distribution,
+        size_alignment: generator.size_alignment,
         dirs_per_dir,
         max_depth: generator.max_depth,
-        seed: generator.seed,
+        leaf_depth_distribution: generator.leaf_depth_distribution,
+        depth_jitter: generator.depth_jitter,
+        structural_levels: generator.structural_levels,
+        seed,
+        structure_seed,
+        content_seed,
+        lex_sortable: generator.lex_sortable || generator.sort_by_size,
+        sort_by_size: generator.sort_by_size,
+        name_hash_jitter: generator.name_hash_jitter,
+        name_style: generator.name_style,
+        name_length_distribution: generator.name_length_distribution,
+        case_collisions: generator.case_collisions,
+        names_from_content: generator.names_from_content || generator.git_objects.is_some(),
+        git_objects: generator.git_objects.is_some(),
+        hidden_ratio: generator.hidden_ratio,
+        dir_wordlist: generator.dir_wordlist.map(Arc::from),
+        file_wordlist: generator.file_wordlist.map(Arc::from),
+        name_fn: generator.name_fn,
+        duplicate_groups: generator.duplicate_groups,
+        pattern,
+        entropy,
+        archive,
+        text_header: generator.text_header.map(Arc::from),
+        sequence_header: generator.sequence_header,
+        sparse_ratio: generator.sparse_ratio,
+        sparse_holes: generator.sparse_holes,
+        preallocate: generator.preallocate,
+        media_mix: generator.media_mix.map(Arc::from),
+        read_only_ratio: generator.read_only_ratio,
+        read_only_dirs: generator.read_only_dirs,
+        xattr_ratio: generator.xattr_ratio,
+        content_source: generator.content_source,
+        seed_paths: generator.seed_paths,
+        special_ratio: generator.special_ratio,
+        broken_symlink_ratio,
+        creation_order: generator.creation_order,
+        repair: generator.repair,
+        repair_dir: None,
+        subtrees: Vec::new(),
+        group_by_extension: false,
+        dir_seeds: generator.dir_seeds,
+        openat: generator.openat,
+        task_granularity: generator.task_granularity,
+        ramp_up: generator.ramp_up,
+        continue_on_error: generator.continue_on_error,
+        rate_limit: generator.rate_limit,
+        byte_rate: generator.byte_rate,
+        write_memory_budget: generator.write_memory_budget,
+        cpu_affinity: generator.cpu_affinity.map(Arc::from),
+        two_phase: generator.two_phase,
+        chain: generator.chain,
+        balanced_fanout: generator.balanced_fanout,
+        max_dir_width: generator.max_dir_width,
+        plan: None,
+        phase: None,
+        checksums: None,
+        samples: None,
+        checkpoint: None,
+        max_total_size: generator.max_total_size,
+        disk_budget: None,
+        #[cfg(feature = "metrics")]
+        progress: None,
+        on_progress: generator.on_progress,
+        progress_interval: generator.progress_interval,
+        fixed_time: generator.fixed_time.map(FileTime::from_system_time),
+        mtime_sequence: generator.mtime_sequence,
 
         informational_dirs_per_dir: dirs_per_dir.round() as usize,
-        informational_total_dirs: num_dirs.round() as usize,
+        informational_total_dirs: max(num_dirs.round() as usize, generator.min_dirs),
         informational_bytes_per_files: bytes_per_file.round() as usize,
-    })
+    }
+}
+
+/// Splits `entries` between files and directories such that the files make for the remaining
+/// entries' worth of directories.
+fn files_for_entries(entries: NonZeroUsize, generator: &Generator) -> NonZeroUsize {
+    let entries = entries.get() as f64;
+    let files = if let Some(dirs) = generator.dirs_exact {
+        entries - dirs as f64
+    } else {
+        // Each directory holds `ratio` files, so files take up ratio / (ratio + 1) of the entries
+        let ratio = match generator.dir_fraction {
+            Some(fraction) => (1. - fraction) / fraction,
+            None => generator.file_to_dir_ratio.get() as f64,
+        };
+        let ratio = generator
+            .max_dir_width
+            .map_or(ratio, |width| ratio.min(max(width.get() / 2, 1) as f64));
+        entries * ratio / (ratio + 1.)
+    };
+    NonZeroUsize::new(files.round() as usize).unwrap_or_else(|| NonZeroUsize::new(1).unwrap())
+}
+
+/// The shallowest depth at which a tree with `fanout` subdirectories per directory has enough
+/// leaves for none of them to hold more than `fanout` files.
+fn balanced_depth(num_files: usize, fanout: usize) -> u32 {
+    let mut depth = 0;
+    let mut capacity = fanout;
+    while capacity < num_files {
+        capacity = capacity.saturating_mul(fanout);
+        depth += 1;
+    }
+    depth
 }
 
-fn print_configuration_info(config: &Configuration) {
+/// The number of directories below the root of a balanced tree.
+fn balanced_dirs(fanout: usize, depth: u32) -> usize {
+    (1..=depth).map(|level| fanout.pow(level)).sum()
+}
+
+/// Divides a by b without first rounding a to the nearest float, which matters once a no longer
+/// fits in an f64's mantissa (i.e. counts in the quadrillions).
+fn precise_div(a: usize, b: usize) -> f64 {
+    if a < 1 << f64::MANTISSA_DIGITS {
+        a as f64 / b as f64
+    } else {
+        (a / b) as f64 + (a % b) as f64 / b as f64
+    }
+}
+
+fn print_configuration_info(config: &Configuration, color: bool) {
     let locale = Locale::en;
+    if config.git_objects {
+        println!(
+            "Up to {} blobs named after the SHA-1 of their contents will be generated in a git \
+             object store.",
+            highlight(config.files.to_formatted_string(&locale), color),
+        );
+        return;
+    }
+    if config.names_from_content {
+        println!(
+            "Up to {} files named after the SHA-256 of their contents will be generated in up to \
+             256 directories.",
+            highlight(config.files.to_formatted_string(&locale), color),
+        );
+        return;
+    }
     println!(
-        "{file_count_type} {} {files_maybe_plural} will be generated in approximately \
+        "{file_count_type} {} {files_maybe_plural} will be generated in {dir_count_type} \
         {} {directories_maybe_plural} distributed across a tree of maximum depth {} where each \
         directory contains approximately {} other {dpd_directories_maybe_plural}.\
-        {bytes_info}",
-        config.files.to_formatted_string(&locale),
-        config.informational_total_dirs.to_formatted_string(&locale),
-        config.max_depth.to_formatted_string(&locale),
-        config
-            .informational_dirs_per_dir
-            .to_formatted_string(&locale),
+        {bytes_info}{empty_info}",
+        highlight(config.files.to_formatted_string(&locale), color),
+        highlight(
+            config.informational_total_dirs.to_formatted_string(&locale),
+            color,
+        ),
+        highlight(config.max_depth.to_formatted_string(&locale), color),
+        highlight(
+            config
+                .informational_dirs_per_dir
+                .to_formatted_string(&locale),
+            color,
+        ),
         file_count_type = if config.files_exact {
             "Exactly"
         } else {
             "About"
         },
+        dir_count_type = if config.dirs_exact.is_some() {
+            "exactly"
+        } else {
+            "approximately"
+        },
         files_maybe_plural = if config.files == 1 { "file" } else { "files" },
         directories_maybe_plural = if config.informational_total_dirs == 1 {
             "directory"
@@ -211,9 +5437,12 @@ fn print_configuration_info(config: &Configuration) {
         bytes_info = if config.bytes > 0 {
             format!(
                 " Each file will contain {byte_count_type} {} {bytes_maybe_plural} of random data.",
-                config
-                    .informational_bytes_per_files
-                    .to_formatted_string(&locale),
+                highlight(
+                    config
+                        .informational_bytes_per_files
+                        .to_formatted_string(&locale),
+                    color,
+                ),
                 byte_count_type = if config.bytes_exact {
                     "exactly"
                 } else {
@@ -228,16 +5457,249 @@ fn print_configuration_info(config: &Configuration) {
         } else {
             "".to_string()
         },
+        empty_info = match config.empty_files {
+            0 => "".to_string(),
+            1 => format!(
+                " Exactly {} additional file will be empty.",
+                highlight(String::from("1"), color),
+            ),
+            n => format!(
+                " Exactly {} additional files will be empty.",
+                highlight(n.to_formatted_string(&locale), color),
+            ),
+        },
     );
+    if config.group_by_extension {
+        let names = config
+            .subtrees
+            .iter()
+            .filter_map(|dir| dir.root_dir.file_name())
+            .map(|name| highlight(name.to_string_lossy().into_owned(), color))
+            .collect::<Vec<_>>();
+        println!(
+            "The files will be grouped by type into {} {directories_maybe_plural}: {}.",
+            highlight(names.len().to_formatted_string(&locale), color),
+            names.join(", "),
+            directories_maybe_plural = if names.len() == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+        );
+    } else if !config.subtrees.is_empty() {
+        let files = config.subtrees.iter().map(|dir| dir.files).sum::<usize>();
+        println!(
+            "Another {} {files_maybe_plural} will be generated into the {} \
+             {directories_maybe_plural} copied from the overlay source.",
+            highlight(files.to_formatted_string(&locale), color),
+            highlight(config.subtrees.len().to_formatted_string(&locale), color),
+            files_maybe_plural = if files == 1 { "file" } else { "files" },
+            directories_maybe_plural = if config.subtrees.len() == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+        );
+    }
+}
+
+/// The counts a generated tree is guaranteed to have given its exactness options.
+struct ExpectedCounts {
+    root_dir: PathBuf,
+    files: Option<usize>,
+    dirs: Option<usize>,
+    bytes: Option<usize>,
+}
+
+impl From<&Configuration> for ExpectedCounts {
+    fn from(config: &Configuration) -> Self {
+        let seeds = SeedEntries::new(&config.seed_paths).unwrap_or_default();
+        Self {
+            root_dir: config.root_dir.clone(),
+            files: if config.files_exact || config.chain || config.balanced_fanout.is_some() {
+                Some(config.files + config.empty_files + seeds.files.len())
+            } else {
+                None
+            },
+            dirs: if config.chain {
+                Some(config.max_depth as usize + seeds.dirs.len())
+            } else if let Some(fanout) = config.balanced_fanout {
+                Some(balanced_dirs(fanout, config.max_depth) + seeds.dirs.len())
+            } else {
+                config.dirs_exact.map(|dirs| dirs + seeds.dirs.len())
+            },
+            bytes: if config.bytes_exact || config.size_schedule.is_some() {
+                Some(config.bytes)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl ExpectedCounts {
+    /// Walks the generated tree, failing if it doesn't match what was promised.
+    fn verify(self) -> CliResult<()> {
+        let mut files = 0;
+        let mut dirs = 0;
+        let mut bytes = 0;
+
+        let mut queue = VecDeque::from([self.root_dir.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            for entry in dir
+                .read_dir()
+                .with_context(|| format!("Failed to read directory {:?}", dir))
+                .with_code(exitcode::IOERR)?
+            {
+                let entry = entry
+                    .with_context(|| format!("Failed to read directory {:?}", dir))
+                    .with_code(exitcode::IOERR)?;
+                // The marker isn't part of the generated tree
+                if dir == self.root_dir && entry.file_name() == MARKER_FILE_NAME {
+                    continue;
+                }
+                let metadata = entry
+                    .metadata()
+                    .with_context(|| format!("Failed to stat {:?}", entry.path()))
+                    .with_code(exitcode::IOERR)?;
+
+                if metadata.is_dir() {
+                    dirs += 1;
+                    queue.push_back(entry.path());
+                } else {
+                    files += 1;
+                    bytes += metadata.len() as usize;
+                }
+            }
+        }
+        event!(Level::DEBUG, files, dirs, bytes, "Self-check counts");
+
+        for (name, expected, actual) in [
+            ("files", self.files, files),
+            ("directories", self.dirs, dirs),
+            ("bytes", self.bytes, bytes),
+        ] {
+            if let Some(expected) = expected && expected != actual {
+                return Err(anyhow!(format!(
+                    "Self-check failed: expected exactly {} {} but found {}.",
+                    expected, name, actual,
+                )))
+                .with_code(exitcode::SOFTWARE);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a CSV catalog of every file in the finished tree, such that it only lists the files that
+/// were actually created along with any copied from an overlay. Each row holds a file's path
+/// relative to the root directory, its type (`file`, `fifo`, or `symlink`), and its size in bytes.
+fn write_catalog(root_dir: &Path, file: &Path) -> CliResult<()> {
+    let out = File::create(file)
+        .with_context(|| format!("Failed to create catalog file {:?}", file))
+        .with_code(exitcode::CANTCREAT)?;
+    let mut out = BufWriter::new(out);
+    writeln!(out, "path,type,bytes")
+        .with_context(|| format!("Failed to write catalog file {:?}", file))
+        .with_code(exitcode::IOERR)?;
+
+    let mut queue = VecDeque::from([root_dir.to_path_buf()]);
+    while let Some(dir) = queue.pop_front() {
+        let mut entries = dir
+            .read_dir()
+            .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?;
+        // Directory iteration order is unspecified, but the catalog should be stable
+        entries.sort_unstable_by_key(fs::DirEntry::file_name);
+
+        for entry in entries {
+            let path = entry.path();
+            let metadata = entry
+                .metadata()
+                .with_context(|| format!("Failed to stat {:?}", path))
+                .with_code(exitcode::IOERR)?;
+            let (kind, bytes) = if metadata.is_dir() {
+                queue.push_back(path);
+                continue;
+            } else if metadata.is_file() {
+                if dir == root_dir && entry.file_name() == MARKER_FILE_NAME {
+                    continue;
+                }
+                ("file", metadata.len())
+            } else if metadata.file_type().is_symlink() {
+                ("symlink", 0)
+            } else {
+                // FIFOs are the only other special files generated or copied
+                ("fifo", 0)
+            };
+
+            let relative = path.strip_prefix(root_dir).unwrap_or(&path);
+            writeln!(out, "{},{},{}", csv_quote(relative), kind, bytes)
+                .with_context(|| format!("Failed to write catalog file {:?}", file))
+                .with_code(exitcode::IOERR)?;
+        }
+    }
+    out.flush()
+        .with_context(|| format!("Failed to write catalog file {:?}", file))
+        .with_code(exitcode::IOERR)
+}
+
+/// Pins the timestamps of every directory in the tree. This can only be done once the tree is
+/// complete since adding entries to a directory bumps its modification time. Listing a directory
+/// may also bump its access time, so each one is pinned after being read.
+fn pin_dir_times(root_dir: PathBuf, time: FileTime) -> CliResult<()> {
+    let mut queue = VecDeque::from([root_dir]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?
+        {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory {:?}", dir))
+                .with_code(exitcode::IOERR)?;
+            if entry
+                .file_type()
+                .with_context(|| format!("Failed to stat {:?}", entry.path()))
+                .with_code(exitcode::IOERR)?
+                .is_dir()
+            {
+                queue.push_back(entry.path());
+            }
+        }
+
+        set_file_times(&dir, time, time)
+            .with_context(|| format!("Failed to set the timestamps of {:?}", dir))
+            .with_code(exitcode::IOERR)?;
+    }
+    Ok(())
 }
 
-fn print_stats(stats: GeneratorStats) {
+fn stats_summary(stats: GeneratorStats, color: bool) -> String {
     let locale = Locale::en;
-    println!(
-        "Created {} {files_maybe_plural}{bytes_info} across {} {directories_maybe_plural}.",
-        stats.files.to_formatted_string(&locale),
-        stats.dirs.to_formatted_string(&locale),
+    format!(
+        "Created {} {files_maybe_plural}{bytes_info}{fifos_info}{symlinks_info} across {} \
+        {directories_maybe_plural}.",
+        highlight(stats.files.to_formatted_string(&locale), color),
+        highlight(stats.dirs.to_formatted_string(&locale), color),
         files_maybe_plural = if stats.files == 1 { "file" } else { "files" },
+        fifos_info = match stats.fifos {
+            0 => "".to_string(),
+            1 => format!(" and {} FIFO", highlight(String::from("1"), color)),
+            fifos => format!(
+                " and {} FIFOs",
+                highlight(fifos.to_formatted_string(&locale), color),
+            ),
+        },
+        symlinks_info = match stats.symlinks {
+            0 => "".to_string(),
+            1 => format!(" and {} broken symlink", highlight(String::from("1"), color)),
+            symlinks => format!(
+                " and {} broken symlinks",
+                highlight(symlinks.to_formatted_string(&locale), color),
+            ),
+        },
         directories_maybe_plural = if stats.dirs == 1 {
             "directory"
         } else {
@@ -245,18 +5707,141 @@ fn print_stats(stats: GeneratorStats) {
         },
         bytes_info = if stats.bytes > 0 {
             event!(Level::INFO, bytes = stats.bytes, "Exact bytes written");
-            format!(" ({})", bytesize::to_string(stats.bytes as u64, false))
+            format!(
+                " ({})",
+                highlight(bytesize::to_string(stats.bytes as u64, false), color),
+            )
         } else {
             "".to_string()
         }
-    );
+    )
+}
+
+fn phase_summary(
+    phase: Phase,
+    (stats, elapsed): (GeneratorStats, Duration),
+    color: bool,
+) -> String {
+    let locale = Locale::en;
+    match phase {
+        Phase::Dirs => format!(
+            "Phase 1: created {} {} in {:.2?}.",
+            highlight(stats.dirs.to_formatted_string(&locale), color),
+            if stats.dirs == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+            elapsed,
+        ),
+        Phase::Files => format!(
+            "Phase 2: created {} {} in {:.2?}.",
+            highlight(
+                (stats.files + stats.fifos + stats.symlinks).to_formatted_string(&locale),
+                color,
+            ),
+            if stats.files + stats.fifos + stats.symlinks == 1 {
+                "file"
+            } else {
+                "files"
+            },
+            elapsed,
+        ),
+    }
+}
+
+/// Makes counts stand out in the human readable output.
+fn highlight(text: String, color: bool) -> String {
+    if color {
+        text.bold().cyan().to_string()
+    } else {
+        text
+    }
+}
+
+/// Creates every directory before any file by running the same deterministic plan twice, once
+/// per phase, and times each of them.
+fn run_two_phase(config: Configuration) -> CliResult<[(GeneratorStats, Duration); 2]> {
+    let mut outcomes = [(GeneratorStats::default(), Duration::ZERO); 2];
+    for (outcome, phase) in outcomes.iter_mut().zip([Phase::Dirs, Phase::Files]) {
+        let config = Configuration {
+            phase: Some(phase),
+            ..config.clone()
+        };
+        let start = Instant::now();
+        let stats = run_generator(config)?;
+        *outcome = (stats, start.elapsed());
+    }
+    Ok(outcomes)
+}
+
+/// Generates the tree into the root directory followed by its subtrees, sharing the root's writers
+/// and budgets with the latter. When grouping by extension, the root only holds the subtrees'
+/// directories.
+fn run_generator(mut config: Configuration) -> CliResult<GeneratorStats> {
+    let subtrees = mem::take(&mut config.subtrees);
+    let mut stats = if config.group_by_extension {
+        GeneratorStats::default()
+    } else {
+        run_generator_in(config.clone())?
+    };
+    for dir in subtrees {
+        if config.group_by_extension {
+            stats.dirs += create_group_dir(&config, &dir.root_dir)?;
+        }
+        stats += run_generator_in(Configuration {
+            plan: config.plan.clone(),
+            phase: config.phase,
+            checksums: config.checksums.clone(),
+            samples: config.samples.clone(),
+            disk_budget: config.disk_budget.clone(),
+            #[cfg(feature = "metrics")]
+            progress: config.progress.clone(),
+            on_progress: config.on_progress.clone(),
+            ..dir
+        })?;
+    }
+    Ok(stats)
+}
+
+/// Creates (or plans) the directory the files of one media format are grouped in, returning the
+/// number of directories created.
+fn create_group_dir(config: &Configuration, dir: &Path) -> CliResult<usize> {
+    if let Some(plan) = &config.plan {
+        plan.send((dir.to_path_buf(), EntryKind::Dir))
+            .context("Nobody is listening for planned paths")
+            .with_code(exitcode::SOFTWARE)?;
+        return Ok(0);
+    }
+    // The directories are already there in the second phase or when repairing
+    if config.phase == Some(Phase::Files) || (config.repair && dir.is_dir()) {
+        return Ok(0);
+    }
+    create_dir(dir)
+        .with_context(|| format!("Failed to create directory {:?}", dir))
+        .with_code(exitcode::IOERR)?;
+    Ok(1)
 }
 
-fn run_generator(config: Configuration) -> CliResult<GeneratorStats> {
-    let parallelism =
-        thread::available_parallelism().unwrap_or(unsafe { NonZeroUsize::new_unchecked(1) });
-    let runtime = tokio::runtime::Builder::new_current_thread()
-        .max_blocking_threads(parallelism.get())
+fn run_generator_in(config: Configuration) -> CliResult<GeneratorStats> {
+    if config.names_from_content {
+        return run_content_addressed(config);
+    }
+    // Planning runs tasks one at a time so that paths are yielded in a deterministic order
+    let parallelism = if config.plan.is_some() {
+        unsafe { NonZeroUsize::new_unchecked(1) }
+    } else if let Some(cores) = &config.cpu_affinity {
+        // One thread per core, validated to be non-empty
+        NonZeroUsize::new(cores.len()).unwrap()
+    } else {
+        thread::available_parallelism().unwrap_or(unsafe { NonZeroUsize::new_unchecked(1) })
+    };
+    let mut runtime = tokio::runtime::Builder::new_current_thread();
+    runtime.max_blocking_threads(parallelism.get());
+    if let Some(cores) = config.cpu_affinity.clone() {
+        runtime.on_thread_start(pin_to_cores(cores));
+    }
+    let runtime = runtime
         .build()
         .context("Failed to create tokio runtime")
         .with_code(exitcode::OSERR)?;
@@ -265,29 +5850,361 @@ fn run_generator(config: Configuration) -> CliResult<GeneratorStats> {
     runtime.block_on(run_generator_async(config, parallelism))
 }
 
+/// Pins each thread the hook is called on to the next of the `cores` in turn, confining the pool
+/// of threads files are created on to them.
+fn pin_to_cores(cores: Arc<[usize]>) -> impl Fn() + Send + Sync + 'static {
+    let next = AtomicUsize::new(0);
+    move || {
+        let core = cores[next.fetch_add(1, Ordering::Relaxed) % cores.len()];
+        if !core_affinity::set_for_current(CoreId { id: core }) {
+            warn!("Failed to pin a thread to CPU core {}.", core);
+        }
+    }
+}
+
+/// Generates `files` files in a content-addressed layout (or git object store), ignoring the
+/// shape of the tree.
+fn run_content_addressed(config: Configuration) -> CliResult<GeneratorStats> {
+    event!(Level::INFO, config = ?config, "Starting content-addressed config");
+    let seed = config.content_seed.unwrap_or(config.seed);
+    let mut random = Xoshiro256PlusPlus::seed_from_u64(seed);
+    let byte_counts = if let Some(sizes) = config.size_schedule {
+        sizes
+    } else if config.bytes_exact {
+        (0..config.files)
+            .map(|i| even_byte_count(config.bytes, config.files, config.size_alignment, i))
+            .collect()
+    } else {
+        let num_bytes_distr = Aligned {
+            distr: FileSizes::new(config.size_distribution, config.bytes_per_file, config.files),
+            alignment: config.size_alignment,
+        };
+        (0..config.files)
+            .map(|_| num_bytes_distr.sample(&mut random).round() as usize)
+            .collect()
+    };
+
+    let stats = if config.git_objects {
+        create_git_objects(&config.root_dir, &byte_counts, &mut random, config.plan.as_ref())
+    } else {
+        create_content_addressed(&config.root_dir, &byte_counts, &mut random, config.plan.as_ref())
+    }?;
+
+    if stats.files < byte_counts.len() {
+        warn!(
+            "Only {} of {} files were created as the rest had the same contents as other files.",
+            stats.files.to_formatted_string(&Locale::en),
+            byte_counts.len().to_formatted_string(&Locale::en),
+        );
+    }
+    Ok(stats)
+}
+
 async fn run_generator_async(
     config: Configuration,
     parallelism: NonZeroUsize,
 ) -> CliResult<GeneratorStats> {
     let max_depth = config.max_depth as usize;
-    let random = {
-        let seed = ((config.files.wrapping_add(max_depth) as f64
+    let derive_seed = |seed: u64| {
+        ((config.files.wrapping_add(max_depth) as f64
             * (config.files_per_dir + config.dirs_per_dir)) as u64)
-            .wrapping_add(config.seed);
-        event!(Level::DEBUG, seed = ?seed, "Starting seed");
-        Xoshiro256PlusPlus::seed_from_u64(seed)
+            .wrapping_add(seed)
+    };
+    let seed = derive_seed(config.seed);
+    event!(Level::DEBUG, seed = ?seed, "Starting seed");
+    // Unless asked otherwise, a single stream drives both the shape of the tree and its files to
+    // keep generated trees stable.
+    let (structure_seed, seed, structure_random) =
+        if config.structure_seed.is_some() || config.content_seed.is_some() {
+            let structure_seed = derive_seed(config.structure_seed.unwrap_or(config.seed));
+            let content_seed = derive_seed(config.content_seed.unwrap_or(config.seed));
+            event!(Level::DEBUG, structure_seed, content_seed, "Split seeds");
+
+            let mut structure_random = Xoshiro256PlusPlus::seed_from_u64(structure_seed);
+            // Keep the streams independent even if both seeds are the same
+            structure_random.jump();
+            (structure_seed, content_seed, Some(structure_random))
+        } else {
+            (seed, seed, None)
+        };
+    let mut random = Xoshiro256PlusPlus::seed_from_u64(seed);
+    let size_schedule = config.size_schedule.map(|mut sizes| {
+        sizes.shuffle(&mut random);
+        sizes
+    });
+    let num_files_distr = AtLeast {
+        distr: Clustered {
+            distr: Normal::new(config.files_per_dir, config.files_per_dir * 0.2).unwrap(),
+            hot_ratio: 1. - config.cluster_factor,
+        },
+        min: config.min_files_per_dir as f64,
     };
-    let num_files_distr = Normal::new(config.files_per_dir, config.files_per_dir * 0.2).unwrap();
     let num_dirs_distr = Normal::new(config.dirs_per_dir, config.dirs_per_dir * 0.2).unwrap();
-    let num_bytes_distr = Normal::new(config.bytes_per_file, config.bytes_per_file * 0.2).unwrap();
+    let num_bytes_distr = Aligned {
+        distr: FileSizes::new(config.size_distribution, config.bytes_per_file, config.files),
+        alignment: config.size_alignment,
+    };
+    let branch_depths = match config.leaf_depth_distribution {
+        Some(DepthDistribution::Normal { mean, cv }) => {
+            Some(BranchDepths::Normal(Normal::new(mean, mean * cv).unwrap()))
+        }
+        None if config.depth_jitter > 0 => Some(BranchDepths::Jittered {
+            max_depth: config.max_depth,
+            jitter: config.depth_jitter,
+        }),
+        None => None,
+    };
+    let leaf_depths = branch_depths.map(|distr| {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(structure_seed);
+        // Stay clear of the structure stream
+        random.long_jump();
+        LeafDepths { distr, random }
+    });
+
+    let naming = Naming {
+        lex_sortable: config.lex_sortable,
+        hash_jitter: if config.name_hash_jitter {
+            Some(structure_seed)
+        } else {
+            None
+        },
+        unicode_random: if config.name_style == NameStyle::UnicodeRandom {
+            Some(structure_seed)
+        } else {
+            None
+        },
+        name_lengths: config.name_length_distribution,
+        case_collisions: config.case_collisions,
+        hidden: if config.hidden_ratio > 0. {
+            Some((structure_seed, (config.hidden_ratio * u64::MAX as f64) as u64))
+        } else {
+            None
+        },
+        timestamps: match &config.name_style {
+            NameStyle::Timestamped {
+                format,
+                start,
+                step,
+            } => Some(Timestamps {
+                format: Arc::from(format.as_str()),
+                start: *start,
+                step: *step,
+            }),
+            _ => None,
+        },
+        dir_words: config.dir_wordlist,
+        file_words: config.file_wordlist,
+        custom: config
+            .name_fn
+            .map(|name_fn| Arc::new(CustomNames::new(name_fn, structure_seed, &config.root_dir))),
+    };
+    let read_only = if config.read_only_ratio > 0. {
+        Some(ReadOnlyEntries {
+            threshold: (config.read_only_ratio * u64::MAX as f64) as u64,
+            seed,
+            root_len: config.root_dir.as_os_str().len(),
+        })
+    } else {
+        None
+    };
+    let xattrs = if config.xattr_ratio > 0. {
+        Some(XattrFiles {
+            threshold: (config.xattr_ratio * u64::MAX as f64) as u64,
+            seed,
+            root_len: config.root_dir.as_os_str().len(),
+        })
+    } else {
+        None
+    };
+    // Directories can only be made read-only once nothing else will be created in them
+    let read_only_dirs = read_only
+        .filter(|_| {
+            config.read_only_dirs && config.phase != Some(Phase::Dirs) && config.plan.is_none()
+        })
+        .map(|read_only| (config.root_dir.clone(), read_only));
+    let disk_budget = config.disk_budget.clone();
+    let options = TaskOptions {
+        naming: naming.clone(),
+        repair: config.repair,
+        openat: config.openat,
+        continue_on_error: config.continue_on_error,
+        rate_limit: config
+            .rate_limit
+            .map(|per_sec| Arc::new(RateLimiter::new(per_sec))),
+        byte_rate: config
+            .byte_rate
+            .map(|per_sec| Arc::new(RateLimiter::new(per_sec))),
+        memory_budget: config
+            .write_memory_budget
+            .map(|bytes| Arc::new(WriteBudget::new(bytes))),
+        phase: config.phase,
+        checkpoint: config.checkpoint.clone(),
+        budget: config.disk_budget.clone(),
+        order: config.creation_order,
+        plan: config.plan,
+        fixed_time: config.fixed_time,
+        positions: if config.mtime_sequence.is_some() || config.sequence_header.is_some() {
+            Some(FilePositions::default())
+        } else {
+            None
+        },
+        mtime_sequence: config.mtime_sequence,
+        checksums: config.checksums,
+        pattern: config.pattern,
+        entropy: config.entropy,
+        archive: config.archive,
+        header: config.text_header,
+        sequence_header: config.sequence_header,
+        size_alignment: config.size_alignment,
+        #[cfg(feature = "metrics")]
+        progress: config.progress,
+        sparse: if config.sparse_ratio > 0. {
+            Some(SparseFiles {
+                threshold: (config.sparse_ratio * u64::MAX as f64) as u64,
+                seed,
+                root_len: config.root_dir.as_os_str().len(),
+                holes: config.sparse_holes,
+            })
+        } else {
+            None
+        },
+        preallocate: config.preallocate,
+        media: config.media_mix.map(|formats| MediaFiles {
+            formats,
+            seed,
+            root_len: config.root_dir.as_os_str().len(),
+        }),
+        read_only,
+        xattrs,
+        special: if config.special_ratio > 0. || config.broken_symlink_ratio > 0. {
+            Some(SpecialFiles {
+                fifos: if config.special_ratio > 0. {
+                    Some((config.special_ratio * u64::MAX as f64) as u64)
+                } else {
+                    None
+                },
+                // Symlinks take the slice of hashes right after the FIFOs'
+                broken_symlinks: if config.broken_symlink_ratio > 0. {
+                    Some(
+                        ((config.special_ratio + config.broken_symlink_ratio) * u64::MAX as f64)
+                            as u64,
+                    )
+                } else {
+                    None
+                },
+                seed,
+                root_len: config.root_dir.as_os_str().len(),
+            })
+        } else {
+            None
+        },
+    };
+    let duplicate_groups = config
+        .duplicate_groups
+        .map(|num_groups| DuplicateGroups { num_groups, seed });
+    let empty_files = EmptyFiles::new(config.empty_files, config.informational_total_dirs);
+    let content_source = config
+        .content_source
+        .as_deref()
+        .map(ContentSource::load)
+        .transpose()?;
 
     macro_rules! run {
         ($generator:expr) => {{
-            run(config.root_dir, max_depth, parallelism, $generator).await
+            run(
+                config.root_dir,
+                max_depth,
+                parallelism,
+                config.task_granularity.map(|depth| depth as usize),
+                config
+                    .ramp_up
+                    .map(|period| Arc::new(RampUp::new(period, parallelism))),
+                naming,
+                leaf_depths,
+                config
+                    .on_progress
+                    .map(|hook| ProgressReporter::new(hook, config.progress_interval)),
+                config.checkpoint,
+                config.disk_budget,
+                config.samples,
+                $generator,
+            )
+            .await
         }};
     }
 
-    if config.files_exact || config.bytes_exact {
+    let dir_seeds = dir_seeds(&config);
+    let stats = if let Some(dir) = config.repair_dir {
+        run!(SingleDirGenerator {
+            dir: dir.dir,
+            num_files: dir.num_files,
+            num_bytes_distr,
+            random: Xoshiro256PlusPlus::seed_from_u64(dir.seed),
+            options,
+        })
+    } else if let Some(layout) = &config.layout {
+        create_layout(
+            &config.root_dir,
+            layout,
+            if config.bytes > 0 {
+                Some(num_bytes_distr)
+            } else {
+                None
+            },
+            if config.bytes_exact {
+                Some(config.bytes)
+            } else {
+                None
+            },
+            random,
+            options,
+        )
+    } else if config.chain {
+        run!(ChainGenerator {
+            num_files: config.files,
+            num_bytes_distr: if config.bytes > 0 {
+                Some(num_bytes_distr)
+            } else {
+                None
+            },
+            bytes_exact: if config.bytes_exact {
+                Some(config.bytes)
+            } else {
+                None
+            },
+            random,
+            options,
+            duplicate_groups,
+            empty_files: config.empty_files,
+            sort_by_size: config.sort_by_size,
+            // Repairs only count the directories that were actually missing
+            batch_depth: if config.repair { 0 } else { max_depth },
+        })
+    } else if let Some(fanout) = config.balanced_fanout {
+        run!(BalancedGenerator::new(
+            fanout,
+            max_depth,
+            config.files,
+            if config.bytes > 0 {
+                Some(num_bytes_distr)
+            } else {
+                None
+            },
+            if config.bytes_exact {
+                Some(config.bytes)
+            } else {
+                None
+            },
+            random,
+            options,
+            duplicate_groups,
+            config.sort_by_size,
+        ))
+    } else if config.files_exact
+        || config.bytes_exact
+        || config.dirs_exact.is_some()
+        || content_source.is_some()
+    {
         run!(OtherFilesAndContentsGenerator::new(
             num_files_distr,
             num_dirs_distr,
@@ -297,8 +6214,9 @@ async fn run_generator_async(
                 None
             },
             random,
+            structure_random,
             if config.files_exact {
-                Some(unsafe { NonZeroUsize::new_unchecked(config.files) })
+                Some(NonZeroUsize::new(config.files).unwrap())
             } else {
                 None
             },
@@ -307,6 +6225,15 @@ async fn run_generator_async(
             } else {
                 None
             },
+            config.dirs_exact,
+            config.min_files,
+            config.min_dirs,
+            size_schedule,
+            options,
+            duplicate_groups,
+            content_source,
+            empty_files,
+            DirWidth::new(config.max_dir_width, max_depth),
         ))
     } else if config.bytes > 0 {
         run!(FilesAndContentsGenerator {
@@ -314,12 +6241,201 @@ async fn run_generator_async(
             num_dirs_distr,
             num_bytes_distr,
             random,
+            structure_random,
+            options,
+            duplicate_groups,
+            floor: FilesFloor::new(config.min_files),
+            dirs_floor: DirsFloor::new(config.min_dirs),
+            empty_files,
+            width: DirWidth::new(config.max_dir_width, max_depth),
+            levels: StructuralLevels::new(config.structural_levels as usize, &config.root_dir),
+            sort_by_size: config.sort_by_size,
+            dir_seeds,
         })
     } else {
         run!(FilesNoContentsGenerator {
             num_files_distr,
             num_dirs_distr,
             random,
+            structure_random,
+            options,
+            floor: FilesFloor::new(config.min_files),
+            dirs_floor: DirsFloor::new(config.min_dirs),
+            empty_files,
+            width: DirWidth::new(config.max_dir_width, max_depth),
+            levels: StructuralLevels::new(config.structural_levels as usize, &config.root_dir),
         })
+    }?;
+
+    if let Some(budget) = disk_budget && budget.exhausted() {
+        warn!(
+            "Stopped generating at the maximum total size with ~{} bytes in use.",
+            budget.used().to_formatted_string(&Locale::en),
+        );
+    }
+    if let Some((root_dir, read_only)) = read_only_dirs {
+        make_dirs_read_only(root_dir, read_only)?;
+    }
+    Ok(stats)
+}
+
+/// Makes the picked directories below the root read-only, each after being read as its entries
+/// can still be listed but not added to.
+fn make_dirs_read_only(root_dir: PathBuf, read_only: ReadOnlyEntries) -> CliResult<()> {
+    let mut queue = VecDeque::from([root_dir.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir
+            .read_dir()
+            .with_context(|| format!("Failed to read directory {:?}", dir))
+            .with_code(exitcode::IOERR)?
+        {
+            let entry = entry
+                .with_context(|| format!("Failed to read directory {:?}", dir))
+                .with_code(exitcode::IOERR)?;
+            if entry
+                .file_type()
+                .with_context(|| format!("Failed to stat {:?}", entry.path()))
+                .with_code(exitcode::IOERR)?
+                .is_dir()
+            {
+                queue.push_back(entry.path());
+            }
+        }
+
+        if dir != root_dir && read_only.is_read_only_dir(&dir) {
+            make_read_only(&dir).with_code(exitcode::IOERR)?;
+        }
+    }
+    Ok(())
+}
+
+/// Samples file sizes averaging `mean` bytes according to a [SizeDistribution].
+#[derive(Clone)]
+enum FileSizes {
+    Normal(Normal<f64>),
+    /// Picks a rank out of `num_files` uniformly and sizes it by Zipf's law, scaled such that the
+    /// expected size is the mean.
+    Zipf {
+        scale: f64,
+        num_files: usize,
+        exponent: f64,
+    },
+}
+
+impl FileSizes {
+    fn new(distr: SizeDistribution, mean: f64, num_files: usize) -> Self {
+        match distr {
+            SizeDistribution::Normal => Self::Normal(Normal::new(mean, mean * 0.2).unwrap()),
+            SizeDistribution::Zipf { exponent } => Self::Zipf {
+                scale: mean * num_files as f64 / generalized_harmonic(num_files, exponent),
+                num_files,
+                exponent,
+            },
+        }
+    }
+}
+
+impl Distribution<f64> for FileSizes {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Self::Normal(distr) => distr.sample(rng),
+            Self::Zipf {
+                scale,
+                num_files,
+                exponent,
+            } => scale * (rng.gen_range(1..=num_files) as f64).powf(-exponent),
+        }
+    }
+}
+
+/// Samples how deep each branch hanging off the root directory grows.
+enum BranchDepths {
+    Normal(Normal<f64>),
+    /// Up to `jitter` levels short of the maximum depth, uniformly.
+    Jittered { max_depth: u32, jitter: u32 },
+}
+
+impl Distribution<f64> for BranchDepths {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Self::Normal(distr) => distr.sample(rng),
+            Self::Jittered { max_depth, jitter } => {
+                f64::from(max_depth.saturating_sub(rng.gen_range(0..=jitter)))
+            }
+        }
+    }
+}
+
+/// The sum of `k^-exponent` over `k` in `1..=n`. Past the first million terms, the tail is
+/// approximated by an integral.
+fn generalized_harmonic(n: usize, exponent: f64) -> f64 {
+    const EXACT_TERMS: usize = 1 << 20;
+
+    let exact = (1..=min(n, EXACT_TERMS))
+        .map(|k| (k as f64).powf(-exponent))
+        .sum::<f64>();
+    if n <= EXACT_TERMS {
+        return exact;
+    }
+
+    let (from, to) = (EXACT_TERMS as f64 + 0.5, n as f64 + 0.5);
+    let tail = if (exponent - 1.).abs() < f64::EPSILON {
+        (to / from).ln()
+    } else {
+        (to.powf(1. - exponent) - from.powf(1. - exponent)) / (1. - exponent)
+    };
+    exact + tail
+}
+
+/// Rounds samples to the nearest multiple of `alignment`, if any.
+#[derive(Clone)]
+struct Aligned<D> {
+    distr: D,
+    alignment: Option<NonZeroUsize>,
+}
+
+impl<D: Distribution<f64>> Distribution<f64> for Aligned<D> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let sample = self.distr.sample(rng);
+        match self.alignment {
+            Some(alignment) => {
+                let alignment = alignment.get() as f64;
+                (sample / alignment).round() * alignment
+            }
+            None => sample,
+        }
+    }
+}
+
+/// Samples from a distribution, clamping samples below a floor to the floor.
+struct AtLeast<D> {
+    distr: D,
+    min: f64,
+}
+
+impl<D: Distribution<f64>> Distribution<f64> for AtLeast<D> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.distr.sample(rng).max(self.min)
+    }
+}
+
+/// Concentrates samples in a random subset of `hot_ratio` of all draws: the others become 0 while
+/// the hot ones are scaled up to keep the expected value unchanged.
+struct Clustered<D> {
+    distr: D,
+    hot_ratio: f64,
+}
+
+impl<D: Distribution<f64>> Distribution<f64> for Clustered<D> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let sample = self.distr.sample(rng);
+        if self.hot_ratio >= 1. {
+            // Don't touch the rng so that unclustered trees stay the same
+            sample
+        } else if rng.gen_range(0. ..1.) < self.hot_ratio {
+            sample / self.hot_ratio
+        } else {
+            0.
+        }
     }
 }
@@ -5,6 +5,8 @@
 #![feature(const_ptr_offset)]
 #![feature(const_mut_refs)]
 #![feature(const_intrinsic_copy)]
+#![feature(write_all_vectored)]
+#![feature(int_roundings)]
 
 pub mod generator;
 
@@ -1,14 +1,28 @@
 #![feature(string_remove_matches)]
 
-use std::{num::NonZeroUsize, path::PathBuf};
+use std::{
+    env, fs, io,
+    io::{BufWriter, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use clap::{Args, Parser, Subcommand, ValueHint};
 use clap_num::si_number;
 use clap_verbosity_flag::Verbosity;
 use cli_errors::{CliExitAnyhowWrapper, CliExitError, CliResult};
 
-use ftzz::generator::{Generator, GeneratorBuilder};
+#[cfg(feature = "syslog")]
+use ftzz::generator::Syslog;
+use ftzz::generator::{
+    diff_trees, ArchiveFormat, CreationOrder, DepthDistribution, ExplainTopic, FillMode, Generator,
+    GeneratorBuilder, Layout, MediaFormat, MtimeSequence, NameStyle, Profile, ProgressHook,
+    SequenceHeader, SizeDistribution, TreeDiff, DEFAULT_BLOCK_SIZE, DEFAULT_DIR_WORDLIST,
+    DEFAULT_FILE_WORDLIST,
+};
 
 /// A random file and directory generator
 #[derive(Parser, Debug)]
@@ -37,6 +51,23 @@ enum Cmd {
     /// By default, generated files are empty, but random data can be used as the file contents with
     /// the `total-bytes` option.
     Generate(Generate),
+    /// Explain how the generation parameters interact, with worked examples
+    ///
+    /// The examples are computed from the same formulas `generate` plans with, so the parameters
+    /// accepted here can be tweaked to preview the shape of a tree before generating it.
+    Explain(Explain),
+    /// Print a deterministic sample of the paths `generate` would create
+    ///
+    /// Given the same parameters (and seed) as a previous `generate` run, prints files evenly
+    /// spaced through the generation plan without touching the file system. Useful for spot
+    /// checking that a tree generated elsewhere contains the expected paths.
+    Sample(Sample),
+    /// Compare two trees by structure and contents
+    ///
+    /// Reports paths that only exist in one of the trees and files whose contents (or symlinks
+    /// whose targets) differ, exiting with an error if there are any. Useful for checking that a
+    /// tree was faithfully reproduced or repaired.
+    Diff(Diff),
 }
 
 #[derive(Args, Debug)]
@@ -52,13 +83,51 @@ struct Generate {
     /// Note: this value is probabilistically respected, meaning any number of files may be
     /// generated so long as we attempt to get close to N.
     #[clap(short = 'n', long = "files", alias = "num-files")]
+    #[clap(required_unless_present_any = & [
+        "size-schedule", "num-entries", "layout", "git-objects",
+    ])]
     #[clap(parse(try_from_str = num_files_parser))]
-    num_files: NonZeroUsize,
+    num_files: Option<NonZeroUsize>,
+
+    /// The total number of files and directories to generate
+    ///
+    /// The entries are split between files and directories according to the file to directory
+    /// ratio, directory fraction, or exact number of directories. Replaces the number of files.
+    #[clap(long = "entries", value_name = "N")]
+    #[clap(conflicts_with_all = & ["num-files", "size-schedule", "chain", "balanced"])]
+    #[clap(parse(try_from_str = num_files_parser))]
+    num_entries: Option<NonZeroUsize>,
 
     /// Whether or not to generate exactly N files
     #[clap(long = "files-exact")]
     files_exact: bool,
 
+    /// Generate at least N files
+    ///
+    /// Any shortfall left by the probabilistic generation is topped up in the root directory. This
+    /// is a cheaper guarantee than generating an exact number of files.
+    #[clap(long = "min-files")]
+    #[clap(parse(try_from_str = min_files_parser))]
+    min_files: Option<usize>,
+
+    /// Generate at least N files in every directory
+    ///
+    /// Useful for tools that assume directories are never empty. Since every directory is
+    /// topped up, the total number of files may end up well above the requested number.
+    #[clap(long = "min-files-per-dir")]
+    #[clap(conflicts_with_all = & ["files-exact", "exact", "dirs-exact", "chain"])]
+    #[clap(parse(try_from_str = min_files_parser))]
+    min_files_per_dir: Option<usize>,
+
+    /// Concentrate files in fewer directories, leaving the rest empty
+    ///
+    /// At 0, files are spread evenly across directories. Values approaching 1 place files in an
+    /// ever smaller (but deterministic) subset of the directories, modeling hotspots.
+    #[clap(long = "cluster-factor", value_name = "FACTOR")]
+    #[clap(default_value = "0")]
+    #[clap(parse(try_from_str = cluster_factor_parser))]
+    cluster_factor: f64,
+
     /// The total amount of random data to be distributed across the generated files
     ///
     /// Note: this value is probabilistically respected, meaning any amount of data may be
@@ -72,6 +141,108 @@ struct Generate {
     #[clap(long = "bytes-exact")]
     bytes_exact: bool,
 
+    /// Pick the number of bytes such that the tree takes up roughly this much space on disk
+    ///
+    /// Disk usage is estimated by rounding every file up to whole blocks and charging a block for
+    /// each directory, which is what `du` reports on most file systems. Replaces the number of
+    /// bytes.
+    #[clap(long = "target-disk-usage", value_name = "SIZE")]
+    #[clap(conflicts_with_all = & ["size-schedule", "content-source"])]
+    #[clap(parse(try_from_str = num_bytes_parser))]
+    target_disk_usage: Option<usize>,
+
+    /// The file system block size disk usage is estimated with
+    #[clap(long = "block-size", value_name = "SIZE")]
+    #[clap(default_value = "4096")]
+    #[clap(parse(try_from_str = block_size_parser))]
+    block_size: NonZeroUsize,
+
+    /// Stop generating once the tree takes up this much space on disk
+    ///
+    /// Counts whatever is already in the root directory, such as an overlay, and is estimated the
+    /// same way as the target disk usage. Generation halts gracefully once the next file or
+    /// directory wouldn't fit, which makes it possible to fill a volume up to a given level.
+    #[clap(long = "max-total-size", value_name = "SIZE")]
+    #[clap(conflicts_with_all = & ["self-check", "checkpoint", "names-from-content"])]
+    #[clap(parse(try_from_str = num_bytes_parser))]
+    max_total_size: Option<usize>,
+
+    /// How file sizes are spread around the average, given as `normal` or `zipf[,EXPONENT]`
+    ///
+    /// With `zipf`, file sizes fall off with their rank raised to the exponent (default: 1): a few
+    /// files are very large while most are tiny, as is typical of caches and databases.
+    #[clap(long = "size-distribution", value_name = "DISTRIBUTION")]
+    #[clap(parse(try_from_str = size_distribution_parser))]
+    size_distribution: Option<SizeDistribution>,
+
+    /// Round the size of every file to the nearest multiple of ALIGNMENT bytes
+    ///
+    /// Mimics files made up of whole blocks or pages, such as database files. Exact numbers of
+    /// bytes are still met by having a single file pick up whatever part of the total isn't a
+    /// multiple of the alignment.
+    #[clap(long = "size-alignment", value_name = "ALIGNMENT")]
+    #[clap(conflicts_with_all = & ["size-schedule", "archive", "media-mix"])]
+    #[clap(parse(try_from_str = size_alignment_parser))]
+    size_alignment: Option<NonZeroUsize>,
+
+    /// Create exactly one file for each size listed in FILE instead of sampling sizes
+    ///
+    /// Sizes are separated by commas or whitespace and may be wrapped in a JSON array (e.g.
+    /// `[0, 4K, 1M]`). Files are placed across the tree at random, but the generated sizes always
+    /// match the schedule exactly. Replaces the number of files and bytes.
+    #[clap(long = "size-schedule", value_name = "FILE")]
+    #[clap(value_hint = ValueHint::FilePath)]
+    #[clap(conflicts_with_all = & [
+        "num-files", "files-exact", "num-bytes", "bytes-exact", "exact", "chain",
+        "duplicate-groups",
+    ])]
+    size_schedule: Option<PathBuf>,
+
+    /// Create exactly the directories described in FILE instead of shaping the tree at random
+    ///
+    /// FILE holds a JSON object whose keys name directories. Objects describe subdirectories and
+    /// the `$files` key of one holds the number of files to create in it, while a bare number
+    /// stands for a directory with only that many files (e.g. `{"$files": 2, "src": {"bin": 3}}`).
+    /// Files are named and filled as usual. Replaces the number of files and directories.
+    #[clap(long = "layout", value_name = "FILE")]
+    #[clap(value_hint = ValueHint::FilePath)]
+    #[clap(conflicts_with_all = & [
+        "num-files", "num-entries", "files-exact", "exact", "dirs-exact", "size-schedule", "chain",
+        "balanced", "file-to-dir-ratio", "dir-fraction", "leaf-depth-distribution", "depth-jitter",
+        "max-dir-width", "min-files", "min-files-per-dir", "names-from-content",
+    ])]
+    layout: Option<PathBuf>,
+
+    /// Generate exactly N directories
+    ///
+    /// Unless a file to directory ratio is specified, files are spread evenly across the
+    /// directories. Can be combined with exact files to hit both targets.
+    #[clap(long = "dirs-exact")]
+    #[clap(parse(try_from_str = num_dirs_parser))]
+    dirs_exact: Option<usize>,
+
+    /// Generate at least N directories
+    ///
+    /// Any shortfall left by the probabilistic generation is topped up with empty directories in
+    /// the root directory rather than spread through the tree, for example to guarantee enough
+    /// directories for parallel traversals. They fit within any maximum depth and are a cheaper
+    /// guarantee than generating an exact number of directories.
+    #[clap(long = "min-dir-count", value_name = "N")]
+    #[clap(conflicts_with_all = & [
+        "dirs-exact", "chain", "balanced", "max-dir-width", "layout", "names-from-content",
+    ])]
+    #[clap(parse(try_from_str = num_dirs_parser))]
+    min_dirs: Option<usize>,
+
+    /// Additionally generate exactly N empty files scattered across the tree
+    ///
+    /// Useful for testing tools that special-case empty files while the others have contents.
+    /// Cannot be combined with special files.
+    #[clap(long = "empty-files", value_name = "N")]
+    #[clap(default_value = "0")]
+    #[clap(parse(try_from_str = min_files_parser))]
+    empty_files: usize,
+
     /// Whether or not to generate exactly N files and bytes
     #[clap(short = 'e', long = "exact")]
     #[clap(conflicts_with_all = & ["files-exact", "bytes-exact"])]
@@ -82,6 +253,47 @@ struct Generate {
     #[clap(default_value = "5")]
     max_depth: u32,
 
+    /// Count the root directory as the first level of `max-depth`
+    ///
+    /// By default only the directories below the root count toward the depth, so a maximum depth
+    /// of 1 generates one level of subdirectories. With this flag, it generates none. Useful for
+    /// matching tools that count the starting directory, like `find -maxdepth`.
+    #[clap(long = "depth-includes-root")]
+    depth_includes_root: bool,
+
+    /// Vary how deep each branch of the tree grows, given as MEAN or MEAN,CV
+    ///
+    /// Every subdirectory of the root directory samples the depth its branch grows to from a
+    /// normal distribution with the given mean and coefficient of variation (default: 0.2),
+    /// bounded by `max-depth`. This produces ragged trees where some branches end early.
+    #[clap(long = "dir-depth-distribution", alias = "leaf-depth-distribution")]
+    #[clap(value_name = "MEAN[,CV]")]
+    #[clap(conflicts_with = "chain")]
+    #[clap(parse(try_from_str = depth_distribution_parser))]
+    leaf_depth_distribution: Option<DepthDistribution>,
+
+    /// End each branch of the tree up to N levels short of `max-depth`
+    ///
+    /// Every subdirectory of the root directory picks how many levels its branch is cut short by
+    /// uniformly at random, making for a ragged tree that stays close to `max-depth`. A simpler
+    /// alternative to `dir-depth-distribution`.
+    #[clap(long = "depth-jitter", value_name = "N")]
+    #[clap(conflicts_with_all = & ["chain", "leaf-depth-distribution"])]
+    depth_jitter: Option<u32>,
+
+    /// Keep the top N levels of the tree free of files
+    ///
+    /// The root directory is level 0, so the directories less than N levels deep only hold
+    /// subdirectories and the files they would have held are pushed further down the tree. Useful
+    /// for mimicking trees that group their contents by category before holding any files.
+    #[clap(long = "structural-levels", value_name = "N")]
+    #[clap(conflicts_with_all = & [
+        "files-exact", "bytes-exact", "exact", "dirs-exact", "chain", "balanced", "layout",
+        "leaf-depth-distribution", "depth-jitter", "shrink-depth", "max-dir-width", "min-files",
+        "empty-files", "size-schedule", "content-source", "names-from-content",
+    ])]
+    structural_levels: Option<u32>,
+
     /// The number of files to generate per directory (default: files / 1000)
     ///
     /// Note: this value is probabilistically respected, meaning not all directories will have N
@@ -90,460 +302,4358 @@ struct Generate {
     #[clap(parse(try_from_str = file_to_dir_ratio_parser))]
     file_to_dir_ratio: Option<NonZeroUsize>,
 
-    /// Change the PRNG's starting seed
+    /// The fraction of all generated entries that should be directories, instead of a ratio
     ///
-    /// For example, you can use bash's `$RANDOM` function.
-    #[clap(long = "seed", alias = "entropy")]
-    #[clap(default_value = "0")]
-    seed: u64,
-}
-
-impl TryFrom<Generate> for Generator {
-    type Error = CliExitError;
+    /// For example, 0.2 makes one in five entries a directory, which works out to four files per
+    /// directory.
+    #[clap(long = "dir-fraction", value_name = "FRACTION")]
+    #[clap(conflicts_with_all = & ["file-to-dir-ratio", "chain", "dirs-exact"])]
+    #[clap(parse(try_from_str = dir_fraction_parser))]
+    dir_fraction: Option<f64>,
 
-    fn try_from(options: Generate) -> Result<Self, Self::Error> {
-        let mut builder = GeneratorBuilder::default();
-        builder
-            .root_dir(options.root_dir)
-            .num_files(options.num_files)
-            .files_exact(options.files_exact || options.exact)
-            .num_bytes(options.num_bytes)
-            .bytes_exact(options.bytes_exact || options.exact)
-            .max_depth(options.max_depth);
-        if let Some(ratio) = options.file_to_dir_ratio {
-            builder.file_to_dir_ratio(ratio);
-        }
-        builder
-            .seed(options.seed)
-            .build()
-            .context("Input validation failed")
-            .with_code(exitcode::DATAERR)
-    }
-}
+    /// Generate a single chain of directories with all files in the deepest one
+    ///
+    /// The chain is exactly `max-depth` directories long and holds exactly N files.
+    #[clap(long = "chain")]
+    #[clap(conflicts_with_all = & ["file-to-dir-ratio", "dirs-exact"])]
+    chain: bool,
 
-#[cfg(test)]
-mod generate_tests {
-    use rstest::rstest;
+    /// Generate a perfectly balanced tree where every directory above the leaves has exactly B
+    /// subdirectories
+    ///
+    /// The tree is just deep enough for no leaf to hold more than B files, overriding
+    /// `max-depth`, and the N files are spread evenly across the leaves. The shape of the tree
+    /// doesn't depend on the seed.
+    #[clap(long = "balanced", value_name = "B")]
+    #[clap(conflicts_with_all = & [
+        "chain", "file-to-dir-ratio", "dir-fraction", "dirs-exact", "leaf-depth-distribution",
+        "depth-jitter", "max-dir-width", "size-schedule", "min-files", "min-files-per-dir",
+        "content-source", "names-from-content",
+    ])]
+    #[clap(parse(try_from_str = fanout_parser))]
+    balanced: Option<usize>,
 
-    use super::*;
+    /// Reduce the maximum depth when there are too few directories to fill it
+    ///
+    /// Small numbers of files spread across a deep tree would otherwise produce chains of
+    /// directories that each contain a single subdirectory.
+    #[clap(long = "shrink-depth")]
+    #[clap(conflicts_with = "chain")]
+    shrink_depth: bool,
 
-    #[test]
-    fn params_are_mapped_correctly() {
-        let options = Generate {
-            root_dir: PathBuf::from("abc"),
-            num_files: NonZeroUsize::new(373).unwrap(),
-            num_bytes: 637,
-            max_depth: 43,
-            file_to_dir_ratio: Some(NonZeroUsize::new(37).unwrap()),
-            seed: 775,
-            files_exact: false,
-            bytes_exact: false,
-            exact: false,
-        };
+    /// Cap the number of entries (files and subdirectories) in any directory
+    ///
+    /// Wide directories degrade performance on some file systems. Files that don't fit are spilled
+    /// into the following directories, adding subdirectories to take them in where possible. Any
+    /// files still left over at the end are placed in a new directory in the root.
+    #[clap(long = "max-dir-width", value_name = "ENTRIES")]
+    #[clap(conflicts_with_all = & [
+        "bytes-exact", "exact", "dirs-exact", "chain", "min-files",
+    ])]
+    #[clap(parse(try_from_str = num_files_parser))]
+    max_dir_width: Option<NonZeroUsize>,
 
-        let generator = Generator::try_from(options).unwrap();
-        let hack = format!("{:?}", generator);
+    /// Whether or not to zero pad file names such that they sort lexicographically
+    ///
+    /// File names within a directory are padded to the width of the largest file name in that
+    /// directory, meaning lexicographic and numeric ordering agree.
+    #[clap(long = "lex-sortable")]
+    lex_sortable: bool,
 
-        assert!(hack.contains("root_dir: \"abc\""));
-        assert!(hack.contains("num_files: 373"));
-        assert!(hack.contains("num_bytes: 637"));
-        assert!(hack.contains("max_depth: 43"));
-        assert!(hack.contains("file_to_dir_ratio: 37"));
-        assert!(hack.contains("seed: 775"));
-    }
+    /// Whether or not to name files in ascending order of size
+    ///
+    /// The sizes of each directory's files are sampled up front and handed out from smallest to
+    /// largest, such that sorting a directory's (zero padded) file names also sorts its files by
+    /// size.
+    #[clap(long = "sort-by-size")]
+    #[clap(conflicts_with_all = & [
+        "files-exact", "bytes-exact", "exact", "dirs-exact", "min-files", "size-schedule",
+    ])]
+    sort_by_size: bool,
 
-    #[rstest]
-    fn files_exact_is_mapped_correctly(
-        #[values(false, true)] files_exact: bool,
-        #[values(false, true)] global_exact: bool,
-    ) {
-        let options = Generate {
-            files_exact,
-            exact: global_exact,
+    /// Whether or not to prefix names with a short hash to spread them across hash buckets
+    ///
+    /// Sequentially named entries can cluster in the buckets of hash-indexed directories, skewing
+    /// benchmarks. The prefix is derived from the seed and is thus reproducible.
+    #[clap(long = "name-hash-jitter")]
+    name_hash_jitter: bool,
 
-            root_dir: PathBuf::new(),
-            num_files: NonZeroUsize::new(1).unwrap(),
-            num_bytes: 0,
-            max_depth: 0,
-            file_to_dir_ratio: None,
-            seed: 0,
-            bytes_exact: false,
-        };
+    /// Preset names, sizes, and contents for a kind of tree: `source-repo`, `media-library`, or
+    /// `logs`
+    ///
+    /// `source-repo` creates code and project files of a few kilobytes each, `media-library`
+    /// creates albums of JPEG photos, and `logs` creates hourly rotated logs, a few of which are
+    /// very large. Unless `total-bytes` is given, it is derived from the number of files. Other
+    /// options override the presets.
+    #[clap(long = "profile", value_name = "NAME")]
+    #[clap(possible_values = & ["source-repo", "media-library", "logs"])]
+    profile: Option<Profile>,
 
-        let generator = Generator::try_from(options).unwrap();
-        let hack = format!("{:?}", generator);
+    /// The characters generated names are made of: `numeric`, `unicode-random`, or
+    /// `timestamped,START,STEP,FORMAT`
+    ///
+    /// `unicode-random` prefixes every name with random characters from a mix of scripts (derived
+    /// from the seed) to stress test file name handling. The characters are legal on all major
+    /// platforms and aren't altered by normalizing file systems.
+    ///
+    /// `timestamped` names the files in each directory after timestamps STEP seconds apart
+    /// starting at the Unix time START, formatted with a strftime FORMAT such as
+    /// `app-%Y-%m-%d.log`. This makes for realistic log rotation fixtures.
+    #[clap(long = "name-style", value_name = "STYLE")]
+    name_style: Option<NameStyle>,
 
-        assert!(hack.contains(&format!("files_exact: {}", files_exact || global_exact)));
-    }
+    /// Name files in pairs that only differ in case (e.g. `a0` and `A0`)
+    ///
+    /// Tests tools against trees that can't be checked out on case-insensitive file systems.
+    /// Generating into a case-insensitive file system is refused instead of overwriting files.
+    #[clap(long = "case-collisions")]
+    #[clap(conflicts_with_all = & [
+        "name-hash-jitter", "name-style", "dir-wordlist", "file-wordlist",
+    ])]
+    case_collisions: bool,
 
-    #[rstest]
-    fn bytes_exact_is_mapped_correctly(
-        #[values(false, true)] bytes_exact: bool,
-        #[values(false, true)] global_exact: bool,
-    ) {
-        let options = Generate {
-            bytes_exact,
-            exact: global_exact,
+    /// Name files after the SHA-256 of their contents in a `.git/objects` style layout
+    ///
+    /// The first two hex digits of each hash name a directory and the rest name the file within
+    /// it, producing a content-addressable tree. All other naming and tree shape options are
+    /// ignored. Files whose contents match an earlier file's are redrawn, but files too small to
+    /// have unique contents, such as empty files, are only created once.
+    #[clap(long = "deterministic-names-from-content")]
+    #[clap(conflicts_with_all = & [
+        "pattern", "text-header", "content-source", "duplicate-groups", "repair", "two-phase",
+        "checksums",
+    ])]
+    names_from_content: bool,
 
-            root_dir: PathBuf::new(),
-            num_files: NonZeroUsize::new(1).unwrap(),
-            num_bytes: 0,
-            max_depth: 0,
-            file_to_dir_ratio: None,
-            seed: 0,
-            files_exact: false,
-        };
+    /// Generate a `.git/objects` style store holding N blobs instead of a tree
+    ///
+    /// Blobs are named after the SHA-1 of their contents and sized like any other files. The most
+    /// recent quarter are loose objects stored under `xx/yyyy...` while the rest are packed with an
+    /// index in `pack/`. The objects are valid, so git tooling can read them back.
+    #[clap(long = "git-objects", value_name = "N")]
+    #[clap(conflicts_with_all = & [
+        "num-files", "num-entries", "size-schedule", "layout", "names-from-content", "pattern",
+        "text-header", "content-source", "duplicate-groups", "repair", "two-phase", "checksums",
+    ])]
+    #[clap(parse(try_from_str = num_files_parser))]
+    git_objects: Option<NonZeroUsize>,
 
-        let generator = Generator::try_from(options).unwrap();
-        let hack = format!("{:?}", generator);
+    /// Prefix this fraction of file and directory names with a dot to hide them
+    ///
+    /// Useful for testing tools that skip or otherwise treat dotfiles specially, such as `ls` and
+    /// shell globs. Names remain unique within each directory.
+    #[clap(long = "hidden-ratio", value_name = "RATIO")]
+    #[clap(default_value = "0")]
+    #[clap(parse(try_from_str = special_ratio_parser))]
+    hidden_ratio: f64,
 
-        assert!(hack.contains(&format!("bytes_exact: {}", bytes_exact || global_exact)));
-    }
-}
+    /// Name directories after the words in FILE (one per line) instead of numbers
+    ///
+    /// Without a FILE, a bundled list of common directory names is used. Once every word has been
+    /// used in a directory, numeric suffixes are appended to keep names unique.
+    #[clap(long = "dir-wordlist", value_name = "FILE", require_equals = true)]
+    #[clap(value_hint = ValueHint::FilePath)]
+    dir_wordlist: Option<Option<PathBuf>>,
 
-#[cli_errors::main]
-fn main() -> CliResult<()> {
-    let args = Ftzz::parse();
+    /// Name files after the words in FILE (one per line) instead of numbers
+    ///
+    /// Without a FILE, a bundled list of common file names is used. Once every word has been used
+    /// in a directory, numeric suffixes are appended to keep names unique.
+    #[clap(long = "file-wordlist", value_name = "FILE", require_equals = true)]
+    #[clap(value_hint = ValueHint::FilePath)]
+    file_wordlist: Option<Option<PathBuf>>,
 
-    #[cfg(not(feature = "trace"))]
-    simple_logger::init_with_level(args.verbose.log_level().unwrap()).unwrap();
-    #[cfg(feature = "trace")]
-    let _guard = {
-        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+    /// Split files into N groups where files within a group have identical contents
+    ///
+    /// Useful for testing deduplication. Requires `total-bytes` and cannot be combined with exact
+    /// bytes.
+    #[clap(long = "dup-groups", alias = "duplicate-groups")]
+    #[clap(parse(try_from_str = duplicate_groups_parser))]
+    duplicate_groups: Option<NonZeroUsize>,
 
-        let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
-            .include_args(true)
-            .build();
-        tracing_subscriber::registry().with(chrome_layer).init();
-        guard
-    };
+    /// Fill files by repeating PATTERN instead of with random data
+    ///
+    /// The pattern is either hex prefixed with `0x` (e.g. `0xDEADBEEF`) or a plain string (e.g.
+    /// `test`). Useful for controlling how compressible the generated files are. File sizes are
+    /// the same as they would be with random data.
+    #[clap(long = "pattern", value_name = "PATTERN")]
+    #[clap(parse(try_from_str = pattern_parser))]
+    pattern: Option<FillMode>,
 
-    match args.cmd {
-        Cmd::Generate(options) => Generator::try_from(options)?.generate(),
-    }
-}
+    /// Wrap the random contents of every file in a tar or zip archive
+    ///
+    /// Given as `FORMAT[,ENTRIES]` (e.g. `zip,3`) where each file's contents are split across
+    /// ENTRIES entries (1 by default). Zip entries are stored uncompressed. File sizes are those of
+    /// the contents rather than of the archives.
+    #[clap(long = "archive", value_name = "FORMAT[,ENTRIES]")]
+    #[clap(conflicts_with_all = & ["pattern", "text-header", "content-source"])]
+    #[clap(parse(try_from_str = archive_parser))]
+    archive: Option<FillMode>,
 
-fn num_files_parser(s: &str) -> Result<NonZeroUsize, String> {
-    let files = lenient_si_number(s)?;
-    if files > 0 {
-        Ok(unsafe { NonZeroUsize::new_unchecked(files) })
-    } else {
-        Err(String::from("At least one file must be generated."))
-    }
-}
+    /// Keep only this fraction (0 to 1) of every file's contents random and zero the rest
+    ///
+    /// 0 produces entirely compressible files while 1 produces incompressible random ones, with
+    /// the compression ratio shrinking roughly in proportion in between. Useful for compression
+    /// ratio experiments. File sizes are the same as they would be with random data.
+    #[clap(long = "content-entropy", value_name = "ENTROPY")]
+    #[clap(conflicts_with_all = & ["pattern", "archive", "content-source"])]
+    #[clap(parse(try_from_str = entropy_parser))]
+    content_entropy: Option<FillMode>,
 
-fn num_bytes_parser(s: &str) -> Result<usize, String> {
-    lenient_si_number(s)
-}
+    /// Nest archives N levels deep, each one being the only entry of the next
+    ///
+    /// The innermost archive holds the file's contents, producing compact files that expand
+    /// deeply when unpacked recursively. Useful for testing how tools guard against archive bombs.
+    #[clap(long = "nesting-depth", value_name = "N")]
+    #[clap(requires = "archive")]
+    nesting_depth: Option<usize>,
 
-fn file_to_dir_ratio_parser(s: &str) -> Result<NonZeroUsize, String> {
-    let ratio = lenient_si_number(s)?;
-    if ratio > 0 {
-        Ok(unsafe { NonZeroUsize::new_unchecked(ratio) })
-    } else {
-        Err(String::from("Cannot have no files per directory."))
-    }
-}
+    /// Overwrite the first bytes of every file with HEADER, such as a byte order mark
+    ///
+    /// The header is either a preset (`utf8-bom` or `utf16le-bom`), hex prefixed with `0x`, or a
+    /// plain string. Useful for testing encoding detection. The header is not prepended: files keep
+    /// their generated sizes with the header in place of their first bytes, so files smaller than
+    /// the header only hold its beginning.
+    #[clap(long = "text-header", value_name = "HEADER")]
+    #[clap(conflicts_with = "content-source")]
+    #[clap(parse(try_from_str = text_header_parser))]
+    text_header: Option<Vec<u8>>,
 
-fn lenient_si_number(s: &str) -> Result<usize, String> {
-    let mut s = s.replace('K', "k");
-    s.remove_matches(",");
-    s.remove_matches("_");
-    si_number(&s)
-}
+    /// Start every file's contents with its sequence number in the tree
+    ///
+    /// Files are numbered from 0 in the order they're created, the same on every run with the same
+    /// seed, so consumers can check that they processed every file in order. With `text`, the
+    /// number is written in decimal followed by a newline while `u64le` writes it as a
+    /// little-endian 64-bit integer. Like text headers, sequence numbers replace the first bytes
+    /// of each file, but files smaller than the header are padded up to its length such that every
+    /// number can be read back in full. Hence, exact byte counts aren't supported.
+    #[clap(long = "sequence-header", value_name = "FORMAT")]
+    #[clap(possible_values = & ["text", "u64le"])]
+    #[clap(conflicts_with_all = & [
+        "text-header", "archive", "content-source", "duplicate-groups", "sparse-ratio",
+        "names-from-content", "bytes-exact", "exact", "size-schedule", "empty-files",
+    ])]
+    sequence_header: Option<SequenceHeader>,
 
-#[cfg(test)]
-mod cli_tests {
-    use clap::IntoApp;
+    /// Create this fraction of files sparse instead of writing their contents
+    ///
+    /// Sparse files are extended to their size without writing anything, so they take up little
+    /// to no space on disk. Useful for testing tools that compare allocated and logical sizes.
+    #[clap(long = "sparse-ratio", value_name = "RATIO")]
+    #[clap(default_value = "0")]
+    #[clap(parse(try_from_str = special_ratio_parser))]
+    sparse_ratio: f64,
 
-    use super::*;
+    /// Leave holes between blocks of data in sparse files instead of leaving them entirely empty
+    ///
+    /// Blocks of 64 KiB are randomly written or skipped, except for the first and last blocks which
+    /// always hold data. Files must thus be larger than two blocks to get holes. Useful for testing
+    /// tools that walk data and hole regions with `SEEK_DATA` and `SEEK_HOLE`.
+    #[clap(long = "sparse-holes")]
+    sparse_holes: bool,
 
-    #[test]
-    fn verify_app() {
-        Ftzz::command().debug_assert();
-    }
-}
+    /// Reserve the full size of every file on disk before writing its contents
+    ///
+    /// Files are allocated with `posix_fallocate` such that they're as contiguous as the file
+    /// system allows and can't run out of space halfway through being written. Useful for write
+    /// performance tests. Platforms other than Linux fall back to plain writes.
+    #[clap(long = "preallocate")]
+    #[clap(conflicts_with_all = & ["sparse-ratio", "archive", "media-mix", "names-from-content"])]
+    preallocate: bool,
 
-#[cfg(test)]
-mod cli_generate_tests {
-    use clap::{
-        ErrorKind::{
-            ArgumentConflict, DisplayHelpOnMissingArgumentOrSubcommand, MissingRequiredArgument,
-            UnknownArgument,
-        },
-        FromArgMatches, IntoApp,
-    };
+    /// Make this fraction of files read-only once they've been written
+    ///
+    /// Useful for testing how tools handle permission errors. Note that read-only files can still
+    /// be deleted, and that root ignores permissions altogether.
+    #[clap(long = "read-only-ratio", value_name = "RATIO")]
+    #[clap(default_value = "0")]
+    #[clap(parse(try_from_str = special_ratio_parser))]
+    read_only_ratio: f64,
 
-    use super::*;
+    /// Also make `read-only-ratio` of the directories read-only once the tree is complete
+    ///
+    /// Nothing can be created in or deleted from read-only directories, so the tree has to be made
+    /// writable again before it can be removed or overwritten.
+    #[clap(long = "read-only-dirs")]
+    read_only_dirs: bool,
 
-    macro_rules! expect_error {
-        ($args:expr, $error:expr) => {
-            let f = Ftzz::try_parse_from($args);
+    /// Give this fraction of files an extended attribute holding a random value
+    ///
+    /// The attribute is named `user.ftzz`, or is an alternate data stream of that name on Windows.
+    /// Useful for stressing tools that copy or archive metadata. The file system must support
+    /// extended attributes.
+    #[clap(long = "xattr-ratio", value_name = "RATIO")]
+    #[clap(default_value = "0")]
+    #[clap(conflicts_with_all = & ["names-from-content", "git-objects"])]
+    #[clap(parse(try_from_str = special_ratio_parser))]
+    xattr_ratio: f64,
 
-            assert!(f.is_err());
-            assert_eq!(f.unwrap_err().kind(), $error);
-        };
-    }
+    /// Create files as minimal but valid media files of these formats
+    ///
+    /// Formats are separated by commas (e.g. `png,jpeg,wav,mp3`) and each file picks one of them,
+    /// so listing a format several times makes it more common. The random contents are stored
+    /// where decoders ignore them, so files pass type sniffing by their magic bytes and headers.
+    /// File sizes are those of the contents rather than of the media files.
+    #[clap(long = "media-mix", value_name = "FORMATS")]
+    #[clap(use_value_delimiter = true)]
+    #[clap(possible_values = & ["png", "jpeg", "jpg", "wav", "mp3"])]
+    #[clap(conflicts_with_all = & ["archive", "content-source", "names-from-content"])]
+    media_mix: Vec<MediaFormat>,
 
-    macro_rules! expect_success {
-        ($args:expr) => {{
-            let m = Ftzz::command().get_matches_from($args);
-            <Generate as FromArgMatches>::from_arg_matches(
-                m.subcommand_matches("generate").unwrap(),
-            )
-            .unwrap()
-        }};
-    }
+    /// Put the files of each media format in their own directory at the top of the tree
+    ///
+    /// Each format of the media mix gets a directory named after its extension (e.g. `png/` or
+    /// `jpg/`) holding a tree of only that format's files, so the files of one type never mix with
+    /// those of another. The files and bytes are split between the directories in proportion to
+    /// how often their format appears in the mix.
+    #[clap(long = "group-by-extension")]
+    #[clap(requires = "media-mix")]
+    #[clap(conflicts_with_all = & [
+        "size-schedule", "target-disk-usage", "dirs-exact", "layout", "min-files", "min-dirs",
+        "empty-files", "seed-paths", "overlay-source", "checkpoint", "dir-seeds",
+    ])]
+    group_by_extension: bool,
 
-    #[test]
-    fn empty_args_displays_help() {
-        expect_error!(
-            Vec::<String>::new(),
-            DisplayHelpOnMissingArgumentOrSubcommand
-        );
-    }
+    /// Fill files with copies of the files found in DIR instead of with random data
+    ///
+    /// Each file is a copy of a randomly chosen file from DIR (searched recursively). With
+    /// `total-bytes`, copies are truncated or repeated to match the usual file sizes. Otherwise,
+    /// files are copied as is. Every file in DIR is loaded into memory.
+    #[clap(long = "content-source", value_name = "DIR")]
+    #[clap(value_hint = ValueHint::DirPath)]
+    #[clap(conflicts_with_all = & [
+        "pattern", "duplicate-groups", "bytes-exact", "size-schedule", "chain",
+    ])]
+    content_source: Option<PathBuf>,
+
+    /// Create this fraction of files as FIFOs instead (Linux only)
+    ///
+    /// Useful for testing tools that must skip or otherwise handle special files. FIFOs are never
+    /// opened, so generation cannot block on them. Cannot be combined with exact bytes.
+    #[clap(long = "special-ratio", value_name = "RATIO")]
+    #[clap(default_value = "0")]
+    #[clap(parse(try_from_str = special_ratio_parser))]
+    special_ratio: f64,
+
+    /// The order in which the entries of each directory are created
+    ///
+    /// Some file systems allocate space differently depending on whether a directory's files or
+    /// its subdirectories come first, which matters when benchmarking. The generated tree is the
+    /// same regardless of the order.
+    #[clap(long = "creation-order")]
+    #[clap(possible_values = & ["dirs-first", "files-first", "interleaved"])]
+    #[clap(default_value = "dirs-first")]
+    creation_order: CreationOrder,
+
+    /// Change the PRNG's starting seed
+    ///
+    /// For example, you can use bash's `$RANDOM` function.
+    #[clap(long = "seed", alias = "entropy")]
+    #[clap(default_value = "0")]
+    seed: u64,
+
+    /// Change the PRNG's starting seed with a memorable phrase
+    ///
+    /// The phrase is hashed and combined with the numeric seed, so the same phrase always produces
+    /// the same tree.
+    #[clap(long = "entropy-phrase", alias = "seed-phrase")]
+    entropy_phrase: Option<String>,
+
+    /// Seed the shape of the directory tree separately from everything else
+    ///
+    /// Keeps the directory skeleton fixed while `content-seed` varies the files, for example to
+    /// benchmark changes with the tree structure as a controlled variable. Defaults to `seed`.
+    #[clap(long = "structure-seed")]
+    structure_seed: Option<u64>,
+
+    /// Seed the placement and contents of files separately from the directory tree
+    ///
+    /// See `structure-seed`. Defaults to `seed`.
+    #[clap(long = "content-seed")]
+    content_seed: Option<u64>,
+
+    /// Write the seed the tree was generated with to a file once generation succeeds
+    ///
+    /// The seed phrase is mixed into the written seed, so passing the file to `read-seed`
+    /// reproduces the same tree in later runs without having to parse any output.
+    #[clap(long = "write-seed", value_name = "FILE")]
+    write_seed: Option<PathBuf>,
+
+    /// Read the seed from a file written by `write-seed`, replacing `seed`
+    #[clap(long = "read-seed", value_name = "FILE")]
+    #[clap(conflicts_with_all = & ["seed", "entropy-phrase"])]
+    read_seed: Option<PathBuf>,
+
+    /// Recreate missing files and directories in an existing tree
+    ///
+    /// The same parameters used to generate the tree must be supplied. Existing files are left
+    /// untouched and only absent paths are created, restoring the tree to its original state.
+    #[clap(long = "repair")]
+    repair: bool,
+
+    /// Only repair the directory at this path relative to the root, leaving the rest of the tree
+    ///
+    /// Its missing files are regenerated from the seed and number of files the marker file records
+    /// for it, so the rest of the tree isn't replayed. The tree must have been generated with
+    /// `dir-seeds`.
+    #[clap(long = "repair-dir", value_name = "DIR")]
+    #[clap(requires_all = & ["repair", "dir-seeds"])]
+    repair_dir: Option<PathBuf>,
+
+    /// Delete the contents of the root directory before generating
+    ///
+    /// Refuses to touch the file system root or your home directory unless `force-dangerous` is
+    /// also specified.
+    #[clap(long = "overwrite")]
+    #[clap(conflicts_with = "repair")]
+    overwrite: bool,
+
+    /// Allow overwriting dangerous root directories
+    #[clap(long = "force-dangerous")]
+    #[clap(requires = "overwrite")]
+    force_dangerous: bool,
+
+    /// Allow runs that would create more than a billion files and directories
+    ///
+    /// The limit guards against typos filling up the disk.
+    #[clap(long = "no-limit")]
+    no_limit: bool,
+
+    /// Verify that the generated tree matches the requested exact counts
+    ///
+    /// Walks the tree after generation and fails if the number of files, directories, or bytes
+    /// doesn't match what the exact options promised.
+    #[clap(long = "self-check")]
+    self_check: bool,
+
+    /// Record how the tree was generated in a `.ftzz-manifest.toml` file at its root
+    ///
+    /// The marker contains the ftzz version, the time of generation, and the fully resolved
+    /// configuration (including the seed) for later inspection.
+    #[clap(long = "write-marker")]
+    write_marker: bool,
+
+    /// Seed the contents of each directory from its path and record the seeds in the marker file
+    ///
+    /// Each directory's files no longer depend on the rest of the tree, so a single corrupted
+    /// directory can be regenerated on its own with `repair-dir`.
+    #[clap(long = "dir-seeds")]
+    #[clap(requires = "write-marker")]
+    dir_seeds: bool,
+
+    /// Create entries under this umask, given in octal, instead of the inherited one
+    ///
+    /// The umask is restored once generation finishes, making file modes reproducible regardless
+    /// of the shell ftzz was started from.
+    #[clap(long = "umask", value_name = "OCTAL")]
+    #[clap(parse(try_from_str = umask_parser))]
+    umask: Option<u32>,
+
+    /// Print a shell script that creates the tree instead of generating it
+    ///
+    /// The script consists of `mkdir` and `touch` commands (files with contents are filled from
+    /// `/dev/urandom`) which can be reviewed or version controlled.
+    #[clap(long = "emit-script")]
+    #[clap(conflicts_with_all = & ["repair", "overwrite", "self-check"])]
+    emit_script: bool,
+
+    /// Print the fully resolved configuration as JSON before generating
+    ///
+    /// Includes derived values such as the average number of files and directories per directory.
+    /// Useful for understanding why a tree came out the way it did.
+    #[clap(long = "print-config")]
+    print_config: bool,
+
+    /// Check the parameters and print OK instead of generating
+    ///
+    /// Only the checks that don't need the file system are run, so the root directory is neither
+    /// created nor inspected.
+    #[clap(long = "validate-only")]
+    #[clap(conflicts_with = "emit-script")]
+    validate_only: bool,
+
+    /// Set the access and modification times of every generated entry to this Unix timestamp
+    ///
+    /// Makes trees metadata-identical across runs, for example to build reproducible archives with
+    /// `$SOURCE_DATE_EPOCH`.
+    #[clap(long = "mtime")]
+    mtime: Option<u64>,
+
+    /// Give files modification times INTERVAL seconds apart starting at the Unix timestamp START
+    ///
+    /// Times increase with each file in the order their directories are queued, regardless of
+    /// when they're actually written, so the files newer than any cutoff are a predictable subset
+    /// of the tree, as an incremental backup would select them. The times are the same on every run
+    /// with the same seed. Formatted as `START,INTERVAL`.
+    #[clap(long = "mtime-sequence", value_name = "START,INTERVAL")]
+    #[clap(parse(try_from_str = mtime_sequence_parser))]
+    #[clap(conflicts_with_all = & ["mtime", "names-from-content"])]
+    mtime_sequence: Option<MtimeSequence>,
+
+    /// Write the SHA-256 of every generated file to FILE in `sha256sum` format
+    ///
+    /// Paths are relative to the root directory, so the tree can later be verified by running
+    /// `sha256sum -c FILE` from within it. FIFOs are left out.
+    #[clap(long = "checksums", value_name = "FILE")]
+    #[clap(value_hint = ValueHint::FilePath)]
+    #[clap(conflicts_with_all = & ["repair", "emit-script"])]
+    checksums: Option<PathBuf>,
+
+    /// Write a CSV catalog of every generated file to FILE
+    ///
+    /// Each row holds a file's path relative to the root directory, its type (`file`, `fifo`, or
+    /// `symlink`), and its size in bytes under a `path,type,bytes` header, such that data pipelines
+    /// can load the tree's listing straight into a dataframe. Directories are left out. The tree is
+    /// listed once generation finishes, so the catalog only holds files that were actually
+    /// created, along with any copied from an overlay source.
+    #[clap(long = "catalog", value_name = "FILE")]
+    #[clap(value_hint = ValueHint::FilePath)]
+    #[clap(conflicts_with_all = & ["validate-only", "emit-script"])]
+    catalog: Option<PathBuf>,
+
+    /// Periodically record progress in FILE such that an interrupted run can pick up where it
+    /// left off
+    ///
+    /// Restarting with the same options and checkpoint skips the work that was already done rather
+    /// than requiring an empty root directory. The checkpoint is deleted once the tree is complete.
+    #[clap(long = "checkpoint", value_name = "FILE")]
+    #[clap(value_hint = ValueHint::FilePath)]
+    #[clap(conflicts_with_all = & ["repair", "overwrite", "two-phase", "checksums", "emit-script"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Record the number of files and directories sampled for every directory in FILE
+    ///
+    /// Each line is a JSON object holding the directory relative to the root, the sampled counts,
+    /// and the averages they were sampled around. Meant for debugging how trees are shaped.
+    #[clap(long = "dump-samples", value_name = "FILE", hide = true)]
+    #[clap(value_hint = ValueHint::FilePath)]
+    #[clap(conflicts_with_all = & ["two-phase", "names-from-content"])]
+    dump_samples: Option<PathBuf>,
+
+    /// Whether or not to create entries relative to open directory handles (Unix only)
+    ///
+    /// Avoids resolving the full path of every file which speeds up generation of deep trees.
+    /// The generated tree is identical either way.
+    #[clap(long = "openat")]
+    openat: bool,
+
+    /// Create directories at least DEPTH levels deep in the task of the subtree they belong to
+    ///
+    /// By default every directory is populated in a task of its own which adds scheduling overhead
+    /// for deep trees full of tiny directories. The generated tree is identical either way.
+    #[clap(long = "task-granularity", value_name = "DEPTH")]
+    #[clap(conflicts_with = "checkpoint")]
+    task_granularity: Option<u32>,
+
+    /// Log entries that fail to be created and keep going instead of aborting
+    ///
+    /// Useful for populating flaky file systems on a best-effort basis. The run still fails at the
+    /// end if any entry could not be created.
+    #[clap(long = "continue-on-error")]
+    continue_on_error: bool,
+
+    /// Create at most this many files and directories per second
+    ///
+    /// Spreads creations out evenly to avoid saturating shared storage.
+    #[clap(long = "rate-limit", value_name = "ENTRIES_PER_SEC")]
+    #[clap(parse(try_from_str = num_files_parser))]
+    rate_limit: Option<NonZeroUsize>,
+
+    /// Write at most this many bytes per second across all files
+    ///
+    /// Caps disk write bandwidth when generating large files to stay within the budget of shared
+    /// storage. Can be combined with `rate-limit`.
+    #[clap(long = "byte-rate", value_name = "BYTES_PER_SEC")]
+    #[clap(parse(try_from_str = byte_rate_parser))]
+    byte_rate: Option<NonZeroUsize>,
+
+    /// Buffer at most this many bytes at once across all concurrent file writes
+    ///
+    /// Files wait for their share of the budget before being written, keeping memory usage in
+    /// check when many large files are written at once. Files larger than the budget are written
+    /// one at a time.
+    #[clap(long = "write-memory-budget", value_name = "BYTES")]
+    #[clap(parse(try_from_str = write_memory_budget_parser))]
+    write_memory_budget: Option<NonZeroUsize>,
+
+    /// Gradually raise the number of directories populated at once over DURATION, e.g. `30s`
+    ///
+    /// Starts with a single task and allows more as the period goes by until every thread is busy,
+    /// avoiding the latency spikes of hitting cold storage with thousands of creations at once.
+    /// DURATION is a number of seconds optionally suffixed by `ms`, `s`, or `m`.
+    #[clap(long = "ramp-up", value_name = "DURATION")]
+    #[clap(conflicts_with_all = & ["names-from-content", "git-objects", "layout"])]
+    #[clap(parse(try_from_str = ramp_up_parser))]
+    ramp_up: Option<Duration>,
+
+    /// Pin the threads files are created on to the cores in CPULIST, e.g. `0-3,6`
+    ///
+    /// Keeps generation from interfering with other work on shared machines. One thread is
+    /// started per listed core.
+    #[clap(long = "cpu-affinity", value_name = "CPULIST")]
+    #[clap(conflicts_with = "names-from-content")]
+    cpu_affinity: Option<CpuList>,
+
+    /// Create every directory before any file and time both phases separately
+    ///
+    /// Useful for benchmarking file creation in isolation. The generated tree is identical either
+    /// way.
+    #[clap(long = "two-phase")]
+    two_phase: bool,
+
+    /// Create these paths before generating the rest of the tree around them
+    ///
+    /// Paths are relative to the root directory and separated by commas (e.g. `.git/,src/main.rs`).
+    /// Paths ending in a slash become directories and the others empty files. Useful for fixtures
+    /// that need landmark paths for tools to detect them.
+    #[clap(long = "seed-paths", value_name = "PATHS")]
+    #[clap(use_value_delimiter = true)]
+    seed_paths: Vec<String>,
+
+    /// Copy this directory into the root directory before generating around its contents
+    ///
+    /// The files and bytes are split evenly between the root and each directory at the top of the
+    /// source, with every share generated into its directory next to the copied entries. Fails
+    /// before copying anything if a copied entry shares its name with a generated one. Only the
+    /// synthetic entries are counted in the summary. Useful for making a real project look bigger.
+    #[clap(long = "overlay-source", value_name = "DIR")]
+    #[clap(value_hint = ValueHint::DirPath)]
+    #[clap(conflicts_with_all = & [
+        "repair", "self-check", "size-schedule", "target-disk-usage", "dirs-exact", "layout",
+        "names-from-content", "git-objects",
+    ])]
+    overlay_source: Option<PathBuf>,
+
+    /// Report progress to stderr in a machine readable format
+    ///
+    /// With `jsonl`, each progress tick is written as a line of JSON with the number of files,
+    /// directories, and bytes generated so far along with the elapsed milliseconds.
+    #[clap(long = "progress-format")]
+    #[clap(possible_values = & ["jsonl"])]
+    progress_format: Option<ProgressFormat>,
+
+    /// Add the kernel's write counters to each progress tick
+    ///
+    /// Each tick gains an `io` object with the bytes passed to write calls, the number of write
+    /// calls, and the bytes sent to storage since generation started, as read from
+    /// `/proc/self/io`. Comparing them with the bytes generated shows how much I/O creating many
+    /// tiny files really costs.
+    #[cfg(target_os = "linux")]
+    #[clap(long = "io-report")]
+    #[clap(requires = "progress-format")]
+    io_report: bool,
+
+    /// Where to additionally send the completion summary and errors
+    ///
+    /// The summary is always printed to stdout. With `syslog`, it is also sent to the system log
+    /// (and thus journald) under the user facility. Requires the `syslog` feature.
+    #[clap(long = "log-target")]
+    #[clap(possible_values = & ["stdout", "syslog"])]
+    #[clap(default_value = "stdout")]
+    log_target: LogTarget,
+
+    /// When to highlight the counts in the human readable output with colors
+    ///
+    /// With `auto` (the default), colors are used when stdout is a terminal and the `NO_COLOR`
+    /// environment variable isn't set. Machine readable output is never colored.
+    #[clap(long = "color", value_name = "WHEN")]
+    #[clap(possible_values = & ["auto", "always", "never"])]
+    color: Option<ColorChoice>,
+
+    /// Never use colors, same as `--color never`
+    #[clap(long = "no-color")]
+    #[clap(conflicts_with = "color")]
+    no_color: bool,
+}
+
+#[derive(Args, Debug)]
+struct Explain {
+    /// The topic to explain
+    #[clap(possible_values = & ["ratio", "depth", "bytes"])]
+    topic: ExplainTopic,
+
+    /// The number of files to use in the example
+    #[clap(short = 'n', long = "files", alias = "num-files")]
+    #[clap(parse(try_from_str = num_files_parser))]
+    #[clap(default_value = "10000")]
+    num_files: NonZeroUsize,
+
+    /// The total amount of random data to use in the example
+    #[clap(short = 'b', long = "total-bytes", aliases = & ["num-bytes", "num-total-bytes"])]
+    #[clap(parse(try_from_str = num_bytes_parser))]
+    #[clap(default_value = "0")]
+    num_bytes: usize,
+
+    /// The maximum directory tree depth to use in the example
+    #[clap(short = 'd', long = "max-depth", alias = "depth")]
+    #[clap(default_value = "5")]
+    max_depth: u32,
+
+    /// The number of files per directory to use in the example (default: files / 1000)
+    #[clap(short = 'r', long = "ftd-ratio")]
+    #[clap(parse(try_from_str = file_to_dir_ratio_parser))]
+    file_to_dir_ratio: Option<NonZeroUsize>,
+}
+
+#[derive(Args, Debug)]
+struct Sample {
+    #[clap(flatten)]
+    options: Generate,
+
+    /// The number of paths to print
+    #[clap(long = "count", value_name = "N")]
+    #[clap(default_value = "10")]
+    count: NonZeroUsize,
+}
+
+#[derive(Args, Debug)]
+struct Diff {
+    /// The original tree
+    #[clap(value_hint = ValueHint::DirPath)]
+    left: PathBuf,
+
+    /// The tree to compare against the original
+    #[clap(value_hint = ValueHint::DirPath)]
+    right: PathBuf,
+}
+
+impl TryFrom<Explain> for Generator {
+    type Error = CliExitError;
+
+    fn try_from(options: Explain) -> Result<Self, Self::Error> {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(PathBuf::new())
+            .num_files(options.num_files)
+            .num_bytes(options.num_bytes)
+            .max_depth(options.max_depth);
+        if let Some(ratio) = options.file_to_dir_ratio {
+            builder.file_to_dir_ratio(ratio);
+        }
+        builder
+            .build()
+            .context("Input validation failed")
+            .with_code(exitcode::DATAERR)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LogTarget {
+    Stdout,
+    Syslog,
+}
+
+impl FromStr for LogTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(Self::Stdout),
+            "syslog" => Ok(Self::Syslog),
+            _ => Err(format!("Unknown log target: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            Self::Auto => {
+                env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+                    && supports_color::on(supports_color::Stream::Stdout).is_some()
+            }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!("Unknown color choice: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ProgressFormat {
+    Jsonl,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(format!("Unknown progress format: {}", s)),
+        }
+    }
+}
+
+/// A Linux style list of CPU cores and inclusive ranges thereof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CpuList(Vec<usize>);
+
+impl FromStr for CpuList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |core: &str| {
+            core.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("Invalid CPU core {:?}: {}", core, e))
+        };
+
+        let mut cores = Vec::new();
+        for range in s.split(',') {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start > end {
+                return Err(format!("The CPU range {:?} is backwards.", range));
+            }
+            cores.extend(start..=end);
+        }
+        cores.sort_unstable();
+        cores.dedup();
+        Ok(Self(cores))
+    }
+}
+
+impl TryFrom<Generate> for Generator {
+    type Error = CliExitError;
+
+    fn try_from(options: Generate) -> Result<Self, Self::Error> {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(options.root_dir)
+            .files_exact(options.files_exact || options.exact)
+            .bytes_exact(options.bytes_exact || options.exact)
+            .max_depth(options.max_depth)
+            .depth_includes_root(options.depth_includes_root);
+        if let Some(num_files) = options.num_files {
+            builder.num_files(num_files);
+        }
+        if let Some(entries) = options.num_entries {
+            builder.num_entries(entries);
+        }
+        if let Some(path) = options.size_schedule {
+            builder.size_schedule(read_size_schedule(&path)?);
+        }
+        if let Some(path) = options.layout {
+            builder.layout(read_layout(&path)?);
+        }
+        if let Some(target) = options.target_disk_usage {
+            builder.target_disk_usage(target);
+        }
+        if let Some(size) = options.max_total_size {
+            builder.max_total_size(size);
+        }
+        if let Some(profile) = options.profile {
+            builder.profile(profile);
+        }
+        // Leave the profile's number of bytes alone unless one was given
+        if options.profile.is_none() || options.num_bytes > 0 {
+            builder.num_bytes(options.num_bytes);
+        }
+        if let Some(distr) = options.size_distribution {
+            builder.size_distribution(distr);
+        }
+        if let Some(alignment) = options.size_alignment {
+            builder.size_alignment(alignment);
+        }
+        if let Some(style) = options.name_style {
+            builder.name_style(style);
+        }
+        if let Some(dirs) = options.dirs_exact {
+            builder.dirs_exact(dirs);
+        }
+        if let Some(min_files) = options.min_files {
+            builder.min_files(min_files);
+        }
+        if let Some(min_dirs) = options.min_dirs {
+            builder.min_dirs(min_dirs);
+        }
+        if let Some(min_files) = options.min_files_per_dir {
+            builder.min_files_per_dir(min_files);
+        }
+        if let Some(distr) = options.leaf_depth_distribution {
+            builder.leaf_depth_distribution(distr);
+        }
+        if let Some(jitter) = options.depth_jitter {
+            builder.depth_jitter(jitter);
+        }
+        if let Some(levels) = options.structural_levels {
+            builder.structural_levels(levels);
+        }
+        if let Some(ratio) = options.file_to_dir_ratio {
+            builder.file_to_dir_ratio(ratio);
+        }
+        if let Some(fraction) = options.dir_fraction {
+            builder.dir_fraction(fraction);
+        }
+        if let Some(fanout) = options.balanced {
+            builder.balanced_fanout(fanout);
+        }
+        if let Some(groups) = options.duplicate_groups {
+            builder.duplicate_groups(groups);
+        }
+        if let Some(fill_mode) = options.pattern {
+            builder.fill_mode(fill_mode);
+        }
+        if let Some(fill_mode) = options.archive {
+            builder.fill_mode(fill_mode);
+        }
+        if let Some(fill_mode) = options.content_entropy {
+            builder.fill_mode(fill_mode);
+        }
+        if let Some(depth) = options.nesting_depth {
+            builder.nesting_depth(depth);
+        }
+        if let Some(header) = options.text_header {
+            builder.text_header(header);
+        }
+        if let Some(header) = options.sequence_header {
+            builder.sequence_header(header);
+        }
+        if let Some(dir) = options.content_source {
+            builder.content_source(dir);
+        }
+        if !options.media_mix.is_empty() {
+            builder.media_mix(options.media_mix);
+        }
+        if let Some(per_sec) = options.rate_limit {
+            builder.rate_limit(per_sec);
+        }
+        if let Some(per_sec) = options.byte_rate {
+            builder.byte_rate(per_sec);
+        }
+        if let Some(bytes) = options.write_memory_budget {
+            builder.write_memory_budget(bytes);
+        }
+        if let Some(period) = options.ramp_up {
+            builder.ramp_up(period);
+        }
+        if let Some(CpuList(cores)) = options.cpu_affinity {
+            builder.cpu_affinity(cores);
+        }
+        if let Some(width) = options.max_dir_width {
+            builder.max_dir_width(width);
+        }
+        if options.no_limit {
+            builder.max_entries(usize::MAX);
+        }
+        if options.log_target == LogTarget::Syslog {
+            #[cfg(feature = "syslog")]
+            builder.syslog(
+                Syslog::connect()
+                    .context("Failed to connect to the system log")
+                    .with_code(exitcode::UNAVAILABLE)?,
+            );
+            #[cfg(not(feature = "syslog"))]
+            return Err(anyhow!(
+                "Logging to syslog requires ftzz to be built with the `syslog` feature."
+            ))
+            .with_code(exitcode::USAGE);
+        }
+        if options.no_color {
+            builder.color(false);
+        } else {
+            builder.color(options.color.unwrap_or(ColorChoice::Auto).enabled());
+        }
+        if options.progress_format == Some(ProgressFormat::Jsonl) {
+            #[cfg(target_os = "linux")]
+            if options.io_report {
+                builder.on_progress(ProgressHook::json_lines_with_io(io::stderr()));
+            } else {
+                builder.on_progress(ProgressHook::json_lines(io::stderr()));
+            }
+            #[cfg(not(target_os = "linux"))]
+            builder.on_progress(ProgressHook::json_lines(io::stderr()));
+        }
+        if let Some(phrase) = options.entropy_phrase {
+            builder.seed_phrase(phrase);
+        }
+        if let Some(seed) = options.structure_seed {
+            builder.structure_seed(seed);
+        }
+        if let Some(seed) = options.content_seed {
+            builder.content_seed(seed);
+        }
+        if let Some(path) = options.dir_wordlist {
+            builder.dir_wordlist(read_wordlist(path, DEFAULT_DIR_WORDLIST)?);
+        }
+        if let Some(path) = options.file_wordlist {
+            builder.file_wordlist(read_wordlist(path, DEFAULT_FILE_WORDLIST)?);
+        }
+        if let Some(secs) = options.mtime {
+            builder.fixed_time(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        }
+        if let Some(sequence) = options.mtime_sequence {
+            builder.mtime_sequence(sequence);
+        }
+        if let Some(file) = options.checksums {
+            builder.checksums(file);
+        }
+        if let Some(file) = options.catalog {
+            builder.catalog(file);
+        }
+        if let Some(file) = options.write_seed {
+            builder.write_seed(file);
+        }
+        if let Some(file) = options.checkpoint {
+            builder.checkpoint(file);
+        }
+        if let Some(file) = options.dump_samples {
+            builder.dump_samples(file);
+        }
+        if let Some(mask) = options.umask {
+            builder.umask(mask);
+        }
+        if let Some(dir) = options.overlay_source {
+            builder.overlay_source(dir);
+        }
+        if let Some(dir) = options.repair_dir {
+            builder.repair_dir(dir);
+        }
+        if let Some(objects) = options.git_objects {
+            builder.git_objects(objects);
+        }
+        if let Some(depth) = options.task_granularity {
+            builder.task_granularity(depth);
+        }
+        let seed = match options.read_seed {
+            Some(file) => read_seed(&file)?,
+            None => options.seed,
+        };
+        builder
+            .seed(seed)
+            .lex_sortable(options.lex_sortable)
+            .sort_by_size(options.sort_by_size)
+            .block_size(options.block_size)
+            .name_hash_jitter(options.name_hash_jitter)
+            .case_collisions(options.case_collisions)
+            .names_from_content(options.names_from_content)
+            .hidden_ratio(options.hidden_ratio)
+            .sparse_ratio(options.sparse_ratio)
+            .sparse_holes(options.sparse_holes)
+            .preallocate(options.preallocate)
+            .group_by_extension(options.group_by_extension)
+            .read_only_ratio(options.read_only_ratio)
+            .read_only_dirs(options.read_only_dirs)
+            .xattr_ratio(options.xattr_ratio)
+            .special_ratio(options.special_ratio)
+            .empty_files(options.empty_files)
+            .creation_order(options.creation_order)
+            .cluster_factor(options.cluster_factor)
+            .repair(options.repair)
+            .openat(options.openat)
+            .continue_on_error(options.continue_on_error)
+            .seed_paths(options.seed_paths)
+            .two_phase(options.two_phase)
+            .chain(options.chain)
+            .shrink_depth(options.shrink_depth)
+            .overwrite(options.overwrite)
+            .force_dangerous(options.force_dangerous)
+            .self_check(options.self_check)
+            .write_marker(options.write_marker)
+            .dir_seeds(options.dir_seeds)
+            .build()
+            .context("Input validation failed")
+            .with_code(exitcode::DATAERR)
+    }
+}
+
+#[cfg(test)]
+mod generate_tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn params_are_mapped_correctly() {
+        let options = Generate {
+            root_dir: PathBuf::from("abc"),
+            num_files: Some(NonZeroUsize::new(373).unwrap()),
+            num_entries: None,
+            num_bytes: 637,
+            max_depth: 43,
+            depth_includes_root: false,
+            leaf_depth_distribution: None,
+            depth_jitter: None,
+            structural_levels: None,
+            file_to_dir_ratio: Some(NonZeroUsize::new(37).unwrap()),
+            dir_fraction: None,
+            seed: 775,
+            entropy_phrase: Some(String::from("dataset-v2")),
+            structure_seed: Some(11),
+            content_seed: Some(12),
+            write_seed: Some(PathBuf::from("seed")),
+            checkpoint: None,
+            dump_samples: None,
+            read_seed: None,
+            files_exact: false,
+            bytes_exact: false,
+            target_disk_usage: None,
+            block_size: NonZeroUsize::new(512).unwrap(),
+            max_total_size: None,
+            size_distribution: Some(SizeDistribution::Zipf { exponent: 1.5 }),
+            size_alignment: None,
+            exact: false,
+            dirs_exact: Some(42),
+            empty_files: 0,
+            size_schedule: None,
+            layout: None,
+            min_files: Some(300),
+            min_dirs: None,
+            min_files_per_dir: None,
+            cluster_factor: 0.25,
+            lex_sortable: true,
+            sort_by_size: false,
+            name_hash_jitter: true,
+            name_style: Some(NameStyle::UnicodeRandom),
+            case_collisions: false,
+            names_from_content: false,
+            git_objects: None,
+            hidden_ratio: 0.25,
+            dir_wordlist: None,
+            file_wordlist: None,
+            duplicate_groups: Some(NonZeroUsize::new(7).unwrap()),
+            pattern: Some(FillMode::Repeating(b"test".to_vec())),
+            archive: None,
+            content_entropy: None,
+            nesting_depth: None,
+            text_header: Some(b"#!".to_vec()),
+            sequence_header: None,
+            sparse_ratio: 0.,
+            sparse_holes: false,
+            preallocate: false,
+            read_only_ratio: 0.,
+            read_only_dirs: false,
+            xattr_ratio: 0.,
+            profile: None,
+            media_mix: Vec::new(),
+            group_by_extension: false,
+            content_source: None,
+            // FIFOs are only supported on Linux
+            #[cfg(target_os = "linux")]
+            special_ratio: 0.5,
+            #[cfg(not(target_os = "linux"))]
+            special_ratio: 0.,
+            creation_order: CreationOrder::Interleaved,
+            repair: true,
+            repair_dir: None,
+            overwrite: false,
+            force_dangerous: false,
+            no_limit: true,
+            self_check: true,
+            write_marker: false,
+            dir_seeds: false,
+            #[cfg(unix)]
+            umask: Some(0o027),
+            #[cfg(not(unix))]
+            umask: None,
+            emit_script: false,
+            print_config: false,
+            validate_only: false,
+            mtime: Some(1_000_000_000),
+            mtime_sequence: None,
+            checksums: None,
+            catalog: None,
+            openat: true,
+            task_granularity: None,
+            ramp_up: None,
+            continue_on_error: true,
+            rate_limit: Some(NonZeroUsize::new(10).unwrap()),
+            byte_rate: None,
+            write_memory_budget: None,
+            cpu_affinity: None,
+            two_phase: true,
+            seed_paths: vec![String::from(".git/")],
+            overlay_source: None,
+            progress_format: Some(ProgressFormat::Jsonl),
+            #[cfg(target_os = "linux")]
+            io_report: false,
+            log_target: LogTarget::Stdout,
+            color: Some(ColorChoice::Always),
+            no_color: false,
+            chain: false,
+            balanced: None,
+            max_dir_width: None,
+            shrink_depth: false,
+        };
+
+        let generator = Generator::try_from(options).unwrap();
+        let hack = format!("{:?}", generator);
+
+        assert!(hack.contains("root_dir: \"abc\""));
+        assert!(hack.contains("num_files: 373"));
+        assert!(hack.contains("num_bytes: 637"));
+        assert!(hack.contains("block_size: 512"));
+        assert!(hack.contains("size_distribution: Zipf { exponent: 1.5 }"));
+        assert!(hack.contains("max_depth: 43"));
+        assert!(hack.contains("file_to_dir_ratio: 37"));
+        assert!(hack.contains("dirs_exact: Some(42)"));
+        assert!(hack.contains("min_files: 300"));
+        assert!(hack.contains("cluster_factor: 0.25"));
+        assert!(hack.contains("seed: 775"));
+        assert!(hack.contains("seed_phrase: Some(\"dataset-v2\")"));
+        assert!(hack.contains("structure_seed: Some(11)"));
+        assert!(hack.contains("content_seed: Some(12)"));
+        assert!(hack.contains(r#"write_seed: Some("seed")"#));
+        assert!(hack.contains("lex_sortable: true"));
+        assert!(hack.contains("name_hash_jitter: true"));
+        assert!(hack.contains("name_style: UnicodeRandom"));
+        assert!(hack.contains("duplicate_groups: Some(7)"));
+        assert!(hack.contains("hidden_ratio: 0.25"));
+        #[cfg(target_os = "linux")]
+        assert!(hack.contains("special_ratio: 0.5"));
+        assert!(hack.contains("creation_order: Interleaved"));
+        assert!(hack.contains("fill_mode: Repeating([116, 101, 115, 116])"));
+        assert!(hack.contains("text_header: Some([35, 33])"));
+        assert!(hack.contains("repair: true"));
+        assert!(hack.contains("openat: true"));
+        assert!(hack.contains("continue_on_error: true"));
+        assert!(hack.contains("rate_limit: Some(10)"));
+        assert!(hack.contains("two_phase: true"));
+        assert!(hack.contains(r#"seed_paths: [".git/"]"#));
+        assert!(hack.contains("on_progress: Some(ProgressHook)"));
+        assert!(hack.contains(&format!("max_entries: {}", usize::MAX)));
+        assert!(hack.contains("self_check: true"));
+        #[cfg(unix)]
+        assert!(hack.contains("umask: Some(23)"));
+        assert!(hack.contains("color: true"));
+        assert!(hack.contains("fixed_time: Some("));
+    }
+
+    #[rstest]
+    fn files_exact_is_mapped_correctly(
+        #[values(false, true)] files_exact: bool,
+        #[values(false, true)] global_exact: bool,
+    ) {
+        let options = Generate {
+            files_exact,
+            exact: global_exact,
+
+            root_dir: PathBuf::new(),
+            num_files: Some(NonZeroUsize::new(1).unwrap()),
+            num_entries: None,
+            num_bytes: 0,
+            max_depth: 0,
+            depth_includes_root: false,
+            leaf_depth_distribution: None,
+            depth_jitter: None,
+            structural_levels: None,
+            file_to_dir_ratio: None,
+            dir_fraction: None,
+            seed: 0,
+            entropy_phrase: None,
+            structure_seed: None,
+            content_seed: None,
+            write_seed: None,
+            checkpoint: None,
+            dump_samples: None,
+            read_seed: None,
+            bytes_exact: false,
+            target_disk_usage: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_total_size: None,
+            size_distribution: None,
+            size_alignment: None,
+            dirs_exact: None,
+            empty_files: 0,
+            size_schedule: None,
+            layout: None,
+            min_files: None,
+            min_dirs: None,
+            min_files_per_dir: None,
+            cluster_factor: 0.,
+            lex_sortable: false,
+            sort_by_size: false,
+            name_hash_jitter: false,
+            name_style: None,
+            case_collisions: false,
+            names_from_content: false,
+            git_objects: None,
+            hidden_ratio: 0.,
+            dir_wordlist: None,
+            file_wordlist: None,
+            duplicate_groups: None,
+            pattern: None,
+            archive: None,
+            content_entropy: None,
+            nesting_depth: None,
+            text_header: None,
+            sequence_header: None,
+            sparse_ratio: 0.,
+            sparse_holes: false,
+            preallocate: false,
+            read_only_ratio: 0.,
+            read_only_dirs: false,
+            xattr_ratio: 0.,
+            profile: None,
+            media_mix: Vec::new(),
+            group_by_extension: false,
+            content_source: None,
+            special_ratio: 0.,
+            creation_order: CreationOrder::DirsFirst,
+            repair: false,
+            repair_dir: None,
+            overwrite: false,
+            force_dangerous: false,
+            no_limit: false,
+            self_check: false,
+            write_marker: false,
+            dir_seeds: false,
+            umask: None,
+            emit_script: false,
+            print_config: false,
+            validate_only: false,
+            mtime: None,
+            mtime_sequence: None,
+            checksums: None,
+            catalog: None,
+            openat: false,
+            task_granularity: None,
+            ramp_up: None,
+            continue_on_error: false,
+            rate_limit: None,
+            byte_rate: None,
+            write_memory_budget: None,
+            cpu_affinity: None,
+            two_phase: false,
+            seed_paths: Vec::new(),
+            overlay_source: None,
+            progress_format: None,
+            #[cfg(target_os = "linux")]
+            io_report: false,
+            log_target: LogTarget::Stdout,
+            color: None,
+            no_color: false,
+            chain: false,
+            balanced: None,
+            max_dir_width: None,
+            shrink_depth: false,
+        };
+
+        let generator = Generator::try_from(options).unwrap();
+        let hack = format!("{:?}", generator);
+
+        assert!(hack.contains(&format!("files_exact: {}", files_exact || global_exact)));
+    }
+
+    #[rstest]
+    fn bytes_exact_is_mapped_correctly(
+        #[values(false, true)] bytes_exact: bool,
+        #[values(false, true)] global_exact: bool,
+    ) {
+        let options = Generate {
+            bytes_exact,
+            target_disk_usage: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_total_size: None,
+            size_distribution: None,
+            size_alignment: None,
+            exact: global_exact,
+
+            root_dir: PathBuf::new(),
+            num_files: Some(NonZeroUsize::new(1).unwrap()),
+            num_entries: None,
+            num_bytes: 0,
+            max_depth: 0,
+            depth_includes_root: false,
+            leaf_depth_distribution: None,
+            depth_jitter: None,
+            structural_levels: None,
+            file_to_dir_ratio: None,
+            dir_fraction: None,
+            seed: 0,
+            entropy_phrase: None,
+            structure_seed: None,
+            content_seed: None,
+            write_seed: None,
+            checkpoint: None,
+            dump_samples: None,
+            read_seed: None,
+            files_exact: false,
+            dirs_exact: None,
+            empty_files: 0,
+            size_schedule: None,
+            layout: None,
+            min_files: None,
+            min_dirs: None,
+            min_files_per_dir: None,
+            cluster_factor: 0.,
+            lex_sortable: false,
+            sort_by_size: false,
+            name_hash_jitter: false,
+            name_style: None,
+            case_collisions: false,
+            names_from_content: false,
+            git_objects: None,
+            hidden_ratio: 0.,
+            dir_wordlist: None,
+            file_wordlist: None,
+            duplicate_groups: None,
+            pattern: None,
+            archive: None,
+            content_entropy: None,
+            nesting_depth: None,
+            text_header: None,
+            sequence_header: None,
+            sparse_ratio: 0.,
+            sparse_holes: false,
+            preallocate: false,
+            read_only_ratio: 0.,
+            read_only_dirs: false,
+            xattr_ratio: 0.,
+            profile: None,
+            media_mix: Vec::new(),
+            group_by_extension: false,
+            content_source: None,
+            special_ratio: 0.,
+            creation_order: CreationOrder::DirsFirst,
+            repair: false,
+            repair_dir: None,
+            overwrite: false,
+            force_dangerous: false,
+            no_limit: false,
+            self_check: false,
+            write_marker: false,
+            dir_seeds: false,
+            umask: None,
+            emit_script: false,
+            print_config: false,
+            validate_only: false,
+            mtime: None,
+            mtime_sequence: None,
+            checksums: None,
+            catalog: None,
+            openat: false,
+            task_granularity: None,
+            ramp_up: None,
+            continue_on_error: false,
+            rate_limit: None,
+            byte_rate: None,
+            write_memory_budget: None,
+            cpu_affinity: None,
+            two_phase: false,
+            seed_paths: Vec::new(),
+            overlay_source: None,
+            progress_format: None,
+            #[cfg(target_os = "linux")]
+            io_report: false,
+            log_target: LogTarget::Stdout,
+            color: None,
+            no_color: false,
+            chain: false,
+            balanced: None,
+            max_dir_width: None,
+            shrink_depth: false,
+        };
+
+        let generator = Generator::try_from(options).unwrap();
+        let hack = format!("{:?}", generator);
+
+        assert!(hack.contains(&format!("bytes_exact: {}", bytes_exact || global_exact)));
+    }
+
+    #[rstest]
+    fn chain_is_mapped_correctly(#[values(false, true)] chain: bool) {
+        let options = Generate {
+            chain,
+            balanced: None,
+
+            max_dir_width: None,
+            shrink_depth: false,
+            root_dir: PathBuf::new(),
+            num_files: Some(NonZeroUsize::new(1).unwrap()),
+            num_entries: None,
+            num_bytes: 0,
+            max_depth: 0,
+            depth_includes_root: false,
+            leaf_depth_distribution: None,
+            depth_jitter: None,
+            structural_levels: None,
+            file_to_dir_ratio: None,
+            dir_fraction: None,
+            seed: 0,
+            entropy_phrase: None,
+            structure_seed: None,
+            content_seed: None,
+            write_seed: None,
+            checkpoint: None,
+            dump_samples: None,
+            read_seed: None,
+            files_exact: false,
+            bytes_exact: false,
+            target_disk_usage: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_total_size: None,
+            size_distribution: None,
+            size_alignment: None,
+            exact: false,
+            dirs_exact: None,
+            empty_files: 0,
+            size_schedule: None,
+            layout: None,
+            min_files: None,
+            min_dirs: None,
+            min_files_per_dir: None,
+            cluster_factor: 0.,
+            lex_sortable: false,
+            sort_by_size: false,
+            name_hash_jitter: false,
+            name_style: None,
+            case_collisions: false,
+            names_from_content: false,
+            git_objects: None,
+            hidden_ratio: 0.,
+            dir_wordlist: None,
+            file_wordlist: None,
+            duplicate_groups: None,
+            pattern: None,
+            archive: None,
+            content_entropy: None,
+            nesting_depth: None,
+            text_header: None,
+            sequence_header: None,
+            sparse_ratio: 0.,
+            sparse_holes: false,
+            preallocate: false,
+            read_only_ratio: 0.,
+            read_only_dirs: false,
+            xattr_ratio: 0.,
+            profile: None,
+            media_mix: Vec::new(),
+            group_by_extension: false,
+            content_source: None,
+            special_ratio: 0.,
+            creation_order: CreationOrder::DirsFirst,
+            repair: false,
+            repair_dir: None,
+            overwrite: false,
+            force_dangerous: false,
+            no_limit: false,
+            self_check: false,
+            write_marker: false,
+            dir_seeds: false,
+            umask: None,
+            emit_script: false,
+            print_config: false,
+            validate_only: false,
+            mtime: None,
+            mtime_sequence: None,
+            checksums: None,
+            catalog: None,
+            openat: false,
+            task_granularity: None,
+            ramp_up: None,
+            continue_on_error: false,
+            rate_limit: None,
+            byte_rate: None,
+            write_memory_budget: None,
+            cpu_affinity: None,
+            two_phase: false,
+            seed_paths: Vec::new(),
+            overlay_source: None,
+            progress_format: None,
+            #[cfg(target_os = "linux")]
+            io_report: false,
+            log_target: LogTarget::Stdout,
+            color: None,
+            no_color: false,
+        };
+
+        let generator = Generator::try_from(options).unwrap();
+        let hack = format!("{:?}", generator);
+
+        assert!(hack.contains(&format!("chain: {}", chain)));
+    }
+
+    #[test]
+    fn wordlists_are_mapped_correctly() {
+        let words = tempfile::NamedTempFile::new().unwrap();
+        fs::write(words.path(), "alpha\n\n  beta \ngamma\n").unwrap();
+        let options = Generate {
+            dir_wordlist: Some(Some(words.path().to_path_buf())),
+            file_wordlist: Some(None),
+
+            root_dir: PathBuf::new(),
+            num_files: Some(NonZeroUsize::new(1).unwrap()),
+            num_entries: None,
+            num_bytes: 0,
+            max_depth: 0,
+            depth_includes_root: false,
+            leaf_depth_distribution: None,
+            depth_jitter: None,
+            structural_levels: None,
+            file_to_dir_ratio: None,
+            dir_fraction: None,
+            seed: 0,
+            entropy_phrase: None,
+            structure_seed: None,
+            content_seed: None,
+            write_seed: None,
+            checkpoint: None,
+            dump_samples: None,
+            read_seed: None,
+            files_exact: false,
+            bytes_exact: false,
+            target_disk_usage: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_total_size: None,
+            size_distribution: None,
+            size_alignment: None,
+            exact: false,
+            dirs_exact: None,
+            empty_files: 0,
+            size_schedule: None,
+            layout: None,
+            min_files: None,
+            min_dirs: None,
+            min_files_per_dir: None,
+            cluster_factor: 0.,
+            lex_sortable: false,
+            sort_by_size: false,
+            name_hash_jitter: false,
+            name_style: None,
+            case_collisions: false,
+            names_from_content: false,
+            git_objects: None,
+            hidden_ratio: 0.,
+            duplicate_groups: None,
+            pattern: None,
+            archive: None,
+            content_entropy: None,
+            nesting_depth: None,
+            text_header: None,
+            sequence_header: None,
+            sparse_ratio: 0.,
+            sparse_holes: false,
+            preallocate: false,
+            read_only_ratio: 0.,
+            read_only_dirs: false,
+            xattr_ratio: 0.,
+            profile: None,
+            media_mix: Vec::new(),
+            group_by_extension: false,
+            content_source: None,
+            special_ratio: 0.,
+            creation_order: CreationOrder::DirsFirst,
+            repair: false,
+            repair_dir: None,
+            overwrite: false,
+            force_dangerous: false,
+            no_limit: false,
+            self_check: false,
+            write_marker: false,
+            dir_seeds: false,
+            umask: None,
+            emit_script: false,
+            print_config: false,
+            validate_only: false,
+            mtime: None,
+            mtime_sequence: None,
+            checksums: None,
+            catalog: None,
+            openat: false,
+            task_granularity: None,
+            ramp_up: None,
+            continue_on_error: false,
+            rate_limit: None,
+            byte_rate: None,
+            write_memory_budget: None,
+            cpu_affinity: None,
+            two_phase: false,
+            seed_paths: Vec::new(),
+            overlay_source: None,
+            progress_format: None,
+            #[cfg(target_os = "linux")]
+            io_report: false,
+            log_target: LogTarget::Stdout,
+            color: None,
+            no_color: false,
+            chain: false,
+            balanced: None,
+            max_dir_width: None,
+            shrink_depth: false,
+        };
+
+        let generator = Generator::try_from(options).unwrap();
+        let hack = format!("{:?}", generator);
+
+        assert!(hack.contains("dir_wordlist: Some([\"alpha\", \"beta\", \"gamma\"])"));
+        assert!(hack.contains("file_wordlist: Some([\"README.md\", "));
+    }
+
+    #[test]
+    fn size_schedule_is_mapped_correctly() {
+        let sizes = tempfile::NamedTempFile::new().unwrap();
+        fs::write(sizes.path(), "[0, 2K,\n1_000 3]\n").unwrap();
+        let options = Generate {
+            num_files: None,
+            num_entries: None,
+            size_schedule: Some(sizes.path().to_path_buf()),
+            layout: None,
+
+            root_dir: PathBuf::new(),
+            num_bytes: 0,
+            max_depth: 0,
+            depth_includes_root: false,
+            leaf_depth_distribution: None,
+            depth_jitter: None,
+            structural_levels: None,
+            file_to_dir_ratio: None,
+            dir_fraction: None,
+            seed: 0,
+            entropy_phrase: None,
+            structure_seed: None,
+            content_seed: None,
+            write_seed: None,
+            checkpoint: None,
+            dump_samples: None,
+            read_seed: None,
+            files_exact: false,
+            bytes_exact: false,
+            target_disk_usage: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            max_total_size: None,
+            size_distribution: None,
+            size_alignment: None,
+            exact: false,
+            dirs_exact: None,
+            empty_files: 0,
+            min_files: None,
+            min_dirs: None,
+            min_files_per_dir: None,
+            cluster_factor: 0.,
+            lex_sortable: false,
+            sort_by_size: false,
+            name_hash_jitter: false,
+            name_style: None,
+            case_collisions: false,
+            names_from_content: false,
+            git_objects: None,
+            hidden_ratio: 0.,
+            dir_wordlist: None,
+            file_wordlist: None,
+            duplicate_groups: None,
+            pattern: None,
+            archive: None,
+            content_entropy: None,
+            nesting_depth: None,
+            text_header: None,
+            sequence_header: None,
+            sparse_ratio: 0.,
+            sparse_holes: false,
+            preallocate: false,
+            read_only_ratio: 0.,
+            read_only_dirs: false,
+            xattr_ratio: 0.,
+            profile: None,
+            media_mix: Vec::new(),
+            group_by_extension: false,
+            content_source: None,
+            special_ratio: 0.,
+            creation_order: CreationOrder::DirsFirst,
+            repair: false,
+            repair_dir: None,
+            overwrite: false,
+            force_dangerous: false,
+            no_limit: false,
+            self_check: false,
+            write_marker: false,
+            dir_seeds: false,
+            umask: None,
+            emit_script: false,
+            print_config: false,
+            validate_only: false,
+            mtime: None,
+            mtime_sequence: None,
+            checksums: None,
+            catalog: None,
+            openat: false,
+            task_granularity: None,
+            ramp_up: None,
+            continue_on_error: false,
+            rate_limit: None,
+            byte_rate: None,
+            write_memory_budget: None,
+            cpu_affinity: None,
+            two_phase: false,
+            seed_paths: Vec::new(),
+            overlay_source: None,
+            progress_format: None,
+            #[cfg(target_os = "linux")]
+            io_report: false,
+            log_target: LogTarget::Stdout,
+            color: None,
+            no_color: false,
+            chain: false,
+            balanced: None,
+            max_dir_width: None,
+            shrink_depth: false,
+        };
+
+        let generator = Generator::try_from(options).unwrap();
+        let hack = format!("{:?}", generator);
+
+        assert!(hack.contains("num_files: 4"));
+        assert!(hack.contains("size_schedule: Some([0, 2000, 1000, 3])"));
+    }
+}
+
+#[cli_errors::main]
+fn main() -> CliResult<()> {
+    let args = Ftzz::parse();
+
+    #[cfg(not(feature = "trace"))]
+    simple_logger::init_with_level(args.verbose.log_level().unwrap()).unwrap();
+    #[cfg(feature = "trace")]
+    let _guard = {
+        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+        let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+            .include_args(true)
+            .build();
+        tracing_subscriber::registry().with(chrome_layer).init();
+        guard
+    };
+
+    match args.cmd {
+        Cmd::Generate(options) => {
+            let emit_script = options.emit_script;
+            let print_config = options.print_config;
+            let validate_only = options.validate_only;
+            let generator = Generator::try_from(options)?;
+            if print_config {
+                generator.write_config(BufWriter::new(io::stdout().lock()))?;
+            }
+            if validate_only {
+                generator.validate_params()?;
+                println!("OK");
+                Ok(())
+            } else if emit_script {
+                generator.write_script(BufWriter::new(io::stdout().lock()))
+            } else {
+                generator.generate()
+            }
+        }
+        Cmd::Explain(options) => {
+            let topic = options.topic;
+            Generator::try_from(options)?.explain(topic, BufWriter::new(io::stdout().lock()))
+        }
+        Cmd::Sample(Sample { options, count }) => {
+            let paths = Generator::try_from(options)?.sample_paths(count);
+            let mut out = BufWriter::new(io::stdout().lock());
+            paths
+                .iter()
+                .try_for_each(|path| writeln!(out, "{}", path.display()))
+                .and_then(|_| out.flush())
+                .context("Failed to write sample")
+                .with_code(exitcode::IOERR)
+        }
+        Cmd::Diff(Diff { left, right }) => {
+            let diff = diff_trees(&left, &right)?;
+            let mut out = BufWriter::new(io::stdout().lock());
+            write_diff(&mut out, &diff)
+                .and_then(|_| out.flush())
+                .context("Failed to write diff")
+                .with_code(exitcode::IOERR)?;
+            if diff.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "The trees differ: {} missing, {} extra, and {} mismatched paths.",
+                    diff.missing.len(),
+                    diff.extra.len(),
+                    diff.mismatched.len(),
+                ))
+                .with_code(exitcode::DATAERR)
+            }
+        }
+    }
+}
+
+/// Lists differences one per line, prefixed with `-` for missing paths, `+` for extra paths, and
+/// `~` for mismatched paths.
+fn write_diff(out: &mut impl Write, diff: &TreeDiff) -> io::Result<()> {
+    for (prefix, paths) in [("-", &diff.missing), ("+", &diff.extra), ("~", &diff.mismatched)] {
+        for path in paths {
+            writeln!(out, "{} {}", prefix, path.display())?;
+        }
+    }
+    Ok(())
+}
+
+fn num_files_parser(s: &str) -> Result<NonZeroUsize, String> {
+    let files = lenient_si_number(s)?;
+    if files > 0 {
+        Ok(unsafe { NonZeroUsize::new_unchecked(files) })
+    } else {
+        Err(String::from("At least one file must be generated."))
+    }
+}
+
+fn min_files_parser(s: &str) -> Result<usize, String> {
+    lenient_si_number(s)
+}
+
+fn num_bytes_parser(s: &str) -> Result<usize, String> {
+    lenient_si_number(s)
+}
+
+fn block_size_parser(s: &str) -> Result<NonZeroUsize, String> {
+    NonZeroUsize::new(lenient_si_number(s)?).ok_or_else(|| String::from("Blocks cannot be empty."))
+}
+
+fn size_alignment_parser(s: &str) -> Result<NonZeroUsize, String> {
+    NonZeroUsize::new(lenient_si_number(s)?)
+        .ok_or_else(|| String::from("Sizes cannot be aligned to zero bytes."))
+}
+
+fn byte_rate_parser(s: &str) -> Result<NonZeroUsize, String> {
+    NonZeroUsize::new(lenient_si_number(s)?)
+        .ok_or_else(|| String::from("At least one byte must be written per second."))
+}
+
+fn write_memory_budget_parser(s: &str) -> Result<NonZeroUsize, String> {
+    NonZeroUsize::new(lenient_si_number(s)?)
+        .ok_or_else(|| String::from("Files cannot be written without buffering any bytes."))
+}
+
+fn ramp_up_parser(s: &str) -> Result<Duration, String> {
+    let (amount, unit) =
+        s.split_at(s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len()));
+    let amount = amount.parse::<f64>().map_err(|e| e.to_string())?;
+    let secs = match unit {
+        "ms" => amount / 1000.,
+        "" | "s" => amount,
+        "m" => amount * 60.,
+        _ => return Err(format!("Unknown unit {:?}, expected ms, s, or m.", unit)),
+    };
+    if secs == 0. {
+        return Err(String::from("The ramp up period must be longer than zero."));
+    }
+    if secs >= u64::MAX as f64 {
+        return Err(String::from("The ramp up period is too long."));
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn num_dirs_parser(s: &str) -> Result<usize, String> {
+    lenient_si_number(s)
+}
+
+fn file_to_dir_ratio_parser(s: &str) -> Result<NonZeroUsize, String> {
+    let ratio = lenient_si_number(s)?;
+    if ratio > 0 {
+        Ok(unsafe { NonZeroUsize::new_unchecked(ratio) })
+    } else {
+        Err(String::from("Cannot have no files per directory."))
+    }
+}
+
+fn dir_fraction_parser(s: &str) -> Result<f64, String> {
+    let fraction = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if fraction > 0. && fraction < 1. {
+        Ok(fraction)
+    } else {
+        Err(String::from(
+            "The fraction must be greater than 0 and less than 1.",
+        ))
+    }
+}
+
+fn umask_parser(s: &str) -> Result<u32, String> {
+    let mask = u32::from_str_radix(s, 8).map_err(|e| e.to_string())?;
+    if mask <= 0o777 {
+        Ok(mask)
+    } else {
+        Err(String::from("The umask must be at most 777."))
+    }
+}
+
+fn fanout_parser(s: &str) -> Result<usize, String> {
+    let fanout = lenient_si_number(s)?;
+    if fanout >= 2 {
+        Ok(fanout)
+    } else {
+        Err(String::from("Every directory must have at least 2 subdirectories."))
+    }
+}
+
+fn duplicate_groups_parser(s: &str) -> Result<NonZeroUsize, String> {
+    let groups = lenient_si_number(s)?;
+    if groups > 0 {
+        Ok(unsafe { NonZeroUsize::new_unchecked(groups) })
+    } else {
+        Err(String::from("At least one duplicate group is required."))
+    }
+}
+
+/// Parses hex prefixed with `0x` or a plain string into bytes.
+fn bytes_parser(s: &str) -> Result<Vec<u8>, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        None => Ok(s.as_bytes().to_vec()),
+        Some(hex) => {
+            // Also keeps the slicing below on character boundaries
+            if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(String::from("Hex bytes can only contain hex digits."));
+            }
+            if hex.len() % 2 != 0 {
+                return Err(String::from("Hex bytes must have an even number of digits."));
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn pattern_parser(s: &str) -> Result<FillMode, String> {
+    let pattern = bytes_parser(s)?;
+    if pattern.is_empty() {
+        Err(String::from("The pattern cannot be empty."))
+    } else {
+        Ok(FillMode::Repeating(pattern))
+    }
+}
+
+fn entropy_parser(s: &str) -> Result<FillMode, String> {
+    let entropy = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0. ..=1.).contains(&entropy) {
+        Ok(FillMode::Blended {
+            permille: (entropy * 1000.).round() as u16,
+        })
+    } else {
+        Err(String::from("The entropy must be between 0 and 1."))
+    }
+}
+
+fn archive_parser(s: &str) -> Result<FillMode, String> {
+    let (format, inner_entries) = match s.split_once(',') {
+        None => (s, 1),
+        Some((format, entries)) => (format, lenient_si_number(entries.trim())?),
+    };
+    let format = match format {
+        "tar" => ArchiveFormat::Tar,
+        "zip" => ArchiveFormat::Zip,
+        _ => return Err(format!("Unknown archive format: {}", format)),
+    };
+    if inner_entries == 0 {
+        return Err(String::from("Archives must contain at least one entry."));
+    }
+    Ok(FillMode::Archive {
+        format,
+        inner_entries,
+    })
+}
+
+fn text_header_parser(s: &str) -> Result<Vec<u8>, String> {
+    let header = match s {
+        "utf8-bom" => vec![0xEF, 0xBB, 0xBF],
+        "utf16le-bom" => vec![0xFF, 0xFE],
+        _ => bytes_parser(s)?,
+    };
+    if header.is_empty() {
+        Err(String::from("The text header cannot be empty."))
+    } else {
+        Ok(header)
+    }
+}
+
+fn size_distribution_parser(s: &str) -> Result<SizeDistribution, String> {
+    match s.split_once(',') {
+        None if s == "normal" => Ok(SizeDistribution::Normal),
+        None if s == "zipf" => Ok(SizeDistribution::Zipf { exponent: 1. }),
+        Some(("zipf", exponent)) => {
+            let exponent = exponent.trim().parse::<f64>().map_err(|e| e.to_string())?;
+            if exponent > 0. && exponent.is_finite() {
+                Ok(SizeDistribution::Zipf { exponent })
+            } else {
+                Err(String::from("The Zipf exponent must be positive."))
+            }
+        }
+        _ => Err(format!("Unknown size distribution: {}", s)),
+    }
+}
+
+fn depth_distribution_parser(s: &str) -> Result<DepthDistribution, String> {
+    let (mean, cv) = s.split_once(',').unwrap_or((s, "0.2"));
+    let mean = mean.trim().parse::<f64>().map_err(|e| e.to_string())?;
+    let cv = cv.trim().parse::<f64>().map_err(|e| e.to_string())?;
+    if !(mean > 0. && mean.is_finite()) {
+        Err(String::from("The mean depth must be positive."))
+    } else if !(cv >= 0. && cv.is_finite()) {
+        Err(String::from(
+            "The coefficient of variation cannot be negative.",
+        ))
+    } else {
+        Ok(DepthDistribution::Normal { mean, cv })
+    }
+}
+
+fn mtime_sequence_parser(s: &str) -> Result<MtimeSequence, String> {
+    let (start, interval) = s
+        .split_once(',')
+        .ok_or_else(|| String::from("Expected a start time and an interval."))?;
+    let start = start.trim().parse::<i64>().map_err(|e| e.to_string())?;
+    let interval = interval.trim().parse::<u64>().map_err(|e| e.to_string())?;
+    if interval == 0 {
+        Err(String::from(
+            "Modification times in a sequence must be at least a second apart.",
+        ))
+    } else {
+        Ok(MtimeSequence { start, interval })
+    }
+}
+
+fn cluster_factor_parser(s: &str) -> Result<f64, String> {
+    let factor = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0. ..1.).contains(&factor) {
+        Ok(factor)
+    } else {
+        Err(String::from(
+            "The factor must be at least 0 and less than 1.",
+        ))
+    }
+}
+
+fn special_ratio_parser(s: &str) -> Result<f64, String> {
+    let ratio = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0. ..=1.).contains(&ratio) {
+        Ok(ratio)
+    } else {
+        Err(String::from("The ratio must be between 0 and 1."))
+    }
+}
+
+/// Reads one word per line from path, falling back to the bundled words if there is no path.
+fn read_wordlist(path: Option<PathBuf>, bundled: &[&str]) -> CliResult<Vec<String>> {
+    let path = match path {
+        None => return Ok(bundled.iter().map(|word| word.to_string()).collect()),
+        Some(path) => path,
+    };
+
+    Ok(fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read wordlist {:?}", path))
+        .with_code(exitcode::NOINPUT)?
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Reads file sizes separated by commas or whitespace, optionally wrapped in a JSON array.
+fn read_size_schedule(path: &Path) -> CliResult<Vec<usize>> {
+    let schedule = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read size schedule {:?}", path))
+        .with_code(exitcode::NOINPUT)?;
+    let schedule = schedule.trim();
+    let schedule = schedule
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(schedule);
+
+    schedule
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|size| !size.is_empty())
+        .map(|size| lenient_si_number(size).map_err(|e| anyhow!("{:?}: {}", size, e)))
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Invalid size schedule {:?}", path))
+        .with_code(exitcode::DATAERR)
+}
+
+fn read_layout(path: &Path) -> CliResult<Layout> {
+    let layout = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read layout {:?}", path))
+        .with_code(exitcode::NOINPUT)?;
+    Layout::from_json(&layout)
+        .map_err(|e| anyhow!(e))
+        .with_context(|| format!("Invalid layout {:?}", path))
+        .with_code(exitcode::DATAERR)
+}
+
+fn read_seed(path: &Path) -> CliResult<u64> {
+    let seed = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read seed file {:?}", path))
+        .with_code(exitcode::NOINPUT)?;
+    seed.trim()
+        .parse::<u64>()
+        .with_context(|| format!("Invalid seed file {:?}", path))
+        .with_code(exitcode::DATAERR)
+}
+
+fn lenient_si_number(s: &str) -> Result<usize, String> {
+    let mut s = s.replace('K', "k");
+    s.remove_matches(",");
+    s.remove_matches("_");
+    si_number(&s)
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use clap::IntoApp;
+
+    use super::*;
+
+    #[test]
+    fn verify_app() {
+        Ftzz::command().debug_assert();
+    }
+
+    #[test]
+    fn explain_parses_topic_and_example_params() {
+        let f = Ftzz::try_parse_from(vec!["ftzz", "explain", "depth", "-n", "10K", "-r", "100"])
+            .unwrap();
+
+        match f.cmd {
+            Cmd::Explain(e) => {
+                assert_eq!(e.topic, ExplainTopic::Depth);
+                assert_eq!(e.num_files.get(), 10_000);
+                assert_eq!(e.max_depth, 5);
+                assert_eq!(e.file_to_dir_ratio.map(NonZeroUsize::get), Some(100));
+            }
+            _ => panic!("Expected the explain subcommand"),
+        }
+    }
+
+    #[test]
+    fn sample_accepts_generate_params() {
+        let f = Ftzz::try_parse_from(vec!["ftzz", "sample", "-n", "1K", "--seed", "7", "dir"])
+            .unwrap();
+
+        match f.cmd {
+            Cmd::Sample(s) => {
+                assert_eq!(s.count.get(), 10);
+                assert_eq!(s.options.num_files.map(NonZeroUsize::get), Some(1000));
+                assert_eq!(s.options.seed, 7);
+                assert_eq!(s.options.root_dir, PathBuf::from("dir"));
+            }
+            _ => panic!("Expected the sample subcommand"),
+        }
+    }
+
+    #[test]
+    fn diff_requires_two_dirs() {
+        let f = Ftzz::try_parse_from(vec!["ftzz", "diff", "a", "b"]).unwrap();
+
+        match f.cmd {
+            Cmd::Diff(d) => {
+                assert_eq!(d.left, PathBuf::from("a"));
+                assert_eq!(d.right, PathBuf::from("b"));
+            }
+            _ => panic!("Expected the diff subcommand"),
+        }
+        assert!(Ftzz::try_parse_from(vec!["ftzz", "diff", "a"]).is_err());
+    }
+
+    #[test]
+    fn diff_lists_each_kind_of_difference() {
+        let diff = TreeDiff {
+            missing: vec![PathBuf::from("0.dir/1")],
+            extra: vec![PathBuf::from("2")],
+            mismatched: vec![PathBuf::from("0")],
+        };
+        let mut out = Vec::new();
+
+        write_diff(&mut out, &diff).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "- 0.dir/1\n+ 2\n~ 0\n");
+    }
+
+    #[test]
+    fn explain_rejects_unknown_topics() {
+        assert!(Ftzz::try_parse_from(vec!["ftzz", "explain", "colors"]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod cli_generate_tests {
+    use clap::{
+        ErrorKind::{
+            ArgumentConflict, DisplayHelpOnMissingArgumentOrSubcommand, InvalidValue,
+            MissingRequiredArgument, UnknownArgument, ValueValidation,
+        },
+        FromArgMatches, IntoApp,
+    };
+    use rstest::rstest;
+
+    use super::*;
+
+    macro_rules! expect_error {
+        ($args:expr, $error:expr) => {
+            let f = Ftzz::try_parse_from($args);
+
+            assert!(f.is_err());
+            assert_eq!(f.unwrap_err().kind(), $error);
+        };
+    }
+
+    macro_rules! expect_success {
+        ($args:expr) => {{
+            let m = Ftzz::command().get_matches_from($args);
+            <Generate as FromArgMatches>::from_arg_matches(
+                m.subcommand_matches("generate").unwrap(),
+            )
+            .unwrap()
+        }};
+    }
+
+    #[test]
+    fn empty_args_displays_help() {
+        expect_error!(
+            Vec::<String>::new(),
+            DisplayHelpOnMissingArgumentOrSubcommand
+        );
+    }
+
+    #[test]
+    fn generate_empty_args_displays_error() {
+        expect_error!(vec!["ftzz", "generate"], MissingRequiredArgument);
+    }
+
+    #[test]
+    fn generate_minimal_use_case_uses_defaults() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir"]);
+
+        assert_eq!(g.root_dir, PathBuf::from("dir"));
+        assert_eq!(g.num_files.unwrap().get(), 1);
+        assert_eq!(g.max_depth, 5);
+        assert_eq!(g.leaf_depth_distribution, None);
+        assert_eq!(g.file_to_dir_ratio, None);
+        assert_eq!(g.dir_fraction, None);
+        assert_eq!(g.seed, 0);
+        assert_eq!(g.entropy_phrase, None);
+        assert_eq!(g.structure_seed, None);
+        assert_eq!(g.content_seed, None);
+        assert!(!g.files_exact);
+        assert!(!g.bytes_exact);
+        assert_eq!(g.dirs_exact, None);
+        assert!(!g.exact);
+        assert!(!g.lex_sortable);
+        assert!(!g.name_hash_jitter);
+        assert_eq!(g.duplicate_groups, None);
+        assert_eq!(g.pattern, None);
+        assert_eq!(g.special_ratio, 0.);
+        assert_eq!(g.creation_order, CreationOrder::DirsFirst);
+        assert_eq!(g.cluster_factor, 0.);
+        assert!(!g.repair);
+        assert!(!g.openat);
+        assert!(!g.chain);
+        assert!(!g.overwrite);
+        assert!(!g.force_dangerous);
+        assert!(!g.no_limit);
+        assert!(!g.self_check);
+        assert_eq!(g.num_bytes, 0);
+    }
+
+    #[test]
+    fn generate_num_files_rejects_negatives() {
+        expect_error!(vec!["ftzz", "generate", "-n", "-1", "dir"], UnknownArgument);
+    }
+
+    #[test]
+    fn generate_num_files_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "--files", "1000", "dir"]);
+
+        assert_eq!(g.num_files.unwrap().get(), 1000);
+    }
+
+    #[test]
+    fn generate_short_num_files_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1000", "dir"]);
+
+        assert_eq!(g.num_files.unwrap().get(), 1000);
+    }
+
+    #[test]
+    fn generate_num_files_accepts_si_numbers() {
+        let g = expect_success!(vec!["ftzz", "generate", "--files", "1K", "dir"]);
+
+        assert_eq!(g.num_files.unwrap().get(), 1000);
+    }
+
+    #[test]
+    fn generate_num_files_accepts_commas() {
+        let g = expect_success!(vec!["ftzz", "generate", "--files", "1,000", "dir"]);
+
+        assert_eq!(g.num_files.unwrap().get(), 1000);
+    }
+
+    #[test]
+    fn generate_num_files_accepts_underscores() {
+        let g = expect_success!(vec!["ftzz", "generate", "--files", "1_000", "dir"]);
+
+        assert_eq!(g.num_files.unwrap().get(), 1000);
+    }
+
+    #[test]
+    fn generate_max_depth_rejects_negatives() {
+        expect_error!(
+            vec!["ftzz", "generate", "--depth", "-1", "-n", "1", "dir"],
+            UnknownArgument
+        );
+    }
+
+    #[test]
+    fn generate_max_depth_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "--depth", "123", "-n", "1", "dir"]);
+
+        assert_eq!(g.max_depth, 123);
+    }
+
+    #[test]
+    fn generate_short_max_depth_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "-d", "123", "-n", "1", "dir"]);
+
+        assert_eq!(g.max_depth, 123);
+    }
+
+    #[test]
+    fn generate_depth_includes_root_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--depth-includes-root"
+        ]);
+
+        assert!(g.depth_includes_root);
+    }
+
+    #[test]
+    fn generate_ratio_rejects_negatives() {
+        expect_error!(
+            vec!["ftzz", "generate", "--ftd-ratio", "-1", "-n", "1", "dir",],
+            UnknownArgument
+        );
+    }
+
+    #[test]
+    fn generate_ratio_accepts_plain_nums() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "--ftd-ratio",
+            "1000",
+            "-n",
+            "1",
+            "dir",
+        ]);
+
+        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+    }
+
+    #[test]
+    fn generate_short_ratio_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "-r", "321", "-n", "1", "dir"]);
+
+        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(321).unwrap()));
+    }
+
+    #[test]
+    fn generate_ratio_accepts_si_numbers() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "--ftd-ratio",
+            "1K",
+            "-n",
+            "1",
+            "dir",
+        ]);
+
+        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+    }
+
+    #[test]
+    fn generate_ratio_accepts_commas() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "--ftd-ratio",
+            "1,000",
+            "-n",
+            "1",
+            "dir",
+        ]);
+
+        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+    }
+
+    #[test]
+    fn generate_ratio_accepts_underscores() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "--ftd-ratio",
+            "1_000",
+            "-n",
+            "1",
+            "dir",
+        ]);
+
+        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+    }
+
+    #[test]
+    fn generate_seed_rejects_negatives() {
+        expect_error!(
+            vec!["ftzz", "generate", "--seed", "-1", "-n", "1", "dir",],
+            UnknownArgument
+        );
+    }
+
+    #[test]
+    fn generate_seed_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "--seed", "231", "-n", "1", "dir",]);
+
+        assert_eq!(g.seed, 231);
+    }
+
+    #[test]
+    fn generate_split_seeds_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--structure-seed",
+            "1",
+            "--content-seed",
+            "2",
+        ]);
+
+        assert_eq!(g.structure_seed, Some(1));
+        assert_eq!(g.content_seed, Some(2));
+    }
+
+    #[test]
+    fn generate_read_seed_replaces_seed() {
+        let seed = tempfile::NamedTempFile::new().unwrap();
+        fs::write(seed.path(), "231\n").unwrap();
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--read-seed",
+            seed.path().to_str().unwrap(),
+        ]);
+
+        let hack = format!("{:?}", Generator::try_from(g).unwrap());
+        assert!(hack.contains("seed: 231,"));
+    }
+
+    #[test]
+    fn generate_read_seed_conflicts_with_seed() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--read-seed",
+                "seed",
+                "--seed",
+                "42",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_read_seed_conflicts_with_entropy_phrase() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--read-seed",
+                "seed",
+                "--entropy-phrase",
+                "dataset-v2",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_entropy_phrase_accepts_strings() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "--entropy-phrase",
+            "dataset-v2",
+            "-n",
+            "1",
+            "dir",
+        ]);
+
+        assert_eq!(g.entropy_phrase, Some(String::from("dataset-v2")));
+    }
+
+    #[test]
+    fn generate_num_bytes_accepts_plain_nums() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--total-bytes",
+            "1000",
+        ]);
+
+        assert_eq!(g.num_bytes, 1000);
+    }
+
+    #[test]
+    fn generate_short_num_bytes_accepts_plain_nums() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "-b", "1000"]);
+
+        assert_eq!(g.num_bytes, 1000);
+    }
+
+    #[test]
+    fn generate_num_bytes_accepts_si_numbers() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--total-bytes",
+            "1K",
+        ]);
+
+        assert_eq!(g.num_bytes, 1000);
+    }
+
+    #[test]
+    fn generate_num_bytes_accepts_commas() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--total-bytes",
+            "1,000",
+        ]);
+
+        assert_eq!(g.num_bytes, 1000);
+    }
+
+    #[test]
+    fn generate_num_bytes_accepts_underscores() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--total-bytes",
+            "1_000",
+        ]);
+
+        assert_eq!(g.num_bytes, 1000);
+    }
+
+    #[test]
+    fn generate_files_exact_and_exact_conflict() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--files-exact",
+                "--exact",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_bytes_exact_and_exact_conflict() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--bytes-exact",
+                "--exact",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_files_exact_and_bytes_exact_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--files-exact",
+            "--bytes-exact",
+        ]);
+
+        assert!(g.files_exact);
+        assert!(g.bytes_exact);
+    }
+
+    #[test]
+    fn generate_dirs_exact_accepts_si_numbers() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--dirs-exact",
+            "1K",
+        ]);
+
+        assert_eq!(g.dirs_exact, Some(1000));
+    }
+
+    #[test]
+    fn generate_exact_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--exact"]);
+
+        assert!(g.exact);
+    }
+
+    #[test]
+    fn generate_short_exact_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "-e"]);
+
+        assert!(g.exact);
+    }
+
+    #[test]
+    fn generate_lex_sortable_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--lex-sortable"]);
+
+        assert!(g.lex_sortable);
+    }
+
+    #[test]
+    fn generate_sort_by_size_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--sort-by-size"]);
+
+        assert!(g.sort_by_size);
+    }
+
+    #[test]
+    fn generate_name_hash_jitter_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--name-hash-jitter",
+        ]);
+
+        assert!(g.name_hash_jitter);
+    }
+
+    #[test]
+    fn generate_name_style_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--name-style",
+            "unicode-random",
+        ]);
+
+        assert_eq!(g.name_style, Some(NameStyle::UnicodeRandom));
+    }
+
+    #[test]
+    fn generate_unknown_name_style_fails() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--name-style",
+                "emoji"
+            ],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_timestamped_name_style_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--name-style",
+            "timestamped,1705276800,86400,app-%Y-%m-%d.log",
+        ]);
+
+        assert_eq!(
+            g.name_style,
+            Some(NameStyle::Timestamped {
+                format: String::from("app-%Y-%m-%d.log"),
+                start: 1_705_276_800,
+                step: 86_400,
+            })
+        );
+    }
+
+    #[test]
+    fn generate_creation_order_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--creation-order",
+            "files-first",
+        ]);
+
+        assert_eq!(g.creation_order, CreationOrder::FilesFirst);
+    }
+
+    #[test]
+    fn generate_unknown_creation_order_fails() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--creation-order",
+                "random"
+            ],
+            InvalidValue
+        );
+    }
+
+    #[test]
+    fn generate_repair_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--repair"]);
+
+        assert!(g.repair);
+    }
+
+    #[test]
+    fn generate_openat_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--openat"]);
+
+        assert!(g.openat);
+    }
+
+    #[test]
+    fn generate_task_granularity_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--task-granularity",
+            "3",
+        ]);
+
+        assert_eq!(g.task_granularity, Some(3));
+    }
+
+    #[test]
+    fn generate_task_granularity_conflicts_with_checkpoint() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--task-granularity",
+                "3",
+                "--checkpoint",
+                "progress",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_chain_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--chain"]);
+
+        assert!(g.chain);
+    }
+
+    #[test]
+    fn generate_max_dir_width_accepts_si_numbers() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--max-dir-width",
+            "10K",
+        ]);
+
+        assert_eq!(g.max_dir_width, Some(NonZeroUsize::new(10_000).unwrap()));
+    }
+
+    #[test]
+    fn generate_max_dir_width_and_files_exact_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--max-dir-width",
+            "10",
+            "--files-exact",
+        ]);
+
+        assert!(g.files_exact);
+        assert_eq!(g.max_dir_width, Some(NonZeroUsize::new(10).unwrap()));
+    }
+
+    #[test]
+    fn generate_max_dir_width_and_exact_conflict() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--max-dir-width", "10", "--exact"],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_chain_and_ratio_conflict() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--chain", "-r", "1"],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_overwrite_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--overwrite",
+            "--force-dangerous",
+        ]);
+
+        assert!(g.overwrite);
+        assert!(g.force_dangerous);
+    }
+
+    #[test]
+    fn generate_force_dangerous_requires_overwrite() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--force-dangerous"],
+            MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn generate_overwrite_and_repair_conflict() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--overwrite",
+                "--repair"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_self_check_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--self-check"]);
+
+        assert!(g.self_check);
+    }
+
+    #[test]
+    fn generate_repair_dir_needs_dir_seeds() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--repair", "--repair-dir", "0.dir"],
+            MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn generate_dir_seeds_needs_write_marker() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--dir-seeds"],
+            MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn generate_write_marker_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--write-marker"]);
+
+        assert!(g.write_marker);
+    }
+
+    #[test]
+    fn generate_write_marker_can_be_combined_with_self_check() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--write-marker",
+            "--self-check",
+        ]);
+
+        assert!(g.write_marker);
+        assert!(g.self_check);
+    }
+
+    #[test]
+    fn generate_umask_is_parsed_as_octal() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--umask", "027"]);
+
+        assert_eq!(g.umask, Some(0o027));
+    }
+
+    #[rstest]
+    #[case("1000")]
+    #[case("8")]
+    #[case("rwx")]
+    fn generate_umask_rejects_invalid_masks(#[case] mask: &str) {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--umask", mask],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_empty_files_accepts_si_numbers() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--empty-files", "1K"]);
+
+        assert_eq!(g.empty_files, 1000);
+    }
+
+    #[test]
+    fn generate_byte_rate_accepts_si_numbers() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--byte-rate", "5M"]);
+
+        assert_eq!(g.byte_rate, NonZeroUsize::new(5_000_000));
+    }
+
+    #[test]
+    fn generate_write_memory_budget_accepts_si_numbers() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--write-memory-budget",
+            "64M",
+        ]);
+
+        assert_eq!(g.write_memory_budget, NonZeroUsize::new(64_000_000));
+    }
+
+    #[test]
+    fn generate_write_memory_budget_rejects_zero() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--write-memory-budget", "0"],
+            ValueValidation
+        );
+    }
+
+    #[rstest]
+    #[case("1500ms", Duration::from_millis(1500))]
+    #[case("30", Duration::from_secs(30))]
+    #[case("2.5s", Duration::from_millis(2500))]
+    #[case("2m", Duration::from_secs(120))]
+    fn generate_ramp_up_accepts_durations(#[case] ramp_up: &str, #[case] expected: Duration) {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--ramp-up", ramp_up]);
+
+        assert_eq!(g.ramp_up, Some(expected));
+    }
+
+    #[rstest]
+    fn generate_ramp_up_rejects_invalid_durations(#[values("0s", "5h", "s")] ramp_up: &str) {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--ramp-up", ramp_up],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_cpu_affinity_accepts_ranges() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--cpu-affinity",
+            "4-6,0,5",
+        ]);
+
+        assert_eq!(g.cpu_affinity, Some(CpuList(vec![0, 4, 5, 6])));
+    }
+
+    #[test]
+    fn generate_cpu_affinity_rejects_backwards_ranges() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--cpu-affinity", "3-1"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_rate_limit_accepts_si_numbers() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--rate-limit", "2K"]);
+
+        assert_eq!(g.rate_limit, NonZeroUsize::new(2000));
+    }
+
+    #[test]
+    fn generate_seed_paths_are_split_on_commas() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--seed-paths",
+            ".git/,a/b",
+        ]);
+
+        assert_eq!(g.seed_paths, vec![".git/", "a/b"]);
+    }
+
+    #[test]
+    fn generate_checkpoint_conflicts_with_overwrite() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--checkpoint",
+                "progress.json",
+                "--overwrite",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_overlay_source_conflicts_with_repair() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--overlay-source",
+                "src",
+                "--repair",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_overlay_source_conflicts_with_dirs_exact() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--overlay-source",
+                "src",
+                "--dirs-exact",
+                "5",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_progress_format_accepts_jsonl() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--progress-format",
+            "jsonl",
+        ]);
+
+        assert_eq!(g.progress_format, Some(ProgressFormat::Jsonl));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn generate_io_report_requires_progress_format() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--io-report"],
+            MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn generate_log_target_defaults_to_stdout() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir"]);
+
+        assert_eq!(g.log_target, LogTarget::Stdout);
+    }
+
+    #[test]
+    fn generate_log_target_accepts_syslog() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--log-target",
+            "syslog",
+        ]);
+
+        assert_eq!(g.log_target, LogTarget::Syslog);
+    }
+
+    #[test]
+    fn generate_names_from_content_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--deterministic-names-from-content",
+        ]);
+
+        assert!(g.names_from_content);
+    }
+
+    #[test]
+    fn generate_git_objects_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "dir", "--git-objects", "10"]);
+
+        assert_eq!(g.git_objects, Some(NonZeroUsize::new(10).unwrap()));
+    }
+
+    #[test]
+    fn generate_git_objects_conflicts_with_num_files() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--git-objects", "10"],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_sparse_ratio_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--sparse-ratio",
+            "0.5",
+        ]);
+
+        assert_eq!(g.sparse_ratio, 0.5);
+    }
+
+    #[test]
+    fn generate_sparse_holes_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--sparse-ratio",
+            "1",
+            "--sparse-holes",
+        ]);
+
+        assert!(g.sparse_holes);
+    }
+
+    #[test]
+    fn generate_dump_samples_conflicts_with_two_phase() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--dump-samples",
+                "samples.jsonl",
+                "--two-phase",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_entries_replaces_files() {
+        let g = expect_success!(vec!["ftzz", "generate", "--entries", "1K", "dir"]);
+
+        assert_eq!(g.num_files, None);
+        assert_eq!(g.num_entries, NonZeroUsize::new(1000));
+    }
+
+    #[test]
+    fn generate_entries_conflicts_with_files() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "--entries", "1K", "dir"],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_max_total_size_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--max-total-size",
+            "1M",
+        ]);
+
+        assert_eq!(g.max_total_size, Some(1_000_000));
+    }
+
+    #[test]
+    fn generate_max_total_size_conflicts_with_self_check() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--max-total-size",
+                "1M",
+                "--self-check",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_size_alignment_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "-b",
+            "1M",
+            "dir",
+            "--size-alignment",
+            "4K",
+        ]);
+
+        assert_eq!(g.size_alignment, NonZeroUsize::new(4_000));
+    }
+
+    #[test]
+    fn generate_size_alignment_conflicts_with_archive() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "-b",
+                "1M",
+                "dir",
+                "--size-alignment",
+                "512",
+                "--archive",
+                "tar",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_preallocate_conflicts_with_sparse_ratio() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "-b",
+                "1M",
+                "dir",
+                "--preallocate",
+                "--sparse-ratio",
+                "0.5",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_read_only_ratio_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--read-only-ratio",
+            "0.25",
+            "--read-only-dirs",
+        ]);
+
+        assert_eq!(g.read_only_ratio, 0.25);
+        assert!(g.read_only_dirs);
+    }
+
+    #[test]
+    fn generate_xattr_ratio_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--xattr-ratio", "0.5"]);
+
+        assert_eq!(g.xattr_ratio, 0.5);
+    }
+
+    #[test]
+    fn generate_xattr_ratio_rejects_ratios_above_one() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--xattr-ratio", "2"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_media_mix_is_split_on_commas() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--media-mix",
+            "png,wav,png",
+        ]);
+
+        assert_eq!(g.media_mix, vec![MediaFormat::Png, MediaFormat::Wav, MediaFormat::Png]);
+    }
+
+    #[test]
+    fn generate_media_mix_conflicts_with_archive() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--media-mix",
+                "mp3",
+                "--archive",
+                "tar",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_group_by_extension_requires_media_mix() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--group-by-extension"],
+            MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn generate_profile_presets_bytes_and_sizes() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "10", "dir", "--profile", "logs"]);
+        assert_eq!(g.profile, Some(Profile::Logs));
+
+        let hack = format!("{:?}", Generator::try_from(g).unwrap());
+        assert!(hack.contains("num_bytes: 655360"));
+        assert!(hack.contains("size_distribution: Zipf { exponent: 1.0 }"));
+    }
+
+    #[test]
+    fn generate_explicit_options_override_profile() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "10",
+            "dir",
+            "--profile",
+            "logs",
+            "-b",
+            "1000",
+            "--size-distribution",
+            "normal",
+        ]);
+
+        let hack = format!("{:?}", Generator::try_from(g).unwrap());
+        assert!(hack.contains("num_bytes: 1000"));
+        assert!(hack.contains("size_distribution: Normal"));
+    }
+
+    #[test]
+    fn generate_unknown_profile_fails() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--profile", "music"],
+            InvalidValue
+        );
+    }
+
+    #[test]
+    fn generate_hidden_ratio_rejects_out_of_range() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--hidden-ratio", "1.5"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_case_collisions_conflict_with_name_styles() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "dir",
+                "--case-collisions",
+                "--name-style",
+                "unicode-random",
+            ],
+            ArgumentConflict
+        );
+    }
 
     #[test]
-    fn generate_empty_args_displays_error() {
-        expect_error!(vec!["ftzz", "generate"], MissingRequiredArgument);
+    fn generate_validate_only_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--validate-only"]);
+
+        assert!(g.validate_only);
     }
 
     #[test]
-    fn generate_minimal_use_case_uses_defaults() {
-        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir"]);
+    fn generate_print_config_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--print-config"]);
 
-        assert_eq!(g.root_dir, PathBuf::from("dir"));
-        assert_eq!(g.num_files.get(), 1);
-        assert_eq!(g.max_depth, 5);
-        assert_eq!(g.file_to_dir_ratio, None);
-        assert_eq!(g.seed, 0);
-        assert!(!g.files_exact);
-        assert!(!g.bytes_exact);
-        assert!(!g.exact);
-        assert_eq!(g.num_bytes, 0);
+        assert!(g.print_config);
     }
 
     #[test]
-    fn generate_num_files_rejects_negatives() {
-        expect_error!(vec!["ftzz", "generate", "-n", "-1", "dir"], UnknownArgument);
+    fn generate_emit_script_can_be_used() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--emit-script"]);
+
+        assert!(g.emit_script);
     }
 
     #[test]
-    fn generate_num_files_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "--files", "1000", "dir"]);
+    fn generate_emit_script_and_overwrite_conflict() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--emit-script",
+                "--overwrite"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_min_files_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1K",
+            "dir",
+            "--min-files",
+            "1K"
+        ]);
 
-        assert_eq!(g.num_files.get(), 1000);
+        assert_eq!(g.min_files, Some(1_000));
     }
 
     #[test]
-    fn generate_short_num_files_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "-n", "1000", "dir"]);
+    fn generate_min_dir_count_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1K",
+            "dir",
+            "--min-dir-count",
+            "100"
+        ]);
+
+        assert_eq!(g.min_dirs, Some(100));
+    }
+
+    #[test]
+    fn generate_min_dir_count_conflicts_with_exact_dirs() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--min-dir-count",
+                "1",
+                "--dirs-exact",
+                "1"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_min_files_per_dir_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1K",
+            "dir",
+            "--min-files-per-dir",
+            "3"
+        ]);
+
+        assert_eq!(g.min_files_per_dir, Some(3));
+    }
+
+    #[test]
+    fn generate_min_files_per_dir_conflicts_with_exact_files() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--min-files-per-dir",
+                "1",
+                "--files-exact"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_wordlists_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--dir-wordlist",
+            "--file-wordlist=words.txt",
+        ]);
+
+        assert_eq!(g.dir_wordlist, Some(None));
+        assert_eq!(g.file_wordlist, Some(Some(PathBuf::from("words.txt"))));
+    }
+
+    #[test]
+    fn generate_mtime_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--mtime",
+            "1000000000",
+        ]);
+
+        assert_eq!(g.mtime, Some(1_000_000_000));
+    }
+
+    #[test]
+    fn generate_mtime_sequence_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--mtime-sequence",
+            "1000000000,60",
+        ]);
+
+        assert_eq!(
+            g.mtime_sequence,
+            Some(MtimeSequence {
+                start: 1_000_000_000,
+                interval: 60,
+            })
+        );
+    }
+
+    #[test]
+    fn generate_mtime_sequence_conflicts_with_mtime() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--mtime-sequence",
+                "0,1",
+                "--mtime",
+                "0",
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_mtime_sequence_requires_an_interval() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--mtime-sequence", "0,0"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_pattern_accepts_hex() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--pattern",
+            "0xDEADbeef",
+        ]);
+
+        assert_eq!(
+            g.pattern,
+            Some(FillMode::Repeating(vec![0xDE, 0xAD, 0xBE, 0xEF]))
+        );
+    }
+
+    #[test]
+    fn generate_color_never_disables_colors() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--color", "never"]);
+
+        assert_eq!(g.color, Some(ColorChoice::Never));
+        assert!(format!("{:?}", Generator::try_from(g).unwrap()).contains("color: false"));
+    }
+
+    #[test]
+    fn generate_no_color_disables_colors() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--no-color"]);
+
+        assert!(format!("{:?}", Generator::try_from(g).unwrap()).contains("color: false"));
+    }
+
+    #[test]
+    fn generate_no_color_conflicts_with_color() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--no-color", "--color", "always"],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_balanced_accepts_fanouts() {
+        let g = expect_success!(vec!["ftzz", "generate", "-n", "1K", "dir", "--balanced", "10"]);
+
+        assert_eq!(g.balanced, Some(10));
+    }
+
+    #[rstest]
+    #[case("1")]
+    #[case("0")]
+    fn generate_balanced_rejects_degenerate_fanouts(#[case] fanout: &str) {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--balanced", fanout],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_balanced_conflicts_with_chain() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--balanced", "2", "--chain"],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_nested_archives_can_be_requested() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--archive",
+            "zip,3",
+            "--nesting-depth",
+            "10",
+        ]);
+
+        assert_eq!(
+            g.archive,
+            Some(FillMode::Archive {
+                format: ArchiveFormat::Zip,
+                inner_entries: 3,
+            })
+        );
+        assert_eq!(g.nesting_depth, Some(10));
+    }
+
+    #[test]
+    fn generate_nesting_depth_requires_an_archive() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--nesting-depth", "10"],
+            MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn generate_archive_rejects_unknown_formats() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--archive", "rar"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_content_entropy_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--content-entropy",
+            "0.25"
+        ]);
+
+        assert_eq!(g.content_entropy, Some(FillMode::Blended { permille: 250 }));
+    }
+
+    #[test]
+    fn generate_content_entropy_rejects_out_of_range_values() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--content-entropy", "1.5"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_sequence_header_can_be_used() {
+        for (format, expected) in [
+            ("text", SequenceHeader::Text),
+            ("u64le", SequenceHeader::U64Le),
+        ] {
+            let g = expect_success!(vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--sequence-header",
+                format,
+            ]);
+
+            assert_eq!(g.sequence_header, Some(expected), "{}", format);
+        }
+    }
+
+    #[test]
+    fn generate_sequence_header_conflicts_with_text_header() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--sequence-header",
+                "text",
+                "--text-header",
+                "utf8-bom",
+            ],
+            ArgumentConflict
+        );
+    }
 
-        assert_eq!(g.num_files.get(), 1000);
+    #[test]
+    fn generate_sequence_header_conflicts_with_exact_bytes() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--sequence-header",
+                "text",
+                "--bytes-exact",
+            ],
+            ArgumentConflict
+        );
     }
 
     #[test]
-    fn generate_num_files_accepts_si_numbers() {
-        let g = expect_success!(vec!["ftzz", "generate", "--files", "1K", "dir"]);
+    fn generate_text_header_accepts_presets_and_bytes() {
+        for (header, expected) in [
+            ("utf8-bom", &[0xEF, 0xBB, 0xBF][..]),
+            ("utf16le-bom", &[0xFF, 0xFE]),
+            ("0x2321", b"#!"),
+            ("#!", b"#!"),
+        ] {
+            let g = expect_success!(vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--text-header",
+                header,
+            ]);
 
-        assert_eq!(g.num_files.get(), 1000);
+            assert_eq!(g.text_header.as_deref(), Some(expected), "{}", header);
+        }
     }
 
     #[test]
-    fn generate_num_files_accepts_commas() {
-        let g = expect_success!(vec!["ftzz", "generate", "--files", "1,000", "dir"]);
+    fn generate_pattern_accepts_strings() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--pattern",
+            "test",
+        ]);
 
-        assert_eq!(g.num_files.get(), 1000);
+        assert_eq!(g.pattern, Some(FillMode::Repeating(b"test".to_vec())));
     }
 
     #[test]
-    fn generate_num_files_accepts_underscores() {
-        let g = expect_success!(vec!["ftzz", "generate", "--files", "1_000", "dir"]);
-
-        assert_eq!(g.num_files.get(), 1000);
+    fn generate_pattern_rejects_empty() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--pattern", ""],
+            ValueValidation
+        );
     }
 
     #[test]
-    fn generate_max_depth_rejects_negatives() {
+    fn generate_pattern_rejects_invalid_hex() {
         expect_error!(
-            vec!["ftzz", "generate", "--depth", "-1", "-n", "1", "dir"],
-            UnknownArgument
+            vec!["ftzz", "generate", "-n", "1", "dir", "--pattern", "0xABC"],
+            ValueValidation
+        );
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--pattern", "0xZZ"],
+            ValueValidation
         );
     }
 
-    #[test]
-    fn generate_max_depth_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "--depth", "123", "-n", "1", "dir"]);
-
-        assert_eq!(g.max_depth, 123);
+    #[rstest]
+    #[case("0xaéb")]
+    #[case("0x+1")]
+    #[case("0x+12a")]
+    fn generate_pattern_rejects_non_hex_digits(#[case] pattern: &str) {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--pattern", pattern],
+            ValueValidation
+        );
     }
 
     #[test]
-    fn generate_short_max_depth_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "-d", "123", "-n", "1", "dir"]);
+    fn generate_cluster_factor_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--cluster-factor",
+            "0.9",
+        ]);
 
-        assert_eq!(g.max_depth, 123);
+        assert_eq!(g.cluster_factor, 0.9);
     }
 
     #[test]
-    fn generate_ratio_rejects_negatives() {
+    fn generate_cluster_factor_rejects_one() {
         expect_error!(
-            vec!["ftzz", "generate", "--ftd-ratio", "-1", "-n", "1", "dir",],
-            UnknownArgument
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--cluster-factor",
+                "1"
+            ],
+            ValueValidation
         );
     }
 
     #[test]
-    fn generate_ratio_accepts_plain_nums() {
+    fn generate_special_ratio_can_be_used() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
-            "--ftd-ratio",
-            "1000",
             "-n",
             "1",
             "dir",
+            "--special-ratio",
+            "0.1",
         ]);
 
-        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+        assert_eq!(g.special_ratio, 0.1);
     }
 
     #[test]
-    fn generate_short_ratio_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "-r", "321", "-n", "1", "dir"]);
+    fn generate_special_ratio_rejects_negatives() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--special-ratio=-0.1"],
+            ValueValidation
+        );
+    }
 
-        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(321).unwrap()));
+    #[test]
+    fn generate_special_ratio_rejects_more_than_one() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--special-ratio",
+                "1.1"
+            ],
+            ValueValidation
+        );
     }
 
     #[test]
-    fn generate_ratio_accepts_si_numbers() {
+    fn generate_checksums_can_be_used() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
-            "--ftd-ratio",
-            "1K",
             "-n",
             "1",
             "dir",
+            "--checksums",
+            "sums",
         ]);
 
-        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+        assert_eq!(g.checksums, Some(PathBuf::from("sums")));
     }
 
     #[test]
-    fn generate_ratio_accepts_commas() {
+    fn generate_catalog_can_be_used() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
-            "--ftd-ratio",
-            "1,000",
             "-n",
             "1",
             "dir",
+            "--catalog",
+            "catalog.csv"
         ]);
 
-        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+        assert_eq!(g.catalog, Some(PathBuf::from("catalog.csv")));
     }
 
     #[test]
-    fn generate_ratio_accepts_underscores() {
+    fn generate_checksums_conflicts_with_repair() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--checksums",
+                "sums",
+                "--repair"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_dup_groups_accepts_si_numbers() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
-            "--ftd-ratio",
-            "1_000",
             "-n",
             "1",
             "dir",
+            "--dup-groups",
+            "1K",
         ]);
 
-        assert_eq!(g.file_to_dir_ratio, Some(NonZeroUsize::new(1000).unwrap()));
+        assert_eq!(g.duplicate_groups, Some(NonZeroUsize::new(1000).unwrap()));
     }
 
     #[test]
-    fn generate_seed_rejects_negatives() {
+    fn generate_dup_groups_rejects_zero() {
         expect_error!(
-            vec!["ftzz", "generate", "--seed", "-1", "-n", "1", "dir",],
-            UnknownArgument
+            vec!["ftzz", "generate", "-n", "1", "dir", "--dup-groups", "0"],
+            ValueValidation
         );
     }
 
     #[test]
-    fn generate_seed_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "--seed", "231", "-n", "1", "dir",]);
-
-        assert_eq!(g.seed, 231);
+    fn generate_content_source_conflicts_with_pattern() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--content-source",
+                "samples",
+                "--pattern",
+                "test",
+            ],
+            ArgumentConflict
+        );
     }
 
     #[test]
-    fn generate_num_bytes_accepts_plain_nums() {
+    fn generate_target_disk_usage_accepts_si_sizes() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
             "-n",
             "1",
             "dir",
-            "--total-bytes",
-            "1000",
+            "--target-disk-usage",
+            "1G",
+            "--block-size",
+            "512",
         ]);
 
-        assert_eq!(g.num_bytes, 1000);
+        assert_eq!(g.target_disk_usage, Some(1_000_000_000));
+        assert_eq!(g.block_size.get(), 512);
     }
 
     #[test]
-    fn generate_short_num_bytes_accepts_plain_nums() {
-        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "-b", "1000"]);
+    fn generate_empty_block_size_fails() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--block-size", "0"],
+            ValueValidation
+        );
+    }
 
-        assert_eq!(g.num_bytes, 1000);
+    #[test]
+    fn generate_size_schedule_replaces_num_files() {
+        let g = expect_success!(vec!["ftzz", "generate", "dir", "--size-schedule", "sizes"]);
+
+        assert_eq!(g.num_files, None);
+        assert_eq!(g.size_schedule, Some(PathBuf::from("sizes")));
     }
 
     #[test]
-    fn generate_num_bytes_accepts_si_numbers() {
+    fn generate_size_schedule_conflicts_with_num_files() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--size-schedule",
+                "sizes"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_layout_replaces_num_files() {
+        let g = expect_success!(vec!["ftzz", "generate", "dir", "--layout", "layout.json"]);
+
+        assert_eq!(g.num_files, None);
+        assert_eq!(g.layout, Some(PathBuf::from("layout.json")));
+    }
+
+    #[test]
+    fn generate_layout_conflicts_with_num_files() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--layout",
+                "layout.json"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_dir_fraction_can_be_used() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
             "-n",
             "1",
             "dir",
-            "--total-bytes",
-            "1K",
+            "--dir-fraction",
+            "0.2"
         ]);
 
-        assert_eq!(g.num_bytes, 1000);
+        assert_eq!(g.dir_fraction, Some(0.2));
     }
 
     #[test]
-    fn generate_num_bytes_accepts_commas() {
+    fn generate_dir_fraction_conflicts_with_ratio() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--dir-fraction",
+                "0.2",
+                "-r",
+                "10"
+            ],
+            ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn generate_dir_fraction_rejects_whole_numbers() {
+        expect_error!(
+            vec!["ftzz", "generate", "-n", "1", "dir", "--dir-fraction", "1"],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_dir_depth_distribution_accepts_mean_and_cv() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
             "-n",
             "1",
             "dir",
-            "--total-bytes",
-            "1,000",
+            "--dir-depth-distribution",
+            "3,0.5",
         ]);
 
-        assert_eq!(g.num_bytes, 1000);
+        assert_eq!(
+            g.leaf_depth_distribution,
+            Some(DepthDistribution::Normal { mean: 3., cv: 0.5 })
+        );
     }
 
     #[test]
-    fn generate_num_bytes_accepts_underscores() {
+    fn generate_dir_depth_distribution_defaults_cv() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
             "-n",
             "1",
             "dir",
-            "--total-bytes",
-            "1_000",
+            "--dir-depth-distribution",
+            "2.5",
         ]);
 
-        assert_eq!(g.num_bytes, 1000);
+        assert_eq!(
+            g.leaf_depth_distribution,
+            Some(DepthDistribution::Normal { mean: 2.5, cv: 0.2 })
+        );
     }
 
     #[test]
-    fn generate_files_exact_and_exact_conflict() {
+    fn generate_dir_depth_distribution_rejects_non_positive_means() {
         expect_error!(
             vec![
                 "ftzz",
@@ -551,15 +4661,48 @@ mod cli_generate_tests {
                 "-n",
                 "1",
                 "dir",
-                "--files-exact",
-                "--exact",
+                "--dir-depth-distribution",
+                "0"
+            ],
+            ValueValidation
+        );
+    }
+
+    #[test]
+    fn generate_depth_jitter_conflicts_with_dir_depth_distribution() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--depth-jitter",
+                "2",
+                "--dir-depth-distribution",
+                "3",
             ],
             ArgumentConflict
         );
     }
 
     #[test]
-    fn generate_bytes_exact_and_exact_conflict() {
+    fn generate_structural_levels_can_be_used() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1K",
+            "dir",
+            "--structural-levels",
+            "2"
+        ]);
+
+        assert_eq!(g.structural_levels, Some(2));
+    }
+
+    #[test]
+    fn generate_structural_levels_conflicts_with_chain() {
         expect_error!(
             vec![
                 "ftzz",
@@ -567,40 +4710,57 @@ mod cli_generate_tests {
                 "-n",
                 "1",
                 "dir",
-                "--bytes-exact",
-                "--exact",
+                "--structural-levels",
+                "1",
+                "--chain",
             ],
             ArgumentConflict
         );
     }
 
     #[test]
-    fn generate_files_exact_and_bytes_exact_can_be_used() {
+    fn generate_size_distribution_accepts_zipf_exponents() {
         let g = expect_success!(vec![
             "ftzz",
             "generate",
             "-n",
             "1",
             "dir",
-            "--files-exact",
-            "--bytes-exact",
+            "--size-distribution",
+            "zipf,1.2"
         ]);
 
-        assert!(g.files_exact);
-        assert!(g.bytes_exact);
+        assert_eq!(g.size_distribution, Some(SizeDistribution::Zipf { exponent: 1.2 }));
     }
 
     #[test]
-    fn generate_exact_can_be_used() {
-        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "--exact"]);
+    fn generate_size_distribution_defaults_zipf_exponent() {
+        let g = expect_success!(vec![
+            "ftzz",
+            "generate",
+            "-n",
+            "1",
+            "dir",
+            "--size-distribution",
+            "zipf"
+        ]);
 
-        assert!(g.exact);
+        assert_eq!(g.size_distribution, Some(SizeDistribution::Zipf { exponent: 1. }));
     }
 
     #[test]
-    fn generate_short_exact_can_be_used() {
-        let g = expect_success!(vec!["ftzz", "generate", "-n", "1", "dir", "-e"]);
-
-        assert!(g.exact);
+    fn generate_size_distribution_rejects_unknown_distributions() {
+        expect_error!(
+            vec![
+                "ftzz",
+                "generate",
+                "-n",
+                "1",
+                "dir",
+                "--size-distribution",
+                "normal,3"
+            ],
+            ValueValidation
+        );
     }
 }
@@ -109,6 +109,16 @@ impl<'a> CStrFastPathBufGuard<'a> {
         buf.inner.push(0); // NUL terminator
         CStrFastPathBufGuard { buf }
     }
+
+    /// The most recently pushed name. Only valid immediately after a [FastPathBuf::push] call.
+    pub fn file_name(&self) -> &CStr {
+        let name = &self.buf.inner[self.buf.last_len + 1..];
+        if cfg!(debug_assertions) {
+            CStr::from_bytes_with_nul(name).unwrap()
+        } else {
+            unsafe { CStr::from_bytes_with_nul_unchecked(name) }
+        }
+    }
 }
 
 impl<'a> Deref for CStrFastPathBufGuard<'a> {
@@ -1,4 +1,19 @@
-use std::{mem::MaybeUninit, ptr, slice};
+use std::{
+    cmp::min,
+    collections::{HashMap, HashSet},
+    fmt,
+    mem::MaybeUninit,
+    path::{is_separator, Path},
+    ptr, slice,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Utc,
+};
+use serde::{Deserialize, Serialize};
 
 struct FileNameCache {
     cache: [MaybeUninit<u8>; 3000],
@@ -86,8 +101,598 @@ pub fn with_dir_name<T>(i: usize, f: impl FnOnce(&str) -> T) -> T {
     })
 }
 
+/// Like [`with_file_name`], but left pads the name with zeros up to `width` characters such that
+/// lexicographic and numeric ordering agree for all names of the same width.
+pub fn with_padded_file_name<T>(i: usize, width: usize, f: impl FnOnce(&str) -> T) -> T {
+    if width <= 1 {
+        return with_file_name(i, f);
+    }
+
+    with_file_name(i, |s| {
+        if s.len() >= width {
+            return f(s);
+        }
+
+        let mut buf = [b'0'; 39]; // 39 to support u128
+        debug_assert!(width <= buf.len());
+        buf[width - s.len()..width].copy_from_slice(s.as_bytes());
+        f(unsafe { std::str::from_utf8_unchecked(&buf[..width]) })
+    })
+}
+
+/// The number of characters needed to name the file with index `max_index`.
+pub const fn file_name_width(mut max_index: usize) -> usize {
+    let mut width = 1;
+    while max_index >= 10 {
+        max_index /= 10;
+        width += 1;
+    }
+    width
+}
+
+/// The characters file and directory names are made of.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameStyle {
+    /// Plain increasing numbers.
+    #[default]
+    Numeric,
+    /// Numbers prefixed with random characters from a broad range of scripts, including non-ASCII.
+    UnicodeRandom,
+    /// Files named after timestamps `step` seconds apart starting at the Unix time `start`, as
+    /// formatted by a chrono `format` string such as `app-%Y-%m-%d.log`. Directories keep numeric
+    /// names.
+    Timestamped {
+        format: String,
+        start: i64,
+        step: u64,
+    },
+}
+
+impl FromStr for NameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "numeric" => Ok(Self::Numeric),
+            "unicode-random" => Ok(Self::UnicodeRandom),
+            _ => match s.strip_prefix("timestamped,").map(|s| s.splitn(3, ',')) {
+                Some(mut params) => {
+                    let start = params.next().unwrap_or_default();
+                    let step = params.next().unwrap_or_default();
+                    let format = params.next().unwrap_or_default();
+                    Ok(Self::Timestamped {
+                        format: String::from(format),
+                        start: start.trim().parse().map_err(|e| e.to_string())?,
+                        step: step.trim().parse().map_err(|e| e.to_string())?,
+                    })
+                }
+                None => Err(format!("Unknown name style: {}", s)),
+            },
+        }
+    }
+}
+
+/// Names files after evenly spaced timestamps.
+#[derive(Debug, Clone)]
+pub struct Timestamps {
+    pub format: Arc<str>,
+    pub start: i64,
+    pub step: u64,
+}
+
+impl Timestamps {
+    fn time(&self, i: usize) -> Option<DateTime<Utc>> {
+        let offset = i64::try_from(self.step)
+            .ok()?
+            .checked_mul(i64::try_from(i).ok()?)?;
+        DateTime::from_timestamp(self.start.checked_add(offset)?, 0)
+    }
+
+    fn name(&self, i: usize) -> String {
+        self.time(i)
+            .expect("Timestamps were validated to stay in range")
+            .format(&self.format)
+            .to_string()
+    }
+}
+
+/// Checks that naming `num_files` files after timestamps produces valid names that stay in range.
+/// Uniqueness is only checked among the first 100,000 names.
+pub fn validate_timestamps(timestamps: &Timestamps, num_files: usize) -> Result<(), String> {
+    const MAX_CHECKED: usize = 100_000;
+
+    if StrftimeItems::new(&timestamps.format).any(|item| item == Item::Error) {
+        return Err(format!(
+            "{:?} is not a valid timestamp format.",
+            timestamps.format
+        ));
+    }
+    if timestamps.step == 0 {
+        return Err(String::from("Timestamps must be at least a second apart."));
+    }
+    if timestamps.time(num_files.saturating_sub(1)).is_none() {
+        return Err(format!(
+            "{} timestamps starting at {} and {} seconds apart run out of range.",
+            num_files, timestamps.start, timestamps.step,
+        ));
+    }
+
+    let mut seen = HashMap::with_capacity(min(num_files, MAX_CHECKED));
+    for i in 0..min(num_files, MAX_CHECKED) {
+        let name = timestamps.name(i);
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+            return Err(format!("{:?} is not a valid file name.", name));
+        }
+        if let Some(first) = seen.insert(name, i) {
+            return Err(format!(
+                "Files {} and {} would have the same name with the timestamp format {:?}, use a \
+                 format with finer fields or a larger step.",
+                first, i, timestamps.format,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Ranges of characters which are legal in file names on every major platform. None of them have
+/// canonical decompositions so that normalizing file systems (e.g. HFS+) hand back the exact bytes
+/// we wrote, and none are ASCII digits so that the numeric suffix keeps names unique.
+const UNICODE_RANGES: [(u32, u32); 11] = [
+    (0x3B1, 25),      // Greek α-ω
+    (0x430, 9),       // Cyrillic а-и (й has a decomposition)
+    (0x43A, 22),      // Cyrillic к-я
+    (0x5D0, 27),      // Hebrew
+    (0x627, 20),      // Arabic
+    (0x4E00, 0x5200), // CJK Unified Ideographs
+    (0x1F600, 80),    // Emoticons
+    // ASCII, never used for the first character to avoid reserved Windows names (CON, COM1, etc.)
+    (b'a' as u32, 26),
+    (b'A' as u32, 26),
+    (b'#' as u32, 7), // #$%&'()
+    (b'+' as u32, 3), // +,-
+];
+const NUM_NON_ASCII_RANGES: usize = 7;
+
+/// Common directory names for human-readable trees.
+pub const DEFAULT_DIR_WORDLIST: &[&str] = &[
+    "src",
+    "docs",
+    "tests",
+    "assets",
+    "lib",
+    "bin",
+    "config",
+    "scripts",
+    "examples",
+    "build",
+    "data",
+    "images",
+    "vendor",
+    "tools",
+    "include",
+    "templates",
+    "public",
+    "styles",
+    "utils",
+    "fixtures",
+];
+
+/// Common file names for human-readable trees.
+pub const DEFAULT_FILE_WORDLIST: &[&str] = &[
+    "README.md",
+    "LICENSE",
+    "main.rs",
+    "index.html",
+    "notes.txt",
+    "config.toml",
+    "Makefile",
+    "app.js",
+    "style.css",
+    "data.json",
+    "report.pdf",
+    "photo.jpg",
+    "setup.py",
+    "todo.txt",
+    "schema.sql",
+    "logo.png",
+    "CHANGELOG.md",
+    "package.json",
+    "server.go",
+    "test.c",
+];
+
+/// Checks that every word is a valid file name and that suffixing words to keep them unique can't
+/// produce another word in the list.
+pub fn validate_wordlist(words: &[String]) -> Result<(), String> {
+    if words.is_empty() {
+        return Err(String::from("Wordlists cannot be empty."));
+    }
+
+    let mut seen = HashSet::with_capacity(words.len());
+    for word in words {
+        if word.is_empty() || word == "." || word == ".." || word.contains(['/', '\0']) {
+            return Err(format!("{:?} is not a valid file name.", word));
+        }
+        let (stem, _) = split_extension(word);
+        if let Some((_, suffix)) = stem.rsplit_once('-') && !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!(
+                "{:?} cannot end in a numeric suffix as those are used to keep names unique.",
+                word,
+            ));
+        }
+        if !seen.insert(word) {
+            return Err(format!(
+                "{:?} appears in the wordlist more than once.",
+                word
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Splits a name into its stem and extension (including the dot), ignoring leading dots.
+pub fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+/// Names entry `i` after a word, appending a numeric suffix to the word's stem once every word has
+/// been used, e.g. `notes.txt`, `notes-1.txt`, `notes-2.txt`.
+fn with_word_name<T>(words: &[String], i: usize, f: impl FnOnce(&str) -> T) -> T {
+    let word = &words[i % words.len()];
+    let round = i / words.len();
+    if round == 0 {
+        return f(word);
+    }
+
+    let (stem, extension) = split_extension(word);
+    f(&format!("{}-{}{}", stem, round, extension))
+}
+/// Names entries in pairs that only differ in case, e.g. `a0`, `A0`, `a1`, `A1`.
+fn with_case_colliding_name<T>(i: usize, width: usize, f: impl FnOnce(&str) -> T) -> T {
+    with_padded_file_name(i / 2, width, |s| {
+        let mut buf = [0u8; 1 + 39]; // 39 to support u128
+        buf[0] = if i % 2 == 0 { b'a' } else { b'A' };
+        buf[1..=s.len()].copy_from_slice(s.as_bytes());
+        f(unsafe { std::str::from_utf8_unchecked(&buf[..=s.len()]) })
+    })
+}
+
+/// Whether a name is being picked for a file or a directory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    File,
+    Dir,
+}
+
+/// Describes the entry a [NameFn] is naming.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NameContext {
+    /// The depth of the directory the entry is in, 0 being the root directory.
+    pub depth: usize,
+    /// The entry's index among its siblings of the same kind.
+    pub index: usize,
+    pub kind: NameKind,
+    /// Derived from the generator's seed and the rest of the context.
+    pub seed: u64,
+}
+
+/// Computes the name of every generated file and directory.
+///
+/// Entries at the same depth with the same index and kind are named alike in every directory, so
+/// the function must return the same name for the same context.
+#[derive(Clone)]
+pub struct NameFn(Arc<dyn Fn(NameContext) -> String + Send + Sync>);
+
+impl<F: Fn(NameContext) -> String + Send + Sync + 'static> From<F> for NameFn {
+    fn from(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for NameFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NameFn")
+    }
+}
+
+/// The names a [NameFn] picked so far at each depth, with those that collide with an earlier
+/// sibling's or aren't valid names suffixed with `~` and the entry's index. Directories and files
+/// share a parent, so they're resolved together, alternating between the two kinds.
+#[derive(Debug, Default)]
+struct ResolvedNames {
+    dirs: Vec<Arc<str>>,
+    files: Vec<Arc<str>>,
+    taken: HashSet<Arc<str>>,
+}
+
+impl ResolvedNames {
+    fn names(&self, kind: NameKind) -> &[Arc<str>] {
+        match kind {
+            NameKind::Dir => &self.dirs,
+            NameKind::File => &self.files,
+        }
+    }
+}
+
+/// Names entries with a [NameFn].
+#[derive(Debug)]
+pub struct CustomNames {
+    name_fn: NameFn,
+    seed: u64,
+    root_depth: usize,
+    resolved: RwLock<HashMap<usize, ResolvedNames>>,
+}
+
+impl CustomNames {
+    pub fn new(name_fn: NameFn, seed: u64, root_dir: &Path) -> Self {
+        Self {
+            name_fn,
+            seed,
+            root_depth: root_dir.components().count(),
+            resolved: RwLock::default(),
+        }
+    }
+
+    fn with_name<T>(&self, depth: usize, i: usize, kind: NameKind, f: impl FnOnce(&str) -> T) -> T {
+        let cached = self
+            .resolved
+            .read()
+            .unwrap()
+            .get(&depth)
+            .and_then(|resolved| resolved.names(kind).get(i).cloned());
+        if let Some(name) = cached {
+            return f(&name);
+        }
+
+        // Names are resolved in order such that collisions always go the same way
+        let name = {
+            let mut resolved = self.resolved.write().unwrap();
+            let resolved = resolved.entry(depth).or_default();
+            for index in resolved.files.len()..=i {
+                for kind in [NameKind::Dir, NameKind::File] {
+                    let salt = split_mix(((depth as u64) << 1) | u64::from(kind == NameKind::Dir));
+                    let context = NameContext {
+                        depth,
+                        index,
+                        kind,
+                        seed: split_mix(self.seed.wrapping_add(index as u64) ^ salt),
+                    };
+                    let name = (self.name_fn.0)(context);
+                    let mut name = name.replace(|c: char| c == '\0' || is_separator(c), "_");
+                    while matches!(name.as_str(), "" | "." | "..")
+                        || resolved.taken.contains(name.as_str())
+                    {
+                        name.push('~');
+                        name.push_str(&index.to_string());
+                    }
+
+                    let name = Arc::<str>::from(name);
+                    resolved.taken.insert(name.clone());
+                    match kind {
+                        NameKind::Dir => resolved.dirs.push(name),
+                        NameKind::File => resolved.files.push(name),
+                    }
+                }
+            }
+            resolved.names(kind)[i].clone()
+        };
+        f(&name)
+    }
+}
+
+const MAX_UNICODE_CHARS: usize = 8;
+const MAX_UNICODE_PREFIX_LEN: usize = NAME_MAX;
+const JITTER_PREFIX_LEN: usize = 5;
+
+/// The longest file name, in bytes, that common file systems accept.
+pub const NAME_MAX: usize = 255;
+/// The shortest name length, in bytes, that always leaves room for an entry's index (up to 20
+/// digits), the directory suffix, the jitter prefix, the hidden dot, and a leading random
+/// character.
+pub const MIN_NAME_LENGTH: usize = 20 + ".dir".len() + JITTER_PREFIX_LEN + 1 + 4;
+
+/// Controls how generated files and directories are named.
+#[derive(Debug, Clone, Default)]
+pub struct Naming {
+    pub lex_sortable: bool,
+    /// Prefix names with a short hash of the seed and entry index to spread entries across
+    /// filesystem hash buckets.
+    pub hash_jitter: Option<u64>,
+    /// Prefix names with random Unicode characters derived from the seed and entry index.
+    pub unicode_random: Option<u64>,
+    /// Pad random names with as many characters as needed to reach a length in bytes picked
+    /// between these bounds, inclusive.
+    pub name_lengths: Option<(usize, usize)>,
+    /// Name directories after these words instead of numbers.
+    pub dir_words: Option<Arc<[String]>>,
+    /// Name files after these words instead of numbers.
+    pub file_words: Option<Arc<[String]>>,
+    /// Name files in pairs that only differ in case.
+    pub case_collisions: bool,
+    /// Prefix names with a dot when the hash of the seed and entry index is below the threshold.
+    pub hidden: Option<(u64, u64)>,
+    /// Name files after timestamps instead of numbers.
+    pub timestamps: Option<Timestamps>,
+    /// Name every entry with a function, overriding all other options.
+    pub custom: Option<Arc<CustomNames>>,
+}
+
+impl Naming {
+    /// The width file names should be padded to if the largest file index in a directory is
+    /// `max_index`.
+    pub fn file_name_width(&self, max_index: usize) -> usize {
+        if self.lex_sortable {
+            file_name_width(max_index)
+        } else {
+            0
+        }
+    }
+
+    /// How many directories below the root `dir` is, which only custom names depend on.
+    pub fn depth(&self, dir: &Path) -> usize {
+        self.custom.as_ref().map_or(0, |custom| {
+            dir.components().count().saturating_sub(custom.root_depth)
+        })
+    }
+
+    pub fn with_file_name<T>(
+        &self,
+        depth: usize,
+        i: usize,
+        width: usize,
+        f: impl FnOnce(&str) -> T,
+    ) -> T {
+        if let Some(custom) = &self.custom {
+            return custom.with_name(depth, i, NameKind::File, f);
+        }
+        if let Some(words) = &self.file_words {
+            return with_word_name(words, i, |s| self.with_hidden(i, s, f));
+        }
+        if self.case_collisions {
+            return with_case_colliding_name(i, width, |s| self.with_hidden(i, s, f));
+        }
+        if let Some(timestamps) = &self.timestamps {
+            return self.with_hidden(i, &timestamps.name(i), f);
+        }
+
+        with_padded_file_name(i, width, |s| {
+            self.with_unicode(i, s, |s| {
+                self.with_jitter(i, s, |s| self.with_hidden(i, s, f))
+            })
+        })
+    }
+
+    pub fn with_dir_name<T>(&self, depth: usize, i: usize, f: impl FnOnce(&str) -> T) -> T {
+        if let Some(custom) = &self.custom {
+            return custom.with_name(depth, i, NameKind::Dir, f);
+        }
+        if let Some(words) = &self.dir_words {
+            return with_word_name(words, i, |s| self.with_hidden(i, s, f));
+        }
+
+        with_dir_name(i, |s| {
+            self.with_unicode(i, s, |s| {
+                self.with_jitter(i, s, |s| self.with_hidden(i, s, f))
+            })
+        })
+    }
+
+    #[inline]
+    fn is_hidden(&self, i: usize) -> bool {
+        self.hidden.is_some_and(|(seed, threshold)| {
+            split_mix(seed.wrapping_add(i as u64) ^ 0xAAAA_AAAA_AAAA_AAAA) < *threshold
+        })
+    }
+
+    #[inline]
+    fn with_unicode<T>(&self, i: usize, name: &str, f: impl FnOnce(&str) -> T) -> T {
+        let seed = match self.unicode_random {
+            None => return f(name),
+            Some(seed) => seed,
+        };
+
+        let mut hash = split_mix(seed.wrapping_add(i as u64) ^ 0x5555_5555_5555_5555);
+        let (num_chars, prefix_len) = match self.name_lengths {
+            None => (1 + (hash % MAX_UNICODE_CHARS as u64) as usize, usize::MAX),
+            Some((min, max)) => {
+                let target = min + (hash % (max - min + 1) as u64) as usize;
+                let jitter_len = if self.hash_jitter.is_some() {
+                    JITTER_PREFIX_LEN
+                } else {
+                    0
+                };
+                let hidden_len = usize::from(self.is_hidden(i));
+                (usize::MAX, target - jitter_len - hidden_len - name.len())
+            }
+        };
+
+        let mut buf = [0u8; MAX_UNICODE_PREFIX_LEN + 39 + 4]; // 39 to support u128, 4 for the dir suffix
+        let mut len = 0;
+        for c in 0..num_chars {
+            let remaining = prefix_len - len;
+            if remaining == 0 {
+                break;
+            }
+
+            hash = split_mix(hash);
+            // Single byte ASCII characters fill the last few bytes exactly
+            let ranges = if c == 0 {
+                0..NUM_NON_ASCII_RANGES
+            } else if remaining < 4 {
+                NUM_NON_ASCII_RANGES..UNICODE_RANGES.len()
+            } else {
+                0..UNICODE_RANGES.len()
+            };
+            let (start, range_len) =
+                UNICODE_RANGES[ranges.start + (hash % ranges.len() as u64) as usize];
+            let code = start + ((hash >> 32) % range_len as u64) as u32;
+            len += char::from_u32(code)
+                .unwrap()
+                .encode_utf8(&mut buf[len..])
+                .len();
+        }
+        buf[len..len + name.len()].copy_from_slice(name.as_bytes());
+
+        f(unsafe { std::str::from_utf8_unchecked(&buf[..len + name.len()]) })
+    }
+
+    #[inline]
+    fn with_jitter<T>(&self, i: usize, name: &str, f: impl FnOnce(&str) -> T) -> T {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        const PREFIX_LEN: usize = JITTER_PREFIX_LEN;
+
+        let seed = match self.hash_jitter {
+            None => return f(name),
+            Some(seed) => seed,
+        };
+
+        let hash = split_mix(seed.wrapping_add(i as u64));
+
+        // 39 to support u128, 4 for the dir suffix
+        let mut buf = [0u8; PREFIX_LEN + MAX_UNICODE_PREFIX_LEN + 39 + 4];
+        for (nibble, b) in buf[..PREFIX_LEN - 1].iter_mut().enumerate() {
+            *b = HEX[(hash >> (60 - 4 * nibble) & 0xF) as usize];
+        }
+        buf[PREFIX_LEN - 1] = b'_';
+        buf[PREFIX_LEN..PREFIX_LEN + name.len()].copy_from_slice(name.as_bytes());
+
+        f(unsafe { std::str::from_utf8_unchecked(&buf[..PREFIX_LEN + name.len()]) })
+    }
+
+    #[inline]
+    fn with_hidden<T>(&self, i: usize, name: &str, f: impl FnOnce(&str) -> T) -> T {
+        if !self.is_hidden(i) {
+            return f(name);
+        }
+
+        // 39 to support u128, 4 for the dir suffix
+        let mut buf = [0u8; 1 + JITTER_PREFIX_LEN + MAX_UNICODE_PREFIX_LEN + 39 + 4];
+        if name.len() >= buf.len() {
+            // Only words can be this long
+            return f(&format!(".{}", name));
+        }
+        buf[0] = b'.';
+        buf[1..=name.len()].copy_from_slice(name.as_bytes());
+
+        f(unsafe { std::str::from_utf8_unchecked(&buf[..=name.len()]) })
+    }
+}
+
+/// The SplitMix64 finalizer
+#[inline]
+fn split_mix(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^ (hash >> 31)
+}
+
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
     #[test]
@@ -115,4 +720,284 @@ mod tests {
             });
         }
     }
+
+    #[test]
+    fn padded_names_are_returned() {
+        with_padded_file_name(7, 0, |s| assert_eq!(s, "7"));
+        with_padded_file_name(7, 3, |s| assert_eq!(s, "007"));
+        with_padded_file_name(1234, 3, |s| assert_eq!(s, "1234"));
+        with_padded_file_name(1234, 6, |s| assert_eq!(s, "001234"));
+    }
+
+    #[test]
+    fn file_name_width_correctness() {
+        assert_eq!(file_name_width(0), 1);
+        assert_eq!(file_name_width(9), 1);
+        assert_eq!(file_name_width(10), 2);
+        assert_eq!(file_name_width(999), 3);
+        assert_eq!(file_name_width(1000), 4);
+        assert_eq!(file_name_width(usize::MAX), usize::MAX.to_string().len());
+    }
+
+    #[test]
+    fn jittered_names_are_unique_and_reproducible() {
+        let naming = Naming {
+            hash_jitter: Some(42),
+            ..Default::default()
+        };
+
+        let mut names = std::collections::HashSet::new();
+        for i in 0..1000 {
+            let name = naming.with_file_name(0, i, 0, str::to_string);
+            assert!(name.ends_with(&format!("_{}", i)));
+            assert_eq!(name, naming.with_file_name(0, i, 0, str::to_string));
+            naming.with_dir_name(0, i, |s| assert_eq!(s, format!("{}.dir", name)));
+
+            names.insert(name);
+        }
+        assert_eq!(names.len(), 1000);
+    }
+
+    #[test]
+    fn unicode_names_are_unique_and_portable() {
+        let naming = Naming {
+            unicode_random: Some(42),
+            ..Default::default()
+        };
+
+        let mut names = std::collections::HashSet::new();
+        for i in 0..10_000 {
+            let name = naming.with_file_name(0, i, 0, str::to_string);
+            assert_eq!(name, naming.with_file_name(0, i, 0, str::to_string));
+            naming.with_dir_name(0, i, |s| assert_eq!(s, format!("{}.dir", name)));
+
+            let prefix = name.strip_suffix(&i.to_string()).unwrap();
+            assert!(!prefix.is_empty());
+            assert!(!prefix.is_ascii());
+            assert!(!prefix.chars().next().unwrap().is_ascii());
+            assert!(!prefix
+                .chars()
+                .any(|c| c.is_ascii_digit() || c.is_control() || "/\\<>:\"|?*.".contains(c)));
+
+            names.insert(name);
+        }
+        assert_eq!(names.len(), 10_000);
+    }
+
+    #[test]
+    fn unicode_names_have_bounded_lengths() {
+        let naming = Naming {
+            hash_jitter: Some(7),
+            unicode_random: Some(42),
+            name_lengths: Some((MIN_NAME_LENGTH, 64)),
+            ..Default::default()
+        };
+
+        let mut lengths = std::collections::HashSet::new();
+        for i in 0..10_000 {
+            for name in [
+                naming.with_file_name(0, i, 0, str::to_string),
+                naming.with_dir_name(0, i, str::to_string),
+            ] {
+                assert!((MIN_NAME_LENGTH..=64).contains(&name.len()), "{}", name);
+                lengths.insert(name.len());
+            }
+        }
+        assert_eq!(lengths.len(), 64 - MIN_NAME_LENGTH + 1);
+    }
+
+    #[test]
+    fn hidden_names_are_unique_and_dot_prefixed_at_the_ratio() {
+        let naming = Naming {
+            hidden: Some((42, u64::MAX / 4)),
+            ..Default::default()
+        };
+
+        let mut names = std::collections::HashSet::new();
+        let mut hidden = 0;
+        for i in 0..10_000 {
+            let name = naming.with_file_name(0, i, 0, str::to_string);
+            let visible = name.strip_prefix('.').unwrap_or(&name);
+            assert_eq!(visible, i.to_string());
+            naming.with_dir_name(0, i, |s| assert_eq!(s, format!("{}.dir", name)));
+
+            hidden += usize::from(name.starts_with('.'));
+            names.insert(name);
+        }
+        assert_eq!(names.len(), 10_000);
+        assert!((2000..3000).contains(&hidden), "{}", hidden);
+    }
+
+    #[test]
+    fn word_names_are_suffixed_once_exhausted() {
+        let naming = Naming {
+            dir_words: Some(Arc::from([String::from("src"), String::from(".git")])),
+            file_words: Some(Arc::from([String::from("a.tar.gz"), String::from("b")])),
+            ..Default::default()
+        };
+
+        let dirs = (0..5)
+            .map(|i| naming.with_dir_name(0, i, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(dirs, ["src", ".git", "src-1", ".git-1", "src-2"]);
+
+        let files = (0..4)
+            .map(|i| naming.with_file_name(0, i, 3, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["a.tar.gz", "b", "a.tar-1.gz", "b-1"]);
+    }
+
+    #[test]
+    fn case_colliding_names_come_in_pairs() {
+        let naming = Naming {
+            case_collisions: true,
+            ..Default::default()
+        };
+
+        let files = (0..5)
+            .map(|i| naming.with_file_name(0, i, 2, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["a00", "A00", "a01", "A01", "a02"]);
+        naming.with_dir_name(0, 1, |s| assert_eq!(s, "1.dir"));
+    }
+
+    #[test]
+    fn timestamped_names_follow_the_step() {
+        let naming = Naming {
+            timestamps: Some(Timestamps {
+                format: Arc::from("app-%Y-%m-%d.log"),
+                start: 1_705_276_800,
+                step: 86_400,
+            }),
+            ..Default::default()
+        };
+
+        let files = (0..3)
+            .map(|i| naming.with_file_name(0, i, 2, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            ["app-2024-01-15.log", "app-2024-01-16.log", "app-2024-01-17.log"]
+        );
+        naming.with_dir_name(0, 1, |s| assert_eq!(s, "1.dir"));
+    }
+
+    #[test]
+    fn custom_names_are_made_unique_and_valid() {
+        let naming = Naming {
+            custom: Some(Arc::new(CustomNames::new(
+                NameFn::from(|context: NameContext| match (context.kind, context.index) {
+                    (NameKind::Dir, 0) => String::from("a/b"),
+                    (NameKind::Dir, _) => String::from(".."),
+                    (NameKind::File, i) => format!("f{}", i % 2),
+                }),
+                42,
+                Path::new("root"),
+            ))),
+            ..Default::default()
+        };
+
+        let files = (0..4)
+            .map(|i| naming.with_file_name(0, i, 2, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["f0", "f1", "f0~2", "f1~3"]);
+        let dirs = (0..2)
+            .map(|i| naming.with_dir_name(1, i, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(dirs, ["a_b", "..~1"]);
+        assert_eq!(naming.depth(Path::new("root/a_b/..~1")), 2);
+    }
+
+    #[test]
+    fn custom_names_are_unique_across_files_and_dirs() {
+        let naming = Naming {
+            custom: Some(Arc::new(CustomNames::new(
+                NameFn::from(|context: NameContext| format!("x{}", context.index)),
+                42,
+                Path::new("root"),
+            ))),
+            ..Default::default()
+        };
+
+        // Files are asked for first, yet directories still win collisions at the same index
+        let files = (0..2)
+            .map(|i| naming.with_file_name(0, i, 2, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["x0~0", "x1~1"]);
+        let dirs = (0..3)
+            .map(|i| naming.with_dir_name(0, i, str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(dirs, ["x0", "x1", "x2"]);
+    }
+
+    #[test]
+    fn timestamp_name_style_is_parsed() {
+        assert_eq!(
+            "timestamped,0,60,%H:%M,%S".parse::<NameStyle>(),
+            Ok(NameStyle::Timestamped {
+                format: String::from("%H:%M,%S"),
+                start: 0,
+                step: 60,
+            })
+        );
+        assert!("timestamped,0".parse::<NameStyle>().is_err());
+    }
+
+    #[test]
+    fn colliding_timestamps_are_rejected() {
+        let timestamps = Timestamps {
+            format: Arc::from("%d"),
+            start: 0,
+            step: 86_400,
+        };
+
+        assert!(validate_timestamps(&timestamps, 31).is_ok());
+        assert!(validate_timestamps(&timestamps, 32).is_err());
+    }
+
+    #[rstest]
+    #[case("%Q", 1, 1)]
+    #[case("%Y/%m", 1, 1)]
+    #[case("%s", 0, 1)]
+    #[case("%s", u64::MAX, 2)]
+    fn invalid_timestamps_are_rejected(
+        #[case] format: &str,
+        #[case] step: u64,
+        #[case] num_files: usize,
+    ) {
+        let timestamps = Timestamps {
+            format: Arc::from(format),
+            start: 0,
+            step,
+        };
+
+        assert!(validate_timestamps(&timestamps, num_files).is_err());
+    }
+
+    #[test]
+    fn default_wordlists_are_valid() {
+        for list in [DEFAULT_DIR_WORDLIST, DEFAULT_FILE_WORDLIST] {
+            validate_wordlist(&list.iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        }
+    }
+
+    #[test]
+    fn invalid_wordlists_are_rejected() {
+        for list in [
+            &[][..],
+            &["a/b"],
+            &[".."],
+            &[""],
+            &["a", "a"],
+            &["v-2"],
+            &["notes-12.txt"],
+        ] {
+            assert!(
+                validate_wordlist(&list.iter().map(|s| s.to_string()).collect::<Vec<_>>()).is_err(),
+                "{:?}",
+                list
+            );
+        }
+        validate_wordlist(&[String::from("a-b"), String::from("x-")]).unwrap();
+    }
 }
@@ -1,20 +1,37 @@
 use std::{
     cmp::{max, min},
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
     fs::{create_dir, create_dir_all, File},
     hash::Hasher,
-    io::{Read, Write},
+    io::{Cursor, Read, Write},
     num::NonZeroUsize,
+    panic::{catch_unwind, AssertUnwindSafe},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
-use more_asserts::assert_le;
-use rand::Rng;
+use chrono::NaiveDate;
+use flate2::{read::ZlibDecoder, write::DeflateEncoder, Compression};
+use more_asserts::{assert_ge, assert_le, assert_lt};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
 use rstest::rstest;
 use seahash::SeaHasher;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use stack_buffer::StackBufReader;
 
-use ftzz::generator::GeneratorBuilder;
+use ftzz::generator::{
+    diff_trees, ArchiveFormat, CreationOrder, DepthDistribution, EntryKind, FillMode, Generator,
+    GeneratorBuilder, GeneratorStats, Layout, MediaFormat, MtimeSequence, NameContext, NameKind,
+    NameStyle, Profile, ProgressHook, ProgressInterval, SequenceHeader, SizeDistribution, TreeDiff,
+    MARKER_FILE_NAME, NAME_MAX,
+};
 
 use crate::inspect::InspectableTempDir;
 
@@ -95,6 +112,69 @@ fn gen_creates_new_dir_if_not_present() {
     assert!(dir.path.join("new").exists());
 }
 
+#[test]
+fn validating_params_leaves_the_file_system_alone() {
+    let dir = InspectableTempDir::new();
+    let root = dir.path.join("new");
+
+    let invalid_ratio = GeneratorBuilder::default()
+        .root_dir(root.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .file_to_dir_ratio(NonZeroUsize::new(100).unwrap())
+        .build();
+    let too_many_entries = GeneratorBuilder::default()
+        .root_dir(root.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .max_entries(100)
+        .build()
+        .unwrap()
+        .validate_params();
+    let valid = GeneratorBuilder::default()
+        .root_dir(root.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .build()
+        .unwrap()
+        .validate_params();
+
+    assert!(invalid_ratio.is_err());
+    assert!(too_many_entries.is_err());
+    assert!(valid.is_ok());
+    assert!(!root.exists());
+}
+
+#[test]
+fn higher_content_entropy_compresses_less() {
+    let compressed_len = |permille: u16| {
+        let dir = InspectableTempDir::new();
+
+        GeneratorBuilder::default()
+            .root_dir(dir.path.clone())
+            .num_files(NonZeroUsize::new(1).unwrap())
+            .files_exact(true)
+            .num_bytes(100_000)
+            .bytes_exact(true)
+            .max_depth(0)
+            .fill_mode(FillMode::Blended { permille })
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        for entry in dir.path.read_dir().unwrap() {
+            encoder.write_all(&fs::read(entry.unwrap().path()).unwrap()).unwrap();
+        }
+        encoder.finish().unwrap().len()
+    };
+
+    let lens = [0, 250, 500, 750, 1000].map(compressed_len);
+    for pair in lens.windows(2) {
+        assert_lt!(pair[0], pair[1], "{:?}", lens);
+    }
+    assert_lt!(lens[0], 1_000);
+    assert_ge!(lens[4], 100_000);
+}
+
 #[rstest]
 #[case(1_000)]
 #[case(10_000)]
@@ -212,122 +292,3633 @@ fn max_depth_is_respected(#[case] max_depth: u32) {
 }
 
 #[test]
-fn fuzz_test() {
+fn leaf_depths_follow_the_distribution() {
     let dir = InspectableTempDir::new();
+    let max_depth = 3;
 
-    let mut rng = rand::thread_rng();
-    let num_files = rng.gen_range(1..25_000);
-    let num_bytes = if rng.gen() {
-        rng.gen_range(0..100_000)
-    } else {
-        0
-    };
-    let max_depth = rng.gen_range(0..100);
-    let ratio = rng.gen_range(1..num_files);
-    let files_exact = rng.gen();
-    let bytes_exact = rng.gen();
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100_000).unwrap())
+        .file_to_dir_ratio(NonZeroUsize::new(1).unwrap())
+        .max_depth(max_depth)
+        .leaf_depth_distribution(DepthDistribution::Normal { mean: 2., cv: 0.4 })
+        .build()
+        .unwrap();
+
+    let mut branch_depths = HashMap::new();
+    for (path, kind) in g.plan_paths() {
+        if kind != EntryKind::Dir {
+            continue;
+        }
+        let path = path.strip_prefix(&dir.path).unwrap();
+        let depth = path.components().count();
+        let branch = branch_depths
+            .entry(path.components().next().unwrap().as_os_str().to_owned())
+            .or_insert(0);
+        *branch = max(*branch, depth);
+    }
+
+    let depths = branch_depths.into_values().collect::<Vec<_>>();
+    let mean = depths.iter().sum::<usize>() as f64 / depths.len() as f64;
+    let std_dev = (depths
+        .iter()
+        .map(|&d| (d as f64 - mean).powi(2))
+        .sum::<f64>()
+        / depths.len() as f64)
+        .sqrt();
+
+    assert!(depths.len() > 20, "{}", depths.len());
+    assert!(depths.iter().all(|&d| (1..=max_depth as usize).contains(&d)));
+    assert!(depths.contains(&1) && depths.contains(&(max_depth as usize)));
+    assert!((mean - 2.).abs() < 0.3, "{}", mean);
+    assert!(std_dev > 0.4 && std_dev < 1., "{}", std_dev);
+}
 
+#[test]
+fn depth_jitter_varies_branch_depths_below_max_depth() {
+    let dir = InspectableTempDir::new();
+    let (max_depth, jitter) = (5, 2);
     let g = GeneratorBuilder::default()
         .root_dir(dir.path.clone())
-        .num_files(NonZeroUsize::new(num_files).unwrap())
-        .num_bytes(num_bytes)
+        .num_files(NonZeroUsize::new(100_000).unwrap())
+        .file_to_dir_ratio(NonZeroUsize::new(1).unwrap())
         .max_depth(max_depth)
-        .file_to_dir_ratio(NonZeroUsize::new(ratio).unwrap())
-        .files_exact(files_exact)
-        .bytes_exact(bytes_exact)
+        .depth_jitter(jitter)
         .build()
         .unwrap();
-    println!("Params: {:?}", g);
-    g.generate().unwrap();
 
-    assert_le!(find_max_depth(&dir.path), max_depth);
-    if files_exact {
-        assert_eq!(count_num_files(&dir.path), num_files);
+    let mut branch_depths = HashMap::new();
+    for (path, kind) in g.plan_paths() {
+        if kind != EntryKind::Dir {
+            continue;
+        }
+        let path = path.strip_prefix(&dir.path).unwrap();
+        let depth = path.components().count();
+        let branch = branch_depths
+            .entry(path.components().next().unwrap().as_os_str().to_owned())
+            .or_insert(0);
+        *branch = max(*branch, depth);
     }
-    if bytes_exact {
-        assert_eq!(count_num_bytes(&dir.path), num_bytes);
+
+    let depths = branch_depths.into_values().collect::<HashSet<_>>();
+    let range = (max_depth - jitter) as usize..=max_depth as usize;
+    assert!(depths.iter().all(|d| range.contains(d)), "{:?}", depths);
+    assert!(depths.len() > 1, "{:?}", depths);
+}
+
+#[test]
+fn progress_is_reported_every_n_files() {
+    let dir = InspectableTempDir::new();
+    let reports = Arc::new(AtomicUsize::new(0));
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+        .progress_interval(ProgressInterval::EveryNFiles(100))
+        .on_progress({
+            let reports = reports.clone();
+            move |_: &GeneratorStats| {
+                reports.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let expected = count_num_files(&dir.path) / 100;
+    let reports = reports.load(Ordering::Relaxed);
+    assert_le!(reports, expected);
+    assert_ge!(reports, expected * 9 / 10);
+}
+
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
 }
 
-/// Recursively hashes the file and directory names in dir
-fn hash_dir(dir: &Path) -> u64 {
-    let mut hasher = SeaHasher::new();
+#[test]
+fn json_lines_progress_is_monotonic() {
+    let dir = InspectableTempDir::new();
+    let out = Arc::new(Mutex::new(Vec::new()));
 
-    let mut entries = Vec::new();
-    let mut queue = VecDeque::from([dir.to_path_buf()]);
-    while let Some(path) = queue.pop_front() {
-        for entry in path.read_dir().unwrap() {
-            entries.push(entry.unwrap());
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .num_bytes(100_000)
+        .progress_interval(ProgressInterval::EveryFile)
+        .on_progress(ProgressHook::json_lines(SharedBuf(out.clone())))
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let out = out.lock().unwrap();
+    let ticks = out
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert!(ticks.len() > 1);
+    for (prev, next) in ticks.iter().zip(&ticks[1..]) {
+        for key in ["files", "dirs", "bytes", "elapsed_ms"] {
+            assert_le!(prev[key].as_u64().unwrap(), next[key].as_u64().unwrap(), "{}", key);
         }
+        assert_ne!(prev["files"], next["files"]);
+    }
+}
 
-        entries.sort_by_key(|e| e.file_name());
-        for entry in &entries {
-            if entry.file_type().unwrap().is_dir() {
-                queue.push_back(entry.path())
-            } else if entry.metadata().unwrap().len() > 0 {
-                for byte in
-                    StackBufReader::<_, 4096>::new(File::open(entry.path()).unwrap()).bytes()
-                {
-                    hasher.write_u8(byte.unwrap());
-                }
-            }
+#[test]
+#[cfg(target_os = "linux")]
+fn io_report_tracks_the_bytes_written() {
+    let dir = InspectableTempDir::new();
+    let out = Arc::new(Mutex::new(Vec::new()));
 
-            hasher.write(entry.file_name().to_str().unwrap().as_bytes());
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(10_000_000)
+        .progress_interval(ProgressInterval::EveryFile)
+        .on_progress(ProgressHook::json_lines_with_io(SharedBuf(out.clone())))
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let out = out.lock().unwrap();
+    let last = out
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<serde_json::Value>(line).unwrap())
+        .next_back()
+        .unwrap();
+    let bytes = last["bytes"].as_u64().unwrap();
+    let written = last["io"]["written_bytes"].as_u64().unwrap();
+    assert_ne!(bytes, 0);
+    // Other tests write from the same process, so the counters can only overshoot
+    assert_ge!(written, bytes);
+    assert_ne!(last["io"]["write_syscalls"].as_u64().unwrap(), 0);
+}
+
+#[test]
+fn dumped_samples_cover_every_visited_directory() {
+    let dir = InspectableTempDir::new();
+    let out = InspectableTempDir::new();
+    let samples_file = out.path.join("samples.jsonl");
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .max_depth(3)
+        .dump_samples(samples_file.clone())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let samples = fs::read_to_string(samples_file)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    // The root directory is visited too
+    assert_eq!(samples.len(), count_num_dirs(&dir.path) + 1);
+    assert_eq!(samples[0]["dir"], "");
+    let sampled_dirs = samples
+        .iter()
+        .map(|sample| sample["dirs"].as_u64().unwrap() as usize)
+        .sum::<usize>();
+    assert_eq!(sampled_dirs, count_num_dirs(&dir.path));
+    for sample in &samples {
+        assert!(sample["planned_files"].as_f64().unwrap() > 0.);
+    }
+}
+
+#[cfg(feature = "syslog")]
+#[test]
+fn summary_is_sent_to_syslog() {
+    use ftzz::generator::{Severity, Syslog, SyslogSink};
+
+    struct MockSink(Arc<Mutex<Vec<(Severity, String)>>>);
+
+    impl SyslogSink for MockSink {
+        fn send(&mut self, severity: Severity, message: &str) -> std::io::Result<()> {
+            self.0.lock().unwrap().push((severity, message.to_string()));
+            Ok(())
         }
-        entries.clear();
     }
 
-    hasher.finish()
+    let dir = InspectableTempDir::new();
+    let messages = Arc::new(Mutex::new(Vec::new()));
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .files_exact(true)
+        .max_depth(0)
+        .syslog(Syslog::new(MockSink(messages.clone())))
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_eq!(
+        *messages.lock().unwrap(),
+        [(
+            Severity::Info,
+            String::from("Created 10 files across 0 directories.")
+        )]
+    );
 }
 
-fn assert_matching_hashes(hash: u64, hash_file: &Path) {
-    if option_env!("REGEN").is_some() {
-        create_dir_all(hash_file.parent().unwrap()).unwrap();
-        File::create(hash_file)
-            .unwrap()
-            .write_all(&hash.to_be_bytes())
-            .unwrap()
-    } else {
-        let mut expected_hash = Vec::new();
-        File::open(&hash_file)
-            .unwrap_or_else(|e| {
-                panic!(
-                    "Regenerate test files with `REGEN=true cargo test` \n{}: {:?}",
-                    e, hash_file,
-                )
-            })
-            .read_to_end(&mut expected_hash)
-            .unwrap();
+#[test]
+fn wide_shallow_tree_completes() {
+    let dir = InspectableTempDir::new();
 
-        assert_eq!(hash.to_be_bytes(), expected_hash.as_slice());
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(20_000).unwrap())
+        .max_depth(1)
+        .file_to_dir_ratio(NonZeroUsize::new(1).unwrap())
+        .build()
+        .unwrap();
+    let num_planned = g.plan_paths().count();
+    g.generate().unwrap();
+
+    let num_dirs = count_num_dirs(&dir.path);
+    assert!(num_dirs > 10_000, "{}", num_dirs);
+    assert_le!(find_max_depth(&dir.path), 1);
+    assert_eq!(num_planned, count_num_files(&dir.path) + num_dirs);
+}
+
+#[rstest]
+fn min_files_is_a_floor(
+    #[values(0, 1, 2, 3, 4, 5, 6, 7)] seed: u64,
+    #[values(0, 1, 3)] max_depth: u32,
+    #[values(0, 10_000)] num_bytes: usize,
+    #[values(false, true)] bytes_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(500).unwrap())
+        .min_files(500)
+        .num_bytes(num_bytes)
+        .bytes_exact(bytes_exact)
+        .max_depth(max_depth)
+        .file_to_dir_ratio(NonZeroUsize::new(50).unwrap())
+        .seed(seed)
+        .lex_sortable(true)
+        .build()
+        .unwrap();
+    let planned = g.plan_paths().count();
+    g.generate().unwrap();
+
+    assert_le!(500, count_num_files(&dir.path));
+    assert_eq!(
+        planned,
+        count_num_files(&dir.path) + count_num_dirs(&dir.path)
+    );
+    if bytes_exact {
+        assert_eq!(count_num_bytes(&dir.path), num_bytes);
     }
 }
 
-fn find_max_depth(dir: &Path) -> u32 {
-    let mut depth = 0;
-    for entry in dir.read_dir().unwrap() {
+#[rstest]
+fn min_dirs_is_a_floor(
+    #[values(0, 1, 2, 3, 4, 5, 6, 7)] seed: u64,
+    #[values(1, 3)] max_depth: u32,
+    #[values(0, 10_000)] num_bytes: usize,
+    #[values(false, true)] files_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(500).unwrap())
+        .files_exact(files_exact)
+        .min_dirs(100)
+        .num_bytes(num_bytes)
+        .max_depth(max_depth)
+        .file_to_dir_ratio(NonZeroUsize::new(100).unwrap())
+        .seed(seed)
+        .build()
+        .unwrap();
+    let planned = g.plan_paths().count();
+    g.generate().unwrap();
+
+    assert_le!(100, count_num_dirs(&dir.path));
+    assert_le!(find_max_depth(&dir.path), max_depth);
+    assert_eq!(
+        planned,
+        count_num_files(&dir.path) + count_num_dirs(&dir.path)
+    );
+    if files_exact {
+        assert_eq!(count_num_files(&dir.path), 500);
+    }
+}
+
+#[test]
+fn missing_dirs_are_topped_up_empty_in_the_root() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .files_exact(true)
+        .min_dirs(50)
+        .max_depth(3)
+        .file_to_dir_ratio(NonZeroUsize::new(1_000).unwrap())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    // Far too few files for the tree to have any directories of its own
+    assert_eq!(count_num_dirs(&dir.path), 50);
+    assert_eq!(find_max_depth(&dir.path), 1);
+    for entry in dir.path.read_dir().unwrap() {
         let path = entry.unwrap().path();
         if path.is_dir() {
-            depth = max(depth, find_max_depth(&path) + 1);
+            assert_eq!(path.read_dir().unwrap().count(), 0);
         }
     }
-    depth
+    assert_eq!(count_num_files(&dir.path), 10);
 }
 
-fn count_num_files(dir: &Path) -> usize {
-    let mut num_files = 0;
-    let mut queue = VecDeque::from([dir.to_path_buf()]);
-    while let Some(path) = queue.pop_front() {
-        for entry in path.read_dir().unwrap() {
-            let entry = entry.unwrap();
-            if entry.file_type().unwrap().is_dir() {
-                queue.push_back(entry.path());
-            } else {
-                num_files += 1;
-            }
-        }
+#[rstest]
+fn depth_including_the_root_is_one_level_shallower(
+    #[values(false, true)] chain: bool,
+    #[values(false, true)] includes_root: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .max_depth(3)
+        .depth_includes_root(includes_root)
+        .chain(chain)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let max_depth = 3 - u32::from(includes_root);
+    if chain {
+        assert_eq!(find_max_depth(&dir.path), max_depth);
+    } else {
+        assert_le!(find_max_depth(&dir.path), max_depth);
     }
-    num_files
+}
+
+#[rstest]
+fn structural_levels_hold_no_files(
+    #[values(0, 1, 2, 3)] seed: u64,
+    #[values(1, 2)] levels: u32,
+    #[values(0, 10_000)] num_bytes: usize,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .max_depth(4)
+        .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+        .structural_levels(levels)
+        .seed(seed)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut files_per_level = [0; 5];
+    let mut queue = VecDeque::from([(dir.path.clone(), 0)]);
+    while let Some((path, depth)) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back((entry.path(), depth + 1));
+            } else {
+                files_per_level[depth] += 1;
+            }
+        }
+    }
+
+    let (structural, deeper) = files_per_level.split_at(levels as usize);
+    assert!(structural.iter().all(|&files| files == 0));
+    assert_lt!(0, deeper.iter().sum::<usize>());
+}
+
+#[rstest]
+fn min_files_per_dir_is_a_floor(
+    #[values(0, 1, 3)] min_files_per_dir: usize,
+    #[values((0, false), (100_000, false), (100_000, true))] bytes: (usize, bool),
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(bytes.0)
+        .bytes_exact(bytes.1)
+        // Sparse enough that some directories would otherwise get no files
+        .file_to_dir_ratio(NonZeroUsize::new(1).unwrap())
+        .min_files_per_dir(min_files_per_dir)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        let mut files = 0;
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                files += 1;
+            }
+        }
+        assert!(files >= min_files_per_dir, "{:?} has {} files", dir, files);
+    }
+}
+
+#[test]
+fn dir_fraction_is_observed_across_the_tree() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .max_depth(3)
+        .dir_fraction(0.2)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let files = count_num_files(&dir.path) as f64;
+    let dirs = count_num_dirs(&dir.path) as f64;
+    let fraction = dirs / (files + dirs);
+    assert_ge!(fraction, 0.17);
+    assert_le!(fraction, 0.23);
+}
+
+#[test]
+fn num_entries_counts_files_and_dirs() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_entries(NonZeroUsize::new(11_000).unwrap())
+        .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+        .max_depth(3)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let entries = count_num_files(&dir.path) + count_num_dirs(&dir.path);
+    assert_ge!(entries, 9_900);
+    assert_le!(entries, 12_100);
+}
+
+#[test]
+fn cluster_factor_concentrates_files() {
+    let gini = |cluster_factor: f64| {
+        let dir = InspectableTempDir::new();
+
+        GeneratorBuilder::default()
+            .root_dir(dir.path.clone())
+            .num_files(NonZeroUsize::new(10_000).unwrap())
+            .cluster_factor(cluster_factor)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+
+        let mut counts = Vec::new();
+        let mut queue = VecDeque::from([dir.path.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            let mut files = 0;
+            for entry in dir.read_dir().unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    queue.push_back(entry.path());
+                } else {
+                    files += 1;
+                }
+            }
+            counts.push(files as f64);
+        }
+
+        counts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = counts.len() as f64;
+        let weighted = counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (i + 1) as f64 * count)
+            .sum::<f64>();
+        2. * weighted / (n * counts.iter().sum::<f64>()) - (n + 1.) / n
+    };
+
+    let ginis = [0., 0.5, 0.9].map(gini);
+    assert!(ginis.windows(2).all(|w| w[0] < w[1]), "{:?}", ginis);
+}
+
+#[rstest]
+fn lex_sortable_names_sort_numerically(#[values(false, true)] files_exact: bool) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .files_exact(files_exact)
+        .lex_sortable(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        let mut names = Vec::new();
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                names.push(entry.file_name().into_string().unwrap());
+            }
+        }
+
+        names.sort();
+        let nums = names
+            .iter()
+            .map(|name| name.parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(nums.windows(2).all(|w| w[0] < w[1]), "{:?}", names);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn case_colliding_names_are_created_distinctly() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .files_exact(true)
+        .max_depth(0)
+        .case_collisions(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut names_by_case = HashMap::<String, Vec<String>>::new();
+    for entry in dir.path.read_dir().unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        names_by_case
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(name);
+    }
+    assert_eq!(names_by_case.len(), 50);
+    for names in names_by_case.values() {
+        assert_eq!(names.len(), 2, "{:?}", names);
+        assert_ne!(names[0], names[1]);
+    }
+}
+
+#[test]
+fn a_directory_is_repaired_from_its_recorded_seed() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .max_depth(3)
+        .write_marker(true)
+        .dir_seeds(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+    let hash = hash_dir(&dir.path);
+
+    let corrupted = dir.path.join("0.dir");
+    for entry in corrupted.read_dir().unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_file() {
+            fs::remove_file(entry.path()).unwrap();
+        }
+    }
+    assert_ne!(hash_dir(&dir.path), hash);
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .max_depth(3)
+        .write_marker(true)
+        .dir_seeds(true)
+        .repair(true)
+        .repair_dir(PathBuf::from("0.dir"))
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_eq!(hash_dir(&dir.path), hash);
+}
+
+#[rstest]
+fn directories_stay_within_max_width(#[values(0, 100_000)] num_bytes: usize) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .num_bytes(num_bytes)
+        .max_depth(2)
+        .files_exact(true)
+        .file_to_dir_ratio(NonZeroUsize::new(500).unwrap())
+        .max_dir_width(NonZeroUsize::new(50).unwrap())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        let mut entries = 0;
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            }
+            entries += 1;
+        }
+        assert_le!(entries, 50, "{:?}", path);
+    }
+    assert_eq!(count_num_files(&dir.path), 10_000);
+}
+
+#[test]
+fn disk_usage_lands_near_the_target() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .target_disk_usage(20_000_000)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let blocks = |len: u64| (len + 4095) / 4096 * 4096;
+    let mut usage = 4096;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                usage += 4096;
+                queue.push_back(entry.path());
+            } else {
+                usage += blocks(entry.metadata().unwrap().len());
+            }
+        }
+    }
+    assert_ge!(usage, 19_000_000);
+    assert_le!(usage, 21_000_000);
+}
+
+#[test]
+fn zipf_sizes_fall_off_with_rank() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .files_exact(true)
+        .num_bytes(10_000_000)
+        .max_depth(0)
+        .size_distribution(SizeDistribution::Zipf { exponent: 1. })
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut sizes = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len() as f64)
+        .collect::<Vec<_>>();
+    sizes.sort_unstable_by(|a, b| b.total_cmp(a));
+    assert_eq!(sizes.len(), 10_000);
+    assert_ge!(sizes.iter().sum::<f64>(), 6_000_000.);
+    assert_le!(sizes.iter().sum::<f64>(), 14_000_000.);
+
+    // Fit the slope of the size-rank curve on a log-log scale, skipping the noisy extremes
+    let points = (10..5_000)
+        .map(|rank| ((rank as f64 + 1.).ln(), sizes[rank].ln()))
+        .collect::<Vec<_>>();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let slope = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>()
+        / points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+    assert_ge!(slope, -1.15);
+    assert_le!(slope, -0.85);
+}
+
+#[rstest]
+fn files_are_named_in_ascending_order_of_size(#[values(false, true)] chain: bool) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(1_000_000)
+        .max_depth(2)
+        .file_to_dir_ratio(NonZeroUsize::new(100).unwrap())
+        .chain(chain)
+        .sort_by_size(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        let mut files = Vec::new();
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                files.push((entry.file_name(), entry.metadata().unwrap().len()));
+            }
+        }
+        files.sort_unstable();
+        assert!(
+            files.windows(2).all(|pair| pair[0].1 <= pair[1].1),
+            "{:?}: {:?}",
+            path,
+            files
+        );
+    }
+    assert_ge!(count_num_files(&dir.path), 500);
+}
+
+#[test]
+fn timestamped_names_parse_back_in_sequence() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .files_exact(true)
+        .max_depth(0)
+        .name_style(NameStyle::Timestamped {
+            format: String::from("app-%Y-%m-%d.log"),
+            start: 1_705_276_800,
+            step: 86_400,
+        })
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut dates = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .map(|entry| {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            NaiveDate::parse_from_str(&name, "app-%Y-%m-%d.log").unwrap()
+        })
+        .collect::<Vec<_>>();
+    dates.sort_unstable();
+
+    let first = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+    assert_eq!(
+        dates,
+        (0..100)
+            .map(|day| first + chrono::Duration::days(day))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn hidden_entries_are_created_at_the_ratio() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10_000).unwrap())
+        .files_exact(true)
+        .max_depth(3)
+        .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+        .hidden_ratio(0.3)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut entries = 0;
+    let mut hidden = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().into_string().unwrap();
+            let visible = name.strip_prefix('.').unwrap_or(&name);
+            let is_dir = entry.file_type().unwrap().is_dir();
+            let index = if is_dir {
+                visible.strip_suffix(".dir").unwrap()
+            } else {
+                visible
+            };
+            assert!(index.parse::<usize>().is_ok(), "{}", name);
+
+            entries += 1;
+            hidden += usize::from(name.starts_with('.'));
+            if is_dir {
+                queue.push_back(entry.path());
+            }
+        }
+    }
+    assert_eq!(count_num_files(&dir.path), 10_000);
+    assert_ge!(hidden * 100, entries * 25);
+    assert_le!(hidden * 100, entries * 35);
+}
+
+#[test]
+fn name_hash_jitter_spreads_names() {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, jitter: bool| {
+        GeneratorBuilder::default()
+            .root_dir(root)
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .max_depth(0)
+            .name_hash_jitter(jitter)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+    };
+    generate(dir.path.join("a"), true);
+    generate(dir.path.join("b"), true);
+    generate(dir.path.join("plain"), false);
+
+    assert_eq!(hash_dir(&dir.path.join("a")), hash_dir(&dir.path.join("b")));
+    assert_eq!(
+        count_num_files(&dir.path.join("a")),
+        count_num_files(&dir.path.join("plain"))
+    );
+
+    let first_chars = dir
+        .path
+        .join("a")
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_str().unwrap().chars().next())
+        .collect::<HashSet<_>>();
+    assert_eq!(first_chars.len(), 16);
+}
+
+#[test]
+fn unicode_names_survive_a_round_trip() {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .max_depth(2)
+        .name_style(NameStyle::UnicodeRandom)
+        .build()
+        .unwrap();
+    let planned = g.plan_paths().map(|(path, _)| path).collect::<HashSet<_>>();
+    g.generate().unwrap();
+
+    let mut num_entries = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        let mut names = HashSet::new();
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            }
+            assert!(planned.contains(&entry.path()), "{:?}", entry.path());
+            assert!(entry.path().exists());
+
+            let name = entry.file_name().into_string().unwrap();
+            assert!(!name.is_empty());
+            assert!(!name.is_ascii(), "{}", name);
+            assert!(names.insert(name));
+        }
+        num_entries += names.len();
+    }
+    assert_eq!(num_entries, planned.len());
+}
+
+#[rstest]
+fn name_lengths_are_bounded(#[values((40, 80), (200, 1_000))] lengths: (usize, usize)) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .max_depth(2)
+        .name_style(NameStyle::UnicodeRandom)
+        .name_length_distribution(lengths.0, lengths.1)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let max = min(lengths.1, NAME_MAX);
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            }
+
+            let name = entry.file_name().into_string().unwrap();
+            assert!((lengths.0..=max).contains(&name.len()), "{}", name);
+        }
+    }
+}
+
+#[rstest]
+fn wordlist_names_are_unique_per_parent(#[values(false, true)] file_words: bool) {
+    let dir = InspectableTempDir::new();
+    let dir_words = ["alpha", "beta", "gamma.d"];
+
+    let mut builder = GeneratorBuilder::default();
+    builder
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .file_to_dir_ratio(NonZeroUsize::new(5).unwrap())
+        .max_depth(2)
+        .dir_wordlist(dir_words.iter().map(|s| s.to_string()).collect());
+    if file_words {
+        builder.file_wordlist(vec![String::from("notes.txt")]);
+    }
+    builder.build().unwrap().generate().unwrap();
+
+    let from_list = |name: &str, words: &[&str]| {
+        words.iter().any(|word| {
+            let (stem, extension) = word
+                .split_once('.')
+                .map_or((*word, ""), |(stem, _)| (stem, &word[stem.len()..]));
+            name == *word
+                || name
+                    .strip_prefix(stem)
+                    .and_then(|rest| rest.strip_suffix(extension))
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .is_some()
+        })
+    };
+
+    let mut num_dirs = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        let mut names = HashSet::new();
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().into_string().unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                assert!(from_list(&name, &dir_words), "{}", name);
+                queue.push_back(entry.path());
+                num_dirs += 1;
+            } else if file_words {
+                assert!(from_list(&name, &["notes.txt"]), "{}", name);
+            } else {
+                assert!(name.parse::<usize>().is_ok(), "{}", name);
+            }
+            assert!(names.insert(name));
+        }
+    }
+    assert!(num_dirs > dir_words.len());
+}
+
+#[test]
+fn name_fn_names_every_entry_and_resolves_collisions() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .file_to_dir_ratio(NonZeroUsize::new(5).unwrap())
+        .max_depth(2)
+        .name_fn(Box::new(|context: NameContext| match context.kind {
+            NameKind::Dir => format!("level{}-{}", context.depth, context.index),
+            // Every other file collides with its predecessor
+            NameKind::File => format!("file{}", context.index / 2),
+        }) as Box<dyn Fn(NameContext) -> String + Send + Sync>)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut num_files = 0;
+    let mut num_disambiguated = 0;
+    let mut queue = VecDeque::from([(dir.path.clone(), 0)]);
+    while let Some((path, depth)) = queue.pop_front() {
+        let mut names = HashSet::new();
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().into_string().unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                let index = name.strip_prefix(&format!("level{}-", depth)).unwrap();
+                assert!(index.parse::<usize>().is_ok(), "{}", name);
+                queue.push_back((entry.path(), depth + 1));
+            } else {
+                let (base, suffix) = name.split_once('~').unwrap_or((&name, ""));
+                let index = base.strip_prefix("file").unwrap().parse::<usize>().unwrap();
+                if !suffix.is_empty() {
+                    assert_eq!(suffix.parse::<usize>().unwrap() / 2, index);
+                    num_disambiguated += 1;
+                }
+                num_files += 1;
+            }
+            assert!(names.insert(name));
+        }
+    }
+    assert_eq!(num_files, 1_000);
+    assert_ge!(num_disambiguated, 250);
+}
+
+#[test]
+fn seed_phrase_is_reproducible() {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, phrase: &str| {
+        GeneratorBuilder::default()
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .seed_phrase(phrase)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        hash_dir(&root)
+    };
+
+    let a = generate(dir.path.join("a"), "dataset-v2");
+    let b = generate(dir.path.join("b"), "dataset-v2");
+    let c = generate(dir.path.join("c"), "dataset-v3");
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn written_seed_reproduces_the_tree() {
+    let dir = InspectableTempDir::new();
+    let seed_file = dir.path.join("seed");
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.join("a"))
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .seed(42)
+        .seed_phrase("dataset-v2")
+        .write_seed(seed_file.clone())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+    let seed = fs::read_to_string(&seed_file)
+        .unwrap()
+        .trim()
+        .parse::<u64>()
+        .unwrap();
+    GeneratorBuilder::default()
+        .root_dir(dir.path.join("b"))
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .seed(seed)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_eq!(hash_dir(&dir.path.join("a")), hash_dir(&dir.path.join("b")));
+}
+
+#[test]
+fn interrupted_run_resumes_from_checkpoint() {
+    let dir = InspectableTempDir::new();
+    let expected = dir.path.join("expected");
+    let actual = dir.path.join("actual");
+    let checkpoint = dir.path.join("checkpoint.json");
+
+    GeneratorBuilder::default()
+        .root_dir(expected.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .file_to_dir_ratio(NonZeroUsize::new(2).unwrap())
+        .seed(42)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    // Slow the first run down enough for a checkpoint to be saved before it's cut short
+    let interrupted = GeneratorBuilder::default()
+        .root_dir(actual.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .file_to_dir_ratio(NonZeroUsize::new(2).unwrap())
+        .seed(42)
+        .checkpoint(checkpoint.clone())
+        .rate_limit(NonZeroUsize::new(500).unwrap())
+        .progress_interval(ProgressInterval::EveryFile)
+        .on_progress(|stats: &GeneratorStats| {
+            if stats.files >= 600 {
+                panic!("Interrupted");
+            }
+        })
+        .build()
+        .unwrap();
+    assert!(catch_unwind(AssertUnwindSafe(|| interrupted.generate())).is_err());
+    assert!(checkpoint.exists());
+
+    GeneratorBuilder::default()
+        .root_dir(actual.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .file_to_dir_ratio(NonZeroUsize::new(2).unwrap())
+        .seed(42)
+        .checkpoint(checkpoint.clone())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert!(!checkpoint.exists());
+    assert_eq!(diff_trees(&expected, &actual).unwrap(), TreeDiff::default());
+}
+
+#[test]
+fn diff_finds_modified_and_extra_files() {
+    let dir = InspectableTempDir::new();
+    let left = dir.path.join("a");
+    let right = dir.path.join("b");
+
+    for root_dir in [&left, &right] {
+        GeneratorBuilder::default()
+            .root_dir(root_dir.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(100_000)
+            .seed(42)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+    }
+
+    assert_eq!(diff_trees(&left, &right).unwrap(), TreeDiff::default());
+
+    let mut modified = None;
+    let mut queue = VecDeque::from([right.clone()]);
+    'walk: while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else if entry.metadata().unwrap().len() > 0 {
+                modified = Some(entry.path());
+                break 'walk;
+            }
+        }
+    }
+    let modified = modified.unwrap();
+    let mut contents = fs::read(&modified).unwrap();
+    contents[0] = !contents[0];
+    fs::write(&modified, contents).unwrap();
+    File::create(right.join("extra")).unwrap();
+
+    assert_eq!(
+        diff_trees(&left, &right).unwrap(),
+        TreeDiff {
+            missing: Vec::new(),
+            extra: vec![PathBuf::from("extra")],
+            mismatched: vec![modified.strip_prefix(&right).unwrap().to_path_buf()],
+        }
+    );
+    assert_eq!(diff_trees(&right, &left).unwrap().missing, vec![PathBuf::from("extra")]);
+}
+
+#[test]
+fn marker_records_the_configuration() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .files_exact(true)
+        .seed(42)
+        .write_marker(true)
+        .self_check(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+    let marker = fs::read_to_string(dir.path.join(MARKER_FILE_NAME)).unwrap();
+    let marker = toml::from_str::<toml::Value>(&marker).unwrap();
+
+    assert_eq!(marker["configuration"]["seed"].as_integer(), Some(42));
+    assert_eq!(marker["configuration"]["files"].as_integer(), Some(100));
+    assert_eq!(marker["ftzz_version"].as_str(), Some(env!("CARGO_PKG_VERSION")));
+}
+
+#[rstest]
+fn trees_are_reproduced_from_their_marker(
+    #[values("random", "dir_seeds", "layout", "grouped")] kind: &str,
+) {
+    let dir = InspectableTempDir::new();
+    let original = dir.path.join("original");
+
+    let mut builder = GeneratorBuilder::default();
+    builder
+        .root_dir(original.clone())
+        .num_bytes(100_000)
+        .max_depth(3)
+        // Too large for a TOML integer
+        .seed(u64::MAX - 1)
+        .write_marker(true);
+    match kind {
+        "random" => builder.num_files(NonZeroUsize::new(1_000).unwrap()),
+        "dir_seeds" => builder
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .dir_seeds(true),
+        "layout" => builder.layout(
+            Layout::from_json(r#"{"$files": 10, "a": {"$files": 20, "b": 30}, "c": 40}"#).unwrap(),
+        ),
+        _ => builder
+            .num_files(NonZeroUsize::new(500).unwrap())
+            .media_mix(vec![MediaFormat::Png, MediaFormat::Wav])
+            .group_by_extension(true),
+    };
+    builder.build().unwrap().generate().unwrap();
+    let hash = hash_dir(&original);
+
+    let manifest = dir.path.join("manifest.toml");
+    fs::copy(original.join(MARKER_FILE_NAME), &manifest).unwrap();
+    let reproduced = dir.path.join("reproduced");
+    Generator::reproduce_from_manifest(&manifest, reproduced.clone()).unwrap();
+    assert_eq!(diff_trees(&original, &reproduced).unwrap(), TreeDiff::default());
+
+    fs::remove_dir_all(&original).unwrap();
+    Generator::reproduce_from_manifest(&manifest, original.clone()).unwrap();
+    assert_eq!(hash_dir(&original), hash);
+}
+
+#[test]
+fn reproducing_needs_an_empty_root() {
+    let dir = InspectableTempDir::new();
+    let original = dir.path.join("original");
+
+    GeneratorBuilder::default()
+        .root_dir(original.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .write_marker(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let e = Generator::reproduce_from_manifest(&original.join(MARKER_FILE_NAME), original.clone())
+        .unwrap_err();
+    assert!(format!("{:?}", e.source.unwrap()).contains("must be empty"));
+}
+
+#[rstest]
+fn content_seed_leaves_structure_unchanged(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(false, true)] exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, content_seed: u64| {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(10_000).unwrap())
+            .num_bytes(num_bytes)
+            .structure_seed(42)
+            .content_seed(content_seed);
+        if exact {
+            builder.files_exact(true).bytes_exact(true).dirs_exact(100);
+        }
+        builder.build().unwrap().generate().unwrap();
+
+        let mut dirs = Vec::new();
+        let mut queue = VecDeque::from([root.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            for entry in dir.read_dir().unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    dirs.push(entry.path().strip_prefix(&root).unwrap().to_path_buf());
+                    queue.push_back(entry.path());
+                }
+            }
+        }
+        dirs.sort();
+        (dirs, find_max_depth(&root), hash_dir(&root))
+    };
+
+    let (dirs1, depth1, hash1) = generate(dir.path.join("1"), 1);
+    let (dirs2, depth2, hash2) = generate(dir.path.join("2"), 2);
+
+    assert!(!dirs1.is_empty());
+    assert_eq!(dirs1, dirs2);
+    assert_eq!(depth1, depth2);
+    assert_ne!(hash1, hash2);
+}
+
+#[rstest]
+fn files_are_filled_with_the_pattern(
+    #[values(&b"test"[..], &[0xDE, 0xAD, 0xBE, 0xEF][..], &[0; 5000][..])] pattern: &[u8],
+    #[values(false, true)] bytes_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .num_bytes(1_000_000)
+        .bytes_exact(bytes_exact)
+        .fill_mode(FillMode::Repeating(pattern.to_vec()))
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let contents = fs::read(entry.path()).unwrap();
+            let expected = pattern
+                .iter()
+                .cycle()
+                .take(contents.len())
+                .copied()
+                .collect::<Vec<_>>();
+            assert!(contents == expected, "{:?}", entry.path());
+        }
+    }
+    if bytes_exact {
+        assert_eq!(count_num_bytes(&dir.path), 1_000_000);
+    }
+}
+
+#[rstest]
+fn files_start_with_the_text_header(
+    #[values(&[0xEF, 0xBB, 0xBF][..], &[0xFF, 0xFE][..])] header: &[u8],
+    #[values(false, true)] bytes_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, header: Option<&[u8]>| {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(root)
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(100_000)
+            .bytes_exact(bytes_exact);
+        if let Some(header) = header {
+            builder.text_header(header.to_vec());
+        }
+        builder.build().unwrap().generate().unwrap();
+    };
+    generate(dir.path.join("plain"), None);
+    generate(dir.path.join("header"), Some(header));
+
+    let mut queue = VecDeque::from([dir.path.join("header")]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let contents = fs::read(entry.path()).unwrap();
+            let plain_path = dir
+                .path
+                .join("plain")
+                .join(entry.path().strip_prefix(dir.path.join("header")).unwrap());
+            let plain = fs::read(plain_path).unwrap();
+            let len = min(header.len(), contents.len());
+            assert_eq!(contents.len(), plain.len(), "{:?}", entry.path());
+            assert_eq!(contents[..len], header[..len], "{:?}", entry.path());
+            assert_eq!(contents[len..], plain[len..], "{:?}", entry.path());
+        }
+    }
+}
+
+#[rstest]
+#[case(MediaFormat::Png, "png")]
+#[case(MediaFormat::Jpeg, "jpg")]
+#[case(MediaFormat::Wav, "wav")]
+#[case(MediaFormat::Mp3, "mp3")]
+fn media_files_are_sniffed_as_their_format(#[case] format: MediaFormat, #[case] extension: &str) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .num_bytes(100_000)
+        .media_mix(vec![format])
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let kind = infer::get_from_path(entry.path()).unwrap();
+            assert_eq!(kind.map(|kind| kind.extension()), Some(extension), "{:?}", entry.path());
+        }
+    }
+}
+
+#[test]
+fn files_grouped_by_extension_only_live_in_their_format_directory() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(500).unwrap())
+        .files_exact(true)
+        .num_bytes(500_000)
+        .media_mix(vec![
+            MediaFormat::Png,
+            MediaFormat::Jpeg,
+            MediaFormat::Wav,
+            MediaFormat::Mp3,
+            MediaFormat::Png,
+        ])
+        .group_by_extension(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut groups = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    groups.sort();
+    assert_eq!(groups, ["jpg", "mp3", "png", "wav"]);
+    assert_eq!(count_num_files(&dir.path), 500);
+
+    for extension in groups {
+        let group = dir.path.join(&extension);
+        let expected_files = if extension == "png" { 200 } else { 100 };
+        assert_eq!(count_num_files(&group), expected_files, "{:?}", group);
+
+        let mut queue = VecDeque::from([group]);
+        while let Some(dir) = queue.pop_front() {
+            for entry in dir.read_dir().unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    queue.push_back(entry.path());
+                    continue;
+                }
+
+                let kind = infer::get_from_path(entry.path()).unwrap();
+                assert_eq!(
+                    kind.map(|kind| kind.extension()),
+                    Some(extension.as_str()),
+                    "{:?}",
+                    entry.path()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn source_repo_profile_generates_small_code_files() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(500).unwrap())
+        .profile(Profile::SourceRepo)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let extensions = HashSet::from([
+        "rs", "ts", "js", "py", "go", "java", "c", "h", "cpp", "rb", "md", "toml", "json",
+    ]);
+    let mut sizes = Vec::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let path = entry.path();
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            assert!(extensions.contains(extension), "{:?}", path);
+            sizes.push(entry.metadata().unwrap().len());
+        }
+    }
+
+    assert_ge!(sizes.len(), 250);
+    assert_le!(*sizes.iter().max().unwrap(), 16 << 10);
+    let mean = sizes.iter().sum::<u64>() / sizes.len() as u64;
+    assert_ge!(mean, 3 << 10);
+    assert_le!(mean, 5 << 10);
+}
+
+#[test]
+fn seed_paths_exist_after_generation() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .seed_paths(vec![
+            String::from(".git/"),
+            String::from("node_modules/left-pad/index.js"),
+            String::from(".git/HEAD"),
+        ])
+        .self_check(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert!(dir.path.join(".git").is_dir());
+    assert!(dir.path.join(".git/HEAD").is_file());
+    assert!(dir.path.join("node_modules/left-pad").is_dir());
+    assert!(dir.path.join("node_modules/left-pad/index.js").is_file());
+}
+
+#[rstest]
+#[case("0")]
+#[case("0.dir/")]
+#[case("0.dir/nested")]
+fn seed_paths_colliding_with_generated_names_fail(#[case] seed_path: &str) {
+    let dir = InspectableTempDir::new();
+
+    let result = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .seed_paths(vec![String::from(seed_path)])
+        .build()
+        .unwrap()
+        .generate();
+
+    assert!(result.is_err());
+    assert_eq!(dir.path.read_dir().unwrap().count(), 0);
+}
+
+#[test]
+fn overlay_contains_source_and_synthetic_files() {
+    let source = InspectableTempDir::new();
+    let dir = InspectableTempDir::new();
+    create_dir_all(source.path.join("src/bin")).unwrap();
+    fs::write(source.path.join("README.md"), "hello").unwrap();
+    fs::write(source.path.join("src/lib.rs"), "pub mod bin;").unwrap();
+    fs::write(source.path.join("src/bin/main.rs"), "fn main() {}").unwrap();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .overlay_source(source.path.clone())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_eq!(fs::read(dir.path.join("README.md")).unwrap(), b"hello");
+    assert_eq!(fs::read(dir.path.join("src/lib.rs")).unwrap(), b"pub mod bin;");
+    assert_eq!(fs::read(dir.path.join("src/bin/main.rs")).unwrap(), b"fn main() {}");
+    assert_eq!(count_num_files(&source.path), 3);
+    assert_eq!(count_num_files(&dir.path), 1_000 + 3);
+    // Half of the files are generated into the copied directory
+    assert_eq!(count_num_files(&dir.path.join("src")), 500 + 2);
+}
+
+#[rstest]
+fn overlay_entries_colliding_with_generated_ones_fail(#[values("0", "src/0.dir")] path: &str) {
+    let source = InspectableTempDir::new();
+    let dir = InspectableTempDir::new();
+    create_dir_all(source.path.join("src")).unwrap();
+    fs::write(source.path.join(path), "hello").unwrap();
+
+    let result = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .overlay_source(source.path.clone())
+        .build()
+        .unwrap()
+        .generate();
+
+    assert!(result.is_err());
+    assert_eq!(dir.path.read_dir().unwrap().count(), 0);
+}
+
+#[test]
+fn generation_halts_before_exceeding_the_max_total_size() {
+    let source = InspectableTempDir::new();
+    let dir = InspectableTempDir::new();
+    create_dir_all(source.path.join("docs")).unwrap();
+    fs::write(source.path.join("docs/big.bin"), vec![0; 1_000_000]).unwrap();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .num_bytes(20_000_000)
+        .overlay_source(source.path.clone())
+        .max_total_size(5_000_000)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let blocks = |len: u64| (len + 4095) / 4096 * 4096;
+    let mut usage = 4096;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                usage += 4096;
+                queue.push_back(entry.path());
+            } else {
+                usage += blocks(entry.metadata().unwrap().len());
+            }
+        }
+    }
+    assert_eq!(fs::read(dir.path.join("docs/big.bin")).unwrap().len(), 1_000_000);
+    assert_lt!(count_num_files(&dir.path), 1_000);
+    assert_ge!(usage, 4_500_000);
+    assert_le!(usage, 5_000_000);
+}
+
+#[test]
+fn layout_creates_exactly_the_described_tree() {
+    let dir = InspectableTempDir::new();
+    let layout = r#"{"$files": 2, "src": {"$files": 10, "bin": 3, "lib": {}}, "docs": 1}"#;
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .layout(Layout::from_json(layout).unwrap())
+        .num_bytes(10_000)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut dirs = HashMap::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        let mut files = 0;
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                files += 1;
+            }
+        }
+        dirs.insert(path.strip_prefix(&dir.path).unwrap().to_path_buf(), files);
+    }
+    assert_eq!(
+        dirs,
+        HashMap::from([
+            (PathBuf::new(), 2),
+            (PathBuf::from("src"), 10),
+            (PathBuf::from("src/bin"), 3),
+            (PathBuf::from("src/lib"), 0),
+            (PathBuf::from("docs"), 1),
+        ])
+    );
+}
+
+#[rstest]
+fn file_sizes_are_multiples_of_the_size_alignment(#[values(false, true)] bytes_exact: bool) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(4_096_000)
+        .bytes_exact(bytes_exact)
+        .size_alignment(NonZeroUsize::new(512).unwrap())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut total = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                let len = entry.metadata().unwrap().len();
+                assert_eq!(len % 512, 0, "{:?} is {} bytes", entry.path(), len);
+                total += len;
+            }
+        }
+    }
+    if bytes_exact {
+        assert_eq!(total, 4_096_000);
+    }
+}
+
+#[test]
+fn rate_limit_throttles_creations() {
+    let dir = InspectableTempDir::new();
+    let start = Instant::now();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .files_exact(true)
+        .max_depth(0)
+        .rate_limit(NonZeroUsize::new(20).unwrap())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_ge!(start.elapsed(), Duration::from_millis(10 * 1000 / 20));
+    assert_eq!(dir.path.read_dir().unwrap().count(), 10);
+}
+
+#[test]
+fn byte_rate_throttles_writes() {
+    let dir = InspectableTempDir::new();
+    let start = Instant::now();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .files_exact(true)
+        .num_bytes(50_000)
+        .bytes_exact(true)
+        .max_depth(0)
+        .byte_rate(NonZeroUsize::new(50_000).unwrap())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_ge!(start.elapsed(), Duration::from_millis(50_000 * 1000 / 50_000));
+    assert_eq!(dir.path.read_dir().unwrap().count(), 10);
+}
+
+#[test]
+fn write_memory_budget_leaves_the_tree_unchanged() {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, budget: Option<usize>| {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(10_000_000)
+            .max_depth(3);
+        if let Some(budget) = budget {
+            builder.write_memory_budget(NonZeroUsize::new(budget).unwrap());
+        }
+        builder.build().unwrap().generate().unwrap();
+        hash_dir(&root)
+    };
+
+    assert_eq!(
+        generate(dir.path.join("budgeted"), Some(10_000)),
+        generate(dir.path.join("unbounded"), None)
+    );
+}
+
+#[rstest]
+fn exact_number_of_empty_files_is_generated(
+    #[values(false, true)] files_exact: bool,
+    #[values(0, 5)] max_depth: u32,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(files_exact)
+        .num_bytes(1_000_000)
+        .max_depth(max_depth)
+        .empty_files(100)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut num_empty = 0;
+    let mut num_files = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                queue.push_back(entry.path());
+            } else if metadata.len() == 0 {
+                num_empty += 1;
+            } else {
+                num_files += 1;
+            }
+        }
+    }
+
+    assert_eq!(num_empty, 100);
+    if files_exact {
+        assert_eq!(num_files, 1_000);
+    }
+}
+
+#[rstest]
+fn files_are_copied_from_the_content_source(#[values(0, 1_000)] num_bytes: usize) {
+    let source = tempfile::tempdir().unwrap();
+    let samples = [
+        b"The quick brown fox jumps over the lazy dog. ".repeat(3),
+        (0..=255).collect::<Vec<u8>>(),
+        b"#!/bin/sh\necho hello\n".to_vec(),
+    ];
+    create_dir(source.path().join("nested")).unwrap();
+    for (i, sample) in samples.iter().enumerate() {
+        fs::write(source.path().join("nested").join(i.to_string()), sample).unwrap();
+    }
+    File::create(source.path().join("empty")).unwrap();
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .num_bytes(num_bytes)
+        .content_source(source.path())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let contents = fs::read(entry.path()).unwrap();
+            assert!(
+                samples.iter().any(|sample| if num_bytes == 0 {
+                    contents == *sample
+                } else {
+                    sample.starts_with(&contents)
+                }),
+                "{:?}",
+                entry.path()
+            );
+        }
+    }
+}
+
+#[rstest]
+fn archives_open_with_the_expected_number_of_entries(
+    #[values(ArchiveFormat::Tar, ArchiveFormat::Zip)] format: ArchiveFormat,
+    #[values(1, 3)] inner_entries: usize,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .files_exact(true)
+        .num_bytes(10_000)
+        .fill_mode(FillMode::Archive {
+            format,
+            inner_entries,
+        })
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut num_archives = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let file = File::open(entry.path()).unwrap();
+            let mut num_entries = 0;
+            match format {
+                ArchiveFormat::Tar => {
+                    for inner in tar::Archive::new(file).entries().unwrap() {
+                        inner.unwrap().read_to_end(&mut Vec::new()).unwrap();
+                        num_entries += 1;
+                    }
+                }
+                ArchiveFormat::Zip => {
+                    let mut archive = zip::ZipArchive::new(file).unwrap();
+                    for i in 0..archive.len() {
+                        // Reading to the end verifies the entry's checksum
+                        archive
+                            .by_index(i)
+                            .unwrap()
+                            .read_to_end(&mut Vec::new())
+                            .unwrap();
+                        num_entries += 1;
+                    }
+                }
+            }
+            assert_eq!(num_entries, inner_entries, "{:?}", entry.path());
+            num_archives += 1;
+        }
+    }
+
+    assert_eq!(num_archives, 100);
+}
+
+#[rstest]
+fn nested_archives_unwrap_to_the_flat_archive(
+    #[values(ArchiveFormat::Tar, ArchiveFormat::Zip)] format: ArchiveFormat,
+) {
+    let dir = InspectableTempDir::new();
+    let flat = dir.path.join("flat");
+    let nested = dir.path.join("nested");
+    let extension = match format {
+        ArchiveFormat::Tar => "tar",
+        ArchiveFormat::Zip => "zip",
+    };
+
+    for (root_dir, nesting_depth) in [(&flat, 1), (&nested, 5)] {
+        GeneratorBuilder::default()
+            .root_dir(root_dir.clone())
+            .num_files(NonZeroUsize::new(10).unwrap())
+            .files_exact(true)
+            .num_bytes(10_000)
+            .fill_mode(FillMode::Archive {
+                format,
+                inner_entries: 3,
+            })
+            .nesting_depth(nesting_depth)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+    }
+
+    let mut num_archives = 0;
+    let mut queue = VecDeque::from([PathBuf::new()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in nested.join(&dir).read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let path = dir.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(path);
+                continue;
+            }
+
+            let mut contents = fs::read(entry.path()).unwrap();
+            for _ in 1..5 {
+                let mut inner = Vec::new();
+                let name = match format {
+                    ArchiveFormat::Tar => {
+                        let mut archive = tar::Archive::new(contents.as_slice());
+                        let mut entries = archive.entries().unwrap();
+                        let mut only = entries.next().unwrap().unwrap();
+                        only.read_to_end(&mut inner).unwrap();
+                        let name = only.path().unwrap().into_owned();
+                        assert!(entries.next().is_none(), "{:?}", path);
+                        name
+                    }
+                    ArchiveFormat::Zip => {
+                        let mut archive = zip::ZipArchive::new(Cursor::new(contents)).unwrap();
+                        assert_eq!(archive.len(), 1, "{:?}", path);
+                        let mut only = archive.by_index(0).unwrap();
+                        // Reading to the end verifies the entry's checksum
+                        only.read_to_end(&mut inner).unwrap();
+                        PathBuf::from(only.name())
+                    }
+                };
+                assert_eq!(name.extension().unwrap(), extension, "{:?}", path);
+                contents = inner;
+            }
+
+            assert_eq!(contents, fs::read(flat.join(&path)).unwrap(), "{:?}", path);
+            num_archives += 1;
+        }
+    }
+
+    assert_eq!(num_archives, 10);
+}
+
+#[test]
+fn duplicate_groups_have_identical_contents() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .duplicate_groups(NonZeroUsize::new(7).unwrap())
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut hashes = HashSet::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                let mut hasher = SeaHasher::new();
+                hasher.write(&fs::read(entry.path()).unwrap());
+                hashes.insert(hasher.finish());
+            }
+        }
+    }
+
+    assert_eq!(hashes.len(), 7);
+}
+
+#[rstest]
+fn repair_restores_deleted_files(
+    #[values(false, true)] files_exact: bool,
+    #[values(false, true)] bytes_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+    let generate = |repair: bool| {
+        GeneratorBuilder::default()
+            .root_dir(dir.path.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(100_000)
+            .max_depth(3)
+            .files_exact(files_exact)
+            .bytes_exact(bytes_exact)
+            .repair(repair)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+    };
+
+    generate(false);
+    let hash = hash_dir(&dir.path);
+
+    let mut num_seen = 0;
+    let mut deleted_dir = false;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            num_seen += 1;
+            if entry.file_type().unwrap().is_dir() {
+                if deleted_dir {
+                    queue.push_back(entry.path());
+                } else {
+                    fs::remove_dir_all(entry.path()).unwrap();
+                    deleted_dir = true;
+                }
+            } else if num_seen % 3 == 0 {
+                fs::remove_file(entry.path()).unwrap();
+            }
+        }
+    }
+    assert_ne!(hash_dir(&dir.path), hash);
+
+    generate(true);
+
+    assert_eq!(hash_dir(&dir.path), hash);
+}
+
+#[rstest]
+fn two_phase_matches_single_phase(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(false, true)] files_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, two_phase: bool| {
+        GeneratorBuilder::default()
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .files_exact(files_exact)
+            .num_bytes(num_bytes)
+            .max_depth(4)
+            .seed(7)
+            .two_phase(two_phase)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        hash_dir(&root)
+    };
+
+    assert_eq!(
+        generate(dir.path.join("two"), true),
+        generate(dir.path.join("one"), false)
+    );
+}
+
+#[rstest]
+fn task_granularity_matches_a_task_per_dir(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(1, 2, 3)] granularity: u32,
+) {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, granularity: Option<u32>| {
+        let mut builder = GeneratorBuilder::default();
+        builder
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(num_bytes)
+            .max_depth(4)
+            .seed(7);
+        if let Some(granularity) = granularity {
+            builder.task_granularity(granularity);
+        }
+        builder.build().unwrap().generate().unwrap();
+        hash_dir(&root)
+    };
+
+    assert_eq!(
+        generate(dir.path.join("batched"), Some(granularity)),
+        generate(dir.path.join("unbatched"), None)
+    );
+}
+
+#[rstest]
+fn openat_matches_std(#[values(0, 100_000)] num_bytes: usize) {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, openat: bool| {
+        GeneratorBuilder::default()
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(num_bytes)
+            .max_depth(10)
+            .openat(openat)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        hash_dir(&root)
+    };
+
+    assert_eq!(
+        generate(dir.path.join("openat"), true),
+        generate(dir.path.join("std"), false)
+    );
+}
+
+#[rstest]
+#[case(1_000, 100, 3)]
+#[case(1_000, 0, 3)]
+#[case(10, 500, 2)]
+#[case(1, 1, 1)]
+#[case(5_000, 37, 5)]
+fn exact_files_and_dirs_are_respected(
+    #[case] num_files: usize,
+    #[case] num_dirs: usize,
+    #[case] max_depth: u32,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(num_files).unwrap())
+        .files_exact(true)
+        .dirs_exact(num_dirs)
+        .max_depth(max_depth)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_le!(find_max_depth(&dir.path), max_depth);
+    assert_eq!(count_num_files(&dir.path), num_files);
+    assert_eq!(count_num_dirs(&dir.path), num_dirs);
+}
+
+#[rstest]
+#[case(0, None)]
+#[case(3, None)]
+#[case(3, Some(20))]
+fn size_schedule_is_reproduced_exactly(#[case] max_depth: u32, #[case] num_dirs: Option<usize>) {
+    let dir = InspectableTempDir::new();
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+    let mut sizes = (0..500)
+        .map(|_| rng.gen_range(0..10_000))
+        .collect::<Vec<_>>();
+
+    let mut builder = GeneratorBuilder::default();
+    builder
+        .root_dir(dir.path.clone())
+        .size_schedule(sizes.clone())
+        .max_depth(max_depth)
+        .self_check(true);
+    if let Some(dirs) = num_dirs {
+        builder.dirs_exact(dirs);
+    }
+    builder.build().unwrap().generate().unwrap();
+
+    let mut generated = Vec::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                generated.push(entry.metadata().unwrap().len() as usize);
+            }
+        }
+    }
+    sizes.sort_unstable();
+    generated.sort_unstable();
+    assert_eq!(generated, sizes);
+}
+
+#[rstest]
+fn degenerate_trees_are_generated(#[values(false, true)] shrink_depth: bool) {
+    let dir = InspectableTempDir::new();
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(3).unwrap())
+        .files_exact(true)
+        .max_depth(5)
+        .file_to_dir_ratio(NonZeroUsize::new(1).unwrap())
+        .shrink_depth(shrink_depth)
+        .build()
+        .unwrap();
+
+    let planned = g.plan_paths().count();
+    g.generate().unwrap();
+
+    assert_eq!(count_num_files(&dir.path), 3);
+    assert_eq!(
+        planned,
+        count_num_files(&dir.path) + count_num_dirs(&dir.path)
+    );
+    if shrink_depth {
+        assert_le!(find_max_depth(&dir.path), 1);
+    }
+}
+
+#[rstest]
+fn chain_puts_all_files_at_the_leaf(#[values(0, 1, 7)] max_depth: u32) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .num_bytes(10_000)
+        .max_depth(max_depth)
+        .chain(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut leaf = dir.path.clone();
+    for _ in 0..max_depth {
+        let entries = leaf
+            .read_dir()
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].file_type().unwrap().is_dir());
+        leaf = entries[0].path();
+    }
+
+    assert_eq!(count_num_files(&leaf), 100);
+    assert_eq!(count_num_files(&dir.path), 100);
+    assert_eq!(count_num_dirs(&dir.path), max_depth as usize);
+}
+
+#[rstest]
+fn balanced_trees_are_uniform(#[values(0, 100_000)] num_bytes: usize) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .balanced_fanout(4)
+        .self_check(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut leaves = Vec::new();
+    let mut queue = VecDeque::from([(dir.path.clone(), 0)]);
+    while let Some((dir, depth)) = queue.pop_front() {
+        let entries = dir
+            .read_dir()
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect::<Vec<_>>();
+        let num_dirs = entries
+            .iter()
+            .filter(|e| e.file_type().unwrap().is_dir())
+            .count();
+        if num_dirs > 0 {
+            assert_eq!(num_dirs, 4, "{:?}", dir);
+            assert_eq!(entries.len(), 4, "{:?}", dir);
+            for entry in entries {
+                queue.push_back((entry.path(), depth + 1));
+            }
+        } else {
+            // 4^5 is the first power of 4 to fit 1,000 files at no more than 4 per leaf
+            assert_eq!(depth, 4, "{:?}", dir);
+            leaves.push(entries.len());
+        }
+    }
+
+    assert_eq!(leaves.len(), 256);
+    assert_eq!(leaves.iter().sum::<usize>(), 1_000);
+    assert!(leaves.iter().all(|&files| files == 3 || files == 4));
+}
+
+#[rstest]
+fn deep_chains_are_created_in_full(#[values(false, true)] openat: bool) {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .max_depth(300)
+        .chain(true)
+        .openat(openat)
+        .build()
+        .unwrap();
+    let planned = g.plan_paths().map(|(path, _)| path).collect::<HashSet<_>>();
+    g.generate().unwrap();
+
+    let leaf = (0..300).fold(dir.path.clone(), |dir, _| dir.join("0.dir"));
+    assert_eq!(count_num_files(&leaf), 10);
+    assert_eq!(count_num_dirs(&dir.path), 300);
+    assert_eq!(planned.len(), 310);
+    assert!(planned.contains(&leaf));
+}
+
+#[rstest]
+#[case(false, false, None)]
+#[case(true, false, None)]
+#[case(true, true, None)]
+#[case(true, true, Some(50))]
+fn planned_paths_match_generation(
+    #[case] files_exact: bool,
+    #[case] bytes_exact: bool,
+    #[case] dirs_exact: Option<usize>,
+) {
+    let dir = InspectableTempDir::new();
+
+    let mut builder = GeneratorBuilder::default();
+    builder
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .files_exact(files_exact)
+        .bytes_exact(bytes_exact);
+    if let Some(dirs) = dirs_exact {
+        builder.dirs_exact(dirs);
+    }
+    let g = builder.build().unwrap();
+
+    let planned = g.plan_paths().collect::<Vec<_>>();
+    assert_eq!(dir.path.read_dir().unwrap().count(), 0);
+    g.generate().unwrap();
+
+    assert_eq!(
+        planned.len(),
+        count_num_files(&dir.path) + count_num_dirs(&dir.path)
+    );
+    for (path, kind) in planned {
+        match kind {
+            EntryKind::Dir => assert!(path.is_dir(), "{:?}", path),
+            EntryKind::File { bytes } => {
+                assert_eq!(
+                    fs::metadata(&path).unwrap().len() as usize,
+                    bytes,
+                    "{:?}",
+                    path
+                )
+            }
+            EntryKind::Fifo | EntryKind::BrokenSymlink { .. } => {
+                unreachable!("No special files were requested")
+            }
+        }
+    }
+}
+
+#[test]
+fn sampled_paths_exist_after_generation() {
+    let dir = InspectableTempDir::new();
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .seed(42)
+        .build()
+        .unwrap();
+
+    let sample = g.sample_paths(NonZeroUsize::new(10).unwrap());
+    assert_eq!(sample, g.sample_paths(NonZeroUsize::new(10).unwrap()));
+    assert_eq!(dir.path.read_dir().unwrap().count(), 0);
+    g.generate().unwrap();
+
+    assert_eq!(sample.len(), 10);
+    assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 10);
+    for path in sample {
+        assert!(path.is_file(), "{:?}", path);
+    }
+}
+
+#[rstest]
+fn catalog_lists_every_generated_file(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(false, true)] overlay: bool,
+) {
+    let dir = InspectableTempDir::new();
+    let source = InspectableTempDir::new();
+    let catalog = InspectableTempDir::new();
+    fs::write(source.path.join("README.md"), "hello").unwrap();
+
+    let mut builder = GeneratorBuilder::default();
+    builder
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .catalog(catalog.path.join("catalog.csv"));
+    if overlay {
+        builder.overlay_source(source.path.clone());
+    }
+    builder.build().unwrap().generate().unwrap();
+    let catalog = fs::read_to_string(catalog.path.join("catalog.csv")).unwrap();
+
+    let mut lines = catalog.lines();
+    assert_eq!(lines.next(), Some("path,type,bytes"));
+    let mut rows = 0;
+    let mut bytes = 0;
+    for line in lines {
+        let columns = line.split(',').collect::<Vec<_>>();
+        assert_eq!(columns.len(), 3, "{:?}", line);
+        assert!(dir.path.join(columns[0]).is_file(), "{:?}", line);
+        assert_eq!(columns[1], "file");
+        bytes += columns[2].parse::<usize>().unwrap();
+        rows += 1;
+    }
+    assert_eq!(rows, count_num_files(&dir.path));
+    assert_eq!(bytes, count_num_bytes(&dir.path));
+    assert_eq!(catalog.contains("\nREADME.md,file,5\n"), overlay);
+}
+
+#[rstest]
+fn emitted_script_matches_plan(#[values(0, 100_000)] num_bytes: usize) {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .build()
+        .unwrap();
+    let mut script = Vec::new();
+    g.write_script(&mut script).unwrap();
+    let script = String::from_utf8(script).unwrap();
+
+    let (mut planned_files, mut planned_dirs) = (0, 0);
+    for (_, kind) in g.plan_paths() {
+        match kind {
+            EntryKind::Dir => planned_dirs += 1,
+            EntryKind::File { .. } => planned_files += 1,
+            EntryKind::Fifo | EntryKind::BrokenSymlink { .. } => {
+                unreachable!("No special files were requested")
+            }
+        }
+    }
+    let count_lines = |prefix: &str| script.lines().filter(|l| l.starts_with(prefix)).count();
+
+    assert!(script.starts_with("#!/bin/sh\n"));
+    // The root directory gets created too
+    assert_eq!(count_lines("mkdir "), planned_dirs + 1);
+    assert_eq!(count_lines("touch ") + count_lines("head "), planned_files);
+    if num_bytes == 0 {
+        assert_eq!(count_lines("touch "), planned_files);
+    }
+    assert_eq!(dir.path.read_dir().unwrap().count(), 0);
+}
+
+#[rstest]
+fn creation_order_is_honored(
+    #[values(
+        CreationOrder::DirsFirst,
+        CreationOrder::FilesFirst,
+        CreationOrder::Interleaved
+    )]
+    order: CreationOrder,
+) {
+    let generate = |order: CreationOrder| {
+        let dir = InspectableTempDir::new();
+
+        let g = GeneratorBuilder::default()
+            .root_dir(dir.path.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(10_000)
+            .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+            .creation_order(order)
+            .build()
+            .unwrap();
+        let planned = g.plan_paths().collect::<Vec<_>>();
+        g.generate().unwrap();
+
+        (planned, hash_dir(&dir.path))
+    };
+    let (planned, hash) = generate(order);
+
+    let mut entries_by_parent = HashMap::<_, Vec<_>>::new();
+    for (path, kind) in planned {
+        entries_by_parent
+            .entry(path.parent().unwrap().to_path_buf())
+            .or_default()
+            .push(matches!(kind, EntryKind::Dir));
+    }
+    for (parent, is_dir) in entries_by_parent {
+        let num_dirs = is_dir.iter().filter(|&&d| d).count();
+        let expected = match order {
+            CreationOrder::DirsFirst => (0..is_dir.len()).map(|i| i < num_dirs).collect(),
+            CreationOrder::FilesFirst => (0..is_dir.len())
+                .map(|i| i >= is_dir.len() - num_dirs)
+                .collect(),
+            CreationOrder::Interleaved => {
+                let alternating = 2 * min(num_dirs, is_dir.len() - num_dirs);
+                (0..is_dir.len())
+                    .map(|i| {
+                        if i < alternating {
+                            i % 2 == 0
+                        } else {
+                            num_dirs > is_dir.len() - num_dirs
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(is_dir, expected, "{:?}", parent);
+    }
+    // Only the order changes, not what gets created
+    assert_eq!(hash, generate(CreationOrder::DirsFirst).1);
+}
+
+#[rstest]
+fn creation_order_decides_which_entries_fit_on_disk(
+    #[values(
+        CreationOrder::DirsFirst,
+        CreationOrder::FilesFirst,
+        CreationOrder::Interleaved
+    )]
+    order: CreationOrder,
+) {
+    let dir = InspectableTempDir::new();
+
+    // Every file takes up a single block like directories do, and there's room for the root and two
+    // more blocks, so generation halts right after the first two entries are created
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .file_to_dir_ratio(NonZeroUsize::new(10).unwrap())
+        .creation_order(order)
+        .max_total_size(3 * 4096)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let (mut dirs, mut files) = (0, 0);
+    for entry in dir.path.read_dir().unwrap() {
+        if entry.unwrap().file_type().unwrap().is_dir() {
+            dirs += 1;
+        } else {
+            files += 1;
+        }
+    }
+    let expected = match order {
+        CreationOrder::DirsFirst => (2, 0),
+        CreationOrder::FilesFirst => (0, 2),
+        CreationOrder::Interleaved => (1, 1),
+    };
+    assert_eq!((dirs, files), expected);
+}
+
+#[cfg(target_os = "linux")]
+#[rstest]
+fn special_files_are_created_without_blocking(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(false, true)] openat: bool,
+) {
+    use std::os::unix::fs::FileTypeExt;
+
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .special_ratio(0.25)
+        .openat(openat)
+        .build()
+        .unwrap();
+    let planned = g
+        .plan_paths()
+        .filter(|(_, kind)| *kind == EntryKind::Fifo)
+        .map(|(path, _)| path)
+        .collect::<HashSet<_>>();
+    g.generate().unwrap();
+
+    let mut fifos = HashSet::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let file_type = entry.file_type().unwrap();
+            if file_type.is_dir() {
+                queue.push_back(entry.path());
+            } else if file_type.is_fifo() {
+                fifos.insert(entry.path());
+            }
+        }
+    }
+
+    assert!(fifos.len() > 100, "{}", fifos.len());
+    assert_eq!(fifos, planned);
+}
+
+#[cfg(unix)]
+#[rstest]
+fn broken_symlinks_fail_to_resolve(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(false, true)] openat: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .broken_symlink_ratio(0.25)
+        .openat(openat)
+        .build()
+        .unwrap();
+    let planned = g
+        .plan_paths()
+        .filter(|(_, kind)| matches!(kind, EntryKind::BrokenSymlink { .. }))
+        .map(|(path, _)| path)
+        .collect::<HashSet<_>>();
+    g.generate().unwrap();
+
+    let mut symlinks = HashSet::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let file_type = entry.file_type().unwrap();
+            if file_type.is_dir() {
+                queue.push_back(entry.path());
+            } else if file_type.is_symlink() {
+                let path = entry.path();
+                assert!(fs::metadata(&path).is_err(), "{:?}", path);
+                assert!(fs::symlink_metadata(&path).is_ok(), "{:?}", path);
+                symlinks.insert(path);
+            }
+        }
+    }
+
+    assert!(symlinks.len() > 100, "{}", symlinks.len());
+    assert_eq!(symlinks, planned);
+}
+
+#[cfg(unix)]
+#[test]
+fn sparse_files_are_created_at_the_ratio() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .num_bytes(10_000_000)
+        .sparse_ratio(0.3)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut files = 0;
+    let mut sparse = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                queue.push_back(entry.path());
+            } else if metadata.len() > 0 {
+                files += 1;
+                sparse += usize::from(metadata.blocks() * 512 < metadata.len() / 2);
+            }
+        }
+    }
+
+    assert_ge!(files, 900);
+    assert_ge!(sparse * 100, files * 25);
+    assert_le!(sparse * 100, files * 35);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn sparse_holes_are_surrounded_by_data() {
+    use std::os::unix::io::AsRawFd;
+
+    use nix::unistd::{lseek, Whence};
+
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(10).unwrap())
+        .files_exact(true)
+        .num_bytes(10 * 1024 * 1024)
+        .bytes_exact(true)
+        .sparse_ratio(1.)
+        .sparse_holes(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut files = 0;
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let file = File::open(entry.path()).unwrap();
+            let fd = file.as_raw_fd();
+            let len = file.metadata().unwrap().len() as i64;
+            assert_eq!(len, 1024 * 1024);
+            assert_eq!(lseek(fd, 0, Whence::SeekData).unwrap(), 0);
+            // The end of the file counts as a hole too, so the first one must come before it
+            let hole = lseek(fd, 0, Whence::SeekHole).unwrap();
+            assert_lt!(hole, len, "{:?}", entry.path());
+            let data = lseek(fd, hole, Whence::SeekData).unwrap();
+            assert_lt!(hole, data);
+            assert_lt!(data, len);
+            files += 1;
+        }
+    }
+    assert_eq!(files, 10);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn preallocated_files_are_fully_allocated_with_unchanged_contents() {
+    use std::os::unix::fs::MetadataExt;
+
+    let expected = InspectableTempDir::new();
+    let actual = InspectableTempDir::new();
+    for (dir, preallocate) in [(&expected, false), (&actual, true)] {
+        GeneratorBuilder::default()
+            .root_dir(dir.path.clone())
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .num_bytes(10_000_000)
+            .preallocate(preallocate)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+    }
+
+    let mut queue = VecDeque::from([actual.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                assert_ge!(metadata.blocks() * 512, metadata.len());
+            }
+        }
+    }
+    assert_eq!(diff_trees(&expected.path, &actual.path).unwrap(), TreeDiff::default());
+}
+
+#[test]
+fn read_only_entries_are_created_at_the_ratio() {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .num_bytes(100_000)
+        .file_to_dir_ratio(NonZeroUsize::new(2).unwrap())
+        .read_only_ratio(0.3)
+        .read_only_dirs(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let (mut files, mut read_only_files) = (0, 0);
+    let (mut dirs, mut read_only_dirs) = (0, 0);
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            let mut permissions = metadata.permissions();
+            if metadata.is_dir() {
+                queue.push_back(entry.path());
+                dirs += 1;
+                read_only_dirs += usize::from(permissions.readonly());
+            } else {
+                files += 1;
+                read_only_files += usize::from(permissions.readonly());
+            }
+
+            // Let the temporary directory be cleaned up
+            permissions.set_readonly(false);
+            fs::set_permissions(entry.path(), permissions).unwrap();
+        }
+    }
+
+    assert_eq!(files, 1_000);
+    assert_ge!(read_only_files * 100, files * 25);
+    assert_le!(read_only_files * 100, files * 35);
+    assert_ge!(dirs, 100);
+    assert_ge!(read_only_dirs * 100, dirs * 20);
+    assert_le!(read_only_dirs * 100, dirs * 40);
+}
+
+#[cfg(unix)]
+#[test]
+fn xattrs_are_attached_at_the_ratio() {
+    use ftzz::generator::XATTR_NAME;
+
+    let dir = InspectableTempDir::new();
+    // Not every file system (e.g. tmpfs on older kernels) supports user attributes
+    let probe = dir.path.join("probe");
+    File::create(&probe).unwrap();
+    if xattr::set(&probe, XATTR_NAME, b"probe").is_err() {
+        return;
+    }
+    fs::remove_file(probe).unwrap();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(true)
+        .num_bytes(100_000)
+        .xattr_ratio(0.3)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let (mut files, mut tagged_files) = (0, 0);
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(dir) = queue.pop_front() {
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            files += 1;
+            if let Some(value) = xattr::get(entry.path(), XATTR_NAME).unwrap() {
+                assert_eq!(value.len(), 16);
+                assert!(value.iter().all(u8::is_ascii_hexdigit));
+                tagged_files += 1;
+            }
+        }
+    }
+
+    assert_eq!(files, 1_000);
+    assert_ge!(tagged_files * 100, files * 25);
+    assert_le!(tagged_files * 100, files * 35);
+}
+
+#[cfg(unix)]
+#[test]
+fn umask_makes_modes_independent_of_the_ambient_one() {
+    use std::{env, os::unix::fs::PermissionsExt, process::Command};
+
+    use nix::sys::stat::{umask, Mode};
+
+    // The umask is shared by the whole process, so changing it would leak into the entries of
+    // concurrently running tests. Run alone in a child process instead.
+    const CHILD: &str = "FTZZ_UMASK_TEST_CHILD";
+    if env::var_os(CHILD).is_none() {
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "umask_makes_modes_independent_of_the_ambient_one"])
+            .env(CHILD, "1")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        return;
+    }
+
+    let dir = InspectableTempDir::new();
+
+    let mut trees = Vec::new();
+    for (i, ambient) in [0o000, 0o077].into_iter().enumerate() {
+        let root_dir = dir.path.join(i.to_string());
+        let previous = umask(Mode::from_bits_truncate(ambient));
+        let result = GeneratorBuilder::default()
+            .root_dir(root_dir.clone())
+            .num_files(NonZeroUsize::new(100).unwrap())
+            .files_exact(true)
+            .num_bytes(10_000)
+            .umask(0o027)
+            .build()
+            .unwrap()
+            .generate();
+        umask(previous);
+        result.unwrap();
+
+        let mut modes = Vec::new();
+        let mut queue = VecDeque::from([root_dir.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            let mode = dir.metadata().unwrap().permissions().mode() & 0o777;
+            modes.push((dir.strip_prefix(&root_dir).unwrap().to_path_buf(), mode));
+            for entry in dir.read_dir().unwrap() {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    queue.push_back(entry.path());
+                } else {
+                    let mode = entry.metadata().unwrap().permissions().mode() & 0o777;
+                    modes.push((entry.path().strip_prefix(&root_dir).unwrap().to_path_buf(), mode));
+                }
+            }
+        }
+        modes.sort();
+        trees.push(modes);
+    }
+
+    assert_eq!(trees[0], trees[1]);
+    assert_ge!(trees[0].len(), 100);
+    for (path, mode) in &trees[0] {
+        let expected = if dir.path.join("0").join(path).is_dir() { 0o750 } else { 0o640 };
+        assert_eq!(*mode, expected, "{:?}", path);
+    }
+}
+
+#[rstest]
+fn files_are_named_after_their_contents(#[values(false, true)] bytes_exact: bool) {
+    let dir = InspectableTempDir::new();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(1_000_000)
+        .bytes_exact(bytes_exact)
+        .names_from_content(true)
+        .build()
+        .unwrap();
+    let planned = g.plan_paths().map(|(path, _)| path).collect::<HashSet<_>>();
+    g.generate().unwrap();
+
+    let mut paths = HashSet::new();
+    for prefix in dir.path.read_dir().unwrap() {
+        let prefix = prefix.unwrap();
+        assert!(prefix.file_type().unwrap().is_dir());
+        paths.insert(prefix.path());
+        for file in prefix.path().read_dir().unwrap() {
+            let file = file.unwrap();
+            let hash = Sha256::digest(fs::read(file.path()).unwrap())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            assert_eq!(
+                format!(
+                    "{}{}",
+                    prefix.file_name().to_str().unwrap(),
+                    file.file_name().to_str().unwrap()
+                ),
+                hash
+            );
+            paths.insert(file.path());
+        }
+    }
+
+    assert_eq!(count_num_files(&dir.path), 1_000);
+    assert_eq!(paths, planned);
+}
+
+#[test]
+fn files_named_after_their_contents_are_redrawn_instead_of_deduplicated() {
+    let dir = InspectableTempDir::new();
+
+    // One byte files only have 256 possible contents, so some of them are bound to collide
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100).unwrap())
+        .num_bytes(100)
+        .bytes_exact(true)
+        .names_from_content(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_eq!(count_num_files(&dir.path), 100);
+}
+
+#[test]
+fn git_objects_follow_the_loose_object_naming_scheme() {
+    let dir = InspectableTempDir::new();
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_bytes(100_000)
+        .git_objects(NonZeroUsize::new(100).unwrap())
+        .build()
+        .unwrap();
+    let planned = g.plan_paths().map(|(path, _)| path).collect::<HashSet<_>>();
+    g.generate().unwrap();
+
+    let is_hex = |name: &str, len: usize| {
+        name.len() == len && name.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    let mut paths = HashSet::new();
+    let mut num_loose = 0;
+    for entry in dir.path.read_dir().unwrap() {
+        let entry = entry.unwrap();
+        let name = entry.file_name().into_string().unwrap();
+        assert!(entry.file_type().unwrap().is_dir());
+        paths.insert(entry.path());
+        if name == "info" {
+            continue;
+        }
+        if name == "pack" {
+            let mut packs = entry
+                .path()
+                .read_dir()
+                .unwrap()
+                .map(|file| file.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            packs.sort();
+            assert_eq!(packs.len(), 2);
+            let id = packs[0].strip_prefix("pack-").unwrap().strip_suffix(".idx").unwrap();
+            assert!(is_hex(id, 40));
+            assert_eq!(packs[1], format!("pack-{}.pack", id));
+            let pack = fs::read(entry.path().join(&packs[1])).unwrap();
+            let index = fs::read(entry.path().join(&packs[0])).unwrap();
+            assert!(pack.starts_with(b"PACK"));
+            assert!(index.starts_with(b"\xfftOc\0\0\0\x02"));
+            assert_eq!(*Sha1::digest(&pack[..pack.len() - 20]), pack[pack.len() - 20..]);
+
+            // Every object the index points to decodes to the blob it's named after
+            let be32 = |at: usize| u32::from_be_bytes(index[at..at + 4].try_into().unwrap());
+            let num_packed = be32(8 + 255 * 4) as usize;
+            let ids = 8 + 256 * 4;
+            let crcs = ids + num_packed * 20;
+            let offsets = crcs + num_packed * 4;
+            assert_eq!(num_packed, 75);
+            for i in 0..num_packed {
+                let offset = be32(offsets + i * 4) as usize;
+                let mut pos = offset;
+                assert_eq!((pack[pos] >> 4) & 0x7, 3);
+                let mut len = usize::from(pack[pos] & 0xF);
+                let mut shift = 4;
+                while pack[pos] & 0x80 != 0 {
+                    pos += 1;
+                    len |= usize::from(pack[pos] & 0x7F) << shift;
+                    shift += 7;
+                }
+                pos += 1;
+
+                let mut contents = Vec::new();
+                let mut decoder = ZlibDecoder::new(&pack[pos..]);
+                decoder.read_to_end(&mut contents).unwrap();
+                assert_eq!(contents.len(), len);
+                let end = pos + decoder.total_in() as usize;
+                assert_eq!(crc32fast::hash(&pack[offset..end]), be32(crcs + i * 4));
+
+                let mut digest = Sha1::new();
+                digest.update(format!("blob {}\0", len));
+                digest.update(&contents);
+                assert_eq!(*digest.finalize(), index[ids + i * 20..ids + (i + 1) * 20]);
+            }
+            paths.extend(packs.iter().map(|pack| entry.path().join(pack)));
+            continue;
+        }
+
+        assert!(is_hex(&name, 2));
+        for file in entry.path().read_dir().unwrap() {
+            let file = file.unwrap();
+            let suffix = file.file_name().into_string().unwrap();
+            assert!(is_hex(&suffix, 38));
+
+            let mut object = Vec::new();
+            ZlibDecoder::new(File::open(file.path()).unwrap())
+                .read_to_end(&mut object)
+                .unwrap();
+            let contents_start = object.iter().position(|&b| b == 0).unwrap() + 1;
+            assert_eq!(
+                object[..contents_start],
+                *format!("blob {}\0", object.len() - contents_start).as_bytes()
+            );
+            let id = Sha1::digest(&object)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            assert_eq!(format!("{}{}", name, suffix), id);
+
+            paths.insert(file.path());
+            num_loose += 1;
+        }
+    }
+
+    assert_eq!(num_loose, 25);
+    assert_eq!(paths, planned);
+}
+
+#[rstest]
+fn checksums_verify_generated_files(
+    #[values(0, 100_000)] num_bytes: usize,
+    #[values(false, true)] duplicate_groups: bool,
+) {
+    let dir = InspectableTempDir::new();
+    let root = dir.path.join("tree");
+    let sums = dir.path.join("sums");
+
+    let mut builder = GeneratorBuilder::default();
+    builder
+        .root_dir(root.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(num_bytes)
+        .checksums(sums.clone());
+    // Duplicate groups need bytes to duplicate
+    if duplicate_groups && num_bytes > 0 {
+        builder.duplicate_groups(NonZeroUsize::new(10).unwrap());
+    }
+    builder.build().unwrap().generate().unwrap();
+
+    // Equivalent to `cd tree && sha256sum -c ../sums`
+    let mut lines = 0;
+    for line in fs::read_to_string(&sums).unwrap().lines() {
+        let (expected, path) = line.split_once("  ").unwrap();
+        let actual = Sha256::digest(fs::read(root.join(path)).unwrap())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        assert_eq!(actual, expected, "{:?}", path);
+        lines += 1;
+    }
+    assert_eq!(lines, count_num_files(&root));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_are_served_during_generation() {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    let dir = InspectableTempDir::new();
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(100_000).unwrap())
+        .metrics_addr(addr)
+        .build()
+        .unwrap();
+    let generation = thread::spawn(move || g.generate().unwrap());
+
+    let scrape = || -> Option<(usize, usize)> {
+        let mut stream = TcpStream::connect(addr).ok()?;
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+
+        let counter = |name: &str| {
+            response
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .map(|value| value.parse::<usize>().unwrap())
+        };
+        Some((
+            counter("ftzz_files_created_total")?,
+            counter("ftzz_dirs_created_total")?,
+        ))
+    };
+    let mut scrapes = Vec::new();
+    while !generation.is_finished() {
+        if let Some(counters) = scrape() {
+            scrapes.push(counters);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    generation.join().unwrap();
+
+    assert!(!scrapes.is_empty());
+    let files = count_num_files(&dir.path);
+    let dirs = count_num_dirs(&dir.path);
+    for window in scrapes.windows(2) {
+        assert!(window[0].0 <= window[1].0 && window[0].1 <= window[1].1);
+    }
+    for (scraped_files, scraped_dirs) in scrapes {
+        assert!(scraped_files <= files && scraped_dirs <= dirs);
+    }
+}
+
+#[test]
+fn overwrite_replaces_existing_tree() {
+    let dir = InspectableTempDir::new();
+    let generate = |root: PathBuf, seed: u64, overwrite: bool| {
+        GeneratorBuilder::default()
+            .root_dir(root.clone())
+            .num_files(NonZeroUsize::new(1_000).unwrap())
+            .num_bytes(10_000)
+            .seed(seed)
+            .overwrite(overwrite)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        hash_dir(&root)
+    };
+
+    let fresh = generate(dir.path.join("fresh"), 1, false);
+    generate(dir.path.join("overwritten"), 0, false);
+    File::create(dir.path.join("overwritten/stray")).unwrap();
+
+    assert_eq!(generate(dir.path.join("overwritten"), 1, true), fresh);
+}
+
+#[rstest]
+fn self_check_passes_on_exact_runs(
+    #[values(false, true)] files_exact: bool,
+    #[values(false, true)] bytes_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(100_000)
+        .files_exact(files_exact)
+        .bytes_exact(bytes_exact)
+        .dirs_exact(42)
+        .self_check(true)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+}
+
+#[test]
+fn fixed_time_pins_all_timestamps() {
+    let dir = InspectableTempDir::new();
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(10_000)
+        .max_depth(3)
+        .fixed_time(time)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    assert_eq!(fs::metadata(&dir.path).unwrap().modified().unwrap(), time);
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            assert_eq!(metadata.modified().unwrap(), time, "{:?}", entry.path());
+            if metadata.is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                assert_eq!(metadata.accessed().unwrap(), time, "{:?}", entry.path());
+            }
+        }
+    }
+}
+
+#[rstest]
+fn sequence_headers_number_every_file_once(
+    #[values(SequenceHeader::Text, SequenceHeader::U64Le)] header: SequenceHeader,
+    #[values(false, true)] files_exact: bool,
+    // Most files are smaller than their header with the latter
+    #[values(1_000_000, 1_000)] num_bytes: usize,
+) {
+    let dir = InspectableTempDir::new();
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .files_exact(files_exact)
+        .num_bytes(num_bytes)
+        .max_depth(3)
+        .sequence_header(header)
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut numbers = Vec::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+                continue;
+            }
+
+            let contents = fs::read(entry.path()).unwrap();
+            numbers.push(match header {
+                SequenceHeader::Text => {
+                    let end = contents.iter().position(|&b| b == b'\n').unwrap();
+                    std::str::from_utf8(&contents[..end]).unwrap().parse::<u64>().unwrap()
+                }
+                SequenceHeader::U64Le => u64::from_le_bytes(contents[..8].try_into().unwrap()),
+            });
+        }
+    }
+
+    numbers.sort_unstable();
+    assert_eq!(numbers, (0..numbers.len() as u64).collect::<Vec<_>>());
+    if files_exact {
+        assert_eq!(numbers.len(), 1_000);
+    }
+}
+
+#[test]
+fn mtime_sequence_cutoff_selects_half_the_files() {
+    let dir = InspectableTempDir::new();
+    let start = 1_000_000_000;
+
+    GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(1_000).unwrap())
+        .num_bytes(10_000)
+        .max_depth(3)
+        .mtime_sequence(MtimeSequence {
+            start,
+            interval: 60,
+        })
+        .build()
+        .unwrap()
+        .generate()
+        .unwrap();
+
+    let mut mtimes = Vec::new();
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                mtimes.push(metadata.modified().unwrap());
+            }
+        }
+    }
+
+    // Every file gets its own step of the sequence
+    mtimes.sort();
+    let num_files = mtimes.len();
+    let expected = (0..num_files as u64)
+        .map(|i| SystemTime::UNIX_EPOCH + Duration::from_secs(start as u64 + i * 60))
+        .collect::<Vec<_>>();
+    assert_eq!(mtimes, expected);
+
+    let cutoff = SystemTime::UNIX_EPOCH + Duration::from_secs(start as u64 + num_files as u64 * 30);
+    let newer = mtimes.iter().filter(|&&mtime| mtime > cutoff).count();
+    assert_ge!(newer, num_files / 2 - 1);
+    assert_le!(newer, num_files / 2 + 1);
+}
+
+#[test]
+fn fuzz_test() {
+    let dir = InspectableTempDir::new();
+
+    let mut rng = rand::thread_rng();
+    let num_files = rng.gen_range(1..25_000);
+    let num_bytes = if rng.gen() {
+        rng.gen_range(0..100_000)
+    } else {
+        0
+    };
+    let max_depth = rng.gen_range(0..100);
+    let ratio = rng.gen_range(1..num_files);
+    let files_exact = rng.gen();
+    let bytes_exact = rng.gen();
+
+    let g = GeneratorBuilder::default()
+        .root_dir(dir.path.clone())
+        .num_files(NonZeroUsize::new(num_files).unwrap())
+        .num_bytes(num_bytes)
+        .max_depth(max_depth)
+        .file_to_dir_ratio(NonZeroUsize::new(ratio).unwrap())
+        .files_exact(files_exact)
+        .bytes_exact(bytes_exact)
+        .build()
+        .unwrap();
+    println!("Params: {:?}", g);
+    g.generate().unwrap();
+
+    assert_le!(find_max_depth(&dir.path), max_depth);
+    if files_exact {
+        assert_eq!(count_num_files(&dir.path), num_files);
+    }
+    if bytes_exact {
+        assert_eq!(count_num_bytes(&dir.path), num_bytes);
+    }
+}
+
+/// Recursively hashes the file and directory names in dir
+fn hash_dir(dir: &Path) -> u64 {
+    let mut hasher = SeaHasher::new();
+
+    let mut entries = Vec::new();
+    let mut queue = VecDeque::from([dir.to_path_buf()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            entries.push(entry.unwrap());
+        }
+
+        entries.sort_by_key(|e| e.file_name());
+        for entry in &entries {
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path())
+            } else if entry.metadata().unwrap().len() > 0 {
+                for byte in
+                    StackBufReader::<_, 4096>::new(File::open(entry.path()).unwrap()).bytes()
+                {
+                    hasher.write_u8(byte.unwrap());
+                }
+            }
+
+            hasher.write(entry.file_name().to_str().unwrap().as_bytes());
+        }
+        entries.clear();
+    }
+
+    hasher.finish()
+}
+
+fn assert_matching_hashes(hash: u64, hash_file: &Path) {
+    if option_env!("REGEN").is_some() {
+        create_dir_all(hash_file.parent().unwrap()).unwrap();
+        File::create(hash_file)
+            .unwrap()
+            .write_all(&hash.to_be_bytes())
+            .unwrap()
+    } else {
+        let mut expected_hash = Vec::new();
+        File::open(&hash_file)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Regenerate test files with `REGEN=true cargo test` \n{}: {:?}",
+                    e, hash_file,
+                )
+            })
+            .read_to_end(&mut expected_hash)
+            .unwrap();
+
+        assert_eq!(hash.to_be_bytes(), expected_hash.as_slice());
+    }
+}
+
+fn find_max_depth(dir: &Path) -> u32 {
+    let mut depth = 0;
+    for entry in dir.read_dir().unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            depth = max(depth, find_max_depth(&path) + 1);
+        }
+    }
+    depth
+}
+
+fn count_num_files(dir: &Path) -> usize {
+    let mut num_files = 0;
+    let mut queue = VecDeque::from([dir.to_path_buf()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                num_files += 1;
+            }
+        }
+    }
+    num_files
+}
+
+fn count_num_dirs(dir: &Path) -> usize {
+    let mut num_dirs = 0;
+    let mut queue = VecDeque::from([dir.to_path_buf()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                num_dirs += 1;
+                queue.push_back(entry.path());
+            }
+        }
+    }
+    num_dirs
 }
 
 fn count_num_bytes(dir: &Path) -> usize {